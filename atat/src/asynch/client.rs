@@ -1,9 +1,17 @@
-use super::AtatClient;
+use super::{
+    write::{write_request, write_with_recovery, RequestLink},
+    AtatClient, PayloadOptions,
+};
 use crate::{
-    helpers::LossyStr,
-    response_slot::{ResponseSlot, ResponseSlotGuard},
-    AtatCmd, Config, Error, Response,
+    helpers::{LossyStr, Traced},
+    init::{Handshake, InitStep, Resync},
+    response::{anchor_response, append_page, parse_validated},
+    response_slot::{CommandStateGuard, ResponseSlot, ResponseSlotGuard, PIPELINE_DEPTH},
+    router::ResponseRouter,
+    AtatCmd, AtatUrc, CommandState, Config, ConfigDelta, Error, InternalError, Response,
+    TraceLevel, UrcSink,
 };
+use core::{future::pending, ops::ControlFlow};
 use embassy_time::{with_timeout, Duration, Instant, TimeoutError, Timer};
 use embedded_io_async::Write;
 use futures::{
@@ -11,12 +19,48 @@ use futures::{
     pin_mut, Future,
 };
 
+/// Error of [`Client::send_retaining`], holding on to the raw response which
+/// failed to parse.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParseFailure<'a> {
+    error: Error,
+    raw: Option<&'a [u8]>,
+}
+
+impl<'a> ParseFailure<'a> {
+    /// The error of the command.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// The exact bytes of the response, if one was received and failed to
+    /// parse.
+    pub fn raw(&self) -> Option<&'a [u8]> {
+        self.raw
+    }
+}
+
+impl From<ParseFailure<'_>> for Error {
+    fn from(failure: ParseFailure<'_>) -> Self {
+        failure.error
+    }
+}
+
 pub struct Client<'a, W: Write, const INGRESS_BUF_SIZE: usize> {
     writer: W,
     res_slot: &'a ResponseSlot<INGRESS_BUF_SIZE>,
     buf: &'a mut [u8],
     config: Config,
-    cooldown_timer: Option<Timer>,
+    /// End of the cooldown of the previous command
+    cooldown_until: Option<Instant>,
+    /// Attempt of the next command, set by [`AtatClient::send_retry`]
+    attempt: u8,
+    /// Whether to wait for the device to be ready before the next command,
+    /// see [`Config::wait_ready`]
+    awaiting_ready: bool,
+    /// Deadline of the current [`AtatClient::send_with_deadline`] call
+    deadline: Option<Instant>,
 }
 
 impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> Client<'a, W, INGRESS_BUF_SIZE> {
@@ -31,38 +75,698 @@ impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> Client<'a, W, INGRESS_BUF_SIZE
             res_slot,
             buf,
             config,
-            cooldown_timer: None,
+            cooldown_until: None,
+            attempt: 1,
+            awaiting_ready: true,
+            deadline: None,
         }
     }
 
-    async fn send_request(&mut self, len: usize) -> Result<(), Error> {
-        if len < 50 {
-            debug!("Sending command: {:?}", LossyStr(&self.buf[..len]));
-        } else {
-            debug!("Sending command with long payload ({} bytes)", len);
+    /// The current configuration of the client.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The command currently in flight, if any.
+    ///
+    /// As the client is borrowed mutably while sending, query the state
+    /// from another task through [`ResponseSlot::state`] of the shared slot.
+    pub fn state(&self) -> Option<CommandState> {
+        self.res_slot.state()
+    }
+
+    /// Reconfigure the client. As the client is borrowed mutably, no command
+    /// can be in flight, and the change is in effect from the next command
+    /// sent. Returns the resulting configuration.
+    pub fn reconfigure(&mut self, delta: ConfigDelta) -> &Config {
+        debug!("Reconfiguring client");
+        self.config.apply(delta);
+        &self.config
+    }
+
+    /// Set the amount of detail logged about the commands sent, eg. to
+    /// enable deep tracing temporarily from a debug channel. See
+    /// [`Config::trace`].
+    pub fn set_trace(&mut self, level: TraceLevel) {
+        self.config.trace = level;
+    }
+
+    /// Perform the initialization handshake: send `AT` until the device
+    /// responds, disable echo, enable `+CME ERROR` result codes, and apply
+    /// the [`Config::command_termination`] and
+    /// [`Config::response_formatting`] if set.
+    ///
+    /// Each step is attempted up to [`Config::init_attempts`] times.
+    pub async fn init(&mut self) -> Result<(), Error> {
+        let mut handshake = Handshake::new(&self.config);
+        while let Some(step) = handshake.step() {
+            let res = self.send(&step).await;
+            handshake.record(res)?;
         }
+        Ok(())
+    }
 
-        self.wait_cooldown_timer().await;
+    /// Resynchronize with the device, eg. after the serial cable or USB
+    /// adapter was hot-plugged, leaving half a frame on either side.
+    ///
+    /// The ingress discards everything it holds, the
+    /// [`Config::attention_sequence`] is written, and every response is
+    /// discarded until a clean `OK`. The cooldown and reattempt state of the
+    /// client is reset. The sequence is written up to
+    /// [`Config::init_attempts`] times.
+    pub async fn resync(&mut self) -> Result<(), Error> {
+        debug!("Resynchronizing");
+        self.cooldown_until = None;
+        self.attempt = 1;
+        self.res_slot.request_clear();
 
-        // Clear any pending response signal
+        let sequence = self.config.attention_sequence;
+        let res_slot = self.res_slot;
+        let mut resync = Resync::new(&self.config);
+        while let Some(attempt) = resync.next_attempt() {
+            res_slot.reset();
+            let _state = res_slot.start_command(CommandState::new::<InitStep>(attempt));
+            Resync::written(write_request(&mut self.writer, &self.config, sequence).await)?;
+            while let Ok(response) = self.wait_response(Resync::TIMEOUT).await {
+                let clean = Resync::is_clean(&response.borrow());
+                drop(response);
+                res_slot.reset();
+                if clean {
+                    return Ok(());
+                }
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Send an AT command like [`AtatClient::send`], delivering progress lines
+    /// emitted by the device while the command is running to `on_progress`,
+    /// eg. for firmware updates or file transfers.
+    ///
+    /// Progress lines are the URC's parsed by `P`, which must also be
+    /// recognized by the URC parser of the ingress. While the command is in
+    /// flight, they are not published to the URC channel. Up to
+    /// [`PROGRESS_DEPTH`] progress lines are queued if the caller falls
+    /// behind.
+    ///
+    /// [`PROGRESS_DEPTH`]: crate::response_slot::PROGRESS_DEPTH
+    pub async fn send_with_progress<Cmd: AtatCmd, P: AtatUrc>(
+        &mut self,
+        cmd: &Cmd,
+        mut on_progress: impl FnMut(P::Response),
+    ) -> Result<Cmd::Response, Error> {
+        let res_slot = self.res_slot;
+        let _filter = res_slot.route_progress(|line| P::parse(line).is_some());
+
+        let send = self.send(cmd);
+        let progress = async {
+            loop {
+                let line = res_slot.wait_progress().await;
+                if let Some(progress) = P::parse(&line) {
+                    on_progress(progress);
+                }
+            }
+        };
+        pin_mut!(send, progress);
+
+        match select(send, progress).await {
+            Either::Left((res, _)) => res,
+            Either::Right(_) => unreachable!(),
+        }
+    }
+
+    /// Send an AT command answered by `OK` right away, whose actual result is
+    /// delivered later by a URC, eg. `AT+UDNSRN` or `AT+QIOPEN`, returning the
+    /// response along with the result picked from the URC by `matcher`.
+    ///
+    /// The URC's parsed by `U`, which must also be recognized by the URC
+    /// parser of the ingress, are handed to `matcher` from the moment the
+    /// command is sent, until it returns `Some`, or `timeout` passed since
+    /// the response. URC's rejected by `matcher`, eg. the result of another
+    /// socket, are handed back to `urcs`, usually the [`UrcChannel`] of the
+    /// ingress. Up to [`PROGRESS_DEPTH`] URC's are queued for `matcher`, and
+    /// URC lines longer than [`PROGRESS_LEN`] are dropped.
+    ///
+    /// [`UrcChannel`]: crate::UrcChannel
+    /// [`PROGRESS_DEPTH`]: crate::response_slot::PROGRESS_DEPTH
+    /// [`PROGRESS_LEN`]: crate::response_slot::PROGRESS_LEN
+    pub async fn send_and_wait_urc<Cmd: AtatCmd, U: AtatUrc, T>(
+        &mut self,
+        cmd: &Cmd,
+        urcs: &dyn UrcSink,
+        mut matcher: impl FnMut(U::Response) -> Option<T>,
+        timeout: Duration,
+    ) -> Result<(Cmd::Response, T), Error> {
+        let res_slot = self.res_slot;
+        let _filter = res_slot.route_progress(|line| U::parse(line).is_some());
+
+        let (res, matched) = {
+            let send = self.send(cmd);
+            let urc = wait_matching_urc::<U, _, INGRESS_BUF_SIZE>(res_slot, urcs, &mut matcher);
+            pin_mut!(send, urc);
+            match select(send, urc).await {
+                Either::Left((res, _)) => (res, None),
+                // The URC raced the response
+                Either::Right((matched, send)) => (send.await, Some(matched)),
+            }
+        };
+        match (res, matched) {
+            (Ok(response), Some(matched)) => Ok((response, matched)),
+            (Ok(response), None) => with_timeout(
+                timeout,
+                wait_matching_urc::<U, _, INGRESS_BUF_SIZE>(res_slot, urcs, &mut matcher),
+            )
+            .await
+            .map(|matched| (response, matched))
+            .map_err(|_| Error::Timeout),
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    /// Send an AT command like [`AtatClient::send`], retaining the raw bytes
+    /// of a response which fails to parse, eg. to retry with a fallback
+    /// parser or for diagnostics.
+    ///
+    /// The bytes are copied to the request buffer, and remain available from
+    /// [`ParseFailure::raw`] until it is dropped. Responses larger than the
+    /// request buffer are not retained.
+    pub async fn send_retaining<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+    ) -> Result<Cmd::Response, ParseFailure<'_>> {
+        match self.send_inner(cmd, true, pending()).await {
+            Ok(response) => Ok(response),
+            Err((error, len)) => Err(ParseFailure {
+                error,
+                raw: len.map(|len| &self.buf[..len]),
+            }),
+        }
+    }
+
+    /// Send an AT command like [`AtatClient::send`], aborting it once `abort`
+    /// completes, eg. on a user request or a cancellation signal. This is the
+    /// counterpart of the blocking `Client::abort_current`.
+    ///
+    /// On abort, the [`Config::abort_token`] is written, and the command
+    /// fails with [`Error::Aborted`] once the device answered it or the
+    /// [`Config::abort_timeout`] passed. The command is not reattempted.
+    ///
+    /// Commands which are not [`AtatCmd::CAN_ABORT`] are refused with
+    /// [`Error::NotAbortable`], unless `force` is set.
+    pub async fn send_abortable<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        abort: impl Future<Output = ()>,
+        force: bool,
+    ) -> Result<Cmd::Response, Error> {
+        if !Cmd::CAN_ABORT && !force {
+            warn!("Refusing to abort {}", Cmd::NAME);
+            return Err(Error::NotAbortable);
+        }
+        self.send_inner(cmd, false, abort).await.map_err(|(e, _)| e)
+    }
+
+    /// Send raw bytes, eg. binary socket data, through the same cooldown,
+    /// tracing and write recovery as commands. The bytes are written as is,
+    /// without a command prefix or termination.
+    ///
+    /// If a `response_timeout` is given, the final result code is awaited for
+    /// up to that long, and the raw response returned from the request
+    /// buffer. Otherwise an empty response is returned once the bytes are
+    /// written.
+    pub async fn send_raw(
+        &mut self,
+        data: &[u8],
+        response_timeout: Option<Duration>,
+    ) -> Result<&[u8], Error> {
+        let res_slot = self.res_slot;
+        let _state = self
+            .send_request(Some(data), 0, self.config.cmd_cooldown, CommandState::raw())
+            .await?;
+        let Some(timeout) = response_timeout else {
+            return Ok(&[]);
+        };
+
+        let mut len = 0;
+        // The response slot guard must be released before returning
+        {
+            let response = self
+                .with_timeout(timeout, res_slot.get())
+                .await
+                .map_err(|_| Error::Timeout)?;
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            append_page(self.buf, &mut len, Result::from(response)?)?;
+        }
+        Ok(&self.buf[..len])
+    }
+
+    /// Send a command without awaiting its response, eg. `AT+CFUN=15`
+    /// restarting the device before it gets to answer.
+    ///
+    /// Returns once the command is written. The ingress swallows the final
+    /// result code of the command if it arrives within
+    /// [`AtatCmd::MAX_TIMEOUT_MS`] and before the device restarts, rather
+    /// than handing it to the next command. A command sent meanwhile would
+    /// have its response swallowed instead, so await the restart first, eg.
+    /// with [`Config::wait_ready`].
+    pub async fn send_no_response<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<(), Error> {
+        self.clear_pending();
+        // Marked before writing, such that an immediate answer is swallowed
+        if Cmd::EXPECTS_RESPONSE_CODE {
+            let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+            self.res_slot
+                .swallow_response(Some(Instant::now() + timeout));
+        }
+        let res = self.send_cmd(cmd, 1).await.map(drop);
+        if res.is_err() {
+            self.res_slot.swallow_response(None);
+        }
+        res
+    }
+
+    /// Send an AT command answered by a prompt for data, eg. `AT+CMGS` or
+    /// `AT+USOST`, followed by `payload`, and parse the final response.
+    ///
+    /// The payload is written in chunks after the prompt, and
+    /// `on_progress` is called with the number of bytes written after each.
+    /// Returning [`ControlFlow::Break`] cancels the transfer, sending the
+    /// cancel byte rather than the terminator, and fails with
+    /// [`Error::Aborted`].
+    ///
+    /// The prompt is awaited for [`AtatCmd::MAX_TIMEOUT_MS`], while the
+    /// transfer and the final response share the time budget of
+    /// [`PayloadOptions::timeout`]. A transfer timing out is cancelled too.
+    /// Either way, the final result code of the cancelled command is awaited
+    /// for [`Config::abort_timeout`] and discarded.
+    pub async fn send_prompt_payload<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        payload: &[u8],
+        options: PayloadOptions,
+        mut on_progress: impl FnMut(usize) -> ControlFlow<()>,
+    ) -> Result<Cmd::Response, Error> {
+        let res_slot = self.res_slot;
+        let _state = self.send_cmd(cmd, 1).await?;
+
+        // The device answers with an error rather than a prompt if it does
+        // not accept the payload
+        {
+            let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+            let response = self.wait_response(timeout).await?;
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            if !matches!(response, Response::Prompt(_)) {
+                parse_validated(cmd, anchor_response::<Cmd>(response.into()))?;
+                return Err(Error::InvalidResponse);
+            }
+        }
+
+        let mut payload_sent = false;
+        let transfer = async {
+            self.write_payload(payload, &options, &mut on_progress)
+                .await?;
+            payload_sent = true;
+            let response = res_slot.get().await;
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            parse_validated(cmd, anchor_response::<Cmd>(response.into()))
+        };
+        let res = with_timeout(options.timeout, transfer)
+            .await
+            .unwrap_or(Err(Error::Timeout));
+        if !payload_sent && matches!(res, Err(Error::Aborted | Error::Timeout)) {
+            // The device is still reading the payload, so cancel it and wait
+            // for it to be rejected, rather than have the rest of the payload
+            // or the result code taken for the next command
+            self.cancel_payload(options.cancel).await?;
+            self.settle_abort().await;
+        }
+        res
+    }
+
+    /// Send an AT command answered in two phases, eg. interactive
+    /// certificate queries: the device emits an intermediate prompt, and
+    /// the real response only after further input.
+    ///
+    /// The intermediate stage is either a prompt, or a line matched by the
+    /// [`AtDigester::with_custom_continuation`] of the ingress. `follow_up`
+    /// is called with the prompt byte or the line, writes the bytes to send
+    /// next into the given buffer, and returns their length. A length
+    /// exceeding the buffer fails with [`Error::RequestTooLarge`]. The whole
+    /// transaction is bounded by [`AtatCmd::MAX_TIMEOUT_MS`].
+    ///
+    /// [`AtDigester::with_custom_continuation`]: crate::AtDigester::with_custom_continuation
+    pub async fn send_expect_prompt_then_response<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        follow_up: impl FnOnce(&[u8], &mut [u8]) -> usize,
+    ) -> Result<Cmd::Response, Error> {
+        let res_slot = self.res_slot;
+        let _state = self.send_cmd(cmd, 1).await?;
+
+        let transaction = async {
+            let len = {
+                let response = res_slot.get().await;
+                let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+                match response {
+                    Response::Prompt(prompt) => follow_up(&[*prompt], self.buf),
+                    Response::Continuation(line) => follow_up(line, self.buf),
+                    // The device answers with the final response rather than
+                    // the intermediate stage if it rejects the command
+                    response => {
+                        parse_validated(cmd, anchor_response::<Cmd>(response.into()))?;
+                        return Err(Error::InvalidResponse);
+                    }
+                }
+            };
+            let Some(request) = self.buf.get(..len) else {
+                error!("Follow-up of {} bytes exceeds the request buffer", len);
+                return Err(Error::RequestTooLarge);
+            };
+            debug!(
+                "Sending follow-up: {:?}",
+                Traced(self.config.trace, request)
+            );
+
+            // Clear the signal of the intermediate stage
+            res_slot.reset();
+            write_request(&mut self.writer, &self.config, request).await?;
+
+            let response = res_slot.get().await;
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            parse_validated(cmd, anchor_response::<Cmd>(response.into()))
+        };
+        let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+        with_timeout(timeout, transaction)
+            .await
+            .map_err(|_| Error::Timeout)?
+    }
+
+    /// Tear down the client, and return the serial writer and the request
+    /// buffer, eg. to hand the serial port to another peripheral. The shared
+    /// response slot is cleared, such that a new client and ingress pair,
+    /// possibly with a different config or digester, can be created on it
+    /// once the ingress is released with [`Ingress::release`].
+    ///
+    /// [`Ingress::release`]: crate::Ingress::release
+    pub fn release(self) -> (W, &'a mut [u8]) {
+        debug!("Releasing client");
+        self.res_slot.clear();
+        (self.writer, self.buf)
+    }
+
+    /// Start pipelining commands: writing several commands before awaiting
+    /// their responses, eg. to speed up chatty initialization sequences.
+    ///
+    /// See [`Pipeline`] for the requirements on the commands and the
+    /// ingress.
+    pub fn pipeline(&mut self) -> Pipeline<'_, 'a, W, INGRESS_BUF_SIZE> {
+        Pipeline { client: self }
+    }
+
+    async fn write_payload(
+        &mut self,
+        payload: &[u8],
+        options: &PayloadOptions,
+        on_progress: &mut impl FnMut(usize) -> ControlFlow<()>,
+    ) -> Result<(), Error> {
+        debug!("Sending payload ({} bytes)", payload.len());
+
+        // Clear the signal of the prompt
         self.res_slot.reset();
 
-        // Write request
+        let mut written = 0;
+        for chunk in payload.chunks(options.chunk_size.max(1)) {
+            self.writer
+                .write_all(chunk)
+                .await
+                .map_err(|_| Error::Write)?;
+            self.config.trace_tx(chunk);
+            written += chunk.len();
+
+            if on_progress(written).is_break() {
+                warn!("Cancelling payload after {} bytes", written);
+                return Err(Error::Aborted);
+            }
+        }
+
+        if let Some(terminator) = options.terminator {
+            self.writer
+                .write_all(&[terminator])
+                .await
+                .map_err(|_| Error::Write)?;
+            self.config.trace_tx(&[terminator]);
+        }
+        self.writer.flush().await.map_err(|_| Error::Write)
+    }
+
+    /// Cancel the payload being written with the cancel byte, see
+    /// [`PayloadOptions::cancel`].
+    async fn cancel_payload(&mut self, cancel: u8) -> Result<(), Error> {
+        with_timeout(self.config.tx_timeout, self.writer.write_all(&[cancel]))
+            .await
+            .map_err(|_| Error::Timeout)?
+            .map_err(|_| Error::Write)?;
+        self.config.trace_tx(&[cancel]);
+        with_timeout(self.config.flush_timeout, self.writer.flush())
+            .await
+            .map_err(|_| Error::Timeout)?
+            .map_err(|_| Error::Write)
+    }
+
+    /// Send a command, retaining the raw bytes of a response which failed to
+    /// parse in the request buffer if `retain` is set, and returning their
+    /// length along with the error. The command is aborted once `abort`
+    /// completes.
+    async fn send_inner<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        retain: bool,
+        abort: impl Future<Output = ()>,
+    ) -> Result<Cmd::Response, (Error, Option<usize>)> {
+        let res = self.exchange(cmd, retain, abort).await;
+        if matches!(res, Err((Error::ModemRestarted, _))) {
+            self.awaiting_ready = true;
+        }
+        if Cmd::CAN_ABORT && matches!(res, Err((Error::Timeout, _))) {
+            // Stop the command, rather than have it answer a reattempt
+            self.abort().await.map_err(|e| (e, None))?;
+            self.settle_abort().await;
+        }
+        res
+    }
+
+    async fn exchange<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        retain: bool,
+        abort: impl Future<Output = ()>,
+    ) -> Result<Cmd::Response, (Error, Option<usize>)> {
+        let attempt = core::mem::replace(&mut self.attempt, 1);
+        let _state = self.send_cmd(cmd, attempt).await.map_err(|e| (e, None))?;
+        if !Cmd::EXPECTS_RESPONSE_CODE {
+            return parse_validated(cmd, Ok(&[])).map_err(|e| (e, None));
+        }
+
+        {
+            let response = self.wait_cmd_response(cmd, retain);
+            pin_mut!(response, abort);
+            if let Either::Left((res, _)) = select(response, abort).await {
+                return res;
+            }
+        }
+        self.abort().await.map_err(|e| (e, None))?;
+        self.settle_abort().await;
+        Err((Error::Aborted, None))
+    }
+
+    /// Wait for the response of a command, collecting the pages of a
+    /// paginated response in the request buffer.
+    async fn wait_cmd_response<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        retain: bool,
+    ) -> Result<Cmd::Response, (Error, Option<usize>)> {
+        let res_slot = self.res_slot;
+        let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+        let Some(token) = Cmd::CONTINUATION else {
+            let response = self
+                .with_timeout(timeout, res_slot.get())
+                .await
+                .map_err(|_| (Error::Timeout, None))?;
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            return parse_retaining(cmd, response.into(), self.buf, retain);
+        };
+
+        let mut pos = 0;
+        loop {
+            // The response slot guard must be released before continuing
+            {
+                let response = self
+                    .with_timeout(timeout, res_slot.get())
+                    .await
+                    .map_err(|_| (Error::Timeout, None))?;
+                let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+                match response {
+                    Response::Continuation(page) => {
+                        append_page(self.buf, &mut pos, page).map_err(|e| (e, None))?
+                    }
+                    Response::Ok(page) if pos > 0 => {
+                        append_page(self.buf, &mut pos, page).map_err(|e| (e, None))?;
+                        // The pages are already retained in the request buffer
+                        return parse_validated(cmd, anchor_response::<Cmd>(Ok(&self.buf[..pos])))
+                            .map_err(|e| (e, retain.then_some(pos)));
+                    }
+                    response => return parse_retaining(cmd, response.into(), self.buf, retain),
+                }
+            }
+            self.send_continuation(token).await.map_err(|e| (e, None))?;
+        }
+    }
+
+    /// Write the abort token, stopping the command running on the device.
+    async fn abort(&mut self) -> Result<(), Error> {
+        debug!("Aborting command");
         with_timeout(
             self.config.tx_timeout,
-            self.writer.write_all(&self.buf[..len]),
+            self.writer.write_all(self.config.abort_token),
         )
         .await
         .map_err(|_| Error::Timeout)?
         .map_err(|_| Error::Write)?;
-
+        self.config.trace_tx(self.config.abort_token);
         with_timeout(self.config.flush_timeout, self.writer.flush())
             .await
             .map_err(|_| Error::Timeout)?
-            .map_err(|_| Error::Write)?;
+            .map_err(|_| Error::Write)
+    }
 
-        self.start_cooldown_timer();
-        Ok(())
+    /// Wait for the final result code of an aborted command, discarding it.
+    async fn settle_abort(&mut self) {
+        match with_timeout(self.config.abort_timeout, self.res_slot.get()).await {
+            Ok(_) => debug!("Command aborted"),
+            Err(_) => warn!("No result code for aborted command"),
+        }
+        self.res_slot.reset();
+    }
+
+    /// Wait for the device to be ready after the client was created or the
+    /// device restarted, see [`Config::wait_ready`].
+    async fn wait_ready(&mut self) -> Result<(), Error> {
+        let Some(timeout) = self.config.ready_timeout.filter(|_| self.awaiting_ready) else {
+            return Ok(());
+        };
+        self.awaiting_ready = false;
+        if with_timeout(timeout, self.res_slot.wait_booted())
+            .await
+            .is_ok()
+        {
+            debug!("Device ready");
+            return Ok(());
+        }
+        debug!("No boot banner, probing the device");
+        self.resync().await
+    }
+
+    /// Reset the reattempt state and the state of any stale command.
+    fn clear_pending(&mut self) {
+        self.attempt = 1;
+        self.res_slot.set_state(None);
+    }
+
+    /// Write the request of a command, from its wire image if it has one.
+    /// The state of the command is reported until the returned guard is
+    /// dropped.
+    async fn send_cmd<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        attempt: u8,
+    ) -> Result<CommandStateGuard<'a, INGRESS_BUF_SIZE>, Error> {
+        // Commands without parameters are sent from their static wire image
+        let len = match Cmd::WIRE_IMAGE {
+            Some(_) => 0,
+            None => cmd.write(self.buf),
+        };
+        let cooldown = Cmd::COOLDOWN_MS.map_or(self.config.cmd_cooldown, |ms| {
+            Duration::from_millis(ms.into())
+        });
+        let state = CommandState::new::<Cmd>(attempt);
+        self.send_request(Cmd::WIRE_IMAGE, len, cooldown, state)
+            .await
+    }
+
+    async fn send_request(
+        &mut self,
+        wire_image: Option<&[u8]>,
+        len: usize,
+        cooldown: Duration,
+        state: CommandState,
+    ) -> Result<CommandStateGuard<'a, INGRESS_BUF_SIZE>, Error> {
+        self.wait_ready().await?;
+        self.wait_cooldown_timer().await?;
+        if self
+            .deadline
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            return Err(Error::DeadlineExceeded);
+        }
+
+        // Clear any pending response signal
+        self.res_slot.reset();
+
+        let request = match wire_image {
+            Some(request) => request,
+            None => &self.buf[..len],
+        };
+        if request.len() < 50 || self.config.trace == TraceLevel::Bytes {
+            debug!("Sending command: {:?}", Traced(self.config.trace, request));
+        } else {
+            debug!(
+                "Sending command with long payload ({} bytes)",
+                request.len()
+            );
+        }
+
+        // Reported before writing, such that the ingress recognizes a
+        // response arriving while the write completes. Cleared again if the
+        // write fails.
+        let res_slot = self.res_slot;
+        let state = res_slot.start_command(CommandState {
+            sent: Instant::now(),
+            ..state
+        });
+
+        // Write request
+        write_with_recovery(self, wire_image, len).await?;
+
+        if self.config.loopback {
+            let request = match wire_image {
+                Some(request) => request,
+                None => &self.buf[..len],
+            };
+            let len = request.len().min(INGRESS_BUF_SIZE);
+            if self.res_slot.signal_response(Ok(&request[..len])).is_err() {
+                error!("Loopback response but a response is already pending");
+            }
+        }
+
+        self.start_cooldown_timer(cooldown);
+        Ok(state)
+    }
+
+    async fn send_continuation(&mut self, token: &[u8]) -> Result<(), Error> {
+        debug!("Sending continuation: {:?}", LossyStr(token));
+
+        // Clear the signal of the previous page
+        self.res_slot.reset();
+
+        with_timeout(self.config.tx_timeout, self.writer.write_all(token))
+            .await
+            .map_err(|_| Error::Timeout)?
+            .map_err(|_| Error::Write)?;
+        self.config.trace_tx(token);
+        with_timeout(self.config.flush_timeout, self.writer.flush())
+            .await
+            .map_err(|_| Error::Timeout)?
+            .map_err(|_| Error::Write)
     }
 
     async fn wait_response<'guard>(
@@ -80,7 +784,13 @@ impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> Client<'a, W, INGRESS_BUF_SIZE
         fut: F,
     ) -> Result<F::Output, TimeoutError> {
         let start = Instant::now();
-        let mut expires = (self.config.get_response_timeout)(start, timeout);
+        // The wait for a response is bounded by the deadline of the call
+        let expiry = || {
+            let expires = (self.config.get_response_timeout)(start, timeout);
+            self.deadline
+                .map_or(expires, |deadline| expires.min(deadline))
+        };
+        let mut expires = expiry();
 
         pin_mut!(fut);
 
@@ -88,7 +798,7 @@ impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> Client<'a, W, INGRESS_BUF_SIZE
             fut = match select(fut, Timer::at(expires)).await {
                 Either::Left((r, _)) => return Ok(r),
                 Either::Right((_, fut)) => {
-                    let new_expires = (self.config.get_response_timeout)(start, timeout);
+                    let new_expires = expiry();
                     if new_expires <= expires {
                         return Err(TimeoutError);
                     }
@@ -99,105 +809,436 @@ impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> Client<'a, W, INGRESS_BUF_SIZE
         }
     }
 
-    fn start_cooldown_timer(&mut self) {
-        self.cooldown_timer = Some(Timer::after(self.config.cmd_cooldown));
+    fn start_cooldown_timer(&mut self, cooldown: Duration) {
+        self.cooldown_until = Some(Instant::now() + cooldown);
     }
 
-    async fn wait_cooldown_timer(&mut self) {
-        if let Some(cooldown) = self.cooldown_timer.take() {
-            cooldown.await
+    async fn wait_cooldown_timer(&mut self) -> Result<(), Error> {
+        if let Some(until) = self.cooldown_until.take() {
+            // Fail early rather than sending after the deadline
+            if self.deadline.is_some_and(|deadline| until >= deadline) {
+                self.cooldown_until = Some(until);
+                return Err(Error::DeadlineExceeded);
+            }
+            Timer::at(until).await;
         }
+        Ok(())
     }
 }
 
-impl<W: Write, const INGRESS_BUF_SIZE: usize> AtatClient for Client<'_, W, INGRESS_BUF_SIZE> {
-    async fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
-        let len = cmd.write(&mut self.buf);
-        self.send_request(len).await?;
-        if !Cmd::EXPECTS_RESPONSE_CODE {
-            cmd.parse(Ok(&[]))
-        } else {
-            let response = self
-                .wait_response(Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into()))
-                .await?;
-            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
-            cmd.parse(response.into())
+/// Wait for a URC parsed by `U` which is picked by `matcher`, handing the
+/// others back to `urcs`, see [`Client::send_and_wait_urc`].
+async fn wait_matching_urc<U: AtatUrc, T, const N: usize>(
+    res_slot: &ResponseSlot<N>,
+    urcs: &dyn UrcSink,
+    matcher: &mut impl FnMut(U::Response) -> Option<T>,
+) -> T {
+    loop {
+        let line = res_slot.wait_progress().await;
+        if let Some(matched) = U::parse(&line).and_then(&mut *matcher) {
+            return matched;
         }
+        urcs.publish_line(&line);
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate as atat;
-    use crate::atat_derive::{AtatCmd, AtatEnum, AtatResp};
-    use crate::Error;
-    use core::sync::atomic::{AtomicU64, Ordering};
-    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-    use embassy_sync::pubsub::PubSubChannel;
-    use embassy_time::{Duration, Instant, Timer};
-    use heapless::String;
-    use tokio::join;
+/// Parse a response, copying its raw bytes to `buf` on failure if `retain` is
+/// set and they fit.
+fn parse_retaining<Cmd: AtatCmd>(
+    cmd: &Cmd,
+    response: Result<&[u8], InternalError>,
+    buf: &mut [u8],
+    retain: bool,
+) -> Result<Cmd::Response, (Error, Option<usize>)> {
+    let raw = match response {
+        Ok(raw) if retain => Some(raw),
+        _ => None,
+    };
+    parse_validated(cmd, anchor_response::<Cmd>(response)).map_err(|e| match raw {
+        Some(raw) if raw.len() <= buf.len() => {
+            buf[..raw.len()].copy_from_slice(raw);
+            (e, Some(raw.len()))
+        }
+        _ => (e, None),
+    })
+}
 
-    const TEST_RX_BUF_LEN: usize = 256;
+/// Commands in flight together, see [`Client::pipeline`].
+///
+/// Commands are written with [`Pipeline::write`], and their responses read
+/// with [`Pipeline::read`] in the order the commands were written, or with
+/// [`Pipeline::read_routed`] by sequence id for protocols answering out of
+/// order. Up to [`PIPELINE_DEPTH`] responses arriving before the client has
+/// taken the pending one are queued in the response slot, and any further
+/// response is dropped, so commands should not be written much further ahead
+/// than their responses are read.
+///
+/// Pipelined commands must complete with a plain final result code: prompts,
+/// continuations and [`AtatCmd::SUCCESS_CODES`] are not supported. The
+/// cooldown is only awaited before the first command. Once a read fails with
+/// anything but an error response, the responses are out of step with the
+/// commands, and the pipeline should be dropped and the link resynchronized
+/// with [`Client::resync`].
+///
+/// [`PIPELINE_DEPTH`]: crate::response_slot::PIPELINE_DEPTH
+pub struct Pipeline<'c, 'a, W: Write, const INGRESS_BUF_SIZE: usize> {
+    client: &'c mut Client<'a, W, INGRESS_BUF_SIZE>,
+}
 
-    #[derive(Clone, AtatCmd)]
-    #[at_cmd("+CFUN", NoResponse, timeout_ms = 180000)]
-    pub struct SetModuleFunctionality {
-        #[at_arg(position = 0)]
-        pub fun: Functionality,
-        #[at_arg(position = 1)]
-        pub rst: Option<ResetMode>,
+impl<W: Write, const INGRESS_BUF_SIZE: usize> Pipeline<'_, '_, W, INGRESS_BUF_SIZE> {
+    /// Number of commands written whose response was not read yet.
+    pub fn in_flight(&self) -> usize {
+        self.client.res_slot.pipelined()
     }
 
-    #[derive(Clone, PartialEq, AtatEnum)]
-    #[at_enum(u8)]
-    pub enum Functionality {
-        #[at_arg(value = 0)]
-        Min,
-        #[at_arg(value = 1)]
-        Full,
-        #[at_arg(value = 4)]
-        APM,
-        #[at_arg(value = 6)]
-        DM,
+    /// Number of responses received by the ingress and not read yet,
+    /// including the ones queued behind the pending response.
+    pub fn pending_frames(&self) -> usize {
+        self.client.res_slot.pending_frames()
     }
 
-    #[derive(Clone, PartialEq, AtatEnum)]
-    #[at_enum(u8)]
-    pub enum ResetMode {
-        #[at_arg(value = 0)]
-        DontReset,
-        #[at_arg(value = 1)]
-        Reset,
+    /// Whether another command can be written without its response being
+    /// dropped by the ingress, as the responses of all commands in flight
+    /// and of the next one fit in the response slot and its queue of
+    /// [`PIPELINE_DEPTH`]. Applications writing commands from a loop should
+    /// read a response whenever this is `false`.
+    ///
+    /// [`PIPELINE_DEPTH`]: crate::response_slot::PIPELINE_DEPTH
+    pub fn can_write(&self) -> bool {
+        self.in_flight() <= PIPELINE_DEPTH
     }
 
-    #[derive(Clone, AtatResp, PartialEq, Debug)]
-    pub struct NoResponse;
+    /// Write a command without awaiting its response.
+    pub async fn write<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<(), Error> {
+        let client = &mut *self.client;
+        let res_slot = client.res_slot;
+        let pipelined = res_slot.pipelined();
+        if pipelined == 0 {
+            client.wait_cooldown_timer().await?;
+            // Clear any pending response signal
+            res_slot.reset();
+        }
 
-    macro_rules! setup {
-        ($config:expr) => {{
-            static TX_CHANNEL: PubSubChannel<CriticalSectionRawMutex, String<64>, 1, 1, 1> =
-                PubSubChannel::new();
-            static RES_SLOT: ResponseSlot<TEST_RX_BUF_LEN> = ResponseSlot::new();
-            static mut BUF: [u8; 1000] = [0; 1000];
+        let request = match Cmd::WIRE_IMAGE {
+            Some(request) => request,
+            None => {
+                let len = cmd.write(client.buf);
+                &client.buf[..len]
+            }
+        };
+        debug!(
+            "Pipelining command #{}: {:?}",
+            pipelined + 1,
+            Traced(client.config.trace, request)
+        );
 
-            let tx_mock = crate::tx_mock::TxMock::new(TX_CHANNEL.publisher().unwrap());
-            let client: Client<crate::tx_mock::TxMock, TEST_RX_BUF_LEN> =
-                Client::new(tx_mock, &RES_SLOT, unsafe { BUF.as_mut() }, $config);
-            (client, TX_CHANNEL.subscriber().unwrap(), &RES_SLOT)
-        }};
+        // Counted before writing, such that the ingress holds on to a response
+        // arriving while the write completes
+        if Cmd::EXPECTS_RESPONSE_CODE {
+            res_slot.set_pipelined(pipelined + 1);
+        }
+        write_request(&mut client.writer, &client.config, request)
+            .await
+            .inspect_err(|_| res_slot.set_pipelined(pipelined))
     }
 
-    #[tokio::test]
-    async fn custom_timeout() {
-        static CALL_COUNT: AtomicU64 = AtomicU64::new(0);
+    /// Await and parse the response of the oldest command written and not
+    /// read yet, which must be `cmd`.
+    pub async fn read<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        if !Cmd::EXPECTS_RESPONSE_CODE {
+            return parse_validated(cmd, Ok(&[]));
+        }
 
-        fn custom_response_timeout(sent: Instant, timeout: Duration) -> Instant {
-            CALL_COUNT.fetch_add(1, Ordering::Relaxed);
-            assert_eq!(
-                Duration::from_millis(SetModuleFunctionality::MAX_TIMEOUT_MS.into()),
-                timeout
+        let client = &mut *self.client;
+        let res_slot = client.res_slot;
+        let res = {
+            let _state = res_slot.start_command(CommandState::new::<Cmd>(1));
+            let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+            let response = client.wait_response(timeout).await?;
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            parse_validated(cmd, anchor_response::<Cmd>(response.into()))
+        };
+
+        // Deliver the response of the next command, if it was queued
+        res_slot.take_pipelined();
+        res
+    }
+
+    /// Await and parse the response of `cmd`, matching responses to commands
+    /// by their sequence id rather than by order, see the [`router`] module.
+    ///
+    /// Responses tagged with the id of another command are set aside in
+    /// `router` until that command is read. Commands without an
+    /// [`AtatCmd::ID_EXTRACTOR`] or [`AtatCmd::sequence_id`] are read like
+    /// [`Pipeline::read`]. Each response is awaited for
+    /// [`AtatCmd::MAX_TIMEOUT_MS`]. Fails with [`Error::RouterFull`] if a
+    /// response must be set aside while `router` is full.
+    ///
+    /// [`router`]: crate::router
+    pub async fn read_routed<Cmd: AtatCmd, const N: usize, const LEN: usize>(
+        &mut self,
+        cmd: &Cmd,
+        router: &mut ResponseRouter<N, LEN>,
+    ) -> Result<Cmd::Response, Error> {
+        let (Some(extract), Some(id)) = (Cmd::ID_EXTRACTOR, cmd.sequence_id()) else {
+            return self.read(cmd).await;
+        };
+        if let Some(response) = router.take(id) {
+            return parse_validated(cmd, anchor_response::<Cmd>(Ok(&response)));
+        }
+
+        let client = &mut *self.client;
+        let res_slot = client.res_slot;
+        let _state = res_slot.start_command(CommandState::new::<Cmd>(1));
+        let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+        loop {
+            let res = {
+                let response = client.wait_response(timeout).await?;
+                let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+                match response {
+                    Response::Ok(r) => match extract(r) {
+                        Some(other) if other != id => router.hold(other, r).err().map(Err),
+                        _ => Some(parse_validated(cmd, anchor_response::<Cmd>(Ok(r)))),
+                    },
+                    response => Some(parse_validated(cmd, response.into())),
+                }
+            };
+
+            // Deliver the next response, if it was queued
+            res_slot.take_pipelined();
+            if let Some(res) = res {
+                return res;
+            }
+        }
+    }
+}
+
+impl<W: Write, const INGRESS_BUF_SIZE: usize> Drop for Pipeline<'_, '_, W, INGRESS_BUF_SIZE> {
+    fn drop(&mut self) {
+        let res_slot = self.client.res_slot;
+        if res_slot.pipelined() > 0 {
+            warn!(
+                "Dropping pipeline with {} responses not read",
+                res_slot.pipelined()
+            );
+        }
+        res_slot.set_pipelined(0);
+        res_slot.reset();
+        self.client
+            .start_cooldown_timer(self.client.config.cmd_cooldown);
+    }
+}
+
+impl<W: Write, const INGRESS_BUF_SIZE: usize> core::fmt::Debug
+    for Pipeline<'_, '_, W, INGRESS_BUF_SIZE>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("in_flight", &self.in_flight())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<W: Write, const INGRESS_BUF_SIZE: usize> defmt::Format
+    for Pipeline<'_, '_, W, INGRESS_BUF_SIZE>
+{
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Pipeline {{ in_flight: {=usize}, .. }}",
+            self.in_flight()
+        )
+    }
+}
+
+impl<W: Write, const INGRESS_BUF_SIZE: usize> core::fmt::Debug for Client<'_, W, INGRESS_BUF_SIZE> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Client")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<W: Write, const INGRESS_BUF_SIZE: usize> defmt::Format for Client<'_, W, INGRESS_BUF_SIZE> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Client {{ config: {}, .. }}", self.config)
+    }
+}
+
+impl<W: Write, const INGRESS_BUF_SIZE: usize> AtatClient for Client<'_, W, INGRESS_BUF_SIZE> {
+    fn set_attempt(&mut self, attempt: u8) {
+        self.attempt = attempt;
+    }
+
+    async fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        let mut retry = 0;
+        loop {
+            match self.send_inner(cmd, false, pending()).await {
+                Err((e, _)) if e.is_transient() && retry + 1 < self.config.retry_attempts => {
+                    let backoff = self.config.retry_backoff(retry);
+                    if self
+                        .deadline
+                        .is_some_and(|deadline| Instant::now() + backoff >= deadline)
+                    {
+                        return Err(Error::DeadlineExceeded);
+                    }
+                    retry += 1;
+                    debug!("Retry {} after {:?}", retry, e);
+                    Timer::after(backoff).await;
+                }
+                res => return res.map_err(|(e, _)| e),
+            }
+        }
+    }
+
+    async fn send_with_deadline<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        deadline: Instant,
+    ) -> Result<Cmd::Response, Error> {
+        self.deadline = Some(deadline);
+        WithDeadline(self).send_retry(cmd).await
+    }
+}
+
+/// Client within a [`AtatClient::send_with_deadline`] call, reporting a
+/// timeout past the deadline as exceeded, and clearing the deadline once
+/// dropped, even if the call is.
+struct WithDeadline<'c, 'a, W: Write, const INGRESS_BUF_SIZE: usize>(
+    &'c mut Client<'a, W, INGRESS_BUF_SIZE>,
+);
+
+impl<W: Write, const INGRESS_BUF_SIZE: usize> Drop for WithDeadline<'_, '_, W, INGRESS_BUF_SIZE> {
+    fn drop(&mut self) {
+        self.0.deadline = None;
+    }
+}
+
+impl<W: Write, const INGRESS_BUF_SIZE: usize> AtatClient
+    for WithDeadline<'_, '_, W, INGRESS_BUF_SIZE>
+{
+    fn set_attempt(&mut self, attempt: u8) {
+        self.0.set_attempt(attempt);
+    }
+
+    async fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        let res = self.0.send(cmd).await;
+        match (res, self.0.deadline) {
+            (Err(Error::Timeout), Some(deadline)) if deadline <= Instant::now() => {
+                Err(Error::DeadlineExceeded)
+            }
+            (res, _) => res,
+        }
+    }
+}
+
+impl<W: Write, const INGRESS_BUF_SIZE: usize> RequestLink for Client<'_, W, INGRESS_BUF_SIZE> {
+    type Writer = W;
+
+    fn parts(&mut self) -> (&mut W, &Config, &[u8]) {
+        (&mut self.writer, &self.config, self.buf)
+    }
+
+    async fn discard_answer(&mut self) {
+        self.res_slot.get().await;
+        self.res_slot.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as atat;
+    use crate::atat_derive::{AtatCmd, AtatEnum, AtatResp, AtatUrc};
+    use crate::tx_mock::TxMock;
+    use crate::{AtatIngress, Error, IngressError};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+    use embassy_sync::pubsub::PubSubChannel;
+    use embassy_time::{Duration, Instant, Timer};
+    use heapless::String;
+    use tokio::join;
+
+    const TEST_RX_BUF_LEN: usize = 256;
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CFUN", NoResponse, timeout_ms = 180000)]
+    pub struct SetModuleFunctionality {
+        #[at_arg(position = 0)]
+        pub fun: Functionality,
+        #[at_arg(position = 1)]
+        pub rst: Option<ResetMode>,
+    }
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CFUN=1", NoResponse, value_sep = false, cooldown_ms = 100)]
+    pub struct SetFullFunctionality;
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd(
+        "+COPS=?",
+        NoResponse,
+        value_sep = false,
+        timeout_ms = 50,
+        abortable = true
+    )]
+    pub struct ScanOperators;
+
+    #[allow(clippy::upper_case_acronyms)]
+    #[derive(Clone, PartialEq, AtatEnum)]
+    #[at_enum(u8)]
+    pub enum Functionality {
+        #[at_arg(value = 0)]
+        Min,
+        #[at_arg(value = 1)]
+        Full,
+        #[at_arg(value = 4)]
+        APM,
+        #[at_arg(value = 6)]
+        DM,
+    }
+
+    #[derive(Clone, PartialEq, AtatEnum)]
+    #[at_enum(u8)]
+    pub enum ResetMode {
+        #[at_arg(value = 0)]
+        DontReset,
+        #[at_arg(value = 1)]
+        Reset,
+    }
+
+    #[derive(Clone, AtatResp, PartialEq, Debug)]
+    pub struct NoResponse;
+
+    macro_rules! setup {
+        ($config:expr) => {{
+            setup!($config, |tx_mock| tx_mock)
+        }};
+        ($config:expr, $tx_mock:expr) => {{
+            static TX_CHANNEL: PubSubChannel<CriticalSectionRawMutex, String<64>, 1, 1, 1> =
+                PubSubChannel::new();
+            static RES_SLOT: ResponseSlot<TEST_RX_BUF_LEN> = ResponseSlot::new();
+            static mut BUF: [u8; 1000] = [0; 1000];
+
+            let tx_mock = crate::tx_mock::TxMock::new(TX_CHANNEL.publisher().unwrap());
+            let tx_mock = ($tx_mock)(tx_mock);
+            let client: Client<TxMock, TEST_RX_BUF_LEN> =
+                Client::new(tx_mock, &RES_SLOT, unsafe { BUF.as_mut() }, $config);
+            (client, TX_CHANNEL.subscriber().unwrap(), &RES_SLOT)
+        }};
+    }
+
+    #[tokio::test]
+    async fn custom_timeout() {
+        static CALL_COUNT: AtomicU64 = AtomicU64::new(0);
+
+        fn custom_response_timeout(sent: Instant, timeout: Duration) -> Instant {
+            CALL_COUNT.fetch_add(1, Ordering::Relaxed);
+            assert_eq!(
+                Duration::from_millis(SetModuleFunctionality::MAX_TIMEOUT_MS.into()),
+                timeout
             );
             // Effectively ignoring the timeout configured for the command
             // The default response timeout is "sent + timeout"
@@ -228,6 +1269,97 @@ mod tests {
         assert_ne!(0, CALL_COUNT.load(Ordering::Relaxed));
     }
 
+    #[tokio::test]
+    async fn reconfigure() {
+        let (mut client, _tx, _slot) = setup!(Config::new());
+        let config = client.reconfigure(ConfigDelta::new().cmd_cooldown(Duration::from_millis(5)));
+        assert_eq!(
+            config,
+            &Config::new().cmd_cooldown(Duration::from_millis(5))
+        );
+    }
+
+    #[tokio::test]
+    async fn trace_hooks() {
+        static TX: std::sync::Mutex<std::vec::Vec<u8>> =
+            std::sync::Mutex::new(std::vec::Vec::new());
+        static RX: std::sync::Mutex<std::vec::Vec<u8>> =
+            std::sync::Mutex::new(std::vec::Vec::new());
+
+        fn on_rx(bytes: &[u8]) {
+            RX.lock().unwrap().extend_from_slice(bytes);
+        }
+
+        let (mut client, mut tx, slot) =
+            setup!(Config::new()
+                .trace_hooks(|bytes| TX.lock().unwrap().extend_from_slice(bytes), on_rx,));
+        let urc_channel = crate::UrcChannel::<crate::test_ingress::NoUrc, 1, 1>::new();
+        let mut buf = [0; 64];
+        let mut ingress: crate::Ingress<_, crate::test_ingress::NoUrc, TEST_RX_BUF_LEN, 1, 1> =
+            crate::Ingress::new(
+                crate::AtDigester::<crate::test_ingress::NoUrc>::new(),
+                &mut buf,
+                slot,
+                &urc_channel,
+            );
+        ingress.set_rx_hook(Some(on_rx));
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        let device = async {
+            tx.next_message_pure().await;
+            ingress.try_write(b"\r\nOK\r\n").unwrap();
+        };
+        let (res, _) = join!(client.send(&cmd), device);
+        assert_eq!(Ok(NoResponse), res);
+
+        assert_eq!(TX.lock().unwrap().as_slice(), b"AT+CFUN=4,0\r\n");
+        assert_eq!(RX.lock().unwrap().as_slice(), b"\r\nOK\r\n");
+    }
+
+    #[tokio::test]
+    async fn result_code_before_write_completes() {
+        #[derive(Clone, AtatCmd)]
+        #[at_cmd("+CIPSEND", NoResponse, timeout_ms = 100, success_code = "SEND OK")]
+        struct SendData;
+
+        let (mut client, mut tx, slot) =
+            setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+        let urc_channel = crate::UrcChannel::<crate::test_ingress::NoUrc, 1, 1>::new();
+        let mut buf = [0; 64];
+        let mut ingress: crate::Ingress<_, crate::test_ingress::NoUrc, TEST_RX_BUF_LEN, 1, 1> =
+            crate::Ingress::new(
+                crate::AtDigester::<crate::test_ingress::NoUrc>::new(),
+                &mut buf,
+                slot,
+                &urc_channel,
+            );
+
+        // Fill the channel, such that the flush of the next request is held
+        // until the device reads it
+        client.send_raw(b"AT\r\n", None).await.unwrap();
+
+        let device = async {
+            // The request is written, but not flushed
+            let written = async {
+                while slot.state().is_none() {
+                    tokio::task::yield_now().await;
+                }
+            };
+            with_timeout(Duration::from_millis(50), written)
+                .await
+                .unwrap();
+            ingress.try_write(b"\r\nSEND OK\r\n").unwrap();
+            assert_eq!(tx.next_message_pure().await, "AT\r\n");
+            assert_eq!(tx.next_message_pure().await, "AT+CIPSEND\r\n");
+        };
+        let (res, _) = join!(client.send(&SendData), device);
+        assert_eq!(res, Ok(NoResponse));
+    }
+
     #[tokio::test]
     async fn custom_timeout_modified_during_request() {
         static CALL_COUNT: AtomicU64 = AtomicU64::new(0);
@@ -275,4 +1407,1129 @@ mod tests {
 
         assert_ne!(0, CALL_COUNT.load(Ordering::Relaxed));
     }
+
+    #[tokio::test]
+    async fn chunked_write() {
+        struct ChunkWriter<'a>(&'a core::cell::RefCell<heapless::Vec<usize, 8>>);
+
+        impl embedded_io::ErrorType for ChunkWriter<'_> {
+            type Error = crate::tx_mock::IoError;
+        }
+
+        impl Write for ChunkWriter<'_> {
+            async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+                self.0.borrow_mut().push(buf.len()).unwrap();
+                Ok(buf.len())
+            }
+        }
+
+        static RES_SLOT: ResponseSlot<TEST_RX_BUF_LEN> = ResponseSlot::new();
+        let chunks = core::cell::RefCell::new(heapless::Vec::new());
+        let mut buf = [0; 64];
+        let mut client = Client::new(
+            ChunkWriter(&chunks),
+            &RES_SLOT,
+            &mut buf,
+            Config::new()
+                .tx_chunk_size(4)
+                .get_response_timeout(|sent, _| sent),
+        );
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        // "AT+CFUN=4,0\r\n"
+        assert_eq!(Err(Error::Timeout), client.send(&cmd).await);
+        assert_eq!(&chunks.borrow()[..], &[4, 4, 4, 1]);
+    }
+
+    #[tokio::test]
+    async fn progress() {
+        #[derive(Clone, AtatResp)]
+        pub struct FotaStatus {
+            pub percent: u8,
+        }
+
+        #[derive(Clone, AtatUrc)]
+        pub enum FotaProgress {
+            #[at_urc("+UFOTAS")]
+            Status(FotaStatus),
+        }
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+
+        let device = async {
+            tx.next_message_pure().await;
+            for line in [&b"+UFOTAS: 50"[..], b"+UFOTAS: 100"] {
+                assert!(slot.try_signal_progress(line));
+                Timer::after(Duration::from_millis(10)).await;
+            }
+            slot.signal_response(Ok(&[])).unwrap();
+        };
+
+        let mut percents = std::vec::Vec::new();
+        let send = client.send_with_progress::<_, FotaProgress>(&cmd, |p| match p {
+            FotaProgress::Status(s) => percents.push(s.percent),
+        });
+        let (res, _) = join!(send, device);
+
+        assert_eq!(Ok(NoResponse), res);
+        assert_eq!(percents, [50, 100]);
+        // Progress lines are no longer routed once the command completed
+        assert!(!slot.try_signal_progress(b"+UFOTAS: 0"));
+
+        // Nor once the send is dropped before completing
+        let send = client.send_with_progress::<_, FotaProgress>(&cmd, |_| {});
+        assert!(with_timeout(Duration::from_millis(10), send).await.is_err());
+        assert!(!slot.try_signal_progress(b"+UFOTAS: 0"));
+    }
+
+    #[tokio::test]
+    async fn timeout_then_reset_under_load() {
+        #[derive(Clone, AtatCmd)]
+        #[at_cmd("+CSQ", NoResponse, value_sep = false, timeout_ms = 50)]
+        pub struct GetSignalQuality;
+
+        #[derive(Clone, AtatUrc)]
+        pub enum Urc {
+            #[at_urc("+CREG")]
+            Registered,
+        }
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+        let urc_channel = crate::UrcChannel::<Urc, 4, 1>::new();
+        let mut urcs = urc_channel.subscribe().unwrap();
+        let mut buf = [0; 256];
+        let mut ingress: crate::Ingress<_, Urc, TEST_RX_BUF_LEN, 4, 1> = crate::Ingress::new(
+            crate::AtDigester::<Urc>::new(),
+            &mut buf,
+            slot,
+            &urc_channel,
+        );
+
+        let (res, _) = join!(client.send(&GetSignalQuality), tx.next_message_pure());
+        assert_eq!(Err(Error::Timeout), res);
+
+        // The late response is left in the slot, and further responses are
+        // dropped and reported, while the URC's in between are delivered
+        assert_eq!(Ok(15), ingress.try_write(b"\r\n+CREG\r\n\r\nOK\r\n"));
+        assert_eq!(
+            Err(IngressError::ResponseSlotBusy),
+            ingress.try_write(b"\r\n+CREG\r\n\r\nERROR\r\n\r\n+CREG\r\n")
+        );
+        for _ in 0..3 {
+            assert!(matches!(
+                urcs.try_next_message_pure(),
+                Some(Urc::Registered)
+            ));
+        }
+
+        // The next command resets the slot, and gets its own response
+        let device = async {
+            tx.next_message_pure().await;
+            assert_eq!(Ok(6), ingress.try_write(b"\r\nOK\r\n"));
+        };
+        let (res, _) = join!(client.send(&GetSignalQuality), device);
+        assert_eq!(Ok(NoResponse), res);
+    }
+
+    #[tokio::test]
+    async fn send_and_wait_urc() {
+        #[derive(Clone, AtatResp)]
+        pub struct SocketOpened {
+            pub socket: u8,
+            pub err: u16,
+        }
+
+        #[derive(Clone, AtatUrc)]
+        pub enum OpenResult {
+            #[at_urc("+QIOPEN")]
+            Opened(SocketOpened),
+        }
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+        let urc_channel = crate::UrcChannel::<OpenResult, 4, 1>::new();
+        let mut urcs = urc_channel.subscribe().unwrap();
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+
+        let device = async {
+            tx.next_message_pure().await;
+            slot.signal_response(Ok(&[])).unwrap();
+            Timer::after(Duration::from_millis(10)).await;
+            // Results of other sockets are handed back to the URC channel,
+            // and back-to-back URC's are queued
+            assert!(slot.try_signal_progress(b"+QIOPEN: 0,0"));
+            assert!(slot.try_signal_progress(b"+QIOPEN: 1,565"));
+
+            // The result may arrive before the response
+            tx.next_message_pure().await;
+            assert!(slot.try_signal_progress(b"+QIOPEN: 1,0"));
+            Timer::after(Duration::from_millis(10)).await;
+            slot.signal_response(Ok(&[])).unwrap();
+
+            tx.next_message_pure().await;
+            slot.signal_response(Ok(&[])).unwrap();
+        };
+
+        let open = |OpenResult::Opened(o): OpenResult| (o.socket == 1).then_some(o.err);
+        let timeout = Duration::from_millis(50);
+        let send = async {
+            let mut res = std::vec::Vec::new();
+            for _ in 0..3 {
+                res.push(
+                    client
+                        .send_and_wait_urc::<_, OpenResult, _>(&cmd, &urc_channel, open, timeout)
+                        .await,
+                );
+            }
+            res
+        };
+        let (res, _) = join!(send, device);
+
+        assert_eq!(
+            res,
+            [
+                Ok((NoResponse, 565)),
+                Ok((NoResponse, 0)),
+                Err(Error::Timeout)
+            ]
+        );
+        assert!(matches!(
+            urcs.try_next_message_pure(),
+            Some(OpenResult::Opened(SocketOpened { socket: 0, err: 0 }))
+        ));
+        assert!(urcs.try_next_message_pure().is_none());
+        assert!(!slot.try_signal_progress(b"+QIOPEN: 1,0"));
+
+        // Nor routed once the send is dropped before completing
+        let send = client.send_and_wait_urc::<_, OpenResult, _>(&cmd, &urc_channel, open, timeout);
+        assert!(with_timeout(Duration::from_millis(10), send).await.is_err());
+        assert!(!slot.try_signal_progress(b"+QIOPEN: 1,0"));
+    }
+
+    #[tokio::test]
+    async fn validate() {
+        fn check_rsrp(resp: &mut ExtendedSignalQuality) -> Result<(), Error> {
+            match resp.rsrp {
+                // 0 is reported when not known
+                Some(0) => resp.rsrp = None,
+                Some(rsrp) if !(-140..=-44).contains(&rsrp) => return Err(Error::Parse),
+                _ => {}
+            }
+            Ok(())
+        }
+
+        #[derive(Clone, AtatResp, PartialEq, Debug)]
+        #[at_resp(validate = check_rsrp)]
+        pub struct ExtendedSignalQuality {
+            #[at_arg(position = 0)]
+            pub rsrp: Option<i16>,
+        }
+
+        #[derive(Clone, AtatCmd)]
+        #[at_cmd("+CESQ", ExtendedSignalQuality)]
+        pub struct GetExtendedSignalQuality;
+
+        let (mut client, mut tx, slot) =
+            setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+
+        let device = async {
+            for line in [&b"+CESQ: 0"[..], b"+CESQ: -90", b"+CESQ: 5"] {
+                tx.next_message_pure().await;
+                slot.signal_response(Ok(line)).unwrap();
+            }
+        };
+
+        let send = async {
+            [
+                client.send(&GetExtendedSignalQuality).await,
+                client.send(&GetExtendedSignalQuality).await,
+                client.send(&GetExtendedSignalQuality).await,
+            ]
+        };
+        let (res, _) = join!(send, device);
+
+        assert_eq!(
+            res,
+            [
+                Ok(ExtendedSignalQuality { rsrp: None }),
+                Ok(ExtendedSignalQuality { rsrp: Some(-90) }),
+                Err(Error::Parse),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn command_state() {
+        #[derive(Clone, AtatCmd)]
+        #[at_cmd("+CFUN", NoResponse, attempts = 2)]
+        pub struct Retried;
+
+        let (mut client, mut tx, slot) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .get_response_timeout(|sent, _| sent + Duration::from_millis(50)));
+
+        let device = async {
+            // The first attempt times out
+            tx.next_message_pure().await;
+            let state = slot.state().unwrap();
+            assert!(state.name.ends_with("Retried"));
+            assert_eq!(state.attempt, 1);
+
+            tx.next_message_pure().await;
+            assert_eq!(slot.state().unwrap().attempt, 2);
+            Timer::after(Duration::from_millis(10)).await;
+            assert!(slot.state().unwrap().elapsed() >= Duration::from_millis(10));
+            slot.signal_response(Ok(&[])).unwrap();
+        };
+
+        assert_eq!(client.state(), None);
+        let (res, _) = join!(client.send_retry(&Retried), device);
+        assert_eq!(Ok(NoResponse), res);
+        assert_eq!(client.state(), None);
+    }
+
+    #[tokio::test]
+    async fn per_command_cooldown() {
+        let (mut client, mut tx, _slot) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .get_response_timeout(|sent, _| sent));
+
+        let start = Instant::now();
+        assert_eq!(
+            Err(Error::Timeout),
+            client.send(&SetFullFunctionality).await
+        );
+        // Sent from the wire image of the command
+        assert_eq!(tx.next_message_pure().await, "AT+CFUN=1\r\n");
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+        assert_eq!(Err(Error::Timeout), client.send(&cmd).await);
+        tx.next_message_pure().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // The next command falls back to the configured cooldown
+        let start = Instant::now();
+        assert_eq!(Err(Error::Timeout), client.send(&cmd).await);
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn continuation() {
+        struct ListMessages;
+
+        impl AtatCmd for ListMessages {
+            type Response = String<64>;
+            const MAX_LEN: usize = 16;
+            const CONTINUATION: Option<&'static [u8]> = Some(b"\r");
+
+            fn write(&self, buf: &mut [u8]) -> usize {
+                buf[..9].copy_from_slice(b"AT+CMGL\r\n");
+                9
+            }
+
+            fn parse(
+                &self,
+                resp: Result<&[u8], crate::InternalError>,
+            ) -> Result<Self::Response, Error> {
+                let resp = core::str::from_utf8(resp?).map_err(|_| Error::Parse)?;
+                String::try_from(resp).map_err(|_| Error::Parse)
+            }
+        }
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CMGL\r\n");
+            slot.signal_continuation(b"+CMGL: 1").unwrap();
+            assert_eq!(tx.next_message_pure().await, "\r");
+            slot.signal_continuation(b"+CMGL: 2").unwrap();
+            assert_eq!(tx.next_message_pure().await, "\r");
+            slot.signal_response(Ok(b"+CMGL: 3")).unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            assert_eq!(
+                client.send(&ListMessages).await.unwrap(),
+                "+CMGL: 1\r\n+CMGL: 2\r\n+CMGL: 3"
+            );
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn retain_raw_response() {
+        struct ReadNumber;
+
+        impl AtatCmd for ReadNumber {
+            type Response = String<64>;
+            const MAX_LEN: usize = 16;
+
+            fn write(&self, buf: &mut [u8]) -> usize {
+                buf[..9].copy_from_slice(b"AT+CNUM\r\n");
+                9
+            }
+
+            fn parse(
+                &self,
+                resp: Result<&[u8], crate::InternalError>,
+            ) -> Result<Self::Response, Error> {
+                let resp = resp?;
+                if !resp.iter().all(u8::is_ascii_digit) {
+                    return Err(Error::Parse);
+                }
+                let resp = core::str::from_utf8(resp).map_err(|_| Error::Parse)?;
+                String::try_from(resp).map_err(|_| Error::Parse)
+            }
+        }
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CNUM\r\n");
+            slot.signal_response(Ok(b"42")).unwrap();
+            assert_eq!(tx.next_message_pure().await, "AT+CNUM\r\n");
+            slot.signal_response(Ok(b"0x2a")).unwrap();
+            assert_eq!(tx.next_message_pure().await, "AT+CNUM\r\n");
+            slot.signal_response(Err(crate::InternalError::Error))
+                .unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            assert_eq!(client.send_retaining(&ReadNumber).await.unwrap(), "42");
+
+            let failure = client.send_retaining(&ReadNumber).await.unwrap_err();
+            assert_eq!(failure.error(), &Error::Parse);
+            let raw = failure.raw().unwrap();
+            assert_eq!(raw, b"0x2a");
+            assert_eq!(
+                u8::from_str_radix(core::str::from_utf8(&raw[2..]).unwrap(), 16),
+                Ok(42)
+            );
+
+            let failure = client.send_retaining(&ReadNumber).await.unwrap_err();
+            assert_eq!(Error::from(failure.clone()), Error::Error);
+            assert_eq!(failure.raw(), None);
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    struct SendMessage;
+
+    impl AtatCmd for SendMessage {
+        type Response = String<64>;
+        const MAX_LEN: usize = 16;
+
+        fn write(&self, buf: &mut [u8]) -> usize {
+            buf[..14].copy_from_slice(b"AT+CMGS=\"123\"\r");
+            14
+        }
+
+        fn parse(
+            &self,
+            resp: Result<&[u8], crate::InternalError>,
+        ) -> Result<Self::Response, Error> {
+            let resp = core::str::from_utf8(resp?).map_err(|_| Error::Parse)?;
+            String::try_from(resp).map_err(|_| Error::Parse)
+        }
+    }
+
+    #[tokio::test]
+    async fn partial_write() {
+        let (mut client, mut tx, slot) = setup!(
+            Config::new().write_recovery(Some(b"\r")),
+            |tx_mock: TxMock<'static>| tx_mock.fail_after(5)
+        );
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        let sent = tokio::spawn(async move {
+            // The partial command line is terminated
+            assert_eq!(tx.next_message_pure().await, "AT+CF\r");
+            slot.signal_response(Err(InternalError::Error)).unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            assert_eq!(client.send(&cmd).await, Err(Error::PartialWrite(5)));
+            assert!(slot.try_get().is_none());
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn partial_write_retry() {
+        let (mut client, mut tx, slot) = setup!(
+            Config::new()
+                .write_recovery(Some(b"\r"))
+                .retry_partial_write(true),
+            |tx_mock: TxMock<'static>| tx_mock.fail_after(5)
+        );
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CF\r");
+            slot.signal_response(Err(InternalError::Error)).unwrap();
+            assert_eq!(tx.next_message_pure().await, "AT+CFUN=4,0\r\n");
+            slot.signal_response(Ok(&[])).unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            assert_eq!(client.send(&cmd).await, Ok(NoResponse));
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resync() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "\r\nAT\r\n");
+            // The response to a command sent before the hot-plug is discarded
+            slot.signal_response(Ok(b"+CSQ: 12,99")).unwrap();
+            while slot.try_get().is_some() {
+                Timer::after_millis(1).await;
+            }
+            slot.signal_response(Ok(&[])).unwrap();
+        });
+
+        let resync = tokio::spawn(async move {
+            assert_eq!(client.resync().await, Ok(()));
+            assert!(client.res_slot.take_clear_request());
+            assert_eq!(client.state(), None);
+        });
+
+        let (sent, resync) = join!(sent, resync);
+        sent.unwrap();
+        resync.unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_no_response() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::DM,
+            rst: Some(ResetMode::Reset),
+        };
+        assert_eq!(client.send_no_response(&cmd).await, Ok(()));
+        assert_eq!(tx.next_message_pure().await, "AT+CFUN=6,1\r\n");
+        assert_eq!(client.state(), None);
+        assert!(slot.take_swallow());
+    }
+
+    #[tokio::test]
+    async fn prompt_payload() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CMGS=\"123\"\r");
+            slot.signal_prompt(b'>').unwrap();
+            assert_eq!(tx.next_message_pure().await, "Hello world\x1a");
+            slot.signal_response(Ok(b"+CMGS: 7")).unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            let mut progress = heapless::Vec::<usize, 4>::new();
+            let options = PayloadOptions::new().chunk_size(4);
+            let res = client
+                .send_prompt_payload(&SendMessage, b"Hello world", options, |n| {
+                    progress.push(n).unwrap();
+                    ControlFlow::Continue(())
+                })
+                .await;
+            assert_eq!(res.unwrap(), "+CMGS: 7");
+            assert_eq!(progress, [4, 8, 11]);
+            assert_eq!(client.state(), None);
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn prompt_payload_cancel() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CMGS=\"123\"\r");
+            slot.signal_prompt(b'>').unwrap();
+            assert_eq!(tx.next_message_pure().await, "Hell\x1b");
+            Timer::after(Duration::from_millis(10)).await;
+            slot.signal_response(Err(crate::InternalError::Error))
+                .unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            let options = PayloadOptions::new().chunk_size(4);
+            let res = client
+                .send_prompt_payload(&SendMessage, b"Hello world", options, |_| {
+                    ControlFlow::Break(())
+                })
+                .await;
+            assert_eq!(res, Err(Error::Aborted));
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+        // The result code of the cancelled command was awaited
+        assert!(slot.try_get().is_none());
+    }
+
+    #[tokio::test]
+    async fn prompt_payload_timeout() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            // The device stops reading while the payload is written
+            Timer::after(Duration::from_millis(10)).await;
+            slot.signal_prompt(b'>').unwrap();
+            Timer::after(Duration::from_millis(50)).await;
+            assert_eq!(tx.next_message_pure().await, "AT+CMGS=\"123\"\r");
+            assert_eq!(tx.next_message_pure().await, "Hello\x1a\x1b");
+            slot.signal_response(Err(crate::InternalError::Error))
+                .unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            let options = PayloadOptions::new().timeout(Duration::from_millis(20));
+            let res = client
+                .send_prompt_payload(&SendMessage, b"Hello", options, |_| {
+                    ControlFlow::Continue(())
+                })
+                .await;
+            assert_eq!(res, Err(Error::Timeout));
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+        assert!(slot.try_get().is_none());
+    }
+
+    #[tokio::test]
+    async fn prompt_payload_rejected() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CMGS=\"123\"\r");
+            slot.signal_response(Err(crate::InternalError::Error))
+                .unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            let res = client
+                .send_prompt_payload(&SendMessage, b"Hello", PayloadOptions::new(), |_| {
+                    ControlFlow::Continue(())
+                })
+                .await;
+            assert_eq!(res, Err(Error::Error));
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn prompt_then_response() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CMGS=\"123\"\r");
+            slot.signal_continuation(b"Enter passphrase").unwrap();
+            assert_eq!(tx.next_message_pure().await, "secret\r");
+            slot.signal_response(Ok(b"+CMGS: 7")).unwrap();
+
+            assert_eq!(tx.next_message_pure().await, "AT+CMGS=\"123\"\r");
+            slot.signal_continuation(b"Enter passphrase").unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            let res = client
+                .send_expect_prompt_then_response(&SendMessage, |line, buf| {
+                    assert_eq!(line, b"Enter passphrase");
+                    buf[..7].copy_from_slice(b"secret\r");
+                    7
+                })
+                .await;
+            assert_eq!(res.unwrap(), "+CMGS: 7");
+            assert_eq!(client.state(), None);
+
+            // A follow-up exceeding the request buffer is refused
+            let res = client
+                .send_expect_prompt_then_response(&SendMessage, |_, buf| buf.len() + 1)
+                .await;
+            assert_eq!(res, Err(Error::RequestTooLarge));
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn init() {
+        let (mut client, mut tx, slot) = setup!(Config::new().line_termination(b'\r', b'\n'));
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT\r\n");
+            slot.signal_response(Err(crate::InternalError::Error))
+                .unwrap();
+            for expected in [
+                "AT\r\n",
+                "ATE0\r\n",
+                "AT+CMEE=1\r\n",
+                "ATS4=10\r\n",
+                "ATS3=13\r\n",
+            ] {
+                assert_eq!(tx.next_message_pure().await, expected);
+                slot.signal_response(Ok(&[])).unwrap();
+            }
+        });
+
+        let send = tokio::spawn(async move {
+            assert_eq!(client.init().await, Ok(()));
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resp_prefix_mismatch() {
+        #[derive(Clone, AtatResp, PartialEq, Debug)]
+        struct SignalQuality {
+            rssi: u8,
+            ber: u8,
+        }
+
+        #[derive(Clone, AtatCmd)]
+        #[at_cmd("+CSQ", SignalQuality, resp_prefix = "+CSQ:")]
+        struct GetSignalQuality;
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            tx.next_message_pure().await;
+            // Late response to a previous command
+            slot.signal_response(Ok(b"+CREG: 1")).unwrap();
+            tx.next_message_pure().await;
+            slot.signal_response(Ok(b"+CSQ: 19,99")).unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            assert_eq!(client.send(&GetSignalQuality).await, Err(Error::Desync));
+            assert_eq!(
+                client.send(&GetSignalQuality).await,
+                Ok(SignalQuality { rssi: 19, ber: 99 })
+            );
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pipeline() {
+        #[derive(Clone, AtatResp, PartialEq, Debug)]
+        struct SignalQuality {
+            rssi: u8,
+            ber: u8,
+        }
+
+        #[derive(Clone, AtatCmd)]
+        #[at_cmd("+CSQ", SignalQuality)]
+        struct GetSignalQuality;
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            for _ in 0..3 {
+                tx.next_message_pure().await;
+            }
+            assert_eq!(slot.pipelined(), 3);
+            // The responses arrive before the client awaits any of them
+            slot.signal_response_in_order(Ok(&[])).unwrap();
+            slot.signal_response_in_order(Ok(b"+CSQ: 19,99")).unwrap();
+            slot.signal_response_in_order(Err(InternalError::Error))
+                .unwrap();
+        });
+
+        let send = tokio::spawn(async move {
+            let cmd = SetModuleFunctionality {
+                fun: Functionality::Full,
+                rst: None,
+            };
+            let mut pipeline = client.pipeline();
+            pipeline.write(&cmd).await.unwrap();
+            pipeline.write(&GetSignalQuality).await.unwrap();
+            pipeline.write(&GetSignalQuality).await.unwrap();
+
+            assert_eq!(pipeline.read(&cmd).await, Ok(NoResponse));
+            assert_eq!(
+                pipeline.read(&GetSignalQuality).await,
+                Ok(SignalQuality { rssi: 19, ber: 99 })
+            );
+            assert_eq!(pipeline.read(&GetSignalQuality).await, Err(Error::Error));
+            assert_eq!(pipeline.in_flight(), 0);
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pipeline_back_pressure() {
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let mut pipeline = client.pipeline();
+        for _ in 0..PIPELINE_DEPTH {
+            pipeline.write(&ScanOperators).await.unwrap();
+            tx.next_message_pure().await;
+            assert!(pipeline.can_write());
+        }
+        pipeline.write(&ScanOperators).await.unwrap();
+        tx.next_message_pure().await;
+        assert!(!pipeline.can_write());
+
+        for _ in 0..=PIPELINE_DEPTH {
+            slot.signal_response_in_order(Ok(&[])).unwrap();
+        }
+        assert_eq!(pipeline.pending_frames(), PIPELINE_DEPTH + 1);
+
+        pipeline.read(&ScanOperators).await.unwrap();
+        assert_eq!(pipeline.pending_frames(), PIPELINE_DEPTH);
+        assert!(pipeline.can_write());
+    }
+
+    #[tokio::test]
+    async fn pipeline_routed() {
+        #[derive(Clone, PartialEq, Debug)]
+        struct Position(u8);
+
+        impl atat::AtatResp for Position {}
+
+        struct GetPosition(u8);
+
+        impl atat::AtatCmd for GetPosition {
+            type Response = Position;
+            const MAX_LEN: usize = 16;
+            const ID_EXTRACTOR: Option<crate::router::IdExtractor> = Some(|resp| {
+                resp.strip_prefix(b"$POS:")?
+                    .first()
+                    .map(|b| (b - b'0').into())
+            });
+
+            fn sequence_id(&self) -> Option<u32> {
+                Some(self.0.into())
+            }
+
+            fn write(&self, buf: &mut [u8]) -> usize {
+                buf[..9].copy_from_slice(b"AT$POS=0\r");
+                buf[7] += self.0;
+                9
+            }
+
+            fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Position, Error> {
+                Ok(Position(resp?[5] - b'0'))
+            }
+        }
+
+        let (mut client, mut tx, slot) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            for _ in 0..3 {
+                tx.next_message_pure().await;
+            }
+            for resp in [&b"$POS:3"[..], b"$POS:1", b"$POS:2"] {
+                slot.signal_response_in_order(Ok(resp)).unwrap();
+            }
+        });
+
+        let send = tokio::spawn(async move {
+            let mut router = crate::router::ResponseRouter::<2, 8>::new();
+            let mut pipeline = client.pipeline();
+            for seq in 1..=3 {
+                pipeline.write(&GetPosition(seq)).await.unwrap();
+            }
+
+            assert_eq!(
+                pipeline.read_routed(&GetPosition(2), &mut router).await,
+                Ok(Position(2))
+            );
+            assert_eq!(router.len(), 2);
+            assert_eq!(
+                pipeline.read_routed(&GetPosition(3), &mut router).await,
+                Ok(Position(3))
+            );
+            assert_eq!(
+                pipeline.read_routed(&GetPosition(1), &mut router).await,
+                Ok(Position(1))
+            );
+            assert!(router.is_empty());
+            assert_eq!(pipeline.in_flight(), 0);
+        });
+
+        let (sent, send) = join!(sent, send);
+        sent.unwrap();
+        send.unwrap();
+    }
+
+    #[tokio::test]
+    async fn retry_transient_errors() {
+        let (mut client, mut tx, slot) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .retry(3, 20)
+            .get_response_timeout(|sent, _| sent + Duration::from_millis(50)));
+
+        let device = async {
+            // The first attempt times out
+            tx.next_message_pure().await;
+            let start = Instant::now();
+            tx.next_message_pure().await;
+            assert!(start.elapsed() >= Duration::from_millis(20));
+            slot.signal_response(Err(InternalError::CmeError(atat::CmeError::SimBusy)))
+                .unwrap();
+            let start = Instant::now();
+            tx.next_message_pure().await;
+            assert!(start.elapsed() >= Duration::from_millis(40));
+            slot.signal_response(Ok(&[])).unwrap();
+
+            // Other errors are not retried
+            tx.next_message_pure().await;
+            slot.signal_response(Err(InternalError::CmeError(atat::CmeError::SimPin)))
+                .unwrap();
+        };
+
+        let send = async {
+            [
+                client.send(&SetFullFunctionality).await,
+                client.send(&SetFullFunctionality).await,
+            ]
+        };
+        let (res, _) = join!(send, device);
+        assert_eq!(
+            res,
+            [Ok(NoResponse), Err(Error::CmeError(atat::CmeError::SimPin))]
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_within_deadline() {
+        let (mut client, mut tx, _slot) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .retry(3, 50)
+            .get_response_timeout(|sent, _| sent + Duration::from_millis(20)));
+
+        // The backoff would end after the deadline, so the first timeout is
+        // not retried
+        let start = Instant::now();
+        let send =
+            client.send_with_deadline(&SetFullFunctionality, start + Duration::from_millis(60));
+        let (res, _) = join!(send, tx.next_message_pure());
+        assert_eq!(res, Err(Error::DeadlineExceeded));
+        assert!(start.elapsed() < Duration::from_millis(60));
+        assert!(tx.try_next_message_pure().is_none());
+    }
+
+    #[tokio::test]
+    async fn deadline_bounds_response_wait() {
+        let (mut client, mut tx, _slot) =
+            setup!(Config::new().cmd_cooldown(Duration::from_millis(100)));
+
+        // The command is written in full, and the wait for its response cut
+        // short by the deadline
+        let start = Instant::now();
+        let send =
+            client.send_with_deadline(&SetFullFunctionality, start + Duration::from_millis(30));
+        let (res, written) = join!(send, tx.next_message_pure());
+        assert_eq!(res, Err(Error::DeadlineExceeded));
+        assert_eq!(written, "AT+CFUN=1\r\n");
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        // The cooldown would end after the deadline
+        let send = client.send_with_deadline(
+            &SetFullFunctionality,
+            Instant::now() + Duration::from_millis(30),
+        );
+        assert_eq!(send.await, Err(Error::DeadlineExceeded));
+        assert!(tx.try_next_message_pure().is_none());
+    }
+
+    #[tokio::test]
+    async fn deadline_through_mut_ref() {
+        async fn send<C: AtatClient>(
+            mut client: C,
+            deadline: Instant,
+        ) -> Result<NoResponse, Error> {
+            client
+                .send_with_deadline(&SetFullFunctionality, deadline)
+                .await
+        }
+
+        let (mut client, mut tx, _slot) = setup!(Config::new());
+
+        // A driver generic over the client, handed a reference to it
+        let start = Instant::now();
+        let send = send(&mut client, start + Duration::from_millis(30));
+        let (res, written) = join!(send, tx.next_message_pure());
+        assert_eq!(res, Err(Error::DeadlineExceeded));
+        assert_eq!(written, "AT+CFUN=1\r\n");
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn abort_on_timeout() {
+        let (mut client, mut tx, slot) =
+            setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+
+        let device = async {
+            assert_eq!("AT+COPS=?\r\n", tx.next_message_pure().await);
+            assert_eq!("\x1b", tx.next_message_pure().await);
+            // The final result code of the aborted command
+            slot.signal_response(Err(InternalError::Error)).unwrap();
+            assert_eq!("AT+CFUN=1\r\n", tx.next_message_pure().await);
+            slot.signal_response(Ok(&[])).unwrap();
+        };
+
+        let send = async {
+            [
+                client.send(&ScanOperators).await,
+                client.send(&SetFullFunctionality).await,
+            ]
+        };
+        let (res, _) = join!(send, device);
+        assert_eq!(res, [Err(Error::Timeout), Ok(NoResponse)]);
+    }
+
+    #[tokio::test]
+    async fn send_abortable() {
+        let (mut client, mut tx, slot) =
+            setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+
+        let device = async {
+            assert_eq!("AT+COPS=?\r\n", tx.next_message_pure().await);
+            assert_eq!("\x1b", tx.next_message_pure().await);
+            // The final result code of the aborted command
+            slot.signal_response(Err(InternalError::Error)).unwrap();
+            assert_eq!("AT+CFUN=1\r\n", tx.next_message_pure().await);
+            slot.signal_response(Ok(&[])).unwrap();
+        };
+
+        let send = async {
+            let abort = Timer::after(Duration::from_millis(10));
+            let aborted = client.send_abortable(&ScanOperators, abort, false).await;
+            let refused = client
+                .send_abortable(&SetFullFunctionality, pending(), false)
+                .await;
+            let sent = client
+                .send_abortable(&SetFullFunctionality, pending(), true)
+                .await;
+            [aborted.map(drop), refused.map(drop), sent.map(drop)]
+        };
+        let (res, _) = join!(send, device);
+        assert_eq!(res, [Err(Error::Aborted), Err(Error::NotAbortable), Ok(())]);
+    }
+
+    #[tokio::test]
+    async fn send_raw() {
+        let (mut client, mut tx, slot) =
+            setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+
+        let device = async {
+            assert_eq!("hello", tx.next_message_pure().await);
+            assert_eq!("world", tx.next_message_pure().await);
+            slot.signal_response(Ok(b"+USOWR: 0,5")).unwrap();
+        };
+
+        let send = async {
+            assert_eq!(client.send_raw(b"hello", None).await, Ok(&b""[..]));
+            assert_eq!(
+                client
+                    .send_raw(b"world", Some(Duration::from_millis(50)))
+                    .await,
+                Ok(&b"+USOWR: 0,5"[..])
+            );
+        };
+        join!(send, device);
+    }
+
+    #[tokio::test]
+    async fn wait_ready() {
+        let (mut client, mut tx, slot) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .wait_ready(Duration::from_millis(50)));
+
+        let device = async {
+            // The first command is held back until the boot banner
+            Timer::after(Duration::from_millis(20)).await;
+            slot.signal_booted();
+            assert_eq!("AT+CFUN=1\r\n", tx.next_message_pure().await);
+            slot.signal_response(Ok(&[])).unwrap();
+
+            // Without a banner after a restart, the device is probed
+            assert_eq!("AT+CFUN=1\r\n", tx.next_message_pure().await);
+            slot.signal_response(Err(InternalError::ModemRestarted))
+                .unwrap();
+            assert_eq!("\r\nAT\r\n", tx.next_message_pure().await);
+            slot.signal_response(Ok(&[])).unwrap();
+            assert_eq!("AT+CFUN=1\r\n", tx.next_message_pure().await);
+            slot.signal_response(Ok(&[])).unwrap();
+        };
+
+        let send = async {
+            let start = Instant::now();
+            let first = client.send(&SetFullFunctionality).await;
+            assert!(start.elapsed() >= Duration::from_millis(20));
+            [
+                first,
+                client.send(&SetFullFunctionality).await,
+                client.send(&SetFullFunctionality).await,
+            ]
+        };
+        let (res, _) = join!(send, device);
+        assert_eq!(
+            res,
+            [Ok(NoResponse), Err(Error::ModemRestarted), Ok(NoResponse)]
+        );
+    }
 }
@@ -1,9 +1,18 @@
 mod client;
+mod payload;
+mod shared_client;
 mod simple_client;
+mod write;
 
-pub use client::Client;
+pub use client::{Client, ParseFailure, Pipeline};
+pub use payload::{PayloadOptions, CTRL_Z, ESC};
+pub use shared_client::SharedClient;
 pub use simple_client::SimpleClient;
 
+use core::{future::poll_fn, task::Poll};
+
+use embassy_time::Instant;
+
 use crate::{AtatCmd, Error};
 
 pub trait AtatClient {
@@ -13,14 +22,21 @@ pub trait AtatClient {
     ///
     /// This function will also make sure that at least `self.config.cmd_cooldown`
     /// has passed since the last response or URC has been received, to allow
-    /// the slave AT device time to deliver URC's.
+    /// the slave AT device time to deliver URC's. Commands that need a longer
+    /// quiet time afterwards can override this with [`AtatCmd::COOLDOWN_MS`].
     async fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error>;
 
+    /// Called by [`AtatClient::send_retry`] before each attempt, such that
+    /// the client can report the attempt of the command in flight. Does
+    /// nothing by default.
+    fn set_attempt(&mut self, _attempt: u8) {}
+
     async fn send_retry<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
         for attempt in 1..=Cmd::ATTEMPTS {
             if attempt > 1 {
                 debug!("Attempt {}:", attempt);
             }
+            self.set_attempt(attempt);
 
             match self.send(cmd).await {
                 Err(Error::Timeout) => {}
@@ -34,6 +50,27 @@ pub trait AtatClient {
         }
         Err(Error::Timeout)
     }
+
+    /// Send an AT command like [`AtatClient::send_retry`], but give up with
+    /// [`Error::DeadlineExceeded`] once `deadline` has passed. The deadline
+    /// caps the total time spent on the call, including all attempts and
+    /// cooldowns. A command is never cancelled while it is being written.
+    ///
+    /// The default implementation only checks the deadline before sending,
+    /// and reports a timeout past the deadline as exceeded.
+    async fn send_with_deadline<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        deadline: Instant,
+    ) -> Result<Cmd::Response, Error> {
+        if Instant::now() >= deadline {
+            return Err(Error::DeadlineExceeded);
+        }
+        match self.send_retry(cmd).await {
+            Err(Error::Timeout) if Instant::now() >= deadline => Err(Error::DeadlineExceeded),
+            res => res,
+        }
+    }
 }
 
 impl<T> AtatClient for &mut T
@@ -43,4 +80,31 @@ where
     async fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
         T::send(self, cmd).await
     }
+
+    fn set_attempt(&mut self, attempt: u8) {
+        T::set_attempt(self, attempt);
+    }
+
+    async fn send_with_deadline<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        deadline: Instant,
+    ) -> Result<Cmd::Response, Error> {
+        T::send_with_deadline(self, cmd, deadline).await
+    }
+}
+
+/// Yield once to the executor, allowing other tasks to run.
+pub(crate) async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
 }
@@ -0,0 +1,90 @@
+use embassy_time::Duration;
+
+/// Ctrl-Z, terminating the payload of eg. `AT+CMGS`
+pub const CTRL_Z: u8 = 0x1a;
+
+/// ESC, cancelling the payload of eg. `AT+CMGS`
+pub const ESC: u8 = 0x1b;
+
+/// Options of a payload sent after a prompt, see
+/// [`Client::send_prompt_payload`].
+///
+/// [`Client::send_prompt_payload`]: super::Client::send_prompt_payload
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PayloadOptions {
+    pub(crate) terminator: Option<u8>,
+    pub(crate) cancel: u8,
+    pub(crate) chunk_size: usize,
+    pub(crate) timeout: Duration,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PayloadOptions {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "PayloadOptions {{ terminator: {}, cancel: {=u8}, chunk_size: {=usize}, timeout: {=u64}ms }}",
+            self.terminator,
+            self.cancel,
+            self.chunk_size,
+            self.timeout.as_millis(),
+        )
+    }
+}
+
+impl Default for PayloadOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PayloadOptions {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            terminator: Some(CTRL_Z),
+            cancel: ESC,
+            chunk_size: 64,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Byte sent after the payload, or `None` for commands stating the
+    /// payload length, eg. `AT+USOST`.
+    ///
+    /// **default**: [`CTRL_Z`]
+    #[must_use]
+    pub const fn terminator(mut self, terminator: Option<u8>) -> Self {
+        self.terminator = terminator;
+        self
+    }
+
+    /// Byte sent instead of the terminator when the transfer is cancelled.
+    ///
+    /// **default**: [`ESC`]
+    #[must_use]
+    pub const fn cancel(mut self, cancel: u8) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Write the payload in chunks of at most `size` bytes, reporting the
+    /// progress after each.
+    ///
+    /// **default**: 64
+    #[must_use]
+    pub const fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size;
+        self
+    }
+
+    /// Time budget for transferring the payload and receiving the final
+    /// response, after the prompt.
+    ///
+    /// **default**: 10 seconds
+    #[must_use]
+    pub const fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = duration;
+        self
+    }
+}
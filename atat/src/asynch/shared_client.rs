@@ -0,0 +1,122 @@
+use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
+use embassy_time::Instant;
+
+use super::AtatClient;
+use crate::{AtatCmd, Error};
+
+/// A client shared by several drivers, eg. a cellular driver and a GNSS
+/// driver talking to the same modem.
+///
+/// `&SharedClient` implements [`AtatClient`] by locking the inner client for
+/// each command, so every driver holds a reference of its own, and commands
+/// of different drivers never interleave. Use a
+/// [`NoopRawMutex`](embassy_sync::blocking_mutex::raw::NoopRawMutex) when all
+/// drivers run on the same executor.
+///
+/// ```
+/// use atat::asynch::{AtatClient, SharedClient};
+/// use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+///
+/// async fn cellular(mut client: impl AtatClient) {}
+/// async fn gnss(mut client: impl AtatClient) {}
+///
+/// async fn run(client: impl AtatClient) {
+///     let shared = SharedClient::<NoopRawMutex, _>::new(client);
+///     futures::future::join(cellular(&shared), gnss(&shared)).await;
+/// }
+/// ```
+pub struct SharedClient<M: RawMutex, C> {
+    client: Mutex<M, C>,
+}
+
+impl<M: RawMutex, C> SharedClient<M, C> {
+    pub const fn new(client: C) -> Self {
+        Self {
+            client: Mutex::new(client),
+        }
+    }
+
+    /// Lock the inner client, eg. to send a sequence of commands no other
+    /// driver may interleave with.
+    pub async fn lock(&self) -> embassy_sync::mutex::MutexGuard<'_, M, C> {
+        self.client.lock().await
+    }
+
+    /// Take back the inner client.
+    pub fn into_inner(self) -> C {
+        self.client.into_inner()
+    }
+}
+
+impl<M: RawMutex, C: AtatClient> AtatClient for &SharedClient<M, C> {
+    async fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        self.client.lock().await.send(cmd).await
+    }
+
+    // The lock is held across all attempts
+    async fn send_retry<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        self.client.lock().await.send_retry(cmd).await
+    }
+
+    async fn send_with_deadline<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        deadline: Instant,
+    ) -> Result<Cmd::Response, Error> {
+        self.client
+            .lock()
+            .await
+            .send_with_deadline(cmd, deadline)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+    use super::*;
+    use crate::{self as atat, asynch::yield_now, atat_derive::AtatCmd, atat_derive::AtatResp};
+
+    #[derive(Clone, AtatResp)]
+    struct NoResponse;
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CFUN?", NoResponse, attempts = 2)]
+    struct GetFunctionality;
+
+    /// Records the driver of each command, yielding while sending
+    #[derive(Default)]
+    struct RecordingClient {
+        busy: bool,
+        sent: usize,
+    }
+
+    impl AtatClient for RecordingClient {
+        async fn send<Cmd: AtatCmd>(&mut self, _cmd: &Cmd) -> Result<Cmd::Response, Error> {
+            assert!(!self.busy, "commands interleaved");
+            self.busy = true;
+            yield_now().await;
+            self.busy = false;
+            self.sent += 1;
+            Err(Error::Timeout)
+        }
+    }
+
+    #[tokio::test]
+    async fn commands_do_not_interleave() {
+        let shared = SharedClient::<NoopRawMutex, _>::new(RecordingClient::default());
+
+        async fn driver(mut client: impl AtatClient) {
+            for _ in 0..3 {
+                assert!(matches!(
+                    client.send_retry(&GetFunctionality).await,
+                    Err(Error::Timeout)
+                ));
+            }
+        }
+
+        tokio::join!(driver(&shared), driver(&shared));
+        assert_eq!(shared.lock().await.sent, 2 * 3 * 2);
+    }
+}
@@ -1,5 +1,13 @@
-use super::AtatClient;
-use crate::{helpers::LossyStr, AtatCmd, Config, DigestResult, Digester, Error, Response};
+use super::{
+    write::{write_request, write_with_recovery, RequestLink},
+    AtatClient,
+};
+use crate::{
+    helpers::{LossyStr, Traced},
+    init::{Handshake, Resync},
+    response::{anchor_response, parse_validated},
+    AtatCmd, Config, ConfigDelta, DigestResult, Digester, Error, Response, TraceLevel,
+};
 use embassy_time::{with_timeout, Duration, Timer};
 use embedded_io_async::{Read, Write};
 
@@ -8,6 +16,9 @@ pub struct SimpleClient<'a, RW: Read + Write, D: Digester> {
     digester: D,
     buf: &'a mut [u8],
     pos: usize,
+    /// Length of the pages of a paginated response, collected at the end of
+    /// the buffer.
+    paged: usize,
     config: Config,
     cooldown_timer: Option<Timer>,
 }
@@ -20,44 +31,140 @@ impl<'a, RW: Read + Write, D: Digester> SimpleClient<'a, RW, D> {
             buf,
             config,
             pos: 0,
+            paged: 0,
             cooldown_timer: None,
         }
     }
 
-    async fn send_request(&mut self, len: usize) -> Result<(), Error> {
-        if len < 50 {
-            debug!("Sending command: {:?}", LossyStr(&self.buf[..len]));
-        } else {
-            debug!("Sending command with long payload ({} bytes)", len);
+    /// The current configuration of the client.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Reconfigure the client. As the client is borrowed mutably, no command
+    /// can be in flight, and the change is in effect from the next command
+    /// sent. Returns the resulting configuration.
+    pub fn reconfigure(&mut self, delta: ConfigDelta) -> &Config {
+        debug!("Reconfiguring client");
+        self.config.apply(delta);
+        &self.config
+    }
+
+    /// Set the amount of detail logged about the commands sent, eg. to
+    /// enable deep tracing temporarily from a debug channel. See
+    /// [`Config::trace`].
+    pub fn set_trace(&mut self, level: TraceLevel) {
+        self.config.trace = level;
+    }
+
+    /// Perform the initialization handshake: send `AT` until the device
+    /// responds, disable echo, enable `+CME ERROR` result codes, and apply
+    /// the [`Config::command_termination`] and
+    /// [`Config::response_formatting`] if set.
+    ///
+    /// Each step is attempted up to [`Config::init_attempts`] times.
+    pub async fn init(&mut self) -> Result<(), Error> {
+        let mut handshake = Handshake::new(&self.config);
+        while let Some(step) = handshake.step() {
+            let res = self.send(&step).await;
+            handshake.record(res)?;
         }
+        Ok(())
+    }
 
+    /// Resynchronize with the device, eg. after the serial cable or USB
+    /// adapter was hot-plugged, leaving half a frame on either side.
+    ///
+    /// Everything received so far is discarded, the
+    /// [`Config::attention_sequence`] is written, and every response is
+    /// discarded until a clean `OK`. The cooldown of the client is reset.
+    /// The sequence is written up to [`Config::init_attempts`] times.
+    pub async fn resync(&mut self) -> Result<(), Error> {
+        debug!("Resynchronizing, discarding {} bytes", self.pos);
+        self.cooldown_timer = None;
+        self.pos = 0;
+        self.digester.reset();
+
+        let sequence = self.config.attention_sequence;
+        let mut resync = Resync::new(&self.config);
+        while resync.next_attempt().is_some() {
+            Resync::written(write_request(&mut self.rw, &self.config, sequence).await)?;
+            loop {
+                match with_timeout(Resync::TIMEOUT, self.wait_response()).await {
+                    Ok(Ok(response)) if Resync::is_clean(&response) => return Ok(()),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => return Err(e),
+                    Err(_) => break,
+                }
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    async fn send_request(
+        &mut self,
+        wire_image: Option<&'static [u8]>,
+        len: usize,
+        cooldown: Duration,
+    ) -> Result<(), Error> {
         self.wait_cooldown_timer().await;
 
+        let request = match wire_image {
+            Some(request) => request,
+            None => &self.buf[..len],
+        };
+        if request.len() < 50 || self.config.trace == TraceLevel::Bytes {
+            debug!("Sending command: {:?}", Traced(self.config.trace, request));
+        } else {
+            debug!(
+                "Sending command with long payload ({} bytes)",
+                request.len()
+            );
+        }
+
         // Write request
-        with_timeout(self.config.tx_timeout, self.rw.write_all(&self.buf[..len]))
+        write_with_recovery(self, wire_image, len).await?;
+
+        self.start_cooldown_timer(cooldown);
+        Ok(())
+    }
+
+    async fn send_continuation(&mut self, token: &[u8]) -> Result<(), Error> {
+        debug!("Sending continuation: {:?}", LossyStr(token));
+
+        with_timeout(self.config.tx_timeout, self.rw.write_all(token))
             .await
             .map_err(|_| Error::Timeout)?
             .map_err(|_| Error::Write)?;
-
+        self.config.trace_tx(token);
         with_timeout(self.config.flush_timeout, self.rw.flush())
             .await
             .map_err(|_| Error::Timeout)?
-            .map_err(|_| Error::Write)?;
-
-        self.start_cooldown_timer();
-        Ok(())
+            .map_err(|_| Error::Write)
     }
 
     async fn wait_response<'guard>(&'guard mut self) -> Result<Response<256>, Error> {
         loop {
-            match self.rw.read(&mut self.buf[self.pos..]).await {
+            let limit = self.buf.len() - self.paged;
+            if self.pos == limit {
+                error!("Buffer full while waiting for a response, clearing");
+                self.pos = 0;
+                self.digester.reset();
+                return Err(Error::ResponseTooLarge);
+            }
+
+            match self.rw.read(&mut self.buf[self.pos..limit]).await {
                 Ok(n) => {
+                    self.config.trace_rx(&self.buf[self.pos..self.pos + n]);
                     self.pos += n;
+                    self.pos = self.digester.normalize(self.buf, self.pos, n);
                 }
                 _ => return Err(Error::Read),
             };
 
-            trace!("Buffer contents: '{:?}'", LossyStr(&self.buf[..self.pos]));
+            if self.config.trace == TraceLevel::Bytes {
+                trace!("Buffer contents: '{:?}'", LossyStr(&self.buf[..self.pos]));
+            }
 
             while self.pos > 0 {
                 let (res, swallowed) = match self.digester.digest(&self.buf[..self.pos]) {
@@ -67,11 +174,21 @@ impl<'a, RW: Read + Write, D: Digester> SimpleClient<'a, RW, D> {
                                 "Received echo or whitespace ({}/{}): {:?}",
                                 swallowed,
                                 self.pos,
-                                LossyStr(&self.buf[..swallowed])
+                                Traced(self.config.trace, &self.buf[..swallowed])
                             );
                         }
                         (None, swallowed)
                     }
+                    (DigestResult::Continuation(page), swallowed) => {
+                        debug!(
+                            "Received page of response ({}/{}): {:?}",
+                            swallowed,
+                            self.pos,
+                            Traced(self.config.trace, page)
+                        );
+
+                        (Some(Response::continuation(page)), swallowed)
+                    }
                     (DigestResult::Urc(urc_line), swallowed) => {
                         warn!("Unable to handle URC! Ignoring: {:?}", LossyStr(urc_line));
                         (None, swallowed)
@@ -81,6 +198,9 @@ impl<'a, RW: Read + Write, D: Digester> SimpleClient<'a, RW, D> {
 
                         (Some(Response::Prompt(prompt)), swallowed)
                     }
+                    (DigestResult::ModemRestarted, swallowed) => {
+                        (Some(Response::ModemRestartedError), swallowed)
+                    }
                     (DigestResult::Response(resp), swallowed) => {
                         match &resp {
                             Ok(r) => {
@@ -91,7 +211,7 @@ impl<'a, RW: Read + Write, D: Digester> SimpleClient<'a, RW, D> {
                                         "Received response ({}/{}): {:?}",
                                         swallowed,
                                         self.pos,
-                                        LossyStr(r)
+                                        Traced(self.config.trace, r)
                                     );
                                 }
                             }
@@ -125,8 +245,8 @@ impl<'a, RW: Read + Write, D: Digester> SimpleClient<'a, RW, D> {
         self.pos -= amt;
     }
 
-    fn start_cooldown_timer(&mut self) {
-        self.cooldown_timer = Some(Timer::after(self.config.cmd_cooldown));
+    fn start_cooldown_timer(&mut self, cooldown: Duration) {
+        self.cooldown_timer = Some(Timer::after(cooldown));
     }
 
     async fn wait_cooldown_timer(&mut self) {
@@ -136,22 +256,122 @@ impl<'a, RW: Read + Write, D: Digester> SimpleClient<'a, RW, D> {
     }
 }
 
+impl<RW: Read + Write, D: Digester> core::fmt::Debug for SimpleClient<'_, RW, D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SimpleClient")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<RW: Read + Write, D: Digester> defmt::Format for SimpleClient<'_, RW, D> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "SimpleClient {{ config: {}, .. }}", self.config)
+    }
+}
+
+impl<RW: Read + Write, D: Digester> RequestLink for SimpleClient<'_, RW, D> {
+    type Writer = RW;
+
+    fn parts(&mut self) -> (&mut RW, &Config, &[u8]) {
+        (&mut self.rw, &self.config, self.buf)
+    }
+
+    async fn discard_answer(&mut self) {
+        let _ = self.wait_response().await;
+    }
+}
+
 impl<RW: Read + Write, D: Digester> AtatClient for SimpleClient<'_, RW, D> {
     async fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
-        let len = cmd.write(&mut self.buf);
+        let mut retry = 0;
+        loop {
+            // The result codes apply to this exchange only
+            self.digester
+                .set_result_codes(Cmd::SUCCESS_CODES, Cmd::ERROR_CODES);
+            let res = self.exchange(cmd).await;
+            self.digester.set_result_codes(&[], &[]);
+            match res {
+                Err(e) if e.is_transient() && retry + 1 < self.config.retry_attempts => {
+                    let backoff = self.config.retry_backoff(retry);
+                    retry += 1;
+                    debug!("Retry {} after {:?}", retry, e);
+                    Timer::after(backoff).await;
+                }
+                res => return res,
+            }
+        }
+    }
+}
 
-        self.send_request(len).await?;
-        if !Cmd::EXPECTS_RESPONSE_CODE {
-            cmd.parse(Ok(&[]))
+impl<RW: Read + Write, D: Digester> SimpleClient<'_, RW, D> {
+    async fn exchange<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        // Commands without parameters are sent from their static wire image
+        let len = match Cmd::WIRE_IMAGE {
+            Some(_) => 0,
+            None => cmd.write(self.buf),
+        };
+
+        let cooldown = Cmd::COOLDOWN_MS.map_or(self.config.cmd_cooldown, |ms| {
+            Duration::from_millis(ms.into())
+        });
+        self.send_request(Cmd::WIRE_IMAGE, len, cooldown).await?;
+        if self.config.loopback {
+            // The request is still in the buffer, as nothing has been read
+            parse_validated(cmd, Ok(Cmd::WIRE_IMAGE.unwrap_or(&self.buf[..len])))
+        } else if !Cmd::EXPECTS_RESPONSE_CODE {
+            parse_validated(cmd, Ok(&[]))
         } else {
-            let response = embassy_time::with_timeout(
-                Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into()),
-                self.wait_response(),
-            )
-            .await
-            .map_err(|_| Error::Timeout)??;
+            let res = self.wait_cmd_response(cmd).await;
+            self.paged = 0;
+            res
+        }
+    }
+
+    /// Wait for the response of a command, collecting the pages of a
+    /// paginated response at the end of the buffer.
+    async fn wait_cmd_response<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+        loop {
+            let response = with_timeout(timeout, self.wait_response())
+                .await
+                .map_err(|_| Error::Timeout)??;
 
-            cmd.parse((&response).into())
+            match (response, Cmd::CONTINUATION) {
+                (Response::Continuation(page), Some(token)) => {
+                    self.append_page(&page)?;
+                    self.send_continuation(token).await?;
+                }
+                (Response::Ok(page), _) if self.paged > 0 => {
+                    self.append_page(&page)?;
+                    let pages = &self.buf[self.buf.len() - self.paged..];
+                    return parse_validated(cmd, anchor_response::<Cmd>(Ok(pages)));
+                }
+                (response, _) => {
+                    return parse_validated(cmd, anchor_response::<Cmd>((&response).into()))
+                }
+            }
         }
     }
+
+    /// Append a page to the pages collected at the end of the buffer, failing
+    /// with [`Error::InvalidResponse`] if they would overlap the received
+    /// bytes.
+    fn append_page(&mut self, page: &[u8]) -> Result<(), Error> {
+        if page.is_empty() {
+            return Ok(());
+        }
+        let sep: &[u8] = if self.paged > 0 { b"\r\n" } else { b"" };
+        let len = self.buf.len();
+        let start = (len - self.paged)
+            .checked_sub(sep.len() + page.len())
+            .filter(|&start| start >= self.pos)
+            .ok_or(Error::InvalidResponse)?;
+        self.buf.copy_within(len - self.paged.., start);
+        self.buf[len - page.len() - sep.len()..len - page.len()].copy_from_slice(sep);
+        self.buf[len - page.len()..].copy_from_slice(page);
+        self.paged += sep.len() + page.len();
+        Ok(())
+    }
 }
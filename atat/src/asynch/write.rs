@@ -0,0 +1,102 @@
+use super::yield_now;
+use crate::{helpers::LossyStr, Config, Error};
+use embassy_time::{with_timeout, Duration, Timer};
+use embedded_io_async::Write;
+
+/// The write side of a client, and the buffer holding its serialized
+/// request, see [`write_with_recovery`].
+pub(super) trait RequestLink {
+    type Writer: Write;
+
+    /// The writer, the configuration and the request buffer of the client.
+    fn parts(&mut self) -> (&mut Self::Writer, &Config, &[u8]);
+
+    /// Wait for the answer of the device to a partial command line, and
+    /// discard it.
+    async fn discard_answer(&mut self);
+}
+
+/// Write `request` in chunks, failing with [`Error::PartialWrite`] if the
+/// write fails after part of it was written.
+pub(super) async fn write_request<W: Write>(
+    writer: &mut W,
+    config: &Config,
+    request: &[u8],
+) -> Result<(), Error> {
+    let chunk_size = config.tx_chunk_size.unwrap_or(request.len()).max(1);
+    let mut written = 0;
+    let mut chunks = request.chunks(chunk_size).peekable();
+    while let Some(mut chunk) = chunks.next() {
+        while !chunk.is_empty() {
+            let n = match with_timeout(config.tx_timeout, writer.write(chunk)).await {
+                Ok(Ok(n)) if n > 0 => n,
+                Ok(_) if written > 0 => return Err(Error::PartialWrite(written)),
+                Ok(_) => return Err(Error::Write),
+                Err(_) if written > 0 => return Err(Error::PartialWrite(written)),
+                Err(_) => return Err(Error::Timeout),
+            };
+            config.trace_tx(&chunk[..n]);
+            written += n;
+            chunk = &chunk[n..];
+        }
+
+        if chunks.peek().is_some() {
+            if config.tx_chunk_delay > Duration::from_ticks(0) {
+                Timer::after(config.tx_chunk_delay).await;
+            } else {
+                yield_now().await;
+            }
+        }
+    }
+
+    with_timeout(config.flush_timeout, writer.flush())
+        .await
+        .map_err(|_| Error::Timeout)?
+        .map_err(|_| Error::Write)
+}
+
+/// Write the request, either the `wire_image` or the first `len` bytes of
+/// the request buffer of the client.
+///
+/// If a write error interrupts the request, it is terminated with the
+/// [`Config::write_recovery`] sequence, and the answer of the device to the
+/// partial command line is awaited for up to the [`Config::tx_timeout`] and
+/// discarded. The request is then written once more if
+/// [`Config::retry_partial_write`] is enabled.
+pub(super) async fn write_with_recovery<L: RequestLink>(
+    link: &mut L,
+    wire_image: Option<&[u8]>,
+    len: usize,
+) -> Result<(), Error> {
+    let (writer, config, buf) = link.parts();
+    let request = wire_image.unwrap_or(&buf[..len]);
+    let written = match write_request(writer, config, request).await {
+        Err(Error::PartialWrite(written)) => written,
+        res => return res,
+    };
+    error!(
+        "Write failed after {}/{} bytes of the request",
+        written,
+        request.len()
+    );
+
+    let Some(sequence) = config.write_recovery else {
+        return Err(Error::PartialWrite(written));
+    };
+    debug!("Terminating the partial request: {:?}", LossyStr(sequence));
+    if write_request(writer, config, sequence).await.is_err() {
+        warn!("Unable to terminate the partial request");
+        return Err(Error::PartialWrite(written));
+    }
+    let (timeout, retry) = (config.tx_timeout, config.retry_partial_write);
+    if with_timeout(timeout, link.discard_answer()).await.is_err() {
+        debug!("No answer to the partial request");
+    }
+    if !retry {
+        return Err(Error::PartialWrite(written));
+    }
+
+    debug!("Retrying the interrupted request");
+    let (writer, config, buf) = link.parts();
+    write_request(writer, config, wire_image.unwrap_or(&buf[..len])).await
+}
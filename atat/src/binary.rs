@@ -0,0 +1,166 @@
+//! Zero-copy access to fixed-layout binary structures in responses, eg. UBX
+//! frames of a GNSS receiver tunneled through AT commands, as an alternative
+//! to parsing text with serde.
+//!
+//! A `#[repr(C, packed)]` struct implementing [`FromBytes`] is read from the
+//! start of a validated byte slice, and its fields are taken as is. Multi-byte
+//! fields hold the byte order of the device, so convert them with eg.
+//! [`u16::from_le`].
+//!
+//! ```
+//! use atat::binary::{self, FromBytes};
+//!
+//! #[derive(Clone, Copy, Debug, PartialEq)]
+//! #[repr(C, packed)]
+//! struct UbxHeader {
+//!     sync: [u8; 2],
+//!     class: u8,
+//!     id: u8,
+//!     len: u16,
+//! }
+//!
+//! // SAFETY: packed, without padding, and every field is `FromBytes`
+//! unsafe impl FromBytes for UbxHeader {}
+//!
+//! let frame = b"\xb5\x62\x01\x07\x02\x00\xaa\xbb";
+//! let (header, payload) = binary::read_from_prefix::<UbxHeader>(frame).unwrap();
+//! assert_eq!({ header.class }, 0x01);
+//! assert_eq!(u16::from_le({ header.len }), 2);
+//! assert_eq!(payload, b"\xaa\xbb");
+//! ```
+//!
+//! [`from_response`] reads a whole response, and fits the `parse` attribute
+//! of [`AtatCmd`](crate::AtatCmd) derives.
+
+use crate::Error;
+
+/// Types which can be read from any bytes of their size.
+///
+/// # Safety
+///
+/// The type must have a stable layout without padding, ie. be
+/// `#[repr(C)]` or `#[repr(C, packed)]` with no padding between or after its
+/// fields, and every bit pattern must be a valid value, ie. all fields must
+/// be `FromBytes` themselves.
+pub unsafe trait FromBytes: Sized {}
+
+macro_rules! impl_from_bytes {
+    ($($t:ty),*) => {
+        $(unsafe impl FromBytes for $t {})*
+    };
+}
+
+impl_from_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+unsafe impl<T: FromBytes, const N: usize> FromBytes for [T; N] {}
+
+/// Read a `T` from the start of `bytes`, returning it along with the bytes
+/// following it. Fails with [`Error::Parse`] if `bytes` is too short.
+///
+/// The value is copied, so `bytes` need not be aligned for `T`.
+pub fn read_from_prefix<T: FromBytes>(bytes: &[u8]) -> Result<(T, &[u8]), Error> {
+    if bytes.len() < core::mem::size_of::<T>() {
+        return Err(Error::Parse);
+    }
+    let (head, rest) = bytes.split_at(core::mem::size_of::<T>());
+    // SAFETY: `head` holds `size_of::<T>()` bytes, any of which are a valid
+    // `T` as it is `FromBytes`, and the read does not require alignment.
+    let value = unsafe { head.as_ptr().cast::<T>().read_unaligned() };
+    Ok((value, rest))
+}
+
+/// Reference a `T` at the start of `bytes` without copying, returning it
+/// along with the bytes following it. Fails with [`Error::Parse`] if `bytes`
+/// is too short, or not aligned for `T`, which never happens for
+/// `#[repr(C, packed)]` structs.
+pub fn ref_from_prefix<T: FromBytes>(bytes: &[u8]) -> Result<(&T, &[u8]), Error> {
+    if bytes.len() < core::mem::size_of::<T>()
+        || bytes.as_ptr().align_offset(core::mem::align_of::<T>()) != 0
+    {
+        return Err(Error::Parse);
+    }
+    let (head, rest) = bytes.split_at(core::mem::size_of::<T>());
+    // SAFETY: `head` is aligned for `T` and holds `size_of::<T>()` bytes, any
+    // of which are a valid `T` as it is `FromBytes`. The reference borrows
+    // `bytes`, which can not be mutated meanwhile.
+    let value = unsafe { &*head.as_ptr().cast::<T>() };
+    Ok((value, rest))
+}
+
+/// Read a response holding exactly one `T`, eg. with
+/// `#[at_cmd("+UBX", UbxHeader, parse = atat::binary::from_response)]`.
+/// Fails with [`Error::Parse`] if the length of the response differs from
+/// the size of `T`.
+pub fn from_response<T: FromBytes>(resp: &[u8]) -> Result<T, Error> {
+    match read_from_prefix(resp)? {
+        (value, []) => Ok(value),
+        _ => Err(Error::Parse),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{self as atat, atat_derive::AtatCmd, AtatCmd, AtatResp};
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(C, packed)]
+    struct Header {
+        class: u8,
+        len: u16,
+        checksum: [u8; 2],
+    }
+
+    unsafe impl FromBytes for Header {}
+
+    impl AtatResp for Header {}
+
+    #[derive(AtatCmd)]
+    #[at_cmd("+UBX", Header, parse = from_response)]
+    struct ReadHeader;
+
+    #[test]
+    fn parse_command() {
+        assert_eq!(
+            ReadHeader.parse(Ok(b"\x01\x02\x00\xaa\xbb")),
+            Ok(Header {
+                class: 1,
+                len: u16::from_ne_bytes([2, 0]),
+                checksum: [0xaa, 0xbb],
+            })
+        );
+    }
+
+    #[test]
+    fn read() {
+        let bytes = b"\x01\x02\x00\xaa\xbbpayload";
+        let (header, rest) = read_from_prefix::<Header>(&bytes[..]).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                class: 1,
+                len: u16::from_ne_bytes([2, 0]),
+                checksum: [0xaa, 0xbb],
+            }
+        );
+        assert_eq!(rest, b"payload");
+
+        let (header_ref, rest) = ref_from_prefix::<Header>(&bytes[..]).unwrap();
+        assert_eq!(*header_ref, header);
+        assert_eq!(rest, b"payload");
+
+        assert_eq!(read_from_prefix::<Header>(&bytes[..4]), Err(Error::Parse));
+        assert_eq!(from_response::<Header>(&bytes[..5]), Ok(header));
+        assert_eq!(from_response::<Header>(&bytes[..]), Err(Error::Parse));
+    }
+
+    #[test]
+    fn unaligned() {
+        let bytes = [0u32; 3];
+        // SAFETY: reinterpreting initialized `u32`s as bytes
+        let bytes = unsafe { core::slice::from_raw_parts(bytes.as_ptr().cast::<u8>(), 12) };
+        assert!(ref_from_prefix::<u32>(bytes).is_ok());
+        assert_eq!(ref_from_prefix::<u32>(&bytes[1..]), Err(Error::Parse));
+        assert_eq!(read_from_prefix::<u32>(&bytes[1..]).map(|r| r.0), Ok(0));
+    }
+}
@@ -11,6 +11,10 @@ impl BlockingTimer {
         }
     }
 
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+
     pub fn wait(self) {
         loop {
             if self.expires_at <= Instant::now() {
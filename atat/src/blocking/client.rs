@@ -1,18 +1,19 @@
-use embassy_time::{Duration, Instant, TimeoutError};
+use embassy_time::{Duration, Instant};
 use embedded_io::Write;
 
-use super::{blocking_timer::BlockingTimer, AtatClient};
+use super::{blocking_timer::BlockingTimer, AtatClient, DynAtatClient, DynCmd};
 use crate::{
-    helpers::LossyStr,
-    response_slot::{ResponseSlot, ResponseSlotGuard},
-    AtatCmd, Config, Error, Response,
+    helpers::{LossyStr, Traced},
+    init::{Handshake, InitStep, Resync},
+    response::{anchor_response, append_page, parse_validated},
+    response_slot::{CommandStateGuard, ResponseSlot, ResponseSlotGuard},
+    AtatCmd, CommandState, Config, ConfigDelta, Error, InternalError, Response, TraceLevel,
 };
 
 /// Client responsible for handling send, receive and timeout from the
-/// userfacing side. The client is decoupled from the ingress-manager through
-/// some spsc queue consumers, where any received responses can be dequeued. The
-/// Client also has an spsc producer, to allow signaling commands like
-/// `reset` to the ingress-manager.
+/// userfacing side. The client is decoupled from the ingress through the
+/// shared [`ResponseSlot`], from which the response to the command in flight
+/// is taken once the ingress signalled it.
 pub struct Client<'a, W, const INGRESS_BUF_SIZE: usize>
 where
     W: Write,
@@ -21,9 +22,28 @@ where
     res_slot: &'a ResponseSlot<INGRESS_BUF_SIZE>,
     buf: &'a mut [u8],
     cooldown_timer: Option<BlockingTimer>,
+    deadline: Option<Instant>,
+    pending: Option<Pending>,
+    /// Attempt of the next command, set by [`AtatClient::send_retry`]
+    attempt: u8,
+    /// Whether to wait for the device to be ready before the next command,
+    /// see [`Config::wait_ready`]
+    awaiting_ready: bool,
+    /// The wait for the device to be ready, once started by
+    /// [`Client::send_nonblocking`]
+    ready_timer: Option<BlockingTimer>,
     config: Config,
 }
 
+/// A command sent with [`Client::send_nonblocking`], awaiting its response.
+struct Pending {
+    sent: Instant,
+    /// Length of the paginated response collected so far
+    pos: usize,
+    /// Whether the command was aborted with [`Client::abort_current`]
+    aborted: bool,
+}
+
 impl<'a, W, const INGRESS_BUF_SIZE: usize> Client<'a, W, INGRESS_BUF_SIZE>
 where
     W: Write,
@@ -39,65 +59,575 @@ where
             res_slot,
             buf,
             cooldown_timer: None,
+            deadline: None,
+            pending: None,
+            attempt: 1,
+            awaiting_ready: true,
+            ready_timer: None,
             config,
         }
     }
 
-    fn send_request(&mut self, len: usize) -> Result<(), Error> {
-        if len < 50 {
-            debug!("Sending command: {:?}", LossyStr(&self.buf[..len]));
-        } else {
-            debug!("Sending command with long payload ({} bytes)", len,);
+    /// The current configuration of the client.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The command currently in flight, if any, eg. one sent with
+    /// [`Client::send_nonblocking`] that is awaiting its response.
+    ///
+    /// The state is also available through [`ResponseSlot::state`] of the
+    /// shared slot.
+    pub fn state(&self) -> Option<CommandState> {
+        self.res_slot.state()
+    }
+
+    /// Reconfigure the client. As the client is borrowed mutably, no command
+    /// can be in flight, and the change is in effect from the next command
+    /// sent. Returns the resulting configuration.
+    pub fn reconfigure(&mut self, delta: ConfigDelta) -> &Config {
+        debug!("Reconfiguring client");
+        self.config.apply(delta);
+        &self.config
+    }
+
+    /// Set the amount of detail logged about the commands sent, eg. to
+    /// enable deep tracing temporarily from a debug channel. See
+    /// [`Config::trace`].
+    pub fn set_trace(&mut self, level: TraceLevel) {
+        self.config.trace = level;
+    }
+
+    /// Tear down the client, and return the serial writer and the request
+    /// buffer, eg. to hand the serial port to another peripheral. A command
+    /// sent with [`Client::send_nonblocking`] is abandoned, and the shared
+    /// response slot cleared, such that a new client and ingress pair can be
+    /// created on it once the ingress is released with [`Ingress::release`].
+    ///
+    /// [`Ingress::release`]: crate::Ingress::release
+    pub fn release(self) -> (W, &'a mut [u8]) {
+        debug!("Releasing client");
+        self.res_slot.clear();
+        (self.writer, self.buf)
+    }
+
+    /// Perform the initialization handshake: send `AT` until the device
+    /// responds, disable echo, enable `+CME ERROR` result codes, and apply
+    /// the [`Config::command_termination`] and
+    /// [`Config::response_formatting`] if set.
+    ///
+    /// Each step is attempted up to [`Config::init_attempts`] times.
+    pub fn init(&mut self) -> Result<(), Error> {
+        let mut handshake = Handshake::new(&self.config);
+        while let Some(step) = handshake.step() {
+            let res = self.send(&step);
+            handshake.record(res)?;
         }
+        Ok(())
+    }
 
-        self.wait_cooldown_timer();
+    /// Resynchronize with the device, eg. after the serial cable or USB
+    /// adapter was hot-plugged, leaving half a frame on either side.
+    ///
+    /// The ingress discards everything it holds, the
+    /// [`Config::attention_sequence`] is written, and every response is
+    /// discarded until a clean `OK`. The cooldown, reattempt and pending
+    /// command state of the client is reset. The sequence is written up to
+    /// [`Config::init_attempts`] times.
+    pub fn resync(&mut self) -> Result<(), Error> {
+        debug!("Resynchronizing");
+        self.cooldown_timer = None;
+        self.attempt = 1;
+        self.clear_pending();
+        self.res_slot.request_clear();
+
+        let sequence = self.config.attention_sequence;
+        let res_slot = self.res_slot;
+        let mut resync = Resync::new(&self.config);
+        while let Some(attempt) = resync.next_attempt() {
+            res_slot.reset();
+            let _state = res_slot.start_command(CommandState::new::<InitStep>(attempt));
+            Resync::written(write_request(&mut self.writer, &self.config, sequence))?;
+            loop {
+                let clean = match self.wait_response(Resync::TIMEOUT) {
+                    Ok(response) => Resync::is_clean(&response.borrow()),
+                    Err(Error::Timeout) => break,
+                    Err(e) => return Err(e),
+                };
+                res_slot.reset();
+                if clean {
+                    return Ok(());
+                }
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Send raw bytes, eg. binary socket data, through the same cooldown,
+    /// tracing and write recovery as commands. The bytes are written as is,
+    /// without a command prefix or termination.
+    ///
+    /// If a `response_timeout` is given, the final result code is awaited for
+    /// up to that long, and the raw response returned from the request
+    /// buffer. Otherwise an empty response is returned once the bytes are
+    /// written.
+    pub fn send_raw(
+        &mut self,
+        data: &[u8],
+        response_timeout: Option<Duration>,
+    ) -> Result<&[u8], Error> {
+        self.clear_pending();
+        let res_slot = self.res_slot;
+        let _state =
+            self.send_request(Some(data), 0, self.config.cmd_cooldown, CommandState::raw())?;
+        let Some(timeout) = response_timeout else {
+            return Ok(&[]);
+        };
+
+        let mut len = 0;
+        // The response slot guard must be released before returning
+        {
+            let response = self.with_timeout(timeout, || res_slot.try_get())?;
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            append_page(self.buf, &mut len, Result::from(response)?)?;
+        }
+        Ok(&self.buf[..len])
+    }
+
+    /// Send a command without awaiting its response, eg. `AT+CFUN=15`
+    /// restarting the device before it gets to answer.
+    ///
+    /// Returns once the command is written. The ingress swallows the final
+    /// result code of the command if it arrives within
+    /// [`AtatCmd::MAX_TIMEOUT_MS`] and before the device restarts, rather
+    /// than handing it to the next command. A command sent meanwhile would
+    /// have its response swallowed instead, so await the restart first, eg.
+    /// with [`Config::wait_ready`].
+    pub fn send_no_response<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<(), Error> {
+        self.clear_pending();
+        // Marked before writing, such that an immediate answer is swallowed
+        if Cmd::EXPECTS_RESPONSE_CODE {
+            let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+            self.res_slot
+                .swallow_response(Some(Instant::now() + timeout));
+        }
+        let res = self.send_cmd(cmd, 1).map(drop);
+        if res.is_err() {
+            self.res_slot.swallow_response(None);
+        }
+        res
+    }
+
+    /// Send an AT command without waiting for its response, eg. from a main
+    /// loop that must not stall, on a client otherwise used with the
+    /// blocking [`AtatClient::send`].
+    ///
+    /// Returns [`nb::Error::WouldBlock`] while the cooldown of the previous
+    /// command is running, or while the device is not ready yet, see
+    /// [`Config::wait_ready`]. If no boot banner is received within the
+    /// timeout, the device is probed as by [`Client::resync`], which blocks.
+    /// Once sent, poll [`Client::check_response`] with the same command for
+    /// the response.
+    pub fn send_nonblocking<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> nb::Result<(), Error> {
+        if let Some(timeout) = self.config.ready_timeout.filter(|_| self.awaiting_ready) {
+            if self.res_slot.take_booted() {
+                debug!("Device ready");
+                self.awaiting_ready = false;
+                self.ready_timer = None;
+            } else if self
+                .ready_timer
+                .get_or_insert_with(|| BlockingTimer::after(timeout))
+                .expires_at()
+                > Instant::now()
+            {
+                return Err(nb::Error::WouldBlock);
+            }
+        }
+        if self
+            .cooldown_timer
+            .as_ref()
+            .is_some_and(|cooldown| cooldown.expires_at() > Instant::now())
+        {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.clear_pending();
+        let attempt = core::mem::replace(&mut self.attempt, 1);
+        // Reported until the response is taken, see `clear_pending`
+        self.send_cmd(cmd, attempt)?.keep();
+        self.pending = Some(Pending {
+            sent: Instant::now(),
+            pos: 0,
+            aborted: false,
+        });
+        Ok(())
+    }
+
+    /// Number of responses received by the ingress and not yet taken by
+    /// [`Client::check_response`].
+    pub fn pending_frames(&self) -> usize {
+        self.res_slot.pending_frames()
+    }
+
+    /// Whether [`Client::send_nonblocking`] can send a command now: no
+    /// command is pending, such that the response of one is not discarded,
+    /// and the cooldown of the previous command has passed.
+    pub fn can_send(&self) -> bool {
+        self.pending.is_none()
+            && self
+                .cooldown_timer
+                .as_ref()
+                .map_or(true, |cooldown| cooldown.expires_at() <= Instant::now())
+    }
+
+    /// Abort the command sent with [`Client::send_nonblocking`] by writing the
+    /// [`Config::abort_token`], such that [`Client::check_response`] fails
+    /// with [`Error::Aborted`] once the device acknowledged the abort.
+    ///
+    /// Commands which are not [`AtatCmd::CAN_ABORT`] are refused with
+    /// [`Error::NotAbortable`], unless `force` is set. Fails with
+    /// [`Error::InvalidResponse`] if no command is pending.
+    pub fn abort_current(&mut self, force: bool) -> Result<(), Error> {
+        let Some(state) = self.res_slot.state().filter(|_| self.pending.is_some()) else {
+            return Err(Error::InvalidResponse);
+        };
+        if !state.abortable && !force {
+            warn!("Refusing to abort {}", state.name);
+            return Err(Error::NotAbortable);
+        }
+
+        self.abort()?;
+        if let Some(pending) = self.pending.as_mut() {
+            pending.aborted = true;
+        }
+        Ok(())
+    }
+
+    /// Check for the response of a command sent with
+    /// [`Client::send_nonblocking`], returning [`nb::Error::WouldBlock`]
+    /// until it is received, or the command timed out.
+    ///
+    /// Fails with [`Error::InvalidResponse`] if no command is pending.
+    pub fn check_response<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> nb::Result<Cmd::Response, Error> {
+        let Some(pending) = self.pending.as_mut() else {
+            return Err(nb::Error::Other(Error::InvalidResponse));
+        };
+        if !Cmd::EXPECTS_RESPONSE_CODE {
+            self.clear_pending();
+            return parse_validated(cmd, Ok(&[])).map_err(nb::Error::Other);
+        }
+
+        let res_slot = self.res_slot;
+        // The response slot guard must be released before continuing
+        let done = {
+            let Some(response) = res_slot.try_get() else {
+                let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+                if (self.config.get_response_timeout)(pending.sent, timeout) <= Instant::now() {
+                    let aborted = pending.aborted;
+                    self.clear_pending();
+                    if Cmd::CAN_ABORT && !aborted {
+                        self.abort()?;
+                    }
+                    if Cmd::CAN_ABORT || aborted {
+                        self.settle_abort();
+                    }
+                    return Err(nb::Error::Other(Error::Timeout));
+                }
+                return Err(nb::Error::WouldBlock);
+            };
+            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+            match (response, Cmd::CONTINUATION) {
+                // Whatever the device answered, the command did not run
+                // to completion
+                _ if pending.aborted => Some(Err(Error::Aborted)),
+                (Response::Continuation(page), Some(_)) => {
+                    append_page(self.buf, &mut pending.pos, page).err().map(Err)
+                }
+                (Response::Ok(page), Some(_)) if pending.pos > 0 => {
+                    let pos = &mut pending.pos;
+                    Some(append_page(self.buf, pos, page).and_then(|()| {
+                        parse_validated(cmd, anchor_response::<Cmd>(Ok(&self.buf[..*pos])))
+                    }))
+                }
+                (response, _) => Some(parse_validated(
+                    cmd,
+                    anchor_response::<Cmd>(response.into()),
+                )),
+            }
+        };
+
+        match (done, Cmd::CONTINUATION) {
+            (None, Some(token)) => {
+                self.send_continuation(token)?;
+                Err(nb::Error::WouldBlock)
+            }
+            (done, _) => {
+                // The response is taken
+                res_slot.reset();
+                self.clear_pending();
+                done.unwrap_or(Err(Error::InvalidResponse))
+                    .map_err(nb::Error::Other)
+            }
+        }
+    }
+
+    fn clear_pending(&mut self) {
+        self.pending = None;
+        self.res_slot.set_state(None);
+    }
+
+    /// Write the request of a command, from its wire image if it has one.
+    /// The state of the command is reported until the returned guard is
+    /// dropped.
+    fn send_cmd<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        attempt: u8,
+    ) -> Result<CommandStateGuard<'a, INGRESS_BUF_SIZE>, Error> {
+        // Commands without parameters are sent from their static wire image
+        let len = match Cmd::WIRE_IMAGE {
+            Some(_) => 0,
+            None => cmd.write(self.buf),
+        };
+        let cooldown = Cmd::COOLDOWN_MS.map_or(self.config.cmd_cooldown, |ms| {
+            Duration::from_millis(ms.into())
+        });
+        let state = CommandState::new::<Cmd>(attempt);
+        self.send_request(Cmd::WIRE_IMAGE, len, cooldown, state)
+    }
+
+    /// Wait for the device to be ready after the client was created or the
+    /// device restarted, see [`Config::wait_ready`].
+    fn wait_ready(&mut self) -> Result<(), Error> {
+        let Some(timeout) = self.config.ready_timeout.filter(|_| self.awaiting_ready) else {
+            return Ok(());
+        };
+        self.awaiting_ready = false;
+        let ready = self
+            .ready_timer
+            .take()
+            .unwrap_or_else(|| BlockingTimer::after(timeout));
+        while !self.res_slot.take_booted() {
+            if self
+                .deadline
+                .is_some_and(|deadline| deadline <= Instant::now())
+            {
+                // Still waiting for the device on the next command
+                self.awaiting_ready = true;
+                self.ready_timer = Some(ready);
+                return Err(Error::DeadlineExceeded);
+            }
+            if ready.expires_at() <= Instant::now() {
+                debug!("No boot banner, probing the device");
+                return self.resync();
+            }
+        }
+        debug!("Device ready");
+        Ok(())
+    }
+
+    fn send_request(
+        &mut self,
+        wire_image: Option<&[u8]>,
+        len: usize,
+        cooldown: Duration,
+        state: CommandState,
+    ) -> Result<CommandStateGuard<'a, INGRESS_BUF_SIZE>, Error> {
+        self.wait_ready()?;
+        self.wait_cooldown_timer()?;
+        if self
+            .deadline
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            return Err(Error::DeadlineExceeded);
+        }
 
         // Clear any pending response signal
         self.res_slot.reset();
 
+        let request = match wire_image {
+            Some(request) => request,
+            None => &self.buf[..len],
+        };
+        if request.len() < 50 || self.config.trace == TraceLevel::Bytes {
+            debug!("Sending command: {:?}", Traced(self.config.trace, request));
+        } else {
+            debug!(
+                "Sending command with long payload ({} bytes)",
+                request.len()
+            );
+        }
+
+        // Reported before writing, such that the ingress recognizes a
+        // response arriving while the write completes. Cleared again if the
+        // write fails.
+        let res_slot = self.res_slot;
+        let state = res_slot.start_command(CommandState {
+            sent: Instant::now(),
+            ..state
+        });
+
         // Write request
+        match write_request(&mut self.writer, &self.config, request) {
+            Err(Error::PartialWrite(written)) => {
+                error!(
+                    "Write failed after {}/{} bytes of the request",
+                    written,
+                    request.len()
+                );
+                if !self.recover_partial_write() || !self.config.retry_partial_write {
+                    return Err(Error::PartialWrite(written));
+                }
+                debug!("Retrying the interrupted request");
+                let request = match wire_image {
+                    Some(request) => request,
+                    None => &self.buf[..len],
+                };
+                write_request(&mut self.writer, &self.config, request)?;
+            }
+            res => res?,
+        }
+
+        if self.config.loopback {
+            let request = match wire_image {
+                Some(request) => request,
+                None => &self.buf[..len],
+            };
+            let len = request.len().min(INGRESS_BUF_SIZE);
+            if self.res_slot.signal_response(Ok(&request[..len])).is_err() {
+                error!("Loopback response but a response is already pending");
+            }
+        }
+
+        self.start_cooldown_timer(cooldown);
+        Ok(state)
+    }
+
+    /// Terminate a request interrupted by a write error with the
+    /// [`Config::write_recovery`] sequence, and discard the answer of the
+    /// device to the partial command line. Returns whether the link
+    /// recovered.
+    fn recover_partial_write(&mut self) -> bool {
+        let Some(sequence) = self.config.write_recovery else {
+            return false;
+        };
+        debug!("Terminating the partial request: {:?}", LossyStr(sequence));
+        if write_request(&mut self.writer, &self.config, sequence).is_err() {
+            warn!("Unable to terminate the partial request");
+            return false;
+        }
+        if self
+            .with_timeout(self.config.tx_timeout, || self.res_slot.try_get().map(drop))
+            .is_err()
+        {
+            debug!("No answer to the partial request");
+        }
+        self.res_slot.reset();
+        true
+    }
+
+    /// Write the abort token, stopping the command running on the device.
+    fn abort(&mut self) -> Result<(), Error> {
+        debug!("Aborting command");
         self.writer
-            .write_all(&self.buf[..len])
+            .write_all(self.config.abort_token)
             .map_err(|_| Error::Write)?;
-        self.writer.flush().map_err(|_| Error::Write)?;
+        self.config.trace_tx(self.config.abort_token);
+        self.writer.flush().map_err(|_| Error::Write)
+    }
 
-        self.start_cooldown_timer();
-        Ok(())
+    /// Wait for the final result code of an aborted command, discarding it.
+    fn settle_abort(&mut self) {
+        if self
+            .with_timeout(self.config.abort_timeout, || {
+                self.res_slot.try_get().map(drop)
+            })
+            .is_err()
+        {
+            warn!("No result code for aborted command");
+        }
+        self.res_slot.reset();
     }
 
-    fn wait_response<'guard>(
-        &'guard mut self,
+    fn send_continuation(&mut self, token: &[u8]) -> Result<(), Error> {
+        debug!("Sending continuation: {:?}", LossyStr(token));
+
+        // Clear the signal of the previous page
+        self.res_slot.reset();
+
+        self.writer.write_all(token).map_err(|_| Error::Write)?;
+        self.config.trace_tx(token);
+        self.writer.flush().map_err(|_| Error::Write)
+    }
+
+    fn wait_response(
+        &mut self,
         timeout: Duration,
-    ) -> Result<ResponseSlotGuard<'guard, INGRESS_BUF_SIZE>, Error> {
+    ) -> Result<ResponseSlotGuard<'_, INGRESS_BUF_SIZE>, Error> {
         self.with_timeout(timeout, || self.res_slot.try_get())
-            .map_err(|_| Error::Timeout)
     }
 
+    /// Poll until a result is available, or the response timeout or the
+    /// deadline of the call passes.
     fn with_timeout<R>(
         &self,
         timeout: Duration,
         mut poll: impl FnMut() -> Option<R>,
-    ) -> Result<R, TimeoutError> {
+    ) -> Result<R, Error> {
         let start = Instant::now();
 
         loop {
             if let Some(res) = poll() {
                 return Ok(res);
             }
-            if (self.config.get_response_timeout)(start, timeout) <= Instant::now() {
-                return Err(TimeoutError);
+            let now = Instant::now();
+            if self.deadline.is_some_and(|deadline| deadline <= now) {
+                return Err(Error::DeadlineExceeded);
+            }
+            if (self.config.get_response_timeout)(start, timeout) <= now {
+                return Err(Error::Timeout);
             }
         }
     }
 
-    fn start_cooldown_timer(&mut self) {
-        self.cooldown_timer = Some(BlockingTimer::after(self.config.cmd_cooldown));
+    fn start_cooldown_timer(&mut self, cooldown: Duration) {
+        self.cooldown_timer = Some(BlockingTimer::after(cooldown));
     }
 
-    fn wait_cooldown_timer(&mut self) {
+    fn wait_cooldown_timer(&mut self) -> Result<(), Error> {
         if let Some(cooldown) = self.cooldown_timer.take() {
+            // Fail early rather than sending after the deadline
+            if self
+                .deadline
+                .is_some_and(|deadline| cooldown.expires_at() >= deadline)
+            {
+                self.cooldown_timer = Some(cooldown);
+                return Err(Error::DeadlineExceeded);
+            }
             cooldown.wait();
         }
+        Ok(())
+    }
+}
+
+impl<W, const INGRESS_BUF_SIZE: usize> core::fmt::Debug for Client<'_, W, INGRESS_BUF_SIZE>
+where
+    W: Write,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Client")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<W, const INGRESS_BUF_SIZE: usize> defmt::Format for Client<'_, W, INGRESS_BUF_SIZE>
+where
+    W: Write,
+{
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Client {{ config: {}, .. }}", self.config)
     }
 }
 
@@ -105,24 +635,193 @@ impl<W, const INGRESS_BUF_SIZE: usize> AtatClient for Client<'_, W, INGRESS_BUF_
 where
     W: Write,
 {
+    fn send_with_deadline<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        deadline: Instant,
+    ) -> Result<Cmd::Response, Error> {
+        self.deadline = Some(deadline);
+        let res = self.send_retry(cmd);
+        self.deadline = None;
+        res
+    }
+
+    fn set_attempt(&mut self, attempt: u8) {
+        self.attempt = attempt;
+    }
+
     fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
-        let len = cmd.write(&mut self.buf);
-        self.send_request(len)?;
+        self.send_recovering(Cmd::CAN_ABORT, |client| client.send_inner(cmd))
+    }
+}
+
+impl<W, const INGRESS_BUF_SIZE: usize> Client<'_, W, INGRESS_BUF_SIZE>
+where
+    W: Write,
+{
+    /// Send a command with `send`, awaiting the device after it restarted,
+    /// aborting an `abortable` command which timed out, and retrying
+    /// transient errors, see [`Config::retry`].
+    fn send_recovering<R>(
+        &mut self,
+        abortable: bool,
+        mut send: impl FnMut(&mut Self) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let mut retry = 0;
+        loop {
+            let res = send(self);
+            if matches!(res, Err(Error::ModemRestarted)) {
+                self.awaiting_ready = true;
+                self.ready_timer = None;
+            }
+            if abortable && matches!(res, Err(Error::Timeout)) {
+                // Stop the command, rather than have it answer a reattempt
+                self.abort()?;
+                self.settle_abort();
+            }
+            match res {
+                Err(e) if e.is_transient() && retry + 1 < self.config.retry_attempts => {
+                    let backoff = BlockingTimer::after(self.config.retry_backoff(retry));
+                    if self
+                        .deadline
+                        .is_some_and(|deadline| backoff.expires_at() >= deadline)
+                    {
+                        return Err(Error::DeadlineExceeded);
+                    }
+                    retry += 1;
+                    debug!("Retry {} after {:?}", retry, e);
+                    backoff.wait();
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn send_inner<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        self.clear_pending();
+        let attempt = core::mem::replace(&mut self.attempt, 1);
+        let res_slot = self.res_slot;
+        let _state = self.send_cmd(cmd, attempt)?;
         if !Cmd::EXPECTS_RESPONSE_CODE {
-            cmd.parse(Ok(&[]))
+            parse_validated(cmd, Ok(&[]))
         } else {
-            let response = self.wait_response(Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into()))?;
-            let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
-            cmd.parse(response.into())
+            let timeout = Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into());
+            let Some(token) = Cmd::CONTINUATION else {
+                let response = self.wait_response(timeout)?;
+                let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+                return parse_validated(cmd, anchor_response::<Cmd>(response.into()));
+            };
+
+            // Collect the pages of a paginated response in the request buffer
+            let mut pos = 0;
+            loop {
+                // The response slot guard must be released before continuing
+                {
+                    let response = self.with_timeout(timeout, || res_slot.try_get())?;
+                    let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+                    match response {
+                        Response::Continuation(page) => append_page(self.buf, &mut pos, page)?,
+                        Response::Ok(page) if pos > 0 => {
+                            append_page(self.buf, &mut pos, page)?;
+                            return parse_validated(
+                                cmd,
+                                anchor_response::<Cmd>(Ok(&self.buf[..pos])),
+                            );
+                        }
+                        response => {
+                            return parse_validated(cmd, anchor_response::<Cmd>(response.into()))
+                        }
+                    }
+                }
+                self.send_continuation(token)?;
+            }
+        }
+    }
+
+    fn send_dyn_inner(
+        &mut self,
+        cmd: &DynCmd<'_>,
+        parse: &mut dyn FnMut(Result<&[u8], InternalError>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.clear_pending();
+        let attempt = core::mem::replace(&mut self.attempt, 1);
+        let len = (cmd.write)(self.buf);
+        let state = CommandState {
+            name: cmd.name,
+            sent: Instant::now(),
+            attempt,
+            abortable: cmd.abortable,
+            success_codes: cmd.success_codes,
+            error_codes: cmd.error_codes,
+            stats_id: cmd.stats_id,
+        };
+        let _state = self.send_request(None, len, self.config.cmd_cooldown, state)?;
+        let Some(timeout) = cmd.timeout else {
+            return parse(Ok(&[]));
+        };
+
+        let response = self.wait_response(timeout)?;
+        let response: &Response<INGRESS_BUF_SIZE> = &response.borrow();
+        parse(response.into())
+    }
+}
+
+impl<W, const INGRESS_BUF_SIZE: usize> DynAtatClient for Client<'_, W, INGRESS_BUF_SIZE>
+where
+    W: Write,
+{
+    fn send_dyn(
+        &mut self,
+        cmd: DynCmd<'_>,
+        parse: &mut dyn FnMut(Result<&[u8], InternalError>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        for attempt in 1..=cmd.attempts {
+            if attempt > 1 {
+                debug!("Attempt {}:", attempt);
+            }
+            self.attempt = attempt;
+
+            match self.send_recovering(cmd.abortable, |client| {
+                client.send_dyn_inner(&cmd, &mut *parse)
+            }) {
+                Err(Error::Timeout) => {}
+                r => return r,
+            }
+        }
+        Err(Error::Timeout)
+    }
+}
+
+/// Write `request` in chunks, failing with [`Error::PartialWrite`] if the
+/// write fails after part of it was written.
+fn write_request<W: Write>(writer: &mut W, config: &Config, request: &[u8]) -> Result<(), Error> {
+    let chunk_size = config.tx_chunk_size.unwrap_or(request.len()).max(1);
+    let mut written = 0;
+    let mut chunks = request.chunks(chunk_size).peekable();
+    while let Some(mut chunk) = chunks.next() {
+        while !chunk.is_empty() {
+            let n = match writer.write(chunk) {
+                Ok(n) if n > 0 => n,
+                _ if written > 0 => return Err(Error::PartialWrite(written)),
+                _ => return Err(Error::Write),
+            };
+            config.trace_tx(&chunk[..n]);
+            written += n;
+            chunk = &chunk[n..];
+        }
+
+        if chunks.peek().is_some() {
+            BlockingTimer::after(config.tx_chunk_delay).wait();
         }
     }
+    writer.flush().map_err(|_| Error::Write)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::atat_derive::{AtatCmd, AtatEnum, AtatResp, AtatUrc};
-    use crate::{self as atat, InternalError};
+    use crate::{self as atat, tx_mock::TxMock, InternalError};
     use core::sync::atomic::{AtomicU64, Ordering};
     use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
     use embassy_sync::pubsub::PubSubChannel;
@@ -146,7 +845,7 @@ mod test {
     }
 
     #[derive(Debug, PartialEq, AtatCmd)]
-    #[at_cmd("+CFUN", NoResponse, error = "InnerError")]
+    #[at_cmd("+CFUN", NoResponse)]
     struct ErrorTester {
         x: u8,
     }
@@ -160,6 +859,16 @@ mod test {
         pub rst: Option<ResetMode>,
     }
 
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd(
+        "+COPS=?",
+        NoResponse,
+        value_sep = false,
+        timeout_ms = 50,
+        abortable = true
+    )]
+    pub struct ScanOperators;
+
     #[derive(Clone, AtatCmd)]
     #[at_cmd("+FUN", NoResponse, timeout_ms = 180000)]
     pub struct Test2Cmd {
@@ -170,7 +879,7 @@ mod test {
     }
 
     #[derive(Clone, AtatCmd)]
-    #[at_cmd("+CUN", TestResponseString, timeout_ms = 180000)]
+    #[at_cmd("+CUN", TestResponseString, timeout_ms = 180000, resp_prefix = "+CUN:")]
     pub struct TestRespStringCmd {
         #[at_arg(position = 0)]
         pub fun: Functionality,
@@ -190,6 +899,7 @@ mod test {
     // #[at_cmd("+CUN", TestResponseStringMixed, timeout_ms = 180000)]
     // pub struct TestUnnamedStruct(Functionality, Option<ResetMode>);
 
+    #[allow(clippy::upper_case_acronyms)]
     #[derive(Clone, PartialEq, AtatEnum)]
     #[at_enum(u8)]
     pub enum Functionality {
@@ -252,13 +962,17 @@ mod test {
 
     macro_rules! setup {
         ($config:expr) => {{
+            setup!($config, |tx_mock| tx_mock)
+        }};
+        ($config:expr, $tx_mock:expr) => {{
             static TX_CHANNEL: PubSubChannel<CriticalSectionRawMutex, String<64>, 1, 1, 1> =
                 PubSubChannel::new();
             static RES_SLOT: ResponseSlot<TEST_RX_BUF_LEN> = ResponseSlot::new();
             static mut BUF: [u8; 1000] = [0; 1000];
 
-            let tx_mock = crate::tx_mock::TxMock::new(TX_CHANNEL.publisher().unwrap());
-            let client: Client<crate::tx_mock::TxMock, TEST_RX_BUF_LEN> =
+            let tx_mock = TxMock::new(TX_CHANNEL.publisher().unwrap());
+            let tx_mock = ($tx_mock)(tx_mock);
+            let client: Client<TxMock, TEST_RX_BUF_LEN> =
                 Client::new(tx_mock, &RES_SLOT, unsafe { BUF.as_mut() }, $config);
             (client, TX_CHANNEL.subscriber().unwrap(), &RES_SLOT)
         }};
@@ -272,8 +986,7 @@ mod test {
 
         let sent = tokio::spawn(async move {
             tx.next_message_pure().await;
-            rx.signal_response(Err(InternalError::Error).into())
-                .unwrap();
+            rx.signal_response(Err(InternalError::Error)).unwrap();
         });
 
         tokio::task::spawn_blocking(move || {
@@ -285,6 +998,37 @@ mod test {
         sent.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn partial_write_retry() {
+        let (mut client, mut tx, rx) = setup!(
+            Config::new()
+                .write_recovery(Some(b"\r"))
+                .retry_partial_write(true),
+            |tx_mock: TxMock<'static>| tx_mock.fail_after(5)
+        );
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        let sent = tokio::spawn(async move {
+            // The partial command line is terminated, and the request retried
+            assert_eq!(tx.next_message_pure().await, "AT+CF\r");
+            rx.signal_response(Err(InternalError::Error)).unwrap();
+            assert_eq!(tx.next_message_pure().await, "AT+CFUN=4,0\r\n");
+            rx.signal_response(Ok(&[])).unwrap();
+        });
+
+        tokio::task::spawn_blocking(move || {
+            assert_eq!(client.send(&cmd), Ok(NoResponse));
+        })
+        .await
+        .unwrap();
+
+        sent.await.unwrap();
+    }
+
     #[tokio::test]
     async fn generic_error_response() {
         let (mut client, mut tx, rx) = setup!(Config::new());
@@ -296,8 +1040,7 @@ mod test {
 
         let sent = tokio::spawn(async move {
             tx.next_message_pure().await;
-            rx.signal_response(Err(InternalError::Error).into())
-                .unwrap();
+            rx.signal_response(Err(InternalError::Error)).unwrap();
         });
 
         tokio::task::spawn_blocking(move || {
@@ -370,6 +1113,327 @@ mod test {
         assert_eq!("AT+CFUN=4,0\r\n", &sent);
     }
 
+    #[tokio::test]
+    async fn nonblocking() {
+        let (mut client, mut tx, rx) =
+            setup!(Config::new().cmd_cooldown(Duration::from_millis(50)));
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        assert_eq!(
+            Err(nb::Error::Other(Error::InvalidResponse)),
+            client.check_response(&cmd)
+        );
+        assert!(client.can_send());
+        client.send_nonblocking(&cmd).unwrap();
+        assert_eq!("AT+CFUN=4,0\r\n", &tx.try_next_message_pure().unwrap());
+        assert_eq!(Err(nb::Error::WouldBlock), client.check_response(&cmd));
+        let state = client.state().unwrap();
+        assert!(state.name.ends_with("SetModuleFunctionality"));
+        assert_eq!(state.attempt, 1);
+        assert!(!client.can_send());
+
+        rx.signal_response(Ok(&[])).unwrap();
+        assert_eq!(client.pending_frames(), 1);
+        assert_eq!(Ok(NoResponse), client.check_response(&cmd));
+        assert_eq!(client.state(), None);
+        assert_eq!(client.pending_frames(), 0);
+
+        // The cooldown is not waited for
+        assert!(!client.can_send());
+        assert_eq!(Err(nb::Error::WouldBlock), client.send_nonblocking(&cmd));
+        Timer::after(Duration::from_millis(50)).await;
+        assert!(client.can_send());
+        client.send_nonblocking(&cmd).unwrap();
+        tx.try_next_message_pure().unwrap();
+
+        // Blocking sends can be mixed in on the same client
+        let sent = tokio::spawn(async move {
+            tx.next_message_pure().await;
+            rx.signal_response(Ok(&[])).unwrap();
+        });
+        tokio::task::spawn_blocking(move || {
+            assert_eq!(client.send(&cmd), Ok(NoResponse));
+        })
+        .await
+        .unwrap();
+        sent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn nonblocking_wait_ready() {
+        let (mut client, mut tx, rx) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .wait_ready(Duration::from_millis(500)));
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+
+        // Held back without blocking until the boot banner
+        let start = Instant::now();
+        assert_eq!(Err(nb::Error::WouldBlock), client.send_nonblocking(&cmd));
+        assert!(start.elapsed() < Duration::from_millis(50));
+        assert!(tx.try_next_message_pure().is_none());
+
+        rx.signal_booted();
+        client.send_nonblocking(&cmd).unwrap();
+        assert_eq!("AT+CFUN=4\r\n", &tx.try_next_message_pure().unwrap());
+    }
+
+    #[tokio::test]
+    async fn abort_current() {
+        let (mut client, mut tx, rx) = setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+        assert_eq!(Err(Error::InvalidResponse), client.abort_current(false));
+
+        // Non-abortable commands are only aborted by force
+        client.send_nonblocking(&cmd).unwrap();
+        assert_eq!("AT+CFUN=4\r\n", &tx.try_next_message_pure().unwrap());
+        assert!(!client.state().unwrap().abortable);
+        assert_eq!(Err(Error::NotAbortable), client.abort_current(false));
+        assert!(tx.try_next_message_pure().is_none());
+        client.abort_current(true).unwrap();
+        assert_eq!("\x1b", &tx.try_next_message_pure().unwrap());
+
+        rx.signal_response(Ok(&[])).unwrap();
+        assert_eq!(
+            Err(nb::Error::Other(Error::Aborted)),
+            client.check_response(&cmd)
+        );
+        assert_eq!(client.state(), None);
+
+        client.send_nonblocking(&ScanOperators).unwrap();
+        assert_eq!("AT+COPS=?\r\n", &tx.try_next_message_pure().unwrap());
+        assert!(client.state().unwrap().abortable);
+        client.abort_current(false).unwrap();
+        assert_eq!("\x1b", &tx.try_next_message_pure().unwrap());
+        rx.signal_response(Err(InternalError::Error)).unwrap();
+        assert_eq!(
+            Err(nb::Error::Other(Error::Aborted)),
+            client.check_response(&ScanOperators)
+        );
+
+        // Abortable commands are aborted once they time out, and the result
+        // code of the aborted command is not taken for the next response
+        let sent = tokio::spawn(async move {
+            assert_eq!("AT+COPS=?\r\n", tx.next_message_pure().await);
+            assert_eq!("\x1b", tx.next_message_pure().await);
+            rx.signal_response(Err(InternalError::Error)).unwrap();
+            assert_eq!("AT+CFUN=4\r\n", tx.next_message_pure().await);
+            rx.signal_response(Ok(&[])).unwrap();
+            (tx, rx)
+        });
+        let (mut client, cmd) = tokio::task::spawn_blocking(move || {
+            assert_eq!(client.send(&ScanOperators), Err(Error::Timeout));
+            assert_eq!(client.send(&cmd), Ok(NoResponse));
+            (client, cmd)
+        })
+        .await
+        .unwrap();
+        let (mut tx, rx) = sent.await.unwrap();
+
+        // Likewise when polled with `check_response`
+        let sent = tokio::spawn(async move {
+            assert_eq!("AT+COPS=?\r\n", tx.next_message_pure().await);
+            assert_eq!("\x1b", tx.next_message_pure().await);
+            rx.signal_response(Err(InternalError::Error)).unwrap();
+            assert_eq!("AT+CFUN=4\r\n", tx.next_message_pure().await);
+            rx.signal_response(Ok(&[])).unwrap();
+        });
+        tokio::task::spawn_blocking(move || {
+            client.send_nonblocking(&ScanOperators).unwrap();
+            assert_eq!(
+                nb::block!(client.check_response(&ScanOperators)),
+                Err(Error::Timeout)
+            );
+            assert_eq!(client.send(&cmd), Ok(NoResponse));
+        })
+        .await
+        .unwrap();
+        sent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn wait_ready() {
+        let (mut client, mut tx, rx) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .wait_ready(Duration::from_millis(50)));
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+
+        let sent = tokio::spawn(async move {
+            // The first command is held back until the boot banner
+            Timer::after(Duration::from_millis(20)).await;
+            rx.signal_booted();
+            assert_eq!("AT+CFUN=4\r\n", tx.next_message_pure().await);
+            rx.signal_response(Ok(&[])).unwrap();
+        });
+
+        tokio::task::spawn_blocking(move || {
+            // The deadline of the call bounds the wait
+            let deadline = Instant::now() + Duration::from_millis(5);
+            assert_eq!(
+                client.send_with_deadline(&cmd, deadline),
+                Err(Error::DeadlineExceeded)
+            );
+            assert_eq!(client.send(&cmd), Ok(NoResponse));
+        })
+        .await
+        .unwrap();
+        sent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn wait_ready_ignores_response_timeout() {
+        fn slow_response_timeout(sent: Instant, _timeout: Duration) -> Instant {
+            sent + Duration::from_secs(10)
+        }
+
+        let (mut client, mut tx, rx) = setup!(Config::new()
+            .cmd_cooldown(Duration::from_ticks(0))
+            .get_response_timeout(slow_response_timeout)
+            .wait_ready(Duration::from_millis(20)));
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+
+        let sent = tokio::spawn(async move {
+            // No boot banner, so the device is probed once the wait is over
+            assert_eq!("\r\nAT\r\n", tx.next_message_pure().await);
+            rx.signal_response(Ok(&[])).unwrap();
+            assert_eq!("AT+CFUN=4\r\n", tx.next_message_pure().await);
+            rx.signal_response(Ok(&[])).unwrap();
+        });
+
+        tokio::task::spawn_blocking(move || {
+            let start = Instant::now();
+            assert_eq!(client.send(&cmd), Ok(NoResponse));
+            assert!(start.elapsed() < Duration::from_secs(1));
+        })
+        .await
+        .unwrap();
+        sent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_raw() {
+        let (mut client, mut tx, rx) = setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+
+        let sent = tokio::spawn(async move {
+            assert_eq!("hello", tx.next_message_pure().await);
+            rx.signal_response(Err(InternalError::Error)).unwrap();
+        });
+        tokio::task::spawn_blocking(move || {
+            assert_eq!(
+                client.send_raw(b"hello", Some(Duration::from_millis(50))),
+                Err(Error::Error)
+            );
+        })
+        .await
+        .unwrap();
+        sent.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dyn_client() {
+        fn query(client: &mut dyn DynAtatClient) -> Result<TestResponseString, Error> {
+            client.send(&TestRespStringCmd {
+                fun: Functionality::APM,
+                rst: None,
+            })
+        }
+
+        let (mut client, mut tx, rx) = setup!(Config::new());
+
+        let sent = tokio::spawn(async move {
+            assert_eq!(tx.next_message_pure().await, "AT+CUN=4\r\n");
+            rx.signal_response(Ok(b"+CUN: 22,16,\"0123456789012345\""))
+                .unwrap();
+        });
+        let res = tokio::task::spawn_blocking(move || query(&mut client))
+            .await
+            .unwrap();
+        sent.await.unwrap();
+
+        assert_eq!(
+            res,
+            Ok(TestResponseString {
+                socket: 22,
+                length: 16,
+                data: String::try_from("0123456789012345").unwrap()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn dyn_client_retry() {
+        #[derive(Clone, AtatCmd)]
+        #[at_cmd(
+            "+COPS=?",
+            NoResponse,
+            value_sep = false,
+            timeout_ms = 50,
+            abortable = true,
+            attempts = 2
+        )]
+        struct ScanOperatorsTwice;
+
+        let (mut client, mut tx, rx) = setup!(Config::new().cmd_cooldown(Duration::from_ticks(0)));
+
+        // The command is aborted once it times out, and attempted again
+        let sent = tokio::spawn(async move {
+            assert_eq!("AT+COPS=?\r\n", tx.next_message_pure().await);
+            assert_eq!("\x1b", tx.next_message_pure().await);
+            rx.signal_response(Err(InternalError::Error)).unwrap();
+            assert_eq!("AT+COPS=?\r\n", tx.next_message_pure().await);
+            rx.signal_response(Ok(&[])).unwrap();
+        });
+        let res = tokio::task::spawn_blocking(move || {
+            let client: &mut dyn DynAtatClient = &mut client;
+            client.send_retry(&ScanOperatorsTwice)
+        })
+        .await
+        .unwrap();
+        sent.await.unwrap();
+
+        assert_eq!(res, Ok(NoResponse));
+    }
+
+    #[tokio::test]
+    async fn resp_prefix_mismatch() {
+        let (mut client, mut tx, rx) = setup!(Config::new());
+        let cmd = TestRespStringCmd {
+            fun: Functionality::APM,
+            rst: None,
+        };
+
+        let sent = tokio::spawn(async move {
+            tx.next_message_pure().await;
+            // Late response to a previous command
+            rx.signal_response(Ok(b"+CREG: 1")).unwrap();
+        });
+        let res = tokio::task::spawn_blocking(move || client.send(&cmd))
+            .await
+            .unwrap();
+        sent.await.unwrap();
+
+        assert_eq!(res, Err(Error::Desync));
+    }
+
     // Test response containing string
     #[tokio::test]
     async fn response_string() {
@@ -486,6 +1550,41 @@ mod test {
         assert_ne!(0, CALL_COUNT.load(Ordering::Relaxed));
     }
 
+    #[tokio::test]
+    async fn deadline_exceeded() {
+        let (mut client, mut tx, _rx) = setup!(Config::new());
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        let sent = tokio::spawn(async move {
+            tx.next_message_pure().await;
+            // Do not emit a response, the command timeout is much longer
+        });
+
+        tokio::task::spawn_blocking(move || {
+            let start = Instant::now();
+            let deadline = start + Duration::from_millis(100);
+            assert_eq!(
+                Err(Error::DeadlineExceeded),
+                client.send_with_deadline(&cmd, deadline)
+            );
+            assert!(Instant::now() < start + Duration::from_millis(1000));
+
+            // The cooldown of the command would end after an expired deadline
+            assert_eq!(
+                Err(Error::DeadlineExceeded),
+                client.send_with_deadline(&cmd, Instant::now())
+            );
+        })
+        .await
+        .unwrap();
+
+        sent.await.unwrap();
+    }
+
     #[tokio::test]
     async fn custom_timeout_modified_during_request() {
         static CALL_COUNT: AtomicU64 = AtomicU64::new(0);
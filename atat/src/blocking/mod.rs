@@ -1,30 +1,41 @@
-mod blocking_timer;
+pub(crate) mod blocking_timer;
 mod client;
 
 pub use client::Client;
 
-use crate::{AtatCmd, Error};
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    response::{anchor_response, parse_validated},
+    AtatCmd, Error, InternalError,
+};
 
 pub trait AtatClient {
     /// Send an AT command.
     ///
     /// `cmd` must implement [`AtatCmd`].
     ///
-    /// This function will block until a response is received, if in Timeout or
-    /// Blocking mode. In Nonblocking mode, the send can be called until it no
-    /// longer returns `nb::Error::WouldBlock`, or `self.check_response(cmd)` can
-    /// be called, with the same result.
+    /// This function will block until a response is received. See
+    /// [`Client::send_nonblocking`] for sending without waiting for the
+    /// response.
     ///
     /// This function will also make sure that at least `self.config.cmd_cooldown`
     /// has passed since the last response or URC has been received, to allow
-    /// the slave AT device time to deliver URC's.
+    /// the slave AT device time to deliver URC's. Commands that need a longer
+    /// quiet time afterwards can override this with [`AtatCmd::COOLDOWN_MS`].
     fn send<A: AtatCmd>(&mut self, cmd: &A) -> Result<A::Response, Error>;
 
+    /// Called by [`AtatClient::send_retry`] before each attempt, such that
+    /// the client can report the attempt of the command in flight. Does
+    /// nothing by default.
+    fn set_attempt(&mut self, _attempt: u8) {}
+
     fn send_retry<A: AtatCmd>(&mut self, cmd: &A) -> Result<A::Response, Error> {
         for attempt in 1..=A::ATTEMPTS {
             if attempt > 1 {
                 debug!("Attempt {}:", attempt);
             }
+            self.set_attempt(attempt);
 
             match self.send(cmd) {
                 Err(Error::Timeout) => {}
@@ -33,4 +44,116 @@ pub trait AtatClient {
         }
         Err(Error::Timeout)
     }
+
+    /// Send an AT command like [`AtatClient::send_retry`], but give up with
+    /// [`Error::DeadlineExceeded`] once `deadline` has passed. The deadline
+    /// caps the total time spent on the call, including all attempts and
+    /// cooldowns. A command is never cancelled while it is being written.
+    ///
+    /// The default implementation only checks the deadline before sending,
+    /// and reports a timeout past the deadline as exceeded.
+    fn send_with_deadline<A: AtatCmd>(
+        &mut self,
+        cmd: &A,
+        deadline: Instant,
+    ) -> Result<A::Response, Error> {
+        if Instant::now() >= deadline {
+            return Err(Error::DeadlineExceeded);
+        }
+        match self.send_retry(cmd) {
+            Err(Error::Timeout) if Instant::now() >= deadline => Err(Error::DeadlineExceeded),
+            res => res,
+        }
+    }
+}
+
+/// A type erased command, see [`DynAtatClient`].
+pub struct DynCmd<'a> {
+    /// Name of the command, reported by [`Client::state`]
+    pub name: &'static str,
+    /// Write the request, and return the number of written bytes
+    pub write: &'a dyn Fn(&mut [u8]) -> usize,
+    /// The response timeout, or `None` if the command does not expect a
+    /// response code
+    pub timeout: Option<Duration>,
+    /// Number of attempts on a timeout, see [`AtatCmd::ATTEMPTS`]
+    pub attempts: u8,
+    /// Whether the command is aborted on a timeout, see
+    /// [`AtatCmd::CAN_ABORT`]
+    pub abortable: bool,
+    /// Final result codes besides `OK`, see [`AtatCmd::SUCCESS_CODES`]
+    pub success_codes: &'static [&'static [u8]],
+    /// Final result codes besides `ERROR`, see [`AtatCmd::ERROR_CODES`]
+    pub error_codes: &'static [&'static [u8]],
+    /// Id of the round-trip time statistics, see [`AtatCmd::STATS_ID`]
+    pub stats_id: Option<u8>,
+}
+
+/// Object safe subset of [`AtatClient`], such that drivers can hold a
+/// `&mut dyn DynAtatClient` rather than being generic over the client.
+///
+/// Commands are sent through `send` and `send_retry` on a
+/// `dyn DynAtatClient`, like [`AtatClient::send`] and
+/// [`AtatClient::send_retry`]: a restart of the device is awaited, an
+/// [`AtatCmd::CAN_ABORT`] command is aborted on a timeout, and transient
+/// errors are retried as configured by [`Config::retry`]. As the command is
+/// erased:
+/// - the request is written by [`AtatCmd::write`], even if the command has
+///   an [`AtatCmd::WIRE_IMAGE`]
+/// - its [`AtatCmd::COOLDOWN_MS`] is not supported, the
+///   [`Config::cmd_cooldown`] applies
+/// - its [`AtatCmd::CONTINUATION`] is not supported, a paginated response
+///   fails with [`Error::InvalidResponse`]
+///
+/// As by [`AtatClient::send_retry`], only timeouts are reattempted,
+/// regardless of [`AtatCmd::REATTEMPT_ON_PARSE_ERR`].
+///
+/// [`Config::retry`]: crate::Config::retry
+/// [`Config::cmd_cooldown`]: crate::Config::cmd_cooldown
+pub trait DynAtatClient {
+    /// Send a type erased command, handing its response to `parse`. The
+    /// command is attempted up to [`DynCmd::attempts`] times.
+    fn send_dyn(
+        &mut self,
+        cmd: DynCmd<'_>,
+        parse: &mut dyn FnMut(Result<&[u8], InternalError>) -> Result<(), Error>,
+    ) -> Result<(), Error>;
+}
+
+impl dyn DynAtatClient + '_ {
+    /// Send an AT command, like [`AtatClient::send`].
+    pub fn send<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        self.send_erased(cmd, 1)
+    }
+
+    /// Send an AT command, reattempting it on a timeout, like
+    /// [`AtatClient::send_retry`].
+    pub fn send_retry<Cmd: AtatCmd>(&mut self, cmd: &Cmd) -> Result<Cmd::Response, Error> {
+        self.send_erased(cmd, Cmd::ATTEMPTS)
+    }
+
+    fn send_erased<Cmd: AtatCmd>(
+        &mut self,
+        cmd: &Cmd,
+        attempts: u8,
+    ) -> Result<Cmd::Response, Error> {
+        let dyn_cmd = DynCmd {
+            name: core::any::type_name::<Cmd>(),
+            write: &|buf| cmd.write(buf),
+            timeout: Cmd::EXPECTS_RESPONSE_CODE
+                .then(|| Duration::from_millis(Cmd::MAX_TIMEOUT_MS.into())),
+            attempts,
+            abortable: Cmd::CAN_ABORT,
+            success_codes: Cmd::SUCCESS_CODES,
+            error_codes: Cmd::ERROR_CODES,
+            stats_id: Cmd::STATS_ID,
+        };
+
+        let mut response = None;
+        self.send_dyn(dyn_cmd, &mut |resp| {
+            response = Some(parse_validated(cmd, anchor_response::<Cmd>(resp))?);
+            Ok(())
+        })?;
+        response.ok_or(Error::InvalidResponse)
+    }
 }
@@ -0,0 +1,410 @@
+//! Runtime discovery of the parameter ranges advertised by AT test commands.
+//!
+//! Most AT commands support a test form, `AT+CMD=?`, where the modem responds
+//! with the values it accepts for each parameter, e.g. `+USOCR: (6,17)` or
+//! `+CFUN: (0,1,4,19),(0-1)`. Drivers can issue a [`TestCmd`] and inspect the
+//! resulting [`Capabilities`] instead of hardcoding what a given firmware
+//! version supports.
+//!
+//! ```
+//! use atat::{AtatCmd, Capabilities, TestCmd};
+//!
+//! let cmd = TestCmd::<64>::new("+USOCR");
+//! let caps: Capabilities<64> = cmd.parse(Ok(b"+USOCR: (6,17)")).unwrap();
+//!
+//! assert!(caps.supports(0, 17));
+//! assert!(!caps.supports(0, 16));
+//! ```
+use heapless::String;
+
+use crate::{AtatCmd, AtatResp, Error, InternalError};
+
+/// Issues the test form (`AT<cmd>=?`) of a command, and parses the response
+/// into [`Capabilities`].
+///
+/// `N` is the maximum number of bytes of the response that can be stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestCmd<'a, const N: usize> {
+    cmd: &'a str,
+}
+
+impl<'a, const N: usize> TestCmd<'a, N> {
+    /// Framing of the command name, `AT` and `=?\r\n`
+    const FRAMING_LEN: usize = 6;
+
+    /// Create a test command for `cmd`, including any leading `+`, e.g.
+    /// `"+USOCR"`.
+    ///
+    /// # Panics
+    ///
+    /// If the test command does not fit [`AtatCmd::MAX_LEN`], at compile
+    /// time if created in a const context.
+    #[must_use]
+    pub const fn new(cmd: &'a str) -> Self {
+        core::assert!(
+            cmd.len() + Self::FRAMING_LEN <= <Self as AtatCmd>::MAX_LEN,
+            "Test command too long"
+        );
+        Self { cmd }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a, const N: usize> defmt::Format for TestCmd<'a, N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "TestCmd({=str})", self.cmd)
+    }
+}
+
+impl<'a, const N: usize> AtatCmd for TestCmd<'a, N> {
+    type Response = Capabilities<N>;
+
+    const MAX_LEN: usize = 64;
+
+    fn write(&self, buf: &mut [u8]) -> usize {
+        let len = self.cmd.len() + Self::FRAMING_LEN;
+        buf[..2].copy_from_slice(b"AT");
+        buf[2..len - 4].copy_from_slice(self.cmd.as_bytes());
+        buf[len - 4..len].copy_from_slice(b"=?\r\n");
+        len
+    }
+
+    fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error> {
+        Capabilities::from_bytes(resp?)
+    }
+}
+
+/// The parameter ranges advertised in the response to an AT test command.
+///
+/// Parameters are indexed in the order they appear in the response. Each
+/// parameter is either a parenthesized list of values and ranges, like
+/// `(0,1,4-6)` or `("IP","IPV6")`, or a single bare value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities<const N: usize> {
+    raw: String<N>,
+}
+
+impl<const N: usize> AtatResp for Capabilities<N> {}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for Capabilities<N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Capabilities({=str})", self.raw.as_str())
+    }
+}
+
+impl<const N: usize> Capabilities<N> {
+    /// Parse the response to a test command, with or without the leading
+    /// `+CMD:` prefix.
+    pub fn from_bytes(resp: &[u8]) -> Result<Self, Error> {
+        let resp = core::str::from_utf8(resp).map_err(|_| Error::Parse)?;
+        let resp = strip_prefix(resp.trim());
+
+        let mut depth = 0usize;
+        let mut quoted = false;
+        for c in resp.bytes() {
+            match c {
+                b'"' => quoted = !quoted,
+                b'(' if !quoted => depth += 1,
+                b')' if !quoted => depth = depth.checked_sub(1).ok_or(Error::Parse)?,
+                _ => {}
+            }
+        }
+        if depth != 0 || quoted {
+            return Err(Error::Parse);
+        }
+
+        Ok(Self {
+            raw: String::try_from(resp).map_err(|_| Error::Parse)?,
+        })
+    }
+
+    /// The raw parameter list, without the `+CMD:` prefix.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        self.raw.as_str()
+    }
+
+    /// Iterate over the advertised parameters.
+    pub fn params(&self) -> impl Iterator<Item = Param<'_>> {
+        Split::new(self.raw.as_str()).map(Param::new)
+    }
+
+    /// Get the parameter at `index`, if advertised.
+    #[must_use]
+    pub fn param(&self, index: usize) -> Option<Param<'_>> {
+        self.params().nth(index)
+    }
+
+    /// Whether the parameter at `index` accepts the numeric `value`.
+    #[must_use]
+    pub fn supports(&self, index: usize, value: i32) -> bool {
+        self.param(index).map_or(false, |p| p.contains(value))
+    }
+}
+
+/// A single parameter of [`Capabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Param<'a>(&'a str);
+
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for Param<'a> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Param({=str})", self.0)
+    }
+}
+
+impl<'a> Param<'a> {
+    fn new(s: &'a str) -> Self {
+        let s = s.trim();
+        let s = s
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .unwrap_or(s);
+        Self(s)
+    }
+
+    /// Whether no values are advertised for this parameter.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the values and ranges accepted by this parameter.
+    pub fn values(&self) -> impl Iterator<Item = Value<'a>> {
+        Split::new(self.0).map(Value::new)
+    }
+
+    /// Whether this parameter accepts the numeric `value`.
+    #[must_use]
+    pub fn contains(&self, value: i32) -> bool {
+        self.values().any(|v| match v {
+            Value::Range { start, end } => (start..=end).contains(&value),
+            Value::Str(_) => false,
+        })
+    }
+
+    /// Whether this parameter accepts the string `value`.
+    #[must_use]
+    pub fn contains_str(&self, value: &str) -> bool {
+        self.values().any(|v| v == Value::Str(value))
+    }
+
+    /// The lowest numeric value accepted by this parameter.
+    #[must_use]
+    pub fn min(&self) -> Option<i32> {
+        self.values()
+            .filter_map(|v| match v {
+                Value::Range { start, .. } => Some(start),
+                Value::Str(_) => None,
+            })
+            .min()
+    }
+
+    /// The highest numeric value accepted by this parameter.
+    #[must_use]
+    pub fn max(&self) -> Option<i32> {
+        self.values()
+            .filter_map(|v| match v {
+                Value::Range { end, .. } => Some(end),
+                Value::Str(_) => None,
+            })
+            .max()
+    }
+}
+
+/// A single value, or range of values, accepted by a [`Param`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value<'a> {
+    /// An inclusive numeric range. Single values are represented with
+    /// `start == end`.
+    Range { start: i32, end: i32 },
+    /// A string value, with any surrounding quotes removed.
+    Str(&'a str),
+}
+
+impl<'a> Value<'a> {
+    fn new(s: &'a str) -> Self {
+        let s = s.trim();
+        if let Some(s) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            return Self::Str(s);
+        }
+
+        // Skip the first character, to allow for a negative start value
+        let range = s
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '-')
+            .and_then(|(i, _)| Some((s[..i].parse().ok()?, s[i + 1..].parse().ok()?)));
+
+        match range {
+            Some((start, end)) => Self::Range { start, end },
+            None => match s.parse() {
+                Ok(v) => Self::Range { start: v, end: v },
+                Err(_) => Self::Str(s),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for Value<'a> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Range { start, end } => defmt::write!(f, "{}-{}", start, end),
+            Self::Str(s) => defmt::write!(f, "{=str}", s),
+        }
+    }
+}
+
+/// Strip a leading `+CMD:` prefix, if any.
+fn strip_prefix(resp: &str) -> &str {
+    if !resp.starts_with('+') {
+        return resp;
+    }
+    match resp.find(':') {
+        Some(i) if !resp[..i].contains(['(', '"', ',']) => resp[i + 1..].trim_start(),
+        _ => resp,
+    }
+}
+
+/// Iterator over the comma separated items of a string, ignoring commas
+/// within parentheses and quotes.
+struct Split<'a> {
+    rest: Option<&'a str>,
+}
+
+impl<'a> Split<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            rest: if s.is_empty() { None } else { Some(s) },
+        }
+    }
+}
+
+impl<'a> Iterator for Split<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let s = self.rest?;
+        let mut depth = 0usize;
+        let mut quoted = false;
+        for (i, c) in s.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                '(' if !quoted => depth += 1,
+                ')' if !quoted => depth = depth.saturating_sub(1),
+                ',' if !quoted && depth == 0 => {
+                    self.rest = Some(&s[i + 1..]);
+                    return Some(&s[..i]);
+                }
+                _ => {}
+            }
+        }
+        self.rest = None;
+        Some(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_test_cmd() {
+        let mut buf = [0; 64];
+        let len = TestCmd::<16>::new("+USOCR").write(&mut buf);
+        assert_eq!(&buf[..len], b"AT+USOCR=?\r\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "Test command too long")]
+    fn test_cmd_too_long() {
+        let _ = TestCmd::<16>::new(core::str::from_utf8(&[b'+'; 59]).unwrap());
+    }
+
+    #[test]
+    fn single_param() {
+        let caps = Capabilities::<32>::from_bytes(b"+USOCR: (6,17)").unwrap();
+        assert_eq!(caps.as_str(), "(6,17)");
+        assert_eq!(caps.params().count(), 1);
+        assert!(caps.supports(0, 6));
+        assert!(caps.supports(0, 17));
+        assert!(!caps.supports(0, 7));
+        assert!(!caps.supports(1, 6));
+    }
+
+    #[test]
+    fn multiple_params_and_ranges() {
+        let caps = Capabilities::<32>::from_bytes(b"+CFUN: (0,1,4,19),(0-1)").unwrap();
+        let fun = caps.param(0).unwrap();
+        assert_eq!(
+            fun.values().collect::<heapless::Vec<_, 4>>(),
+            [
+                Value::Range { start: 0, end: 0 },
+                Value::Range { start: 1, end: 1 },
+                Value::Range { start: 4, end: 4 },
+                Value::Range { start: 19, end: 19 },
+            ]
+        );
+        assert_eq!(fun.min(), Some(0));
+        assert_eq!(fun.max(), Some(19));
+
+        let rst = caps.param(1).unwrap();
+        assert_eq!(
+            rst.values().collect::<heapless::Vec<_, 1>>(),
+            [Value::Range { start: 0, end: 1 }]
+        );
+        assert!(caps.supports(1, 1));
+        assert!(!caps.supports(1, 2));
+    }
+
+    #[test]
+    fn negative_ranges() {
+        let caps = Capabilities::<32>::from_bytes(b"+UTEMP: (-40--10,-5-85)").unwrap();
+        let param = caps.param(0).unwrap();
+        assert_eq!(param.min(), Some(-40));
+        assert_eq!(param.max(), Some(85));
+        assert!(param.contains(-20));
+        assert!(!param.contains(-7));
+        assert!(param.contains(0));
+    }
+
+    #[test]
+    fn string_and_empty_params() {
+        let caps =
+            Capabilities::<64>::from_bytes(b"+CGDCONT: (1-16),(\"IP\",\"IPV6\"),,,(0-2)").unwrap();
+        assert_eq!(caps.params().count(), 5);
+        assert!(caps.param(1).unwrap().contains_str("IPV6"));
+        assert!(!caps.param(1).unwrap().contains_str("PPP"));
+        assert!(!caps.param(1).unwrap().contains(0));
+        assert!(caps.param(2).unwrap().is_empty());
+        assert!(caps.supports(4, 2));
+    }
+
+    #[test]
+    fn without_prefix() {
+        let caps = Capabilities::<32>::from_bytes(b"(0-255)").unwrap();
+        assert!(caps.supports(0, 255));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Capabilities::<32>::from_bytes(b"+USOCR: (6,17"),
+            Err(Error::Parse)
+        );
+        assert_eq!(
+            Capabilities::<4>::from_bytes(b"+USOCR: (6,17)"),
+            Err(Error::Parse)
+        );
+    }
+
+    #[test]
+    fn parse_error_response() {
+        assert_eq!(
+            TestCmd::<32>::new("+USOCR").parse(Err(InternalError::Error)),
+            Err(Error::Error)
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Composition of several commands into a single command line, eg.
+//! `AT+CMEE=1;+CREG=2`, saving round-trips during initialization sequences.
+//!
+//! ```
+//! use atat::{atat_derive::{AtatCmd, AtatResp}, AtatCmd, Chained};
+//!
+//! #[derive(Clone, AtatResp)]
+//! pub struct NoResponse;
+//!
+//! #[derive(Clone, AtatCmd)]
+//! #[at_cmd("+CMEE", NoResponse)]
+//! pub struct SetReportMobileTerminationError {
+//!     #[at_arg(position = 0)]
+//!     pub n: u8,
+//! }
+//!
+//! #[derive(Clone, AtatCmd)]
+//! #[at_cmd("+CREG", NoResponse)]
+//! pub struct SetNetworkRegistrationStatus {
+//!     #[at_arg(position = 0)]
+//!     pub n: u8,
+//! }
+//!
+//! let cmee = SetReportMobileTerminationError { n: 1 };
+//! let creg = SetNetworkRegistrationStatus { n: 2 };
+//! let cmd = Chained((&cmee, &creg));
+//!
+//! let mut buf = [0; Chained::<(&SetReportMobileTerminationError, &SetNetworkRegistrationStatus)>::MAX_LEN];
+//! let len = cmd.write(&mut buf);
+//! assert_eq!(&buf[..len], b"AT+CMEE=1;+CREG=2\r\n");
+//! ```
+//!
+//! The device answers a command line with the information responses of the
+//! commands in order, followed by a single final result code. The combined
+//! response is split between the commands by their [`AtatCmd::RESP_PREFIX`]:
+//! each command takes the leading lines starting with its prefix, and
+//! commands without a prefix take no lines, except for the last command,
+//! which takes all remaining lines. Commands with an information response
+//! should thus set a prefix, unless they come last.
+//!
+//! The command line fails as a whole on an error result code, without telling
+//! which command failed. The device executes the commands up to the failing
+//! one.
+
+use crate::{AtatCmd, AtatResp, Error, InternalError};
+
+/// Several commands sent as a single command line, see the [module
+/// documentation](self).
+///
+/// Implemented for tuples of two to four commands. Borrow the commands, eg.
+/// `Chained((&a, &b))`, to chain commands without moving them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chained<T>(pub T);
+
+/// Length of `buf` without its trailing line termination
+fn trim_termination(buf: &[u8]) -> usize {
+    let mut len = buf.len();
+    while len > 0 && matches!(buf[len - 1], b'\r' | b'\n') {
+        len -= 1;
+    }
+    len
+}
+
+/// Append the command written at `buf[len..len + n]` to the command line in
+/// `buf[..len]`, returning the length of the command line.
+fn append(buf: &mut [u8], len: usize, n: usize) -> usize {
+    // The command line prefix is only given once
+    let skip = if buf[len..len + n].starts_with(b"AT") {
+        2
+    } else {
+        0
+    };
+    buf.copy_within(len + skip..len + n, len);
+    len + n - skip
+}
+
+/// Split off the lines of a combined response belonging to `Cmd`, returning
+/// them along with the remaining lines.
+fn split_response<Cmd: AtatCmd>(resp: &[u8], last: bool) -> (&[u8], &[u8]) {
+    let prefix = match Cmd::RESP_PREFIX {
+        _ if last => return (resp, &[]),
+        Some(prefix) => prefix,
+        None => return (&[], resp),
+    };
+
+    let mut end = 0;
+    let mut rest = resp;
+    while rest.starts_with(prefix) {
+        let line_len = rest
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .unwrap_or(rest.len());
+        end = resp.len() - rest.len() + line_len;
+        rest = rest.get(line_len + 2..).unwrap_or(&[]);
+    }
+    (&resp[..end], rest)
+}
+
+const fn min_attempts(attempts: &[u8]) -> u8 {
+    let mut min = u8::MAX;
+    let mut i = 0;
+    while i < attempts.len() {
+        if attempts[i] < min {
+            min = attempts[i];
+        }
+        i += 1;
+    }
+    min
+}
+
+const fn max_cooldown(cooldowns: &[Option<u32>]) -> Option<u32> {
+    let mut max = None;
+    let mut i = 0;
+    while i < cooldowns.len() {
+        max = match (max, cooldowns[i]) {
+            (Some(a), Some(b)) if a >= b => Some(a),
+            (_, Some(b)) => Some(b),
+            (max, None) => max,
+        };
+        i += 1;
+    }
+    max
+}
+
+macro_rules! impl_chained {
+    ($($cmd:ident: $idx:tt: $last:literal),+) => {
+        impl<$($cmd: AtatResp),+> AtatResp for ($($cmd,)+) {
+            fn validate(&mut self) -> Result<(), Error> {
+                $(self.$idx.validate()?;)+
+                Ok(())
+            }
+        }
+
+        impl<$($cmd: AtatCmd),+> AtatCmd for Chained<($($cmd,)+)> {
+            type Response = ($($cmd::Response,)+);
+
+            // Each command but the first drops its prefix and replaces the
+            // termination of the previous one with `;`
+            const MAX_LEN: usize = 0 $(+ $cmd::MAX_LEN)+;
+
+            const CAN_ABORT: bool = true $(&& $cmd::CAN_ABORT)+;
+
+            const MAX_TIMEOUT_MS: u32 = 0 $(+ $cmd::MAX_TIMEOUT_MS)+;
+
+            // Reattempting repeats every command of the line
+            const ATTEMPTS: u8 = min_attempts(&[$($cmd::ATTEMPTS),+]);
+
+            const REATTEMPT_ON_PARSE_ERR: bool = true $(&& $cmd::REATTEMPT_ON_PARSE_ERR)+;
+
+            const EXPECTS_RESPONSE_CODE: bool = false $(|| $cmd::EXPECTS_RESPONSE_CODE)+;
+
+            const COOLDOWN_MS: Option<u32> = max_cooldown(&[$($cmd::COOLDOWN_MS),+]);
+
+            fn write(&self, buf: &mut [u8]) -> usize {
+                let mut len = 0;
+                $(
+                    if len == 0 {
+                        len = self.0.$idx.write(buf);
+                    } else {
+                        len = trim_termination(&buf[..len]);
+                        buf[len] = b';';
+                        len += 1;
+                        let n = self.0.$idx.write(&mut buf[len..]);
+                        len = append(buf, len, n);
+                    }
+                )+
+                len
+            }
+
+            // The lines remaining after the last command are not read
+            #[allow(unused_assignments)]
+            fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error> {
+                let mut rest = resp.map_err(Error::from)?;
+                Ok(($(
+                    {
+                        let (lines, remaining) = split_response::<$cmd>(rest, $last);
+                        rest = remaining;
+                        self.0.$idx.parse(Ok(lines))?
+                    },
+                )+))
+            }
+        }
+    };
+}
+
+impl_chained!(A: 0: false, B: 1: true);
+impl_chained!(A: 0: false, B: 1: false, C: 2: true);
+impl_chained!(A: 0: false, B: 1: false, C: 2: false, D: 3: true);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        self as atat,
+        atat_derive::{AtatCmd, AtatResp},
+    };
+
+    #[derive(Debug, Clone, PartialEq, AtatResp)]
+    struct NoResponse;
+
+    #[derive(Debug, Clone, PartialEq, AtatResp)]
+    struct NetworkRegistrationStatus {
+        #[at_arg(position = 0)]
+        n: u8,
+        #[at_arg(position = 1)]
+        stat: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, AtatResp)]
+    struct SignalQuality {
+        #[at_arg(position = 0)]
+        rssi: u8,
+        #[at_arg(position = 1)]
+        ber: u8,
+    }
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CMEE", NoResponse, timeout_ms = 100)]
+    struct SetReportMobileTerminationError {
+        #[at_arg(position = 0)]
+        n: u8,
+    }
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd(
+        "+CREG?",
+        NetworkRegistrationStatus,
+        resp_prefix = "+CREG:",
+        attempts = 3
+    )]
+    struct GetNetworkRegistrationStatus;
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CSQ", SignalQuality, attempts = 2)]
+    struct GetSignalQuality;
+
+    #[test]
+    fn write() {
+        type Cmd<'a> = Chained<(
+            &'a SetReportMobileTerminationError,
+            GetNetworkRegistrationStatus,
+            GetSignalQuality,
+        )>;
+
+        let cmee = SetReportMobileTerminationError { n: 1 };
+        let cmd: Cmd = Chained((&cmee, GetNetworkRegistrationStatus, GetSignalQuality));
+        let mut buf = [0; Cmd::MAX_LEN];
+        let len = cmd.write(&mut buf);
+        assert_eq!(&buf[..len], b"AT+CMEE=1;+CREG?;+CSQ\r\n");
+
+        assert_eq!(Cmd::MAX_TIMEOUT_MS, 2100);
+        assert_eq!(Cmd::ATTEMPTS, 1);
+    }
+
+    #[test]
+    fn parse() {
+        let cmd = Chained((
+            SetReportMobileTerminationError { n: 1 },
+            GetNetworkRegistrationStatus,
+            GetSignalQuality,
+        ));
+        assert_eq!(
+            cmd.parse(Ok(b"+CREG: 2,1\r\n+CSQ: 19,99")),
+            Ok((
+                NoResponse,
+                NetworkRegistrationStatus { n: 2, stat: 1 },
+                SignalQuality { rssi: 19, ber: 99 },
+            ))
+        );
+        assert_eq!(cmd.parse(Err(InternalError::Error)), Err(Error::Error));
+    }
+}
@@ -1,5 +1,7 @@
 use embassy_time::{Duration, Instant};
 
+use crate::TraceLevel;
+
 /// Configuration of both the ingress manager, and the AT client. Some of these
 /// parameters can be changed on the fly, through issuing a [`Command`] from the
 /// client.
@@ -10,11 +12,60 @@ pub struct Config {
     pub(crate) cmd_cooldown: Duration,
     pub(crate) tx_timeout: Duration,
     pub(crate) flush_timeout: Duration,
+    pub(crate) tx_chunk_size: Option<usize>,
+    pub(crate) tx_chunk_delay: Duration,
+    pub(crate) loopback: bool,
+    pub(crate) init_attempts: u8,
+    pub(crate) command_termination: Option<u8>,
+    pub(crate) response_formatting: Option<u8>,
+    pub(crate) abort_token: &'static [u8],
+    pub(crate) abort_timeout: Duration,
+    pub(crate) write_recovery: Option<&'static [u8]>,
+    pub(crate) retry_partial_write: bool,
+    pub(crate) attention_sequence: &'static [u8],
+    pub(crate) trace: TraceLevel,
+    pub(crate) retry_attempts: u8,
+    pub(crate) retry_backoff: Duration,
     pub(crate) get_response_timeout: GetTimeout,
+    pub(crate) ready_timeout: Option<Duration>,
+    pub(crate) tx_hook: Option<TraceHook>,
+    pub(crate) rx_hook: Option<TraceHook>,
 }
 
 pub type GetTimeout = fn(Instant, Duration) -> Instant;
 
+/// Hook mirroring the raw bytes of the AT traffic, see
+/// [`Config::trace_hooks`].
+pub type TraceHook = fn(&[u8]);
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Config {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Config {{ cmd_cooldown: {=u64}ms, tx_timeout: {=u64}ms, flush_timeout: {=u64}ms, tx_chunk_size: {}, tx_chunk_delay: {=u64}ms, loopback: {=bool}, init_attempts: {=u8}, command_termination: {}, response_formatting: {}, abort_token: {=[u8]:a}, abort_timeout: {=u64}ms, write_recovery: {}, retry_partial_write: {=bool}, attention_sequence: {=[u8]:a}, trace: {}, retry_attempts: {=u8}, retry_backoff: {=u64}ms, ready_timeout: {=u64}ms }}",
+            self.cmd_cooldown.as_millis(),
+            self.tx_timeout.as_millis(),
+            self.flush_timeout.as_millis(),
+            self.tx_chunk_size,
+            self.tx_chunk_delay.as_millis(),
+            self.loopback,
+            self.init_attempts,
+            self.command_termination,
+            self.response_formatting,
+            self.abort_token,
+            self.abort_timeout.as_millis(),
+            self.write_recovery,
+            self.retry_partial_write,
+            self.attention_sequence,
+            self.trace,
+            self.retry_attempts,
+            self.retry_backoff.as_millis(),
+            self.ready_timeout.map_or(0, |t| t.as_millis()),
+        )
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::new()
@@ -32,7 +83,24 @@ impl Config {
             cmd_cooldown: Duration::from_millis(20),
             tx_timeout: Duration::from_millis(1000),
             flush_timeout: Duration::from_millis(1000),
+            tx_chunk_size: None,
+            tx_chunk_delay: Duration::from_ticks(0),
+            loopback: false,
+            init_attempts: 10,
+            command_termination: None,
+            response_formatting: None,
+            abort_token: &[0x1b],
+            abort_timeout: Duration::from_millis(500),
+            write_recovery: None,
+            retry_partial_write: false,
+            attention_sequence: b"\r\nAT\r\n",
+            trace: TraceLevel::Frames,
+            retry_attempts: 1,
+            retry_backoff: Duration::from_ticks(0),
             get_response_timeout,
+            ready_timeout: None,
+            tx_hook: None,
+            rx_hook: None,
         }
     }
 
@@ -48,12 +116,171 @@ impl Config {
         self
     }
 
+    /// Write requests in chunks of at most `size` bytes, rather than as a
+    /// single write. This allows large requests, eg. certificate uploads, to
+    /// be sent without starving other tasks, as the async client yields to
+    /// the executor between chunks. The `tx_timeout` applies to each chunk.
+    #[must_use]
+    pub const fn tx_chunk_size(mut self, size: usize) -> Self {
+        self.tx_chunk_size = Some(size);
+        self
+    }
+
+    /// Delay between writing two chunks, when `tx_chunk_size` is set. Useful
+    /// for devices with a small receive buffer and no flow control.
+    #[must_use]
+    pub const fn tx_chunk_delay(mut self, duration: Duration) -> Self {
+        self.tx_chunk_delay = duration;
+        self
+    }
+
+    /// Signal every written request straight back as its own response,
+    /// instead of waiting for the device to respond. Intended for self-tests
+    /// of the AT pipeline, see [`SelfTest`].
+    ///
+    /// [`SelfTest`]: crate::loopback::SelfTest
+    #[must_use]
+    pub const fn loopback(mut self, enabled: bool) -> Self {
+        self.loopback = enabled;
+        self
+    }
+
     #[must_use]
     pub const fn cmd_cooldown(mut self, duration: Duration) -> Self {
         self.cmd_cooldown = duration;
         self
     }
 
+    /// Number of times each step of the initialization handshake is
+    /// attempted, before `init` gives up. Defaults to 10.
+    #[must_use]
+    pub const fn init_attempts(mut self, attempts: u8) -> Self {
+        self.init_attempts = attempts;
+        self
+    }
+
+    /// Command line termination (`S3`) and response formatting (`S4`)
+    /// characters to apply during the initialization handshake, see
+    /// [`Config::command_termination`] and [`Config::response_formatting`].
+    #[must_use]
+    pub const fn line_termination(self, s3: u8, s4: u8) -> Self {
+        self.command_termination(s3).response_formatting(s4)
+    }
+
+    /// Command line termination character (`S3`) to apply during the
+    /// initialization handshake. Left at the device default, usually `\r`,
+    /// if not set.
+    ///
+    /// Only tells the device how commands are terminated: the commands must
+    /// be written to match, eg. with `#[at_cmd(.., termination = "\r")]`.
+    #[must_use]
+    pub const fn command_termination(mut self, s3: u8) -> Self {
+        self.command_termination = Some(s3);
+        self
+    }
+
+    /// Response formatting character (`S4`) to apply during the
+    /// initialization handshake, independently of the command termination.
+    /// Left at the device default, usually `\n`, if not set.
+    ///
+    /// The digester must be set up to match the line endings of the
+    /// responses, eg. with [`AtDigester::with_line_normalization`] for
+    /// devices answering with bare `\n`.
+    ///
+    /// [`AtDigester::with_line_normalization`]: crate::digest::AtDigester::with_line_normalization
+    #[must_use]
+    pub const fn response_formatting(mut self, s4: u8) -> Self {
+        self.response_formatting = Some(s4);
+        self
+    }
+
+    /// Bytes written to abort a running command, see [`AtatCmd::CAN_ABORT`].
+    /// Most devices abort on any character received while a command is
+    /// running. Defaults to ESC.
+    ///
+    /// [`AtatCmd::CAN_ABORT`]: crate::AtatCmd::CAN_ABORT
+    #[must_use]
+    pub const fn abort_token(mut self, token: &'static [u8]) -> Self {
+        self.abort_token = token;
+        self
+    }
+
+    /// How long to wait for the final result code of an aborted command
+    /// before sending the next command, so it is not taken for the response
+    /// of the next command. Defaults to 500 ms.
+    #[must_use]
+    pub const fn abort_timeout(mut self, duration: Duration) -> Self {
+        self.abort_timeout = duration;
+        self
+    }
+
+    /// Bytes written to terminate a request interrupted by a write error, so
+    /// the device discards the partial command line rather than prepending it
+    /// to the next request. The answer of the device to the partial command
+    /// is awaited for up to the [`Config::tx_timeout`], and discarded.
+    /// Defaults to `None`, no recovery. Typically `Some(b"\r")`, terminating
+    /// the partial command line.
+    #[must_use]
+    pub const fn write_recovery(mut self, sequence: Option<&'static [u8]>) -> Self {
+        self.write_recovery = sequence;
+        self
+    }
+
+    /// Write a request interrupted by a write error once more, after the
+    /// partial command line was terminated, see [`Config::write_recovery`].
+    /// Only enable this for links prone to transient write errors, and
+    /// devices which do not execute a partial command line.
+    #[must_use]
+    pub const fn retry_partial_write(mut self, enabled: bool) -> Self {
+        self.retry_partial_write = enabled;
+        self
+    }
+
+    /// Bytes written to resynchronize with the device, eg. after a hot-plug,
+    /// until it answers with a clean `OK`. The leading line termination ends
+    /// any command line left half written. Defaults to `\r\nAT\r\n`.
+    #[must_use]
+    pub const fn attention_sequence(mut self, sequence: &'static [u8]) -> Self {
+        self.attention_sequence = sequence;
+        self
+    }
+
+    /// Amount of detail logged about the commands sent, see [`TraceLevel`].
+    /// Adjustable at runtime with eg. [`asynch::Client::set_trace`].
+    ///
+    /// [`asynch::Client::set_trace`]: crate::asynch::Client::set_trace
+    #[must_use]
+    pub const fn trace(mut self, level: TraceLevel) -> Self {
+        self.trace = level;
+        self
+    }
+
+    /// Have `send` attempt a command up to `attempts` times, when it times
+    /// out or fails with a transient `+CME ERROR`, see
+    /// [`Error::is_transient`]. The first retry is delayed by `backoff_ms`,
+    /// doubling with each further retry. Defaults to a single attempt.
+    ///
+    /// The retries happen within each attempt of `send_retry`, which retries
+    /// according to [`AtatCmd::ATTEMPTS`] on top. Within `send_with_deadline`,
+    /// a retry whose backoff would end after the deadline is given up with
+    /// [`Error::DeadlineExceeded`] instead.
+    ///
+    /// [`Error::is_transient`]: crate::Error::is_transient
+    /// [`Error::DeadlineExceeded`]: crate::Error::DeadlineExceeded
+    /// [`AtatCmd::ATTEMPTS`]: crate::AtatCmd::ATTEMPTS
+    #[must_use]
+    pub const fn retry(mut self, attempts: u8, backoff_ms: u32) -> Self {
+        self.retry_attempts = attempts;
+        self.retry_backoff = Duration::from_millis(backoff_ms as u64);
+        self
+    }
+
+    /// Delay before retry `retry` of a command, starting at 0, see
+    /// [`Config::retry`].
+    pub(crate) fn retry_backoff(&self, retry: u8) -> Duration {
+        self.retry_backoff * (1 << retry.min(16))
+    }
+
     /// Set a custom computation for determining the reponse timeout instant
     /// for a request sent at a specific time. The timeout is recomputed
     /// continously, so it is possible to for example artificially extend the
@@ -65,4 +292,193 @@ impl Config {
         self.get_response_timeout = compute;
         self
     }
+
+    /// Hold back the first command after the client is created, or after the
+    /// device restarted, until the device printed a boot banner recognized by
+    /// [`AtDigester::with_boot_banners`], eg. `+PACSP0`. If no banner is
+    /// received within `timeout`, the client probes the device with the
+    /// [`Config::attention_sequence`] until it answers, as by `resync`.
+    ///
+    /// [`AtDigester::with_boot_banners`]: crate::AtDigester::with_boot_banners
+    #[must_use]
+    pub const fn wait_ready(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = Some(timeout);
+        self
+    }
+
+    /// Mirror all AT traffic, eg. to a debug UART or a flash log, by calling
+    /// `on_tx` with the bytes written by the client, and `on_rx` with the
+    /// bytes received, as they are. The hooks should return quickly.
+    ///
+    /// Clients reading from an [`Ingress`] leave the receive side to it, so
+    /// pass `on_rx` to [`Ingress::set_rx_hook`] as well, to run it on the
+    /// ingress task. Clients reading the device themselves, eg.
+    /// [`SimpleClient`], call `on_rx` directly.
+    ///
+    /// [`Ingress`]: crate::Ingress
+    /// [`Ingress::set_rx_hook`]: crate::Ingress::set_rx_hook
+    /// [`SimpleClient`]: crate::asynch::SimpleClient
+    #[must_use]
+    pub const fn trace_hooks(mut self, on_tx: TraceHook, on_rx: TraceHook) -> Self {
+        self.tx_hook = Some(on_tx);
+        self.rx_hook = Some(on_rx);
+        self
+    }
+
+    pub(crate) fn trace_tx(&self, bytes: &[u8]) {
+        if let Some(hook) = self.tx_hook {
+            hook(bytes);
+        }
+    }
+
+    pub(crate) fn trace_rx(&self, bytes: &[u8]) {
+        if let Some(hook) = self.rx_hook {
+            hook(bytes);
+        }
+    }
+}
+
+/// A partial update of a [`Config`], applied through eg.
+/// [`asynch::Client::reconfigure`]. Fields left unset keep their current
+/// value.
+///
+/// [`asynch::Client::reconfigure`]: crate::asynch::Client::reconfigure
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ConfigDelta {
+    cmd_cooldown: Option<Duration>,
+    tx_timeout: Option<Duration>,
+    flush_timeout: Option<Duration>,
+    tx_chunk_size: Option<Option<usize>>,
+    tx_chunk_delay: Option<Duration>,
+    loopback: Option<bool>,
+    get_response_timeout: Option<GetTimeout>,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ConfigDelta {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "ConfigDelta {{ cmd_cooldown: {}, tx_timeout: {}, flush_timeout: {}, tx_chunk_size: {}, tx_chunk_delay: {}, loopback: {}, .. }}",
+            self.cmd_cooldown.map(|d| d.as_millis()),
+            self.tx_timeout.map(|d| d.as_millis()),
+            self.flush_timeout.map(|d| d.as_millis()),
+            self.tx_chunk_size,
+            self.tx_chunk_delay.map(|d| d.as_millis()),
+            self.loopback,
+        )
+    }
+}
+
+impl ConfigDelta {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            cmd_cooldown: None,
+            tx_timeout: None,
+            flush_timeout: None,
+            tx_chunk_size: None,
+            tx_chunk_delay: None,
+            loopback: None,
+            get_response_timeout: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn cmd_cooldown(mut self, duration: Duration) -> Self {
+        self.cmd_cooldown = Some(duration);
+        self
+    }
+
+    #[must_use]
+    pub const fn tx_timeout(mut self, duration: Duration) -> Self {
+        self.tx_timeout = Some(duration);
+        self
+    }
+
+    #[must_use]
+    pub const fn flush_timeout(mut self, duration: Duration) -> Self {
+        self.flush_timeout = Some(duration);
+        self
+    }
+
+    /// Change the chunk size of writes, or write requests as a single write
+    /// with `None`.
+    #[must_use]
+    pub const fn tx_chunk_size(mut self, size: Option<usize>) -> Self {
+        self.tx_chunk_size = Some(size);
+        self
+    }
+
+    #[must_use]
+    pub const fn tx_chunk_delay(mut self, duration: Duration) -> Self {
+        self.tx_chunk_delay = Some(duration);
+        self
+    }
+
+    #[must_use]
+    pub const fn loopback(mut self, enabled: bool) -> Self {
+        self.loopback = Some(enabled);
+        self
+    }
+
+    #[must_use]
+    pub const fn get_response_timeout(mut self, compute: GetTimeout) -> Self {
+        self.get_response_timeout = Some(compute);
+        self
+    }
+}
+
+impl Config {
+    /// Apply the fields set in `delta`.
+    pub fn apply(&mut self, delta: ConfigDelta) {
+        if let Some(cmd_cooldown) = delta.cmd_cooldown {
+            self.cmd_cooldown = cmd_cooldown;
+        }
+        if let Some(tx_timeout) = delta.tx_timeout {
+            self.tx_timeout = tx_timeout;
+        }
+        if let Some(flush_timeout) = delta.flush_timeout {
+            self.flush_timeout = flush_timeout;
+        }
+        if let Some(tx_chunk_size) = delta.tx_chunk_size {
+            self.tx_chunk_size = tx_chunk_size;
+        }
+        if let Some(tx_chunk_delay) = delta.tx_chunk_delay {
+            self.tx_chunk_delay = tx_chunk_delay;
+        }
+        if let Some(loopback) = delta.loopback {
+            self.loopback = loopback;
+        }
+        if let Some(get_response_timeout) = delta.get_response_timeout {
+            self.get_response_timeout = get_response_timeout;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_delta() {
+        let mut config = Config::new().tx_chunk_size(16);
+        config.apply(
+            ConfigDelta::new()
+                .cmd_cooldown(Duration::from_millis(500))
+                .tx_chunk_size(None),
+        );
+
+        assert_eq!(
+            config,
+            Config::new().cmd_cooldown(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn retry_backoff() {
+        let config = Config::new().retry(4, 100);
+        assert_eq!(config.retry_backoff(0), Duration::from_millis(100));
+        assert_eq!(config.retry_backoff(2), Duration::from_millis(400));
+    }
 }
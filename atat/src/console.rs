@@ -0,0 +1,190 @@
+//! Interactive debug console, for poking the device with raw AT commands
+//! during development, eg. over RTT or a debug UART.
+//!
+//! Input is fed to the [`Console`] as it arrives. Once a full line has been
+//! received, it is sent as-is through the regular client, such that any
+//! cooldowns and pending responses are respected, and the raw response is
+//! written to the output.
+//!
+//! ```
+//! # async fn example(client: &mut impl atat::asynch::AtatClient, rtt_in: &[u8], mut rtt_out: impl core::fmt::Write) -> core::fmt::Result {
+//! use atat::console::Console;
+//!
+//! let mut console = Console::<128>::new();
+//! console.feed(client, rtt_in, &mut rtt_out).await
+//! # }
+//! ```
+use core::fmt::Write;
+
+use heapless::String;
+
+use crate::{asynch, blocking, Error};
+
+/// An input line did not fit in the console buffer, and was discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LineTooLong;
+
+/// Line buffered console, sending lines of at most `N - 2` characters.
+pub struct Console<const N: usize> {
+    line: String<N>,
+    overflow: bool,
+}
+
+impl<const N: usize> Console<N> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            line: String::new(),
+            overflow: false,
+        }
+    }
+
+    /// Push a single input byte, returning the command to send once a line
+    /// is complete. Handles backspace, and ignores empty lines.
+    pub fn push(&mut self, c: u8) -> Option<Result<String<N>, LineTooLong>> {
+        match c {
+            b'\r' | b'\n' => {
+                let line = core::mem::take(&mut self.line);
+                if core::mem::take(&mut self.overflow) {
+                    return Some(Err(LineTooLong));
+                }
+                if line.trim().is_empty() {
+                    return None;
+                }
+                let mut cmd = line;
+                cmd.push_str("\r\n").ok()?;
+                Some(Ok(cmd))
+            }
+            0x08 | 0x7f => {
+                self.line.pop();
+                None
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => {
+                // Leave room for the line termination
+                if self.line.len() + 2 >= N || self.line.push(c as char).is_err() {
+                    self.overflow = true;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Feed input to the console, sending any completed lines through
+    /// `client` and writing the responses to `out`.
+    pub async fn feed<C: asynch::AtatClient, W: Write>(
+        &mut self,
+        client: &mut C,
+        input: &[u8],
+        out: &mut W,
+    ) -> core::fmt::Result {
+        for &c in input {
+            match self.push(c) {
+                Some(Ok(cmd)) => print_response(out, client.send(&cmd).await)?,
+                Some(Err(LineTooLong)) => writeln!(out, "ERROR: line too long")?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Blocking variant of [`Console::feed`].
+    pub fn feed_blocking<C: blocking::AtatClient, W: Write>(
+        &mut self,
+        client: &mut C,
+        input: &[u8],
+        out: &mut W,
+    ) -> core::fmt::Result {
+        for &c in input {
+            match self.push(c) {
+                Some(Ok(cmd)) => print_response(out, client.send(&cmd))?,
+                Some(Err(LineTooLong)) => writeln!(out, "ERROR: line too long")?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for Console<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> core::fmt::Debug for Console<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Console")
+            .field("line", &self.line.as_str())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for Console<N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Console {{ line: {=str}, .. }}", self.line.as_str())
+    }
+}
+
+fn print_response<W: Write>(
+    out: &mut W,
+    response: Result<String<256>, Error>,
+) -> core::fmt::Result {
+    match response {
+        Ok(response) => {
+            if !response.is_empty() {
+                writeln!(out, "{}", response)?;
+            }
+            writeln!(out, "OK")
+        }
+        Err(e) => writeln!(out, "ERROR: {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asynch::Client, Config, ResponseSlot};
+
+    #[test]
+    fn line_editing() {
+        let mut console = Console::<16>::new();
+        for &c in b"AT+CSX\x08Q" {
+            assert_eq!(console.push(c), None);
+        }
+        assert_eq!(
+            console.push(b'\r'),
+            Some(Ok(String::try_from("AT+CSQ\r\n").unwrap()))
+        );
+        assert_eq!(console.push(b'\n'), None);
+
+        for &c in b"AT+0123456789ABCD" {
+            assert_eq!(console.push(c), None);
+        }
+        assert_eq!(console.push(b'\n'), Some(Err(LineTooLong)));
+        assert_eq!(console.push(b'\n'), None);
+    }
+
+    #[tokio::test]
+    async fn feed() {
+        static RES_SLOT: ResponseSlot<256> = ResponseSlot::new();
+        let mut tx = [0; 64];
+        let mut buf = [0; 64];
+        let mut client = Client::new(
+            &mut tx[..],
+            &RES_SLOT,
+            &mut buf,
+            Config::new().loopback(true),
+        );
+
+        let mut console = Console::<64>::new();
+        let mut out = std::string::String::new();
+        console
+            .feed(&mut client, b"AT+CGMI\r\n", &mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, "AT+CGMI\r\n\nOK\n");
+    }
+}
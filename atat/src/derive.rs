@@ -1,9 +1,23 @@
 use heapless::{String, Vec};
-use serde_at::HexStr;
+use serde_at::{
+    serde::{
+        de::{MapAccess, SeqAccess},
+        ser::SerializeStruct,
+        Deserialize, Serialize,
+    },
+    DecimalComma, HexStr, Scaled, StrictHexStr, Timestamp,
+};
+
+use crate::Error;
 
 /// Trait used by [`atat_derive`] to estimate lengths of the serialized commands, at compile time.
 ///
 /// [`atat_derive`]: https://crates.io/crates/atat_derive
+#[diagnostic::on_unimplemented(
+    message = "the serialized length of `{Self}` is not known",
+    label = "`{Self}` does not implement `AtatLen`",
+    note = "derive `AtatLen` (or `AtatEnum`) for `{Self}`, or give the maximum length of the field with `#[at_arg(len = ..)]`"
+)]
 pub trait AtatLen {
     const LEN: usize;
 }
@@ -47,6 +61,24 @@ impl_length!(HexStr<u32>, 30);
 impl_length!(HexStr<u64>, 66);
 impl_length!(HexStr<u128>, 130);
 
+impl<T> AtatLen for StrictHexStr<T>
+where
+    HexStr<T>: AtatLen,
+{
+    const LEN: usize = <HexStr<T> as AtatLen>::LEN;
+}
+
+// "yyyy/MM/dd,hh:mm:ss+zz"
+impl_length!(Timestamp, 24);
+
+impl<S> AtatLen for Scaled<S> {
+    const LEN: usize = 3;
+}
+
+impl<T: AtatLen> AtatLen for DecimalComma<T> {
+    const LEN: usize = 1 + T::LEN + 1;
+}
+
 impl<const T: usize> AtatLen for String<T> {
     const LEN: usize = 1 + T + 1;
 }
@@ -72,13 +104,173 @@ impl<const L: usize> AtatLen for HexStr<[u8; L]> {
     const LEN: usize = (2 + L * 4 - 1) * 2;
 }
 
+/// Deserialize a response, as done by commands derived with [`atat_derive`].
+///
+/// With the `parse-diagnostics` feature enabled, failures also record where
+/// the response failed to parse, see [`take_parse_diagnostic`].
+///
+/// [`take_parse_diagnostic`]: crate::take_parse_diagnostic
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub fn parse_response<'a, T: Deserialize<'a>>(resp: &'a [u8]) -> Result<T, Error> {
+    #[cfg(feature = "parse-diagnostics")]
+    {
+        serde_at::from_slice_with_diagnostic(resp, serde_at::DeserializeOptions::default()).map_err(
+            |(_, d)| {
+                warn!(
+                    "Failed to parse field {:?} as {}, at position {}",
+                    d.field, d.expected, d.position
+                );
+                crate::error::set_parse_diagnostic(crate::ParseDiagnostic {
+                    field: d.field,
+                    position: d.position,
+                    expected: d.expected,
+                });
+                Error::Parse
+            },
+        )
+    }
+    #[cfg(not(feature = "parse-diagnostics"))]
+    {
+        serde_at::from_slice(resp).map_err(|_| Error::Parse)
+    }
+}
+
+/// Serialize a field of a command, as done by commands derived with
+/// [`atat_derive`].
+///
+/// The derived code goes through these helpers rather than calling serde
+/// directly, so a field type missing an implementation is reported at the
+/// field, against a bound of this module.
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub fn serialize_field<S, T>(state: &mut S, key: &'static str, value: &T) -> Result<(), S::Error>
+where
+    S: SerializeStruct,
+    T: ?Sized + Serialize,
+{
+    state.serialize_field(key, value)
+}
+
+/// A group of parameters serialized in place of a field of a command with
+/// `#[at_arg(flatten)]`, eg. an APN, username and password shared by several
+/// commands. Derived with [`atat_derive`].
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a parameter group",
+    label = "`{Self}` does not implement `AtatParams`",
+    note = "derive `AtatParams` for `{Self}`"
+)]
+pub trait AtatParams {
+    /// Number of separators between the parameters, including those of
+    /// nested groups, to account for multi-byte separators in
+    /// [`AtatCmd::MAX_LEN`](crate::AtatCmd::MAX_LEN).
+    const SEPARATORS: usize;
+
+    /// Serialize the parameters as fields of the enclosing command.
+    fn serialize_params<S: SerializeStruct>(&self, state: &mut S) -> Result<(), S::Error>;
+}
+
+impl<T: AtatParams + ?Sized> AtatParams for &T {
+    const SEPARATORS: usize = T::SEPARATORS;
+
+    fn serialize_params<S: SerializeStruct>(&self, state: &mut S) -> Result<(), S::Error> {
+        (**self).serialize_params(state)
+    }
+}
+
+/// Serialize a field marked with `#[at_arg(flatten)]`, as done by commands
+/// derived with [`atat_derive`]. See [`serialize_field`].
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub fn serialize_flattened<S, T>(state: &mut S, value: &T) -> Result<(), S::Error>
+where
+    S: SerializeStruct,
+    T: ?Sized + AtatParams,
+{
+    value.serialize_params(state)
+}
+
+/// Split off the footer line of a response declared with the
+/// `length_footer` option of `#[at_cmd(..)]`, eg. `+XLEN: 7` after a 7 byte
+/// payload, as done by commands derived with [`atat_derive`]. Returns the
+/// payload preceding the footer, or fails with [`Error::InvalidResponse`] if
+/// the footer is missing or its length does not match the payload.
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub fn strip_length_footer<'a>(resp: &'a [u8], footer: &[u8]) -> Result<&'a [u8], Error> {
+    let (payload, line) = match resp.windows(2).rposition(|w| w == b"\r\n") {
+        Some(i) => (&resp[..i], &resp[i + 2..]),
+        None => (&[][..], resp),
+    };
+    let len = line
+        .strip_prefix(footer)
+        .and_then(|len| core::str::from_utf8(len).ok())
+        .and_then(|len| len.trim().parse::<usize>().ok());
+    match len {
+        Some(len) if len == payload.len() => Ok(payload),
+        Some(len) => {
+            warn!(
+                "Response footer tells {} bytes, payload holds {}",
+                len,
+                payload.len()
+            );
+            Err(Error::InvalidResponse)
+        }
+        None => {
+            warn!(
+                "Response footer {:?} missing",
+                crate::helpers::LossyStr(footer)
+            );
+            Err(Error::InvalidResponse)
+        }
+    }
+}
+
+/// Prepend the prefix symbol of a command, chosen at runtime with the
+/// `prefix_field` option of `#[at_cmd(..)]`, to its name, as done by commands
+/// derived with [`atat_derive`]. `buf` holds at least 4 bytes more than `cmd`.
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub fn prefixed_cmd<'b>(prefix: char, cmd: &str, buf: &'b mut [u8]) -> &'b str {
+    let len = prefix.encode_utf8(buf).len();
+    buf[len..len + cmd.len()].copy_from_slice(cmd.as_bytes());
+    // Both parts are valid UTF-8
+    core::str::from_utf8(&buf[..len + cmd.len()]).unwrap()
+}
+
+/// Deserialize the next field of a response from a sequence, as done by
+/// responses derived with [`atat_derive`]. See [`serialize_field`].
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub fn next_element<'de, A, T>(seq: &mut A) -> Result<Option<T>, A::Error>
+where
+    A: SeqAccess<'de>,
+    T: Deserialize<'de>,
+{
+    seq.next_element()
+}
+
+/// Deserialize the next field of a response from a map, as done by responses
+/// derived with [`atat_derive`]. See [`serialize_field`].
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub fn next_value<'de, A, T>(map: &mut A) -> Result<T, A::Error>
+where
+    A: MapAccess<'de>,
+    T: Deserialize<'de>,
+{
+    map.next_value()
+}
+
 #[cfg(test)]
 mod tests {
     use std::convert::TryFrom;
 
     use crate as atat;
     use atat::{derive::AtatLen, AtatCmd};
-    use atat_derive::{AtatCmd, AtatEnum, AtatResp};
+    use atat_derive::{AtatCmd, AtatEnum, AtatParams, AtatResp};
     use heapless::{String, Vec};
     use serde_at::{from_str, to_string, HexStr, SerializeOptions};
 
@@ -222,6 +414,359 @@ mod tests {
         );
     }
 
+    #[derive(Debug, PartialEq, AtatCmd)]
+    #[at_cmd("+UCFG", NoResponse, param_sep = "; ")]
+    struct ParamSepTester {
+        x: u8,
+        y: u16,
+        z: SimpleEnum,
+    }
+
+    #[derive(Debug, PartialEq, AtatCmd)]
+    #[at_cmd("+CFUN", NoResponse)]
+    struct WireFormatTester {
+        #[at_arg(position = 1)]
+        rst: Option<u8>,
+        #[at_arg(position = 0)]
+        fun: u8,
+    }
+
+    #[test]
+    fn test_wire_format() {
+        assert_eq!(
+            <WireFormatTester as AtatCmd>::WIRE_FORMAT,
+            "AT+CFUN=<fun>[,<rst>]\\r\\n"
+        );
+    }
+
+    #[derive(Debug, PartialEq, AtatCmd)]
+    #[at_cmd("+CMGL", NoResponse, continuation = "\r")]
+    struct ContinuationTester;
+
+    #[test]
+    fn test_continuation() {
+        assert_eq!(
+            <ContinuationTester as AtatCmd>::CONTINUATION,
+            Some(&b"\r"[..])
+        );
+        assert_eq!(<WireFormatTester as AtatCmd>::CONTINUATION, None);
+    }
+
+    #[derive(Debug, PartialEq, AtatCmd)]
+    #[at_cmd("+CSQ", NoResponse, resp_prefix = "+CSQ:")]
+    struct RespPrefixTester;
+
+    #[test]
+    fn test_resp_prefix() {
+        assert_eq!(
+            <RespPrefixTester as AtatCmd>::RESP_PREFIX,
+            Some(&b"+CSQ:"[..])
+        );
+        assert_eq!(<WireFormatTester as AtatCmd>::RESP_PREFIX, None);
+    }
+
+    #[test]
+    fn test_wire_image() {
+        let mut buf = [0; 16];
+        let len = ContinuationTester.write(&mut buf);
+        assert_eq!(
+            <ContinuationTester as AtatCmd>::WIRE_IMAGE,
+            Some(&buf[..len])
+        );
+        assert_eq!(
+            <ContinuationTester as AtatCmd>::WIRE_IMAGE,
+            Some(&b"AT+CMGL\r\n"[..])
+        );
+        assert_eq!(<WireFormatTester as AtatCmd>::WIRE_IMAGE, None);
+    }
+
+    #[test]
+    fn test_nested_urc() {
+        use atat::{AtatUrc, Parser};
+        use atat_derive::AtatUrc;
+
+        #[derive(Debug, PartialEq, Clone, AtatResp)]
+        struct Registration {
+            stat: u8,
+        }
+
+        #[derive(Debug, PartialEq, Clone, AtatUrc)]
+        enum BaseUrc {
+            #[at_urc("+CREG")]
+            Creg(Registration),
+            #[at_urc("RING")]
+            Ring,
+        }
+
+        #[derive(Debug, PartialEq, Clone, AtatUrc)]
+        enum VendorUrc {
+            #[at_urc(nested)]
+            Base(BaseUrc),
+            #[at_urc("+QIND")]
+            Qind,
+        }
+
+        assert_eq!(
+            <VendorUrc as AtatUrc>::parse(b"+CREG: 5"),
+            Some(VendorUrc::Base(BaseUrc::Creg(Registration { stat: 5 })))
+        );
+        assert_eq!(
+            <VendorUrc as AtatUrc>::parse(b"RING"),
+            Some(VendorUrc::Base(BaseUrc::Ring))
+        );
+        assert_eq!(
+            <VendorUrc as AtatUrc>::parse(b"+QIND"),
+            Some(VendorUrc::Qind)
+        );
+        assert_eq!(<VendorUrc as AtatUrc>::parse(b"+CGREG: 1"), None);
+
+        assert_eq!(
+            <VendorUrc as Parser>::parse(b"\r\n+CREG: 5\r\n"),
+            Ok((&b"+CREG: 5"[..], 12))
+        );
+        assert_eq!(
+            <VendorUrc as Parser>::parse(b"\r\n+QIND\r\n"),
+            Ok((&b"+QIND"[..], 9))
+        );
+        assert!(<VendorUrc as Parser>::parse(b"\r\n+CGREG: 1\r\n").is_err());
+    }
+
+    #[cfg(feature = "parse-diagnostics")]
+    #[test]
+    fn test_parse_diagnostic() {
+        #[derive(Debug, PartialEq, AtatResp)]
+        struct SignalQuality {
+            rssi: u8,
+            ber: u8,
+        }
+
+        #[derive(Debug, PartialEq, AtatCmd)]
+        #[at_cmd("+CSQ", SignalQuality)]
+        struct GetSignalQuality;
+
+        assert_eq!(
+            GetSignalQuality.parse(Ok(b"+CSQ: 19,99")),
+            Ok(SignalQuality { rssi: 19, ber: 99 })
+        );
+        assert_eq!(
+            GetSignalQuality.parse(Ok(b"+CSQ: 19,-1")),
+            Err(atat::Error::Parse)
+        );
+        assert_eq!(
+            atat::take_parse_diagnostic(),
+            Some(atat::ParseDiagnostic {
+                field: Some(1),
+                position: 9,
+                expected: "u8",
+            })
+        );
+    }
+
+    #[derive(Debug, PartialEq, AtatCmd)]
+    #[at_cmd("+WMAC", NoResponse)]
+    struct HexFormatTester {
+        #[at_arg(hex(prefix = true, caps = false))]
+        id: HexStr<u16>,
+        #[at_arg(hex(caps = true, group = 2, sep = ':'))]
+        mac: HexStr<u32>,
+        raw: HexStr<u8>,
+    }
+
+    #[test]
+    fn test_hex_format_serialize() {
+        let mut buf = [0; 64];
+        let len = HexFormatTester {
+            id: HexStr {
+                val: 0xBEEF,
+                ..Default::default()
+            },
+            mac: HexStr {
+                val: 0xDEAD_BEEF,
+                hex_in_caps: false,
+                ..Default::default()
+            },
+            raw: HexStr {
+                val: 0xAB,
+                add_0x_with_encoding: true,
+                ..Default::default()
+            },
+        }
+        .write(&mut buf);
+        assert_eq!(
+            &buf[..len],
+            b"AT+WMAC=\"0xbeef\",\"DE:AD:BE:EF\",\"0xAB\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_param_sep_serialize() {
+        let mut buf = [0; 32];
+        let len = ParamSepTester {
+            x: 8,
+            y: 1024,
+            z: SimpleEnum::C,
+        }
+        .write(&mut buf);
+        assert_eq!(&buf[..len], b"AT+UCFG=8; 1024; 2\r\n");
+        assert_eq!(
+            <ParamSepTester as AtatCmd>::WIRE_FORMAT,
+            "AT+UCFG=<x>; <y>; <z>\\r\\n"
+        );
+        assert_eq!(
+            <ParamSepTester as AtatCmd>::MAX_LEN,
+            "AT+UCFG=\r\n".len() + 3 + 5 + 3 + 2 * "; ".len() + 2
+        );
+    }
+
+    #[derive(Clone, AtatParams)]
+    struct Credentials<'a> {
+        #[at_arg(len = 16)]
+        apn: &'a str,
+        #[at_arg(len = 8)]
+        username: &'a str,
+        #[at_arg(len = 8)]
+        password: Option<&'a str>,
+    }
+
+    #[derive(AtatCmd)]
+    #[at_cmd("+CGAUTH", NoResponse)]
+    struct FlattenTester<'a> {
+        cid: u8,
+        #[at_arg(flatten)]
+        credentials: Credentials<'a>,
+        auth: SimpleEnum,
+    }
+
+    #[test]
+    fn test_flatten_serialize() {
+        let mut cmd = FlattenTester {
+            cid: 1,
+            credentials: Credentials {
+                apn: "internet",
+                username: "user",
+                password: Some("pass"),
+            },
+            auth: SimpleEnum::B,
+        };
+        let mut buf = [0; 64];
+        let len = cmd.write(&mut buf);
+        assert_eq!(
+            &buf[..len],
+            b"AT+CGAUTH=1,\"internet\",\"user\",\"pass\",1\r\n"
+        );
+
+        cmd.credentials.password = None;
+        let len = cmd.write(&mut buf);
+        assert_eq!(&buf[..len], b"AT+CGAUTH=1,\"internet\",\"user\",,1\r\n");
+
+        assert_eq!(
+            <FlattenTester as AtatCmd>::WIRE_FORMAT,
+            "AT+CGAUTH=<cid>,<credentials..>,<auth>\\r\\n"
+        );
+        assert_eq!(<Credentials as AtatLen>::LEN, (16 + 2) + (8 + 2) + 8 + 2);
+        assert_eq!(
+            <FlattenTester as AtatCmd>::MAX_LEN,
+            "AT+CGAUTH=\r\n".len() + 3 + <Credentials as AtatLen>::LEN + 3 + 2 * ",".len() + 2
+        );
+    }
+
+    #[derive(AtatCmd)]
+    #[at_cmd("+CGAUTH", NoResponse, param_sep = "; ")]
+    struct FlattenParamSepTester<'a> {
+        cid: u8,
+        #[at_arg(flatten)]
+        credentials: Credentials<'a>,
+    }
+
+    #[test]
+    fn test_flatten_param_sep() {
+        let cmd = FlattenParamSepTester {
+            cid: 255,
+            credentials: Credentials {
+                apn: "0123456789abcdef",
+                username: "01234567",
+                password: Some("01234567"),
+            },
+        };
+        // The separators within the group are accounted for
+        let mut buf = [0; <FlattenParamSepTester as AtatCmd>::MAX_LEN];
+        let len = cmd.write(&mut buf);
+        assert_eq!(
+            &buf[..len],
+            b"AT+CGAUTH=255; \"0123456789abcdef\"; \"01234567\"; \"01234567\"\r\n"
+        );
+        assert_eq!(
+            ATAT_FLATTENPARAMSEPTESTER_LEN,
+            3 + 1 + <Credentials as AtatLen>::LEN
+        );
+        assert_eq!(
+            <FlattenParamSepTester as AtatCmd>::MAX_LEN,
+            "AT+CGAUTH=\r\n".len() + ATAT_FLATTENPARAMSEPTESTER_LEN + 3 * ("; ".len() - 1) + 2
+        );
+    }
+
+    #[derive(AtatCmd)]
+    #[at_cmd("XYZCMD", NoResponse, prefix_field = namespace)]
+    struct PrefixTester {
+        namespace: char,
+        mode: u8,
+    }
+
+    #[test]
+    fn test_prefix_field() {
+        let mut cmd = PrefixTester {
+            namespace: '+',
+            mode: 1,
+        };
+        let mut buf = [0; 32];
+        let len = cmd.write(&mut buf);
+        assert_eq!(&buf[..len], b"AT+XYZCMD=1\r\n");
+
+        cmd.namespace = '*';
+        let len = cmd.write(&mut buf);
+        assert_eq!(&buf[..len], b"AT*XYZCMD=1\r\n");
+
+        assert_eq!(
+            <PrefixTester as AtatCmd>::WIRE_FORMAT,
+            "AT<namespace>XYZCMD=<mode>\\r\\n"
+        );
+        assert_eq!(<PrefixTester as AtatCmd>::WIRE_IMAGE, None);
+        assert_eq!(
+            <PrefixTester as AtatCmd>::MAX_LEN,
+            "AT=\r\n".len() + 4 + "XYZCMD".len() + 3 + 2
+        );
+    }
+
+    #[derive(Clone, Debug, PartialEq, AtatResp)]
+    struct SocketStatus {
+        id: u8,
+        pending: u8,
+    }
+
+    #[derive(AtatCmd)]
+    #[at_cmd("+XSTAT", SocketStatus, length_footer = "+XLEN:")]
+    struct FooterTester;
+
+    #[test]
+    fn test_length_footer() {
+        assert_eq!(
+            FooterTester.parse(Ok(b"+XSTAT: 0,5\r\n+XLEN: 11")),
+            Ok(SocketStatus { id: 0, pending: 5 })
+        );
+        assert_eq!(
+            FooterTester.parse(Ok(b"+XSTAT: 0,5\r\n+XLEN: 12")),
+            Err(atat::Error::InvalidResponse)
+        );
+        assert_eq!(
+            FooterTester.parse(Ok(b"+XSTAT: 0,5")),
+            Err(atat::Error::InvalidResponse)
+        );
+        assert_eq!(
+            atat::derive::strip_length_footer(b"+XLEN: 0", b"+XLEN:"),
+            Ok(&b""[..])
+        );
+    }
+
     #[test]
     fn test_mixed_enum() {
         assert_not_impl!(MixedEnum, TryFrom<u8>);
@@ -1,16 +1,39 @@
 use core::marker::PhantomData;
 
-use crate::InternalError;
+use crate::{helpers::LossyStr, InternalError};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum DigestResult<'a> {
     Urc(&'a [u8]),
     Response(Result<&'a [u8], InternalError<'a>>),
+    /// A page of a paginated response, after which the device waits for a
+    /// continuation token before sending the rest.
+    Continuation(&'a [u8]),
     Prompt(u8),
+    /// The device printed a boot banner, eg. `RDY`, meaning it restarted
+    /// and any in-flight command will never be answered.
+    ModemRestarted,
     None,
 }
 
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for DigestResult<'a> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            DigestResult::Urc(u) => defmt::write!(f, "DigestResult::Urc({=[u8]:a})", u),
+            DigestResult::Response(r) => defmt::write!(f, "DigestResult::Response({})", r),
+            DigestResult::Continuation(c) => {
+                defmt::write!(f, "DigestResult::Continuation({=[u8]:a})", c)
+            }
+            DigestResult::Prompt(p) => defmt::write!(f, "DigestResult::Prompt({=u8})", p),
+            DigestResult::ModemRestarted => defmt::write!(f, "DigestResult::ModemRestarted"),
+            DigestResult::None => defmt::write!(f, "DigestResult::None"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ParseError {
     Incomplete,
     NoMatch,
@@ -25,8 +48,92 @@ impl From<nom::Err<nom::error::Error<&[u8]>>> for ParseError {
     }
 }
 
+/// Splits received bytes into responses, prompts and URC's.
+///
+/// The trait is object safe, so a digester can be selected at runtime, eg.
+/// based on the detected modem vendor, by passing a `&mut dyn Digester` to
+/// the ingress.
 pub trait Digester {
     fn digest<'a>(&mut self, buf: &'a [u8]) -> (DigestResult<'a>, usize);
+
+    /// Forget the state of a partially received frame, called when the
+    /// ingress is cleared.
+    fn reset(&mut self) {}
+
+    /// Rewrite bytes in place before they are digested, eg. to normalize line
+    /// endings. The last `new` of the `len` bytes in `buf` were just
+    /// received, and `buf` may hold spare capacity beyond `len`. Returns the
+    /// new length. Does nothing by default.
+    fn normalize(&mut self, _buf: &mut [u8], len: usize, _new: usize) -> usize {
+        len
+    }
+
+    /// Set the final result codes of the command in flight besides `OK` and
+    /// `ERROR`, see [`AtatCmd::SUCCESS_CODES`]. Called by the ingress before
+    /// digesting, with empty codes while no command is in flight. Ignored by
+    /// default.
+    ///
+    /// [`AtatCmd::SUCCESS_CODES`]: crate::AtatCmd::SUCCESS_CODES
+    fn set_result_codes(
+        &mut self,
+        _success: &'static [&'static [u8]],
+        _error: &'static [&'static [u8]],
+    ) {
+    }
+
+    /// Digest consecutive frames from `buf`, passing each non-empty result to
+    /// `f`, until no further progress is made or `budget` results have been
+    /// produced.
+    ///
+    /// Returns the total number of bytes swallowed, which the caller must
+    /// consume from its buffer, and the number of results produced. If the
+    /// budget was exhausted, more frames may be ready to be digested.
+    fn digest_many<'a>(
+        &mut self,
+        buf: &'a [u8],
+        budget: usize,
+        mut f: impl FnMut(DigestResult<'a>),
+    ) -> (usize, usize)
+    where
+        Self: Sized,
+    {
+        let mut swallowed = 0;
+        let mut count = 0;
+        while count < budget && swallowed < buf.len() {
+            let (res, len) = self.digest(&buf[swallowed..]);
+            if len == 0 {
+                break;
+            }
+            swallowed += len;
+            if res != DigestResult::None {
+                count += 1;
+                f(res);
+            }
+        }
+        (swallowed, count)
+    }
+}
+
+impl<D: Digester + ?Sized> Digester for &mut D {
+    fn digest<'a>(&mut self, buf: &'a [u8]) -> (DigestResult<'a>, usize) {
+        (**self).digest(buf)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset();
+    }
+
+    fn normalize(&mut self, buf: &mut [u8], len: usize, new: usize) -> usize {
+        (**self).normalize(buf, len, new)
+    }
+
+    fn set_result_codes(
+        &mut self,
+        success: &'static [&'static [u8]],
+        error: &'static [&'static [u8]],
+    ) {
+        (**self).set_result_codes(success, error);
+    }
 }
 
 pub trait Parser {
@@ -38,6 +145,56 @@ pub trait Parser {
     fn parse(buf: &[u8]) -> Result<(&[u8], usize), ParseError>;
 }
 
+/// Stateful alternative to [`Parser`], for URC's whose format depends on
+/// previously received URC's, eg. a configuration URC announcing the fields
+/// of subsequent reports.
+///
+/// A matcher is set with [`AtDigester::with_urc_matcher`], and is consulted
+/// before the stateless [`Parser`] of the digester. `()` is the matcher that
+/// never matches.
+pub trait UrcMatcher {
+    /// Parse a URC, if it exists, following the rules of [`Parser::parse`].
+    ///
+    /// The matcher may update its state from the complete URC's it matches.
+    /// A URC is digested again if it could not be published, see
+    /// [`AtatIngress::try_advance`], so an update must be idempotent, eg.
+    /// taken from the content of the URC rather than counting them.
+    ///
+    /// [`AtatIngress::try_advance`]: crate::AtatIngress::try_advance
+    fn parse<'a>(&mut self, buf: &'a [u8]) -> Result<(&'a [u8], usize), ParseError>;
+
+    /// Forget the state learned from received URC's, called when the device
+    /// restarted.
+    fn reset(&mut self) {}
+}
+
+impl UrcMatcher for () {
+    fn parse<'a>(&mut self, _buf: &'a [u8]) -> Result<(&'a [u8], usize), ParseError> {
+        Err(ParseError::NoMatch)
+    }
+}
+
+impl<M: UrcMatcher + ?Sized> UrcMatcher for &mut M {
+    fn parse<'a>(&mut self, buf: &'a [u8]) -> Result<(&'a [u8], usize), ParseError> {
+        (**self).parse(buf)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset();
+    }
+}
+
+/// Format of `+CME ERROR` result codes, as selected with `AT+CMEE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CmeMode {
+    /// `AT+CMEE=1`, eg. `+CME ERROR: 3`
+    Numeric,
+    /// `AT+CMEE=2`, eg. `+CME ERROR: Operation not allowed`. Requires the
+    /// `string_errors` feature to be parsed.
+    Verbose,
+}
+
 /// A Digester that tries to implement the basic AT standard.
 /// This digester should work for most usecases of ATAT.
 ///
@@ -57,29 +214,139 @@ pub trait Parser {
 /// - `DigestResult::Response(Result\<RESPONS`E>)
 /// - `DigestResult::Urc(\<UR`C>)
 /// - `DigestResult::Prompt(\<CHA`R>)
+/// - `DigestResult::ModemRestarted`, on boot banners if configured
 /// - `DigestResult::None`
 ///
 /// Usually \<RESPONSE CODE> is one of \['OK', 'ERROR', 'CME ERROR: \<NUMBER/STRING>', 'CMS ERROR: \<NUMBER/STRING>'],
 /// but can be others as well depending on manufacturer.
 ///
 /// Usually \<PROMPT> can be one of \['>', '@'], and is command specific and only valid for few selected commands.
-pub struct AtDigester<P: Parser> {
+pub struct AtDigester<P: Parser, M: UrcMatcher = ()> {
     _urc_parser: PhantomData<P>,
+    urc_matcher: M,
     custom_success: fn(&[u8]) -> Result<(&[u8], usize), ParseError>,
     custom_error: fn(&[u8]) -> Result<(&[u8], usize), ParseError>,
     custom_prompt: fn(&[u8]) -> Result<(u8, usize), ParseError>,
+    custom_continuation: fn(&[u8]) -> Result<(&[u8], usize), ParseError>,
+    length_header: fn(&[u8]) -> Result<(usize, usize), ParseError>,
+    /// Header and payload length of a binary payload being received
+    payload: Option<(usize, usize)>,
+    /// Whether the last echo was interrupted, eg. by a URC, and continues
+    echo_pending: bool,
+    urc_filter: fn(&[u8]) -> bool,
+    strict: bool,
+    normalize_line_endings: bool,
+    success_codes: &'static [&'static [u8]],
+    error_codes: &'static [&'static [u8]],
+    cme_mode: Option<CmeMode>,
+    boot_banners: &'static [&'static [u8]],
+    ack_commands: &'static [&'static [u8]],
+    /// Echoes awaiting their final result code, oldest first, see
+    /// [`AtDigester::with_ack_commands`]
+    echoes: EchoQueue,
+}
+
+/// Up to 8 echoed commands awaiting their final result code, as a bit per
+/// echo telling whether it was an acknowledgement, oldest in the lowest bit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct EchoQueue {
+    acks: u8,
+    len: u8,
+}
+
+impl EchoQueue {
+    const fn new() -> Self {
+        Self { acks: 0, len: 0 }
+    }
+
+    fn push(&mut self, ack: bool) {
+        if self.len == 8 {
+            // Results were lost, the oldest echoes are stale
+            self.pop();
+        }
+        self.acks |= u8::from(ack) << self.len;
+        self.len += 1;
+    }
+
+    /// Whether the oldest echo was an acknowledgement, if any.
+    fn pop(&mut self) -> Option<bool> {
+        if self.len == 0 {
+            return None;
+        }
+        let ack = self.acks & 1 == 1;
+        self.acks >>= 1;
+        self.len -= 1;
+        Some(ack)
+    }
 }
 
+/// Boot banners printed by common devices once they are ready after a
+/// restart, for use with [`AtDigester::with_boot_banners`].
+pub const BOOT_BANNERS: &[&[u8]] = &[b"RDY", b"+PBREADY"];
+
 impl<P: Parser> AtDigester<P> {
     #[must_use]
     pub const fn new() -> Self {
         Self {
             _urc_parser: PhantomData,
+            urc_matcher: (),
             custom_success: |_| Err(ParseError::NoMatch),
             custom_error: |_| Err(ParseError::NoMatch),
             custom_prompt: |_| Err(ParseError::NoMatch),
+            custom_continuation: |_| Err(ParseError::NoMatch),
+            length_header: |_| Err(ParseError::NoMatch),
+            payload: None,
+            echo_pending: false,
+            urc_filter: |_| true,
+            strict: false,
+            normalize_line_endings: false,
+            success_codes: &[],
+            error_codes: &[],
+            cme_mode: None,
+            boot_banners: &[],
+            ack_commands: &[],
+            echoes: EchoQueue::new(),
         }
     }
+}
+
+impl<P: Parser, M: UrcMatcher> AtDigester<P, M> {
+    /// Match URC's with a stateful [`UrcMatcher`], before the stateless
+    /// [`Parser`] `P`.
+    #[must_use]
+    pub fn with_urc_matcher<N: UrcMatcher>(self, matcher: N) -> AtDigester<P, N> {
+        AtDigester {
+            _urc_parser: PhantomData,
+            urc_matcher: matcher,
+            custom_success: self.custom_success,
+            custom_error: self.custom_error,
+            custom_prompt: self.custom_prompt,
+            custom_continuation: self.custom_continuation,
+            length_header: self.length_header,
+            payload: self.payload,
+            echo_pending: self.echo_pending,
+            urc_filter: self.urc_filter,
+            strict: self.strict,
+            normalize_line_endings: self.normalize_line_endings,
+            success_codes: self.success_codes,
+            error_codes: self.error_codes,
+            cme_mode: self.cme_mode,
+            boot_banners: self.boot_banners,
+            ack_commands: self.ack_commands,
+            echoes: self.echoes,
+        }
+    }
+
+    /// The stateful URC matcher of the digester.
+    pub fn urc_matcher(&self) -> &M {
+        &self.urc_matcher
+    }
+
+    /// Mutable access to the stateful URC matcher, eg. to configure it from
+    /// the response to a command.
+    pub fn urc_matcher_mut(&mut self) -> &mut M {
+        &mut self.urc_matcher
+    }
 
     #[must_use]
     pub fn with_custom_success(self, f: fn(&[u8]) -> Result<(&[u8], usize), ParseError>) -> Self {
@@ -104,6 +371,179 @@ impl<P: Parser> AtDigester<P> {
             ..self
         }
     }
+
+    /// Match the pause prompt of paginated responses, eg. long `+CMGL`
+    /// listings. `f` returns the page data and the total length including the
+    /// pause prompt, and the client answers with the command's
+    /// [`AtatCmd::CONTINUATION`] token.
+    ///
+    /// [`AtatCmd::CONTINUATION`]: crate::AtatCmd::CONTINUATION
+    #[must_use]
+    pub fn with_custom_continuation(
+        self,
+        f: fn(&[u8]) -> Result<(&[u8], usize), ParseError>,
+    ) -> Self {
+        Self {
+            custom_continuation: f,
+            ..self
+        }
+    }
+
+    /// Match the header of responses stating the length of the binary
+    /// payload following it, eg. `+QIRD: 512\r\n` of `AT+QIRD` reads. `f`
+    /// returns the length of the header, including its line ending, and the
+    /// length of the payload, see [`parser::length_header`].
+    ///
+    /// Once a header is matched, the stated number of bytes is awaited
+    /// across writes to the ingress, regardless of their content, and the
+    /// payload is emitted as the response once followed by `OK`. The
+    /// ingress buffer must hold the complete response.
+    ///
+    /// ```
+    /// use atat::{digest::parser, AtDigester};
+    /// # enum Urc {}
+    /// # impl atat::Parser for Urc {
+    /// #     fn parse(_: &[u8]) -> Result<(&[u8], usize), atat::digest::ParseError> {
+    /// #         Err(atat::digest::ParseError::NoMatch)
+    /// #     }
+    /// # }
+    ///
+    /// let digester = AtDigester::<Urc>::new()
+    ///     .with_length_header(|buf| parser::length_header(buf, b"+QIRD:"));
+    /// ```
+    #[must_use]
+    pub fn with_length_header(self, f: fn(&[u8]) -> Result<(usize, usize), ParseError>) -> Self {
+        Self {
+            length_header: f,
+            ..self
+        }
+    }
+
+    /// Pre-screen matched URC's before they are handed to the ingress. URC's
+    /// for which `f` returns `false` are consumed and dropped during
+    /// digestion, so they never take up a slot in the URC channel.
+    #[must_use]
+    pub fn with_urc_filter(self, f: fn(&[u8]) -> bool) -> Self {
+        Self {
+            urc_filter: f,
+            ..self
+        }
+    }
+
+    /// Reject unexpected bytes instead of tolerating them. In strict mode,
+    /// any line that is neither an `AT` echo, a URC nor part of a response
+    /// fails the in-flight command with [`InternalError::Desync`], and
+    /// discards everything received so far.
+    ///
+    /// An echo interrupted by a URC is still recognized, as long as its rest
+    /// ends with the echoed command line termination, eg. `AT+C`, followed by
+    /// a URC, followed by `SQ\r`.
+    #[must_use]
+    pub fn with_strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
+
+    /// Normalize the line endings of quirky devices, mapping `\r\r\n` and
+    /// bare `\n` to `\r\n`, before anything is matched.
+    ///
+    /// Requires echo to be disabled, as an echoed command is told apart from
+    /// a response by its `\r\r\n` ending, and must not be combined with
+    /// binary payloads, see [`AtDigester::with_length_header`].
+    #[must_use]
+    pub fn with_line_normalization(self, enabled: bool) -> Self {
+        Self {
+            normalize_line_endings: enabled,
+            ..self
+        }
+    }
+
+    /// Select strict mode at runtime, see [`AtDigester::with_strict`].
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Start out with a known `+CME ERROR` format, eg. as configured by the
+    /// initialization handshake.
+    #[must_use]
+    pub fn with_cme_mode(self, mode: CmeMode) -> Self {
+        Self {
+            cme_mode: Some(mode),
+            ..self
+        }
+    }
+
+    /// Record a change of the `+CME ERROR` format, eg. after sending
+    /// `AT+CMEE=2` to debug a failing command.
+    pub fn set_cme_mode(&mut self, mode: CmeMode) {
+        self.cme_mode = Some(mode);
+    }
+
+    /// Detect restarts of the device by the lines it prints when booting, eg.
+    /// [`BOOT_BANNERS`]. A banner fails the in-flight command with
+    /// [`Error::ModemRestarted`], and forgets the detected `+CME ERROR`
+    /// format.
+    ///
+    /// [`Error::ModemRestarted`]: crate::Error::ModemRestarted
+    #[must_use]
+    pub fn with_boot_banners(self, banners: &'static [&'static [u8]]) -> Self {
+        Self {
+            boot_banners: banners,
+            ..self
+        }
+    }
+
+    /// Commands written besides those of the client, eg. `AT+CNMA`
+    /// acknowledging a `+CMT` URC from a URC handler, whose echo and final
+    /// result code may interleave with the exchange of the command in
+    /// flight.
+    ///
+    /// The echoes are tracked in order, as the device answers the commands in
+    /// the order it echoed them, and the final result codes of the commands
+    /// starting with one of `commands` are dropped rather than taken as the
+    /// response of the command in flight. Requires echo to be enabled.
+    ///
+    /// ```
+    /// use atat::{AtDigester, DigestResult, Digester};
+    /// # enum Urc {}
+    /// # impl atat::Parser for Urc {
+    /// #     fn parse(_: &[u8]) -> Result<(&[u8], usize), atat::digest::ParseError> {
+    /// #         Err(atat::digest::ParseError::NoMatch)
+    /// #     }
+    /// # }
+    ///
+    /// let mut digester = AtDigester::<Urc>::new().with_ack_commands(&[b"AT+CNMA"]);
+    /// digester.digest(b"AT+CNMA\r\r\n");
+    /// digester.digest(b"AT+CSQ\r\r\n");
+    /// assert_eq!(digester.digest(b"\r\nOK\r\n"), (DigestResult::None, 6));
+    /// ```
+    #[must_use]
+    pub fn with_ack_commands(self, commands: &'static [&'static [u8]]) -> Self {
+        Self {
+            ack_commands: commands,
+            ..self
+        }
+    }
+
+    /// The active `+CME ERROR` format, as last set or detected from received
+    /// errors. Errors in either format map to the same [`CmeError`] variant.
+    ///
+    /// [`CmeError`]: crate::CmeError
+    pub fn cme_mode(&self) -> Option<CmeMode> {
+        self.cme_mode
+    }
+}
+
+impl<P: Parser, M: UrcMatcher> core::fmt::Debug for AtDigester<P, M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AtDigester").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<P: Parser, M: UrcMatcher> defmt::Format for AtDigester<P, M> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "AtDigester {{ .. }}")
+    }
 }
 
 impl<P: Parser> Default for AtDigester<P> {
@@ -112,29 +552,141 @@ impl<P: Parser> Default for AtDigester<P> {
     }
 }
 
-impl<P: Parser> Digester for AtDigester<P> {
+impl<P: Parser, M: UrcMatcher> Digester for AtDigester<P, M> {
+    fn normalize(&mut self, buf: &mut [u8], len: usize, new: usize) -> usize {
+        if !self.normalize_line_endings || self.payload.is_some() {
+            return len;
+        }
+        parser::normalize_line_endings(buf, len, len - new)
+    }
+
+    fn set_result_codes(
+        &mut self,
+        success: &'static [&'static [u8]],
+        error: &'static [&'static [u8]],
+    ) {
+        self.success_codes = success;
+        self.error_codes = error;
+    }
+
     fn digest<'a>(&mut self, input: &'a [u8]) -> (DigestResult<'a>, usize) {
+        match self.digest_exchange(input) {
+            (DigestResult::Response(_), swallowed) if self.echoes.pop() == Some(true) => {
+                debug!("Dropping result of acknowledgement ({})", swallowed);
+                (DigestResult::None, swallowed)
+            }
+            res => res,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.payload = None;
+        self.echo_pending = false;
+        self.echoes = EchoQueue::new();
+    }
+}
+
+impl<P: Parser, M: UrcMatcher> AtDigester<P, M> {
+    fn digest_exchange<'a>(&mut self, input: &'a [u8]) -> (DigestResult<'a>, usize) {
+        // The rest of a binary payload, which must not be parsed for anything
+        // else
+        if let Some((header_len, len)) = self.payload {
+            return match parser::payload(input, header_len, len) {
+                Some((payload, total)) => {
+                    self.payload = None;
+                    (DigestResult::Response(Ok(payload)), total)
+                }
+                None => (DigestResult::None, 0),
+            };
+        }
+
         // 1. Optionally discard space and echo
         let buf = parser::trim_start_ascii_space(input);
         let space_bytes = input.len() - buf.len();
+
+        // Boot banners, telling that the device restarted. Checked before
+        // echo, as the banner may not be preceded by a line ending.
+        if let Some(len) = parser::boot_banner(buf, self.boot_banners) {
+            warn!("Device restarted: {:?}", LossyStr(&buf[..len]));
+            self.cme_mode = None;
+            self.echoes = EchoQueue::new();
+            self.urc_matcher.reset();
+            return (DigestResult::ModemRestarted, len + space_bytes);
+        }
+
         let (buf, space_and_echo_bytes) = match nom::combinator::opt(parser::echo)(buf) {
             Ok((buf, echo)) => (buf, space_bytes + echo.unwrap_or_default().len()),
             Err(nom::Err::Incomplete(_)) => return (DigestResult::None, 0),
             Err(_) => panic!("NOM ERROR - opt(echo)"),
         };
 
+        // The rest of an echo interrupted by a URC, ending with the echoed
+        // command line termination
+        let echo = &input[space_bytes..space_and_echo_bytes];
+        let echo_continued = self.echo_pending && echo.ends_with(b"\r");
+        if self.strict && !parser::is_echo(echo) && !echo_continued {
+            warn!(
+                "Unexpected bytes in strict mode: {:?}",
+                LossyStr(&input[..space_and_echo_bytes])
+            );
+            return (
+                DigestResult::Response(Err(InternalError::Desync)),
+                input.len(),
+            );
+        }
+
+        if !echo.is_empty() {
+            // Only echoes need tracking while acknowledgements may interleave
+            if !echo_continued && !self.ack_commands.is_empty() && parser::is_echo(echo) {
+                let ack = self.ack_commands.iter().any(|c| echo.starts_with(c));
+                self.echoes.push(ack);
+            }
+            self.echo_pending = !echo.ends_with(b"\r");
+        }
+
         // Incomplete. Eat whitespace and echo and do nothing else.
         let incomplete = (DigestResult::None, space_and_echo_bytes);
 
-        // 2. Match for URC's
-        match P::parse(buf) {
-            Ok((urc, len)) => return (DigestResult::Urc(urc), len),
+        // 2. Match for URC's, stateful first
+        let urc = match self.urc_matcher.parse(buf) {
+            Err(ParseError::NoMatch) => P::parse(buf),
+            res => res,
+        };
+        match urc {
+            Ok((urc, len)) if !(self.urc_filter)(urc) => {
+                trace!("Dropping filtered URC: {:?}", LossyStr(urc));
+                return (DigestResult::None, len + space_and_echo_bytes);
+            }
+            Ok((urc, len)) => return (DigestResult::Urc(urc), len + space_and_echo_bytes),
+            Err(ParseError::Incomplete) => return incomplete,
+            _ => {}
+        }
+
+        // Binary payloads of a length stated by their header
+        match (self.length_header)(buf) {
+            Ok((header_len, len)) => {
+                if let Some((payload, total)) = parser::payload(buf, header_len, len) {
+                    return (
+                        DigestResult::Response(Ok(payload)),
+                        total + space_and_echo_bytes,
+                    );
+                }
+                trace!("Awaiting payload of {} bytes", len);
+                self.payload = Some((header_len, len));
+                return incomplete;
+            }
             Err(ParseError::Incomplete) => return incomplete,
             _ => {}
         }
 
         // 3. Parse for success responses
-        // Custom successful replies first, if any
+        // Final result codes of the command in flight, eg. `SEND OK`
+        if let Some((result, len)) = parser::result_code(buf, self.success_codes, self.error_codes)
+        {
+            return (result, len + space_and_echo_bytes);
+        }
+
+        // Custom successful replies, if any
         match (self.custom_success)(buf) {
             Ok((response, len)) => {
                 return (
@@ -146,6 +698,15 @@ impl<P: Parser> Digester for AtDigester<P> {
             _ => {}
         }
 
+        // Pages of paginated responses
+        match (self.custom_continuation)(buf) {
+            Ok((page, len)) => {
+                return (DigestResult::Continuation(page), len + space_and_echo_bytes)
+            }
+            Err(ParseError::Incomplete) => return incomplete,
+            _ => {}
+        }
+
         // Generic success replies
         match parser::success_response(buf) {
             Ok((_, (result, len))) => return (result, len + space_and_echo_bytes),
@@ -180,6 +741,18 @@ impl<P: Parser> Digester for AtDigester<P> {
             _ => {}
         }
 
+        // Mobile equipment errors, tracking the format they are reported in
+        if let Ok((_, (error, mode, len))) = parser::cme_error(buf) {
+            if self.cme_mode != Some(mode) {
+                debug!("Detected +CME ERROR mode {:?}", mode);
+                self.cme_mode = Some(mode);
+            }
+            return (
+                DigestResult::Response(Err(InternalError::CmeError(error))),
+                len + space_and_echo_bytes,
+            );
+        }
+
         // Generic error matches
         if let Ok((_, (result, len))) = parser::error_response(buf) {
             return (result, len + space_and_echo_bytes);
@@ -190,10 +763,105 @@ impl<P: Parser> Digester for AtDigester<P> {
     }
 }
 
+/// Helpers for unit testing digesters, eg. the handlers of
+/// [`AtDigester::with_custom_error`], without an [`Ingress`]. Requires the
+/// `std` feature.
+///
+/// [`Ingress`]: crate::Ingress
+#[cfg(any(test, feature = "std"))]
+pub mod test_helpers {
+    use std::vec::Vec;
+
+    use super::{DigestResult, Digester};
+    use crate::InternalError;
+
+    /// An owned [`DigestResult`], see [`digest_all`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Digested {
+        Urc(Vec<u8>),
+        Response(Result<Vec<u8>, OwnedError>),
+        Continuation(Vec<u8>),
+        Prompt(u8),
+        ModemRestarted,
+    }
+
+    /// An owned [`InternalError`], holding the bytes of
+    /// [`InternalError::Custom`] in a [`Vec`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum OwnedError {
+        Custom(Vec<u8>),
+        /// Any other error, none of which borrow from the response.
+        Other(InternalError<'static>),
+    }
+
+    impl From<InternalError<'_>> for OwnedError {
+        fn from(e: InternalError<'_>) -> Self {
+            Self::Other(match e {
+                InternalError::Custom(c) => return Self::Custom(c.to_vec()),
+                InternalError::Read => InternalError::Read,
+                InternalError::Write => InternalError::Write,
+                InternalError::Timeout => InternalError::Timeout,
+                InternalError::InvalidResponse => InternalError::InvalidResponse,
+                InternalError::Aborted => InternalError::Aborted,
+                InternalError::Desync => InternalError::Desync,
+                InternalError::ModemRestarted => InternalError::ModemRestarted,
+                InternalError::ResponseTooLarge => InternalError::ResponseTooLarge,
+                InternalError::Parse => InternalError::Parse,
+                InternalError::Error => InternalError::Error,
+                InternalError::CmeError(e) => InternalError::CmeError(e),
+                InternalError::CmsError(e) => InternalError::CmsError(e),
+                InternalError::ConnectionError(e) => InternalError::ConnectionError(e),
+            })
+        }
+    }
+
+    impl From<DigestResult<'_>> for Option<Digested> {
+        fn from(res: DigestResult<'_>) -> Self {
+            Some(match res {
+                DigestResult::Urc(urc) => Digested::Urc(urc.to_vec()),
+                DigestResult::Response(res) => {
+                    Digested::Response(res.map(<[u8]>::to_vec).map_err(OwnedError::from))
+                }
+                DigestResult::Continuation(page) => Digested::Continuation(page.to_vec()),
+                DigestResult::Prompt(prompt) => Digested::Prompt(prompt),
+                DigestResult::ModemRestarted => Digested::ModemRestarted,
+                DigestResult::None => return None,
+            })
+        }
+    }
+
+    /// Feed `chunks` to `digester` one at a time, as the ingress receives
+    /// them, and collect the results. Returns the results along with the
+    /// bytes left undigested.
+    pub fn digest_all<D: Digester + ?Sized>(
+        digester: &mut D,
+        chunks: &[&[u8]],
+    ) -> (Vec<Digested>, Vec<u8>) {
+        let mut results = Vec::new();
+        let mut buf = Vec::new();
+        for chunk in chunks {
+            let len = buf.len();
+            buf.extend_from_slice(chunk);
+            let len = digester.normalize(&mut buf, len + chunk.len(), chunk.len());
+            buf.truncate(len);
+
+            while !buf.is_empty() {
+                let (res, swallowed) = digester.digest(&buf);
+                results.extend(Option::<Digested>::from(res));
+                if swallowed == 0 {
+                    break;
+                }
+                buf.drain(..swallowed);
+            }
+        }
+        (results, buf)
+    }
+}
+
 pub mod parser {
     use crate::error::{CmeError, CmsError, ConnectionError};
 
-    use super::{DigestResult, InternalError};
+    use super::{CmeMode, DigestResult, InternalError};
 
     use core::str::FromStr;
 
@@ -207,6 +875,20 @@ pub mod parser {
         IResult,
     };
 
+    /// Matches a complete line equal to one of `banners`, with or without
+    /// leading line endings, returning its length.
+    pub fn boot_banner(buf: &[u8], banners: &[&[u8]]) -> Option<usize> {
+        let start = buf
+            .iter()
+            .take_while(|&&c| c == b'\r' || c == b'\n')
+            .count();
+        let line = &buf[start..];
+        banners.iter().find_map(|banner| {
+            let rest = line.strip_prefix(*banner)?.strip_prefix(b"\r\n")?;
+            Some(buf.len() - rest.len())
+        })
+    }
+
     /// Matches the equivalent of regex: "\r\n{token}(:.*)?\r\n"
     pub fn urc_helper<'a, T, Error: ParseError<&'a [u8]>>(
         token: T,
@@ -235,6 +917,40 @@ pub mod parser {
         }
     }
 
+    /// Matches any URC of a nested URC type, for composing the URC parser of
+    /// an enum with `#[at_urc(nested)]` variants.
+    pub fn nested_urc<'a, P: super::Parser, Error: ParseError<&'a [u8]>>(
+        i: &'a [u8],
+    ) -> IResult<&'a [u8], (&'a [u8], usize), Error> {
+        match P::parse(i) {
+            Ok((urc, len)) => Ok((&i[len..], (urc, len))),
+            Err(super::ParseError::Incomplete) => Err(nom::Err::Incomplete(nom::Needed::Unknown)),
+            Err(super::ParseError::NoMatch) => Err(nom::Err::Error(Error::from_error_kind(
+                i,
+                nom::error::ErrorKind::Alt,
+            ))),
+        }
+    }
+
+    /// Matches `+CME ERROR` result codes in either numeric or verbose format,
+    /// returning the format along with the error.
+    pub fn cme_error(buf: &[u8]) -> IResult<&[u8], (CmeError, CmeMode, usize)> {
+        // Matches the equivalent of regex: "\r\n\+CME ERROR:\s*(\d+)\r\n"
+        let result = map(numeric_error("\r\n+CME ERROR:"), |(error_code, len)| {
+            (CmeError::from(error_code), CmeMode::Numeric, len)
+        })(buf);
+
+        // Matches the equivalent of regex: "\r\n\+CME ERROR:\s*([^\n\r]+)\r\n"
+        #[cfg(feature = "string_errors")]
+        let result = result.or_else(|_| {
+            map(string_error("\r\n+CME ERROR:"), |(error_msg, len)| {
+                (CmeError::from_verbose(error_msg), CmeMode::Verbose, len)
+            })(buf)
+        });
+
+        result
+    }
+
     pub fn error_response(buf: &[u8]) -> IResult<&[u8], (DigestResult, usize)> {
         alt((
             // Matches the equivalent of regex: "\r\n\+CME ERROR:\s*(\d+)\r\n"
@@ -259,7 +975,7 @@ pub mod parser {
             #[cfg(feature = "string_errors")]
             map(string_error("\r\n+CME ERROR:"), |(error_msg, len)| {
                 (
-                    DigestResult::Response(Err(InternalError::CmeError(CmeError::from_msg(
+                    DigestResult::Response(Err(InternalError::CmeError(CmeError::from_verbose(
                         error_msg,
                     )))),
                     len,
@@ -324,6 +1040,44 @@ pub mod parser {
         )))
     }
 
+    /// Matches the first line ending in one of the `success` or `error`
+    /// result codes, eg. `SEND OK`, or `0, SEND OK` as reported for a link
+    /// by devices with multiple connections, along with the information
+    /// response preceding it. Returns the result and the total length.
+    pub fn result_code<'a>(
+        buf: &'a [u8],
+        success: &[&[u8]],
+        error: &[&[u8]],
+    ) -> Option<(DigestResult<'a>, usize)> {
+        if success.is_empty() && error.is_empty() {
+            return None;
+        }
+        let mut start = 0;
+        while let Some(n) = buf[start..].windows(2).position(|w| w == b"\r\n") {
+            let end = start + n;
+            let line = &buf[start..end];
+            let matches = |code: &&[u8]| {
+                line.strip_suffix(*code).is_some_and(|link| {
+                    link.is_empty() || link.ends_with(b" ") || link.ends_with(b",")
+                })
+            };
+            if success.iter().any(matches) {
+                let data = trim_ascii_whitespace(&buf[..start]);
+                return Some((DigestResult::Response(Ok(data)), end + 2));
+            }
+            if error.iter().any(matches) {
+                let result = Err(InternalError::Custom(line));
+                return Some((DigestResult::Response(result), end + 2));
+            }
+            if line == b"OK" || line == b"ERROR" {
+                // Completes the command before any of the result codes
+                return None;
+            }
+            start = end + 2;
+        }
+        None
+    }
+
     pub fn success_response(buf: &[u8]) -> IResult<&[u8], (DigestResult, usize)> {
         let (i, ((data, tag), ws)) = alt((
             tuple((
@@ -345,6 +1099,67 @@ pub mod parser {
         ))
     }
 
+    /// Matches a header line stating the length of the binary payload
+    /// following it, eg. `\r\n+QIRD: 512\r\n`, for use with
+    /// [`AtDigester::with_length_header`]. The length is the first parameter
+    /// after `prefix`, and the leading line ending is optional.
+    ///
+    /// Returns the length of the header and of the payload.
+    ///
+    /// [`AtDigester::with_length_header`]: super::AtDigester::with_length_header
+    pub fn length_header(buf: &[u8], prefix: &[u8]) -> Result<(usize, usize), super::ParseError> {
+        let line = buf.strip_prefix(b"\r\n").unwrap_or(buf);
+        let common = line.len().min(prefix.len());
+        if line[..common] != prefix[..common] {
+            return Err(super::ParseError::NoMatch);
+        }
+        let Some(end) = line.windows(2).position(|w| w == b"\r\n") else {
+            return Err(super::ParseError::Incomplete);
+        };
+
+        let params = trim_start_ascii_space(&line[prefix.len()..end]);
+        let digits = params.iter().take_while(|c| c.is_ascii_digit()).count();
+        let len = core::str::from_utf8(&params[..digits])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .ok_or(super::ParseError::NoMatch)?;
+        Ok((buf.len() - line.len() + end + 2, len))
+    }
+
+    /// Splits the binary payload of `len` bytes following a header from
+    /// `buf`, returning it and the total length including the final `OK`,
+    /// once complete.
+    pub(crate) fn payload(buf: &[u8], header_len: usize, len: usize) -> Option<(&[u8], usize)> {
+        let end = header_len + len;
+        let (_, (_, ok_len)) = success_response(buf.get(end..)?).ok()?;
+        Some((&buf[header_len..end], end + ok_len))
+    }
+
+    /// Map `\r\r\n` and bare `\n` line endings in `buf[..len]` to `\r\n`,
+    /// from `start` on, returning the new length. Bare `\n` are left as is
+    /// if `buf` has no spare capacity.
+    pub(crate) fn normalize_line_endings(buf: &mut [u8], mut len: usize, start: usize) -> usize {
+        // Look back for the start of a line ending split between writes
+        let mut i = start.saturating_sub(2);
+        while i < len {
+            if buf[i] == b'\n' {
+                if i >= 2 && buf[i - 2..i] == *b"\r\r" {
+                    buf.copy_within(i - 1..len, i - 2);
+                    len -= 1;
+                    continue;
+                }
+                if (i == 0 || buf[i - 1] != b'\r') && len < buf.len() {
+                    buf.copy_within(i..len, i + 1);
+                    buf[i] = b'\r';
+                    len += 1;
+                    i += 1;
+                }
+            }
+            i += 1;
+        }
+        len
+    }
+
     /// Matches a full AT echo. Eg `AT+USORD=3,16\r\n`
     pub fn echo(buf: &[u8]) -> IResult<&[u8], &[u8]> {
         if buf.len() < 2 {
@@ -354,6 +1169,12 @@ pub mod parser {
         recognize(nom::bytes::complete::take_until("\r\n"))(buf)
     }
 
+    /// Whether a line swallowed as echo is empty, or looks like an echoed
+    /// `AT` command.
+    pub fn is_echo(line: &[u8]) -> bool {
+        line.is_empty() || (line.len() >= 2 && line[..2].eq_ignore_ascii_case(b"AT"))
+    }
+
     pub fn take_until_including<T, Input, Error: ParseError<Input>>(
         tag: T,
     ) -> impl Fn(Input) -> IResult<Input, (Input, Input), Error>
@@ -490,10 +1311,9 @@ mod test {
 
     use super::parser::{echo, urc_helper};
     use super::*;
-    use crate::{
-        error::{CmeError, CmsError, ConnectionError},
-        helpers::LossyStr,
-    };
+    use crate::error::CmeError;
+    #[cfg(feature = "string_errors")]
+    use crate::error::{CmsError, ConnectionError};
 
     const TEST_RX_BUF_LEN: usize = 256;
 
@@ -862,6 +1682,167 @@ mod test {
         assert!(buf.is_empty());
     }
 
+    #[test]
+    fn normalize_line_endings() {
+        let mut buf = [0; 32];
+        let data = b"\r\r\nRING\r\n\nOK\n";
+        buf[..data.len()].copy_from_slice(data);
+        let len = parser::normalize_line_endings(&mut buf, data.len(), 0);
+        assert_eq!(&buf[..len], b"\r\nRING\r\n\r\nOK\r\n");
+
+        // Line endings split between writes
+        let mut buf = [0; 32];
+        buf[..8].copy_from_slice(b"\r\nRING\r\r");
+        let len = parser::normalize_line_endings(&mut buf, 8, 0);
+        assert_eq!(len, 8);
+        buf[8] = b'\n';
+        let len = parser::normalize_line_endings(&mut buf, 9, 8);
+        assert_eq!(&buf[..len], b"\r\nRING\r\n");
+
+        // Bare line endings are kept without spare capacity
+        let mut buf = *b"OK\n";
+        assert_eq!(parser::normalize_line_endings(&mut buf, 3, 0), 3);
+        assert_eq!(&buf, b"OK\n");
+    }
+
+    #[test]
+    fn line_ending_matrix() {
+        // Response line endings, independently of the command termination
+        let cases = [
+            ("\r\n", false),
+            ("\r\n", true),
+            ("\n", true),
+            ("\r\r\n", true),
+        ];
+        for (ending, normalize) in cases {
+            let mut digester =
+                AtDigester::<UrcTestParser>::new().with_line_normalization(normalize);
+            let mut check = |data: &str, expected: DigestResult| {
+                let data = data.replace("\r\n", ending);
+                let mut buf = [0; 64];
+                buf[..data.len()].copy_from_slice(data.as_bytes());
+                let len = digester.normalize(&mut buf, data.len(), data.len());
+                assert_eq!(
+                    digester.digest(&buf[..len]),
+                    (expected, len),
+                    "{:?}, normalize: {}",
+                    ending,
+                    normalize
+                );
+            };
+
+            check(
+                "\r\n+CSQ: 20,99\r\n\r\nOK\r\n",
+                DigestResult::Response(Ok(b"+CSQ: 20,99")),
+            );
+            check(
+                "\r\nERROR\r\n",
+                DigestResult::Response(Err(InternalError::Error)),
+            );
+            check("\r\n+UUSORD: 0,1\r\n", DigestResult::Urc(b"+UUSORD: 0,1"));
+        }
+
+        // Echo of a command terminated with a bare `\r`
+        let mut digester = AtDigester::<UrcTestParser>::new();
+        assert_eq!(
+            digester.digest(b"AT+CSQ\r\r\n+CSQ: 20,99\r\n\r\nOK\r\n"),
+            (DigestResult::Response(Ok(b"+CSQ: 20,99")), 28)
+        );
+    }
+
+    #[test]
+    fn result_codes() {
+        let mut digester = AtDigester::<UrcTestParser>::new();
+        digester.set_result_codes(&[b"SEND OK"], &[b"SEND FAIL"]);
+
+        let (res, bytes) = digester.digest(b"\r\nRecv 4 bytes\r\n\r\nSEND OK\r\n");
+        assert_eq!(
+            (res, bytes),
+            (DigestResult::Response(Ok(b"Recv 4 bytes")), 27)
+        );
+
+        let (res, bytes) = digester.digest(b"\r\nSEND FAIL\r\n");
+        assert_eq!(
+            (res, bytes),
+            (
+                DigestResult::Response(Err(InternalError::Custom(b"SEND FAIL"))),
+                13
+            )
+        );
+
+        // Result codes reported for a link
+        let (res, bytes) = digester.digest(b"\r\n0, SEND OK\r\n");
+        assert_eq!((res, bytes), (DigestResult::Response(Ok(b"")), 14));
+        let (res, _) = digester.digest(b"\r\n1,SEND FAIL\r\n");
+        assert_eq!(
+            res,
+            DigestResult::Response(Err(InternalError::Custom(b"1,SEND FAIL")))
+        );
+        let (res, _) = digester.digest(b"\r\nRESEND OK\r\n");
+        assert_eq!(res, DigestResult::None);
+
+        // Incomplete result codes are awaited
+        let (res, _) = digester.digest(b"\r\nSEND OK");
+        assert_eq!(res, DigestResult::None);
+
+        // A plain OK completes the command before any result code
+        let (res, _) = digester.digest(b"\r\nOK\r\n\r\nSEND OK\r\n");
+        assert_eq!(res, DigestResult::Response(Ok(b"")));
+
+        digester.set_result_codes(&[], &[]);
+        let (res, _) = digester.digest(b"\r\nSEND OK\r\n");
+        assert_eq!(res, DigestResult::None);
+    }
+
+    #[test]
+    fn urc_matcher() {
+        /// Reports are only URC's once enabled by a configuration URC
+        #[derive(Default)]
+        struct ReportMatcher {
+            enabled: bool,
+        }
+
+        impl UrcMatcher for ReportMatcher {
+            fn parse<'a>(&mut self, buf: &'a [u8]) -> Result<(&'a [u8], usize), ParseError> {
+                if let Ok((_, (urc, len))) = urc_helper::<_, ()>("+UCFG")(buf) {
+                    self.enabled = urc.ends_with(b"1");
+                    return Ok((urc, len));
+                }
+                if !self.enabled {
+                    return Err(ParseError::NoMatch);
+                }
+                let (_, r) = urc_helper("+UREP")(buf)?;
+                Ok(r)
+            }
+
+            fn reset(&mut self) {
+                self.enabled = false;
+            }
+        }
+
+        let mut digester = AtDigester::<UrcTestParser>::new()
+            .with_boot_banners(BOOT_BANNERS)
+            .with_urc_matcher(ReportMatcher::default());
+
+        let (res, _) = digester.digest(b"\r\n+UREP: 5\r\n");
+        assert!(!matches!(res, DigestResult::Urc(_)));
+
+        let (res, bytes) = digester.digest(b"\r\n+UCFG: 1\r\n");
+        assert_eq!((res, bytes), (DigestResult::Urc(b"+UCFG: 1"), 12));
+        assert!(digester.urc_matcher().enabled);
+
+        let (res, bytes) = digester.digest(b"\r\n+UREP: 5\r\n");
+        assert_eq!((res, bytes), (DigestResult::Urc(b"+UREP: 5"), 12));
+
+        // The stateless parser still applies
+        let (res, bytes) = digester.digest(b"\r\n+CIEV: 3,1\r\n");
+        assert_eq!((res, bytes), (DigestResult::Urc(b"+CIEV: 3,1"), 14));
+
+        let (res, _) = digester.digest(b"\r\nRDY\r\n");
+        assert_eq!(res, DigestResult::ModemRestarted);
+        assert!(!digester.urc_matcher().enabled);
+    }
+
     #[test]
     fn multi_line_response() {
         let mut digester = AtDigester::<UrcTestParser>::new();
@@ -900,6 +1881,199 @@ mod test {
         assert!(buf.is_empty());
     }
 
+    #[test]
+    fn urc_filter() {
+        let mut digester =
+            AtDigester::<UrcTestParser>::new().with_urc_filter(|urc| !urc.starts_with(b"+CIEV"));
+
+        assert_eq!(
+            (DigestResult::None, 14),
+            digester.digest(b"\r\n+CIEV: 1,2\r\n")
+        );
+        assert_eq!(
+            (DigestResult::Urc(b"+UUSORD: 3,16"), 17),
+            digester.digest(b"\r\n+UUSORD: 3,16\r\n")
+        );
+    }
+
+    #[test]
+    fn custom_continuation() {
+        let mut digester = AtDigester::<UrcTestParser>::new().with_custom_continuation(|buf| {
+            let (_, (data, tag)) =
+                parser::take_until_including::<_, _, nom::error::Error<_>>("\r\n-- More --")(buf)?;
+            Ok((
+                data.strip_prefix(b"\r\n").unwrap_or(data),
+                data.len() + tag.len(),
+            ))
+        });
+
+        assert_eq!(
+            (DigestResult::None, 0),
+            digester.digest(b"\r\n+CMGL: 1,\"REC READ\"\r\n-- Mo")
+        );
+        assert_eq!(
+            (DigestResult::Continuation(b"+CMGL: 1,\"REC READ\""), 33),
+            digester.digest(b"\r\n+CMGL: 1,\"REC READ\"\r\n-- More --")
+        );
+        assert_eq!(
+            (DigestResult::Response(Ok(b"+CMGL: 2,\"REC READ\"")), 27),
+            digester.digest(b"\r\n+CMGL: 2,\"REC READ\"\r\nOK\r\n")
+        );
+    }
+
+    #[test]
+    fn length_header() {
+        let mut digester = AtDigester::<UrcTestParser>::new()
+            .with_length_header(|buf| parser::length_header(buf, b"+QIRD:"));
+
+        assert_eq!((DigestResult::None, 0), digester.digest(b"\r\n+QIRD: 1"));
+        // The payload contains line endings and a result code
+        assert_eq!(
+            (DigestResult::None, 0),
+            digester.digest(b"\r\n+QIRD: 10\r\n\r\nOK\r\n")
+        );
+        assert_eq!(
+            (DigestResult::None, 0),
+            digester.digest(b"\r\n+QIRD: 10\r\n\r\nOK\r\n\x00\xffab\r\n")
+        );
+        assert_eq!(
+            (DigestResult::Response(Ok(b"\r\nOK\r\n\x00\xffab")), 31),
+            digester.digest(b"\r\n+QIRD: 10\r\n\r\nOK\r\n\x00\xffab\r\n\r\nOK\r\n")
+        );
+
+        // Complete at once, with more parameters
+        assert_eq!(
+            (DigestResult::Response(Ok(b"abc")), 31),
+            digester.digest(b"\r\n+QIRD: 3,\"1.2.3.4\"\r\nabc\r\nOK\r\n")
+        );
+
+        // An abandoned payload is forgotten on reset
+        digester.digest(b"\r\n+QIRD: 10\r\nab");
+        digester.reset();
+        assert_eq!(
+            (DigestResult::Response(Ok(b"")), 6),
+            digester.digest(b"\r\nOK\r\n")
+        );
+    }
+
+    #[test]
+    fn strict_mode() {
+        let mut digester = AtDigester::<UrcTestParser>::new().with_strict(true);
+
+        assert_eq!(
+            (DigestResult::None, 13),
+            digester.digest(b"AT+USORD=3,16\r\n")
+        );
+        assert_eq!(
+            (DigestResult::Urc(b"+UUSORD: 3,16"), 17),
+            digester.digest(b"\r\n+UUSORD: 3,16\r\n")
+        );
+        assert_eq!(
+            (DigestResult::Response(Err(InternalError::Desync)), 24),
+            digester.digest(b"GARBAGE\r\n+UUSORD: 3,16\r\n")
+        );
+
+        digester.set_strict(false);
+        assert_eq!((DigestResult::None, 7), digester.digest(b"GARBAGE\r\n"));
+    }
+
+    #[test]
+    fn interleaved_acknowledgements() {
+        let mut digester = AtDigester::<UrcTestParser>::new().with_ack_commands(&[b"AT+CNMA"]);
+
+        // The acknowledgement is echoed after the command in flight
+        assert_eq!((DigestResult::None, 7), digester.digest(b"AT+CSQ\r\r\n"));
+        assert_eq!((DigestResult::None, 8), digester.digest(b"AT+CNMA\r\r\n"));
+        assert_eq!(
+            (DigestResult::Response(Ok(b"+CSQ: 20,99")), 21),
+            digester.digest(b"\r\n+CSQ: 20,99\r\n\r\nOK\r\n")
+        );
+        assert_eq!((DigestResult::None, 6), digester.digest(b"\r\nOK\r\n"));
+
+        // The acknowledgement is echoed first, and a URC interleaves
+        assert_eq!((DigestResult::None, 8), digester.digest(b"AT+CNMA\r\r\n"));
+        assert_eq!((DigestResult::None, 7), digester.digest(b"AT+CSQ\r\r\n"));
+        assert_eq!(
+            (DigestResult::Urc(b"+UUSORD: 3,16"), 17),
+            digester.digest(b"\r\n+UUSORD: 3,16\r\n")
+        );
+        assert_eq!(
+            (DigestResult::None, 19),
+            digester.digest(b"\r\n+CMS ERROR: 340\r\n")
+        );
+        assert_eq!(
+            (DigestResult::Response(Ok(b"+CSQ: 20,99")), 21),
+            digester.digest(b"\r\n+CSQ: 20,99\r\n\r\nOK\r\n")
+        );
+
+        // Without pending acknowledgements, results are delivered as is
+        assert_eq!(
+            (DigestResult::Response(Ok(b"")), 6),
+            digester.digest(b"\r\nOK\r\n")
+        );
+    }
+
+    #[test]
+    fn urc_inside_echo() {
+        for strict in [false, true] {
+            let mut digester = AtDigester::<UrcTestParser>::new().with_strict(strict);
+
+            assert_eq!(
+                (DigestResult::Urc(b"+UUSORD: 3,16"), 21),
+                digester.digest(b"AT+C\r\n+UUSORD: 3,16\r\n")
+            );
+            assert_eq!(
+                (DigestResult::Response(Ok(b"+CSQ: 20,99")), 24),
+                digester.digest(b"SQ\r\r\n+CSQ: 20,99\r\n\r\nOK\r\n")
+            );
+        }
+
+        // Several URC's, and the rest of the echo split across writes
+        let mut digester = AtDigester::<UrcTestParser>::new().with_strict(true);
+        assert_eq!((DigestResult::None, 0), digester.digest(b"AT+USO"));
+        assert_eq!(
+            (DigestResult::Urc(b"+UUSORD: 3,16"), 23),
+            digester.digest(b"AT+USO\r\n+UUSORD: 3,16\r\n")
+        );
+        assert_eq!(
+            (DigestResult::Urc(b"+UUSORD: 0,5"), 16),
+            digester.digest(b"\r\n+UUSORD: 0,5\r\nRD=3,16\r")
+        );
+        assert_eq!(
+            (DigestResult::Response(Ok(b"")), 14),
+            digester.digest(b"RD=3,16\r\r\nOK\r\n")
+        );
+
+        // A terminated echo is not continued
+        assert_eq!(
+            (DigestResult::Response(Err(InternalError::Desync)), 12),
+            digester.digest(b"GARBAGE\r\r\nOK")
+        );
+    }
+
+    #[test]
+    fn digest_many() {
+        let mut digester = AtDigester::<UrcTestParser>::new();
+        let buf = b"\r\nOK\r\n\r\n+UUSORD: 3,16\r\n\r\nOK\r\n";
+        let mut results = heapless::Vec::<_, 4>::new();
+
+        let (swallowed, count) = digester.digest_many(buf, 2, |res| results.push(res).unwrap());
+        assert_eq!((swallowed, count), (23, 2));
+        assert_eq!(
+            results,
+            [
+                DigestResult::Response(Ok(b"")),
+                DigestResult::Urc(b"+UUSORD: 3,16")
+            ]
+        );
+
+        results.clear();
+        let (swallowed, count) =
+            digester.digest_many(&buf[swallowed..], 2, |res| results.push(res).unwrap());
+        assert_eq!((swallowed, count), (6, 1));
+        assert_eq!(results, [DigestResult::Response(Ok(b""))]);
+    }
+
     #[test]
     fn error_response() {
         let mut digester = AtDigester::<UrcTestParser>::new();
@@ -1072,6 +2246,61 @@ mod test {
         assert!(buf.is_empty());
     }
 
+    #[test]
+    #[cfg(feature = "string_errors")]
+    fn cme_mode_switching() {
+        let mut digester = AtDigester::<UrcTestParser>::new().with_cme_mode(CmeMode::Numeric);
+        assert_eq!(digester.cme_mode(), Some(CmeMode::Numeric));
+
+        let (res, _) = digester.digest(b"\r\n+CME ERROR: 10\r\n");
+        assert_eq!(
+            res,
+            DigestResult::Response(Err(InternalError::CmeError(CmeError::SimNotInserted)))
+        );
+        assert_eq!(digester.cme_mode(), Some(CmeMode::Numeric));
+
+        // Toggled to verbose for debugging, without telling the digester
+        let (res, _) = digester.digest(b"\r\n+CME ERROR: SIM not inserted\r\n");
+        assert_eq!(
+            res,
+            DigestResult::Response(Err(InternalError::CmeError(CmeError::SimNotInserted)))
+        );
+        assert_eq!(digester.cme_mode(), Some(CmeMode::Verbose));
+
+        // Verbose text beyond the handful of common messages
+        let (res, _) =
+            digester.digest(b"\r\n+CME ERROR: Requested service option not subscribed\r\n");
+        assert_eq!(
+            res,
+            DigestResult::Response(Err(InternalError::CmeError(
+                CmeError::ServiceOptionNotSubscribed
+            )))
+        );
+
+        digester.set_cme_mode(CmeMode::Numeric);
+        assert_eq!(digester.cme_mode(), Some(CmeMode::Numeric));
+    }
+
+    #[test]
+    fn boot_banner() {
+        let mut digester = AtDigester::<UrcTestParser>::new();
+        assert_eq!(digester.digest(b"\r\nRDY\r\n").0, DigestResult::None);
+
+        let mut digester = AtDigester::<UrcTestParser>::new()
+            .with_cme_mode(CmeMode::Numeric)
+            .with_boot_banners(BOOT_BANNERS);
+        assert_eq!(
+            digester.digest(b"\r\nRDY\r\n\r\nOK\r\n"),
+            (DigestResult::ModemRestarted, 7)
+        );
+        assert_eq!(digester.cme_mode(), None);
+        assert_eq!(
+            digester.digest(b"+PBREADY\r\n"),
+            (DigestResult::ModemRestarted, 10)
+        );
+        assert_eq!(digester.digest(b"\r\nRDY"), (DigestResult::None, 0));
+    }
+
     #[test]
     fn data_ready_prompt() {
         let mut digester = AtDigester::<UrcTestParser>::new();
@@ -1220,6 +2449,37 @@ mod test {
         assert!(buf.is_empty());
     }
 
+    #[test]
+    fn digest_all_custom_error() {
+        use super::test_helpers::{digest_all, Digested, OwnedError};
+
+        let mut digester = AtDigester::<UrcTestParser>::new().with_custom_error(|buf| {
+            let (_reminder, (head, error, tail)) = sequence::tuple((
+                bytes::streaming::tag(b"\r\n"),
+                combinator::recognize(sequence::tuple((
+                    bytes::streaming::tag(b"FAIL: "),
+                    bytes::streaming::take_until("\r\n"),
+                ))),
+                bytes::streaming::tag(b"\r\n"),
+            ))(buf)?;
+
+            Ok((error, head.len() + error.len() + tail.len()))
+        });
+
+        let (results, remaining) = digest_all(
+            &mut digester,
+            &[b"\r\nFA", b"IL: 42\r\n", b"\r\nOK\r\n\r\n+U"],
+        );
+        assert_eq!(
+            results,
+            [
+                Digested::Response(Err(OwnedError::Custom(b"FAIL: 42".to_vec()))),
+                Digested::Response(Ok(std::vec![])),
+            ]
+        );
+        assert_eq!(remaining, b"\r\n+U");
+    }
+
     #[test]
     fn custom_success_with_prompt() {
         let mut digester = AtDigester::<UrcTestParser>::new().with_custom_success(|buf| {
@@ -0,0 +1,211 @@
+//! Feeding an ingress from a circular DMA buffer, as in the common STM32
+//! "IDLE interrupt + DMA" pattern.
+//!
+//! The UART RX DMA stream runs in circular mode, continuously writing into a
+//! fixed buffer, and the IDLE line, half transfer and transfer complete
+//! interrupts signal that new bytes are available. The [`DmaReader`] keeps
+//! track of how far the buffer has been consumed, and copies the newly
+//! available bytes to the ingress, handling wraparound of the buffer.
+//!
+//! ```
+//! use atat::{dma::DmaReader, AtatIngress};
+//!
+//! /// Called from the UART IDLE interrupt and the DMA half transfer and
+//! /// transfer complete interrupts, where `ndtr` is the number of transfers
+//! /// the DMA stream has left before wrapping around.
+//! fn on_uart_rx<I: AtatIngress>(reader: &mut DmaReader<'_, I>, dma_buf: &[u8], ndtr: usize) {
+//!     let position = dma_buf.len() - ndtr;
+//!     if reader.try_write_until(dma_buf, position).is_err() {
+//!         // Bytes were lost, the ingress has been cleared
+//!     }
+//! }
+//! ```
+//!
+//! The interrupts must be serviced before the DMA stream has written a full
+//! buffer length since the last call, or bytes are overwritten before they
+//! are read. Enabling the half transfer interrupt guarantees this, as long
+//! as it is serviced within half a buffer of received bytes.
+use crate::{AtatIngress, IngressError};
+
+/// Error feeding the ingress from a DMA buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// More bytes than the length of the DMA buffer were received since the
+    /// last call, so some were overwritten before being read. The ingress is
+    /// cleared, as the line being received is corrupted.
+    Overrun,
+    /// The ingress failed to accept the received bytes
+    Ingress(IngressError),
+}
+
+impl From<IngressError> for Error {
+    fn from(e: IngressError) -> Self {
+        Self::Ingress(e)
+    }
+}
+
+/// Copies newly received bytes from a circular DMA buffer to an ingress.
+pub struct DmaReader<'a, I: AtatIngress> {
+    ingress: &'a mut I,
+    pos: usize,
+}
+
+impl<'a, I: AtatIngress> DmaReader<'a, I> {
+    pub fn new(ingress: &'a mut I) -> Self {
+        Self { ingress, pos: 0 }
+    }
+
+    /// Restart reading from the start of the DMA buffer. Call this when the
+    /// DMA stream is restarted.
+    pub fn reset(&mut self) {
+        self.pos = 0;
+    }
+
+    /// Write `count` bytes, newly available in `dma_buf` since the last call,
+    /// to the ingress.
+    ///
+    /// `count` may span the end of the buffer, but must not exceed its
+    /// length, in which case [`Error::Overrun`] is returned.
+    pub fn try_write_available(&mut self, dma_buf: &[u8], count: usize) -> Result<(), Error> {
+        let len = dma_buf.len();
+        if len == 0 || count == 0 {
+            return Ok(());
+        }
+        if count > len {
+            warn!(
+                "DMA overrun, {} bytes received in a {} byte buffer",
+                count, len
+            );
+            self.pos = (self.pos + count) % len;
+            self.ingress.clear();
+            return Err(Error::Overrun);
+        }
+
+        // The bytes are consumed even if the ingress fails, as they were
+        // committed to it nonetheless
+        let start = self.pos;
+        let end = start + count;
+        self.pos = end % len;
+        if end <= len {
+            self.ingress.try_write(&dma_buf[start..end])?;
+        } else {
+            let head = self.ingress.try_write(&dma_buf[start..]);
+            self.ingress.try_write(&dma_buf[..end - len])?;
+            head?;
+        }
+        Ok(())
+    }
+
+    /// Write the bytes received in `dma_buf` up to `position`, the index the
+    /// DMA stream will write next, eg. the buffer length minus the remaining
+    /// number of transfers (`NDTR` on STM32).
+    ///
+    /// As only the position is known, a full buffer length received since
+    /// the last call cannot be told apart from no bytes received.
+    pub fn try_write_until(&mut self, dma_buf: &[u8], position: usize) -> Result<(), Error> {
+        let len = dma_buf.len();
+        if len == 0 {
+            return Ok(());
+        }
+        // A position equal to the length is wrapping around to the start
+        let position = position % len;
+        let count = (position + len - self.pos) % len;
+        self.try_write_available(dma_buf, count)
+    }
+}
+
+impl<I: AtatIngress> core::fmt::Debug for DmaReader<'_, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DmaReader")
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<I: AtatIngress> defmt::Format for DmaReader<'_, I> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "DmaReader {{ pos: {=usize}, .. }}", self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_ingress::ingress;
+    use crate::{Response, ResponseSlot, UrcChannel};
+    use heapless::Vec;
+
+    #[test]
+    fn wraparound() {
+        let res_slot = ResponseSlot::<64>::new();
+        let urc_channel = UrcChannel::new();
+        let mut buf = [0; 64];
+        let mut ingress = ingress(&mut buf, &res_slot, &urc_channel);
+        let mut reader = DmaReader::new(&mut ingress);
+
+        let mut dma_buf = *b"\r\n+CS\0\0\0";
+        reader.try_write_until(&dma_buf, 5).unwrap();
+        assert!(res_slot.try_get().is_none());
+
+        // The DMA stream wraps around, and continues at the start
+        dma_buf[5..].copy_from_slice(b"Q\r\n");
+        dma_buf[..4].copy_from_slice(b"OK\r\n");
+        reader.try_write_available(&dma_buf, 7).unwrap();
+        assert_eq!(
+            *res_slot.try_get().unwrap().borrow(),
+            Response::Ok(Vec::from_slice(b"+CSQ").unwrap())
+        );
+        res_slot.reset();
+
+        // Nothing new
+        reader.try_write_until(&dma_buf, 4).unwrap();
+        assert!(res_slot.try_get().is_none());
+    }
+
+    #[test]
+    fn overrun() {
+        let res_slot = ResponseSlot::<64>::new();
+        let urc_channel = UrcChannel::new();
+        let mut buf = [0; 64];
+        let mut ingress = ingress(&mut buf, &res_slot, &urc_channel);
+        let mut reader = DmaReader::new(&mut ingress);
+
+        let dma_buf = *b"\r\nOK\r\n";
+        reader.try_write_until(&dma_buf, 2).unwrap();
+        assert_eq!(
+            reader.try_write_available(&dma_buf, 10),
+            Err(Error::Overrun)
+        );
+        assert!(res_slot.try_get().is_none());
+
+        // Reading continues after the lost bytes
+        reader.try_write_available(&dma_buf, 6).unwrap();
+        assert_eq!(*res_slot.try_get().unwrap().borrow(), Response::default());
+    }
+
+    #[test]
+    fn ingress_error() {
+        let res_slot = ResponseSlot::<64>::new();
+        let urc_channel = UrcChannel::new();
+        let mut buf = [0; 64];
+        let mut ingress = ingress(&mut buf, &res_slot, &urc_channel);
+        let mut reader = DmaReader::new(&mut ingress);
+
+        // The second response is dropped, as the first is never picked up
+        let mut dma_buf = *b"\r\nOK\r\n\r\nERROR\r\n\0\0\0\0\0\0";
+        reader.try_write_until(&dma_buf, 6).unwrap();
+        assert_eq!(
+            reader.try_write_until(&dma_buf, 15),
+            Err(Error::Ingress(IngressError::ResponseSlotBusy))
+        );
+        assert_eq!(*res_slot.try_get().unwrap().borrow(), Response::default());
+        res_slot.reset();
+
+        // The bytes are not fed again
+        dma_buf[15..].copy_from_slice(b"\r\nOK\r\n");
+        reader.try_write_until(&dma_buf, 21).unwrap();
+        assert_eq!(*res_slot.try_get().unwrap().borrow(), Response::default());
+    }
+}
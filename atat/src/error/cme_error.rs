@@ -329,11 +329,17 @@ pub enum CmeError {
     NoNetworkSlicesAvailable = 232,
     /// nick=WirelineAccessAreaNotAllowed
     WirelineAccessAreaNotAllowed = 233,
+    /// Code without a dedicated variant, eg. a vendor specific extension like
+    /// the u-blox `1000` range
+    Vendor(u16),
 }
 
-impl From<u16> for CmeError {
-    fn from(v: u16) -> Self {
-        match v {
+impl CmeError {
+    /// The variant of a numeric code, or [`CmeError::Vendor`] for codes without
+    /// a dedicated variant.
+    #[must_use]
+    pub const fn from_code(code: u16) -> Self {
+        match code {
             0 => Self::PhoneFailure,
             1 => Self::NoConnection,
             2 => Self::LinkReserved,
@@ -386,7 +392,7 @@ impl From<u16> for CmeError {
             61 => Self::DataMissing,
             62 => Self::CallBarred,
             63 => Self::MessageWaitingIndicationSubscriptionFailure,
-            // 100 => Self::Unknown,
+            100 => Self::Unknown,
             102 => Self::ImsiUnknownInHss,
             103 => Self::IllegalUe,
             104 => Self::ImsiUnknownInVlr,
@@ -497,11 +503,199 @@ impl From<u16> for CmeError {
             231 => Self::UnauthorizedForCag,
             232 => Self::NoNetworkSlicesAvailable,
             233 => Self::WirelineAccessAreaNotAllowed,
-            _ => Self::Unknown,
+            code => Self::Vendor(code),
+        }
+    }
+
+    /// Whether the error is likely to clear up by itself, such that the
+    /// command may succeed when retried, eg. while the SIM is busy
+    /// initializing. See [`Config::retry`](crate::Config::retry).
+    #[must_use]
+    pub const fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::LinkReserved | Self::SimBusy | Self::NetworkTimeout
+        )
+    }
+
+    /// The numeric code of the error.
+    #[must_use]
+    pub const fn code(&self) -> u16 {
+        match self {
+            Self::PhoneFailure => 0,
+            Self::NoConnection => 1,
+            Self::LinkReserved => 2,
+            Self::NotAllowed => 3,
+            Self::NotSupported => 4,
+            Self::PhSimPin => 5,
+            Self::PhFsimPin => 6,
+            Self::PhFsimPuk => 7,
+            Self::SimNotInserted => 10,
+            Self::SimPin => 11,
+            Self::SimPuk => 12,
+            Self::SimFailure => 13,
+            Self::SimBusy => 14,
+            Self::SimWrong => 15,
+            Self::IncorrectPassword => 16,
+            Self::SimPin2 => 17,
+            Self::SimPuk2 => 18,
+            Self::MemoryFull => 20,
+            Self::InvalidIndex => 21,
+            Self::NotFound => 22,
+            Self::MemoryFailure => 23,
+            Self::TextTooLong => 24,
+            Self::InvalidChars => 25,
+            Self::DialStringTooLong => 26,
+            Self::DialStringInvalid => 27,
+            Self::NoNetwork => 30,
+            Self::NetworkTimeout => 31,
+            Self::NetworkNotAllowed => 32,
+            Self::NetworkPin => 40,
+            Self::NetworkPuk => 41,
+            Self::NetworkSubsetPin => 42,
+            Self::NetworkSubsetPuk => 43,
+            Self::ServicePin => 44,
+            Self::ServicePuk => 45,
+            Self::CorpPin => 46,
+            Self::CorpPuk => 47,
+            Self::HiddenKeyRequired => 48,
+            Self::EapMethodNotSupported => 49,
+            Self::IncorrectParameters => 50,
+            Self::CommandDisabled => 51,
+            Self::CommandAborted => 52,
+            Self::NotAttachedRestricted => 53,
+            Self::NotAllowedEmergencyOnly => 54,
+            Self::NotAllowedRestricted => 55,
+            Self::FixedDialNumberOnly => 56,
+            Self::TemporarilyOutOfService => 57,
+            Self::LanguageOrAlphabetNotSupported => 58,
+            Self::UnexpectedDataValue => 59,
+            Self::SystemFailure => 60,
+            Self::DataMissing => 61,
+            Self::CallBarred => 62,
+            Self::MessageWaitingIndicationSubscriptionFailure => 63,
+            Self::Unknown => 100,
+            Self::ImsiUnknownInHss => 102,
+            Self::IllegalUe => 103,
+            Self::ImsiUnknownInVlr => 104,
+            Self::ImeiNotAccepted => 105,
+            Self::IllegalMe => 106,
+            Self::PsServicesNotAllowed => 107,
+            Self::PsAndNonPsServicesNotAllowed => 108,
+            Self::UeIdentityNotDerivedFromNetwork => 109,
+            Self::ImplicitlyDetached => 110,
+            Self::PlmnNotAllowed => 111,
+            Self::AreaNotAllowed => 112,
+            Self::RoamingNotAllowedInArea => 113,
+            Self::PsServicesNotAllowedInPlmn => 114,
+            Self::NoCellsInArea => 115,
+            Self::MscTemporarilyNotReachable => 116,
+            Self::NetworkFailureAttach => 117,
+            Self::CsDomainUnavailable => 118,
+            Self::EsmFailure => 119,
+            Self::Congestion => 122,
+            Self::MbmsBearerCapabilitiesInsufficientForService => 124,
+            Self::NotAuthorizedForCsg => 125,
+            Self::InsufficientResources => 126,
+            Self::MissingOrUnknownApn => 127,
+            Self::UnknownPdpAddressOrType => 128,
+            Self::UserAuthenticationFailed => 129,
+            Self::ActivationRejectedByGgsnOrGw => 130,
+            Self::ActivationRejectedUnspecified => 131,
+            Self::ServiceOptionNotSupported => 132,
+            Self::ServiceOptionNotSubscribed => 133,
+            Self::ServiceOptionOutOfOrder => 134,
+            Self::NsapiOrPtiAlreadyInUse => 135,
+            Self::RegularDeactivation => 136,
+            Self::QosNotAccepted => 137,
+            Self::CallCannotBeIdentified => 138,
+            Self::CsServiceTemporarilyUnavailable => 139,
+            Self::FeatureNotSupported => 140,
+            Self::SemanticErrorInTftOperation => 141,
+            Self::SyntacticalErrorInTftOperation => 142,
+            Self::UnknownPdpContext => 143,
+            Self::SemanticErrorsInPacketFilter => 144,
+            Self::SyntacticalErrorInPacketFilter => 145,
+            Self::PdpContextWithoutTftAlreadyActivated => 146,
+            Self::MulticastGroupMembershipTimeout => 147,
+            Self::GprsUnknown => 148,
+            Self::PdpAuthFailure => 149,
+            Self::InvalidMobileClass => 150,
+            Self::LastPdnDisconnectionNotAllowedLegacy => 151,
+            Self::LastPdnDisconnectionNotAllowed => 171,
+            Self::SemanticallyIncorrectMessage => 172,
+            Self::InvalidMandatoryInformation => 173,
+            Self::MessageTypeNotImplemented => 174,
+            Self::ConditionalIeError => 175,
+            Self::UnspecifiedProtocolError => 176,
+            Self::OperatorDeterminedBarring => 177,
+            Self::MaximumNumberOfBearersReached => 178,
+            Self::RequestedApnNotSupported => 179,
+            Self::RequestRejectedBcmViolation => 180,
+            Self::UnsupportedQciOr5QiValue => 181,
+            Self::UserDataViaControlPlaneCongested => 182,
+            Self::SmsProvidedViaGprsInRoutingArea => 183,
+            Self::InvalidPtiValue => 184,
+            Self::NoBearerActivated => 185,
+            Self::MessageNotCompatibleWithProtocolState => 186,
+            Self::RecoveryOnTimerExpiry => 187,
+            Self::InvalidTransactionIdValue => 188,
+            Self::ServiceOptionNotAuthorizedInPlmn => 189,
+            Self::NetworkFailureActivation => 190,
+            Self::ReactivationRequested => 191,
+            Self::Ipv4OnlyAllowed => 192,
+            Self::Ipv6OnlyAllowed => 193,
+            Self::SingleAddressBearersOnlyAllowed => 194,
+            Self::CollisionWithNetworkInitiatedRequest => 195,
+            Self::Ipv4V6OnlyAllowed => 196,
+            Self::NonIpOnlyAllowed => 197,
+            Self::BearerHandlingUnsupported => 198,
+            Self::ApnRestrictionIncompatible => 199,
+            Self::MultipleAccessToPdnConnectionNotAllowed => 200,
+            Self::EsmInformationNotReceived => 201,
+            Self::PdnConnectionNonexistent => 202,
+            Self::MultiplePdnConnectionSameApnNotAllowed => 203,
+            Self::SevereNetworkFailure => 204,
+            Self::InsufficientResourcesForSliceAndDnn => 205,
+            Self::UnsupportedSscMode => 206,
+            Self::InsufficientResourcesForSlice => 207,
+            Self::MessageTypeNotCompatibleWithProtocolState => 208,
+            Self::IeNotImplemented => 209,
+            Self::N1ModeNotAllowed => 210,
+            Self::RestrictedServiceArea => 211,
+            Self::LadnUnavailable => 212,
+            Self::MissingOrUnknownDnnInSlice => 213,
+            Self::NgksiAlreadyInUse => 214,
+            Self::PayloadNotForwarded => 215,
+            Self::Non3GppAccessTo5GcnNotAllowed => 216,
+            Self::ServingNetworkNotAuthorized => 217,
+            Self::DnnNotSupportedInSlice => 218,
+            Self::InsufficientUserPlaneResourcesForPduSessio => 219,
+            Self::OutOfLadnServiceArea => 220,
+            Self::PtiMismatch => 221,
+            Self::MaxDataRateForUserPlaneIntegrityTooLow => 222,
+            Self::SemanticErrorInQosOperation => 223,
+            Self::SyntacticalErrorInQosOperation => 224,
+            Self::InvalidMappedEpsBearerIdentity => 225,
+            Self::RedirectionTo5GcnRequired => 226,
+            Self::RedirectionToEpcRequired => 227,
+            Self::TemporarilyUnauthorizedForSnpn => 228,
+            Self::PermanentlyUnauthorizedForSnpn => 229,
+            Self::EthernetOnlyAllowed => 230,
+            Self::UnauthorizedForCag => 231,
+            Self::NoNetworkSlicesAvailable => 232,
+            Self::WirelineAccessAreaNotAllowed => 233,
+            Self::Vendor(code) => *code,
         }
     }
 }
 
+impl From<u16> for CmeError {
+    fn from(v: u16) -> Self {
+        Self::from_code(v)
+    }
+}
+
 #[cfg(feature = "string_errors")]
 impl CmeError {
     pub const fn from_msg(s: &[u8]) -> Self {
@@ -528,6 +722,53 @@ impl CmeError {
     }
 }
 
+/// Highest numeric code with a dedicated [`CmeError`] variant
+const MAX_CODE: u16 = 233;
+
+impl CmeError {
+    /// Map a verbose `+CME ERROR` message, as reported with `AT+CMEE=2`, to
+    /// the same variant as its numeric code, by matching it against the
+    /// [`Display`] text of each variant. Matching ignores ASCII case.
+    ///
+    /// [`Display`]: core::fmt::Display
+    #[must_use]
+    pub fn from_verbose(msg: &[u8]) -> Self {
+        (0..=MAX_CODE)
+            .map(Self::from_code)
+            .filter(|e| !matches!(e, Self::Vendor(_)))
+            .find(|e| {
+                let mut cmp = EqIgnoreCase {
+                    rest: msg,
+                    equal: true,
+                };
+                core::fmt::write(&mut cmp, format_args!("{}", e)).is_ok()
+                    && cmp.equal
+                    && cmp.rest.is_empty()
+            })
+            .unwrap_or(Self::Unknown)
+    }
+}
+
+/// Writer comparing everything written to it against `rest`
+struct EqIgnoreCase<'a> {
+    rest: &'a [u8],
+    equal: bool,
+}
+
+impl core::fmt::Write for EqIgnoreCase<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        if self.equal
+            && self.rest.len() >= s.len()
+            && self.rest[..s.len()].eq_ignore_ascii_case(s.as_bytes())
+        {
+            self.rest = &self.rest[s.len()..];
+        } else {
+            self.equal = false;
+        }
+        Ok(())
+    }
+}
+
 impl core::fmt::Display for CmeError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
@@ -586,6 +827,7 @@ impl core::fmt::Display for CmeError {
                 write!(f, "Message waiting indication subscription failure")
             }
             Self::Unknown => write!(f, "Unknown error"),
+            Self::Vendor(code) => write!(f, "Vendor error {}", code),
             Self::ImsiUnknownInHss => write!(f, "IMSI unknown in HLR/HSS"),
             Self::IllegalUe => write!(f, "Illegal MS/UE"),
             Self::ImsiUnknownInVlr => write!(f, "IMSI unknown in VLR"),
@@ -743,7 +985,7 @@ impl core::fmt::Display for CmeError {
 }
 
 #[cfg(feature = "defmt")]
-impl<'a> defmt::Format for CmeError {
+impl defmt::Format for CmeError {
     fn format(&self, f: defmt::Formatter) {
         match self {
             Self::PhoneFailure => defmt::write!(f, "Phone failure"),
@@ -809,6 +1051,7 @@ impl<'a> defmt::Format for CmeError {
                 defmt::write!(f, "Message waiting indication subscription failure")
             }
             Self::Unknown => defmt::write!(f, "Unknown error"),
+            Self::Vendor(code) => defmt::write!(f, "Vendor error {=u16}", code),
             Self::ImsiUnknownInHss => defmt::write!(f, "IMSI unknown in HLR/HSS"),
             Self::IllegalUe => defmt::write!(f, "Illegal MS/UE"),
             Self::ImsiUnknownInVlr => defmt::write!(f, "IMSI unknown in VLR"),
@@ -1000,3 +1243,62 @@ impl<'a> defmt::Format for CmeError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CmeError;
+    use crate::Error;
+
+    #[test]
+    fn code() {
+        for code in [0, 3, 100, 233] {
+            assert_eq!(CmeError::from_code(code).code(), code);
+        }
+        assert_eq!(CmeError::from_code(100), CmeError::Unknown);
+        assert_eq!(CmeError::from_code(9), CmeError::Vendor(9));
+        assert_eq!(CmeError::from_code(1003), CmeError::Vendor(1003));
+        assert_eq!(CmeError::Vendor(1003).code(), 1003);
+    }
+
+    #[test]
+    fn vendor_cme() {
+        #[derive(Debug, PartialEq)]
+        enum UbloxError {
+            SocketNotFound,
+        }
+
+        impl TryFrom<u16> for UbloxError {
+            type Error = ();
+
+            fn try_from(code: u16) -> Result<Self, ()> {
+                match code {
+                    1003 => Ok(Self::SocketNotFound),
+                    _ => Err(()),
+                }
+            }
+        }
+
+        let e = Error::CmeError(CmeError::from_code(1003));
+        assert_eq!(e.vendor_cme(), Some(UbloxError::SocketNotFound));
+        let e = Error::CmeError(CmeError::from_code(1004));
+        assert_eq!(e.vendor_cme::<UbloxError>(), None);
+        let e = Error::CmeError(CmeError::NotAllowed);
+        assert_eq!(e.vendor_cme::<UbloxError>(), None);
+    }
+
+    #[test]
+    fn from_verbose() {
+        for code in [3, 10, 112, 233] {
+            let e = CmeError::from(code);
+            let mut msg = heapless::String::<64>::new();
+            core::fmt::write(&mut msg, format_args!("{}", e)).unwrap();
+            assert_eq!(CmeError::from_verbose(msg.as_bytes()), e);
+        }
+        assert_eq!(
+            CmeError::from_verbose(b"operation NOT allowed"),
+            CmeError::NotAllowed
+        );
+        assert_eq!(CmeError::from_verbose(b"Operation not"), CmeError::Unknown);
+        assert_eq!(CmeError::from_verbose(b"raspberry"), CmeError::Unknown);
+    }
+}
@@ -52,11 +52,17 @@ pub enum CmsError {
     NoCnmaAckExpected = 340,
     /// nick=Unknown
     Unknown = 500,
+    /// Code without a dedicated variant, eg. a manufacturer specific code
+    /// from `512` and up
+    Vendor(u16),
 }
 
-impl From<u16> for CmsError {
-    fn from(v: u16) -> Self {
-        match v {
+impl CmsError {
+    /// The variant of a numeric code, or [`CmsError::Vendor`] for codes without
+    /// a dedicated variant.
+    #[must_use]
+    pub const fn from_code(code: u16) -> Self {
+        match code {
             300 => Self::MeFailure,
             301 => Self::SmsServiceReserved,
             302 => Self::NotAllowed,
@@ -79,9 +85,47 @@ impl From<u16> for CmsError {
             331 => Self::NoNetwork,
             332 => Self::NetworkTimeout,
             340 => Self::NoCnmaAckExpected,
-            _ => Self::Unknown,
+            500 => Self::Unknown,
+            code => Self::Vendor(code),
         }
     }
+
+    /// The numeric code of the error.
+    #[must_use]
+    pub const fn code(&self) -> u16 {
+        match self {
+            Self::MeFailure => 300,
+            Self::SmsServiceReserved => 301,
+            Self::NotAllowed => 302,
+            Self::NotSupported => 303,
+            Self::InvalidPduParameter => 304,
+            Self::InvalidTextParameter => 305,
+            Self::SimNotInserted => 310,
+            Self::SimPin => 311,
+            Self::PhSimPin => 312,
+            Self::SimFailure => 313,
+            Self::SimBusy => 314,
+            Self::SimWrong => 315,
+            Self::SimPuk => 316,
+            Self::SimPin2 => 317,
+            Self::SimPuk2 => 318,
+            Self::MemoryFailure => 320,
+            Self::InvalidIndex => 321,
+            Self::MemoryFull => 322,
+            Self::SmscAddressUnknown => 330,
+            Self::NoNetwork => 331,
+            Self::NetworkTimeout => 332,
+            Self::NoCnmaAckExpected => 340,
+            Self::Unknown => 500,
+            Self::Vendor(code) => *code,
+        }
+    }
+}
+
+impl From<u16> for CmsError {
+    fn from(v: u16) -> Self {
+        Self::from_code(v)
+    }
 }
 
 #[cfg(feature = "string_errors")]
@@ -138,12 +182,13 @@ impl core::fmt::Display for CmsError {
             Self::NetworkTimeout => write!(f, "Network timeout"),
             Self::NoCnmaAckExpected => write!(f, "No CNMA acknowledgement expected"),
             Self::Unknown => write!(f, "Unknown"),
+            Self::Vendor(code) => write!(f, "Vendor error {}", code),
         }
     }
 }
 
 #[cfg(feature = "defmt")]
-impl<'a> defmt::Format for CmsError {
+impl defmt::Format for CmsError {
     fn format(&self, f: defmt::Formatter) {
         match self {
             Self::MeFailure => defmt::write!(f, "ME failure"),
@@ -169,6 +214,7 @@ impl<'a> defmt::Format for CmsError {
             Self::NetworkTimeout => defmt::write!(f, "Network timeout"),
             Self::NoCnmaAckExpected => defmt::write!(f, "No CNMA acknowledgement expected"),
             Self::Unknown => defmt::write!(f, "Unknown"),
+            Self::Vendor(code) => defmt::write!(f, "Vendor error {=u16}", code),
         }
     }
 }
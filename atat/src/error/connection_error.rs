@@ -32,7 +32,7 @@ impl core::fmt::Display for ConnectionError {
 }
 
 #[cfg(feature = "defmt")]
-impl<'a> defmt::Format for ConnectionError {
+impl defmt::Format for ConnectionError {
     fn format(&self, f: defmt::Formatter) {
         match self {
             Self::Unknown => defmt::write!(f, "Unknown"),
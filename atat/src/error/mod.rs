@@ -6,6 +6,11 @@ pub use cme_error::CmeError;
 pub use cms_error::CmsError;
 pub use connection_error::ConnectionError;
 
+#[cfg(feature = "parse-diagnostics")]
+use core::cell::Cell;
+#[cfg(feature = "parse-diagnostics")]
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+
 /// Errors returned used internally within the crate
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InternalError<'a> {
@@ -19,6 +24,12 @@ pub enum InternalError<'a> {
     InvalidResponse,
     /// Command was aborted
     Aborted,
+    /// Unexpected bytes were received, in strict mode
+    Desync,
+    /// The device restarted while waiting for a response
+    ModemRestarted,
+    /// The response did not fit in the ingress or response buffer
+    ResponseTooLarge,
     /// Failed to parse received response
     Parse,
     /// Error response containing any error message
@@ -42,6 +53,9 @@ impl<'a> defmt::Format for InternalError<'a> {
             InternalError::Timeout => defmt::write!(f, "InternalError::Timeout"),
             InternalError::InvalidResponse => defmt::write!(f, "InternalError::InvalidResponse"),
             InternalError::Aborted => defmt::write!(f, "InternalError::Aborted"),
+            InternalError::Desync => defmt::write!(f, "InternalError::Desync"),
+            InternalError::ModemRestarted => defmt::write!(f, "InternalError::ModemRestarted"),
+            InternalError::ResponseTooLarge => defmt::write!(f, "InternalError::ResponseTooLarge"),
             InternalError::Parse => defmt::write!(f, "InternalError::Parse"),
             InternalError::Error => defmt::write!(f, "InternalError::Error"),
             InternalError::CmeError(e) => defmt::write!(f, "InternalError::CmeError({:?})", e),
@@ -64,12 +78,32 @@ pub enum Error {
     Read,
     /// Serial write error
     Write,
+    /// Serial write error after the given number of bytes of the request were
+    /// written, see [`Config::write_recovery`](crate::Config::write_recovery)
+    PartialWrite(usize),
     /// Timed out while waiting for a response
     Timeout,
+    /// The overall deadline of the call passed, see
+    /// [`AtatClient::send_with_deadline`](crate::blocking::AtatClient::send_with_deadline)
+    DeadlineExceeded,
     /// Invalid response from module
     InvalidResponse,
     /// Command was aborted
     Aborted,
+    /// The command in flight can not be aborted safely, see
+    /// [`AtatCmd::CAN_ABORT`](crate::AtatCmd::CAN_ABORT)
+    NotAbortable,
+    /// Unexpected bytes were received, in strict mode
+    Desync,
+    /// The device restarted while waiting for a response
+    ModemRestarted,
+    /// The response did not fit in the ingress or response buffer
+    ResponseTooLarge,
+    /// The request did not fit in the request buffer of the client
+    RequestTooLarge,
+    /// No more out of order responses can be set aside, see
+    /// [`ResponseRouter`](crate::router::ResponseRouter)
+    RouterFull,
     /// Failed to parse received response
     Parse,
     /// Generic error response without any error message
@@ -86,6 +120,70 @@ pub enum Error {
     CustomMessage(heapless::Vec<u8, 64>),
 }
 
+/// Details on where a response failed to parse, telling whether the device
+/// responded with more or fewer parameters than expected, or whether the
+/// response struct is ordered differently.
+#[cfg(feature = "parse-diagnostics")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParseDiagnostic {
+    /// Index of the field that failed to parse, within the innermost struct
+    pub field: Option<usize>,
+    /// Offset into the response where parsing failed
+    pub position: usize,
+    /// The type the field was expected to hold, eg. `"u8"`
+    pub expected: &'static str,
+}
+
+#[cfg(feature = "parse-diagnostics")]
+static PARSE_DIAGNOSTIC: Mutex<CriticalSectionRawMutex, Cell<Option<ParseDiagnostic>>> =
+    Mutex::new(Cell::new(None));
+
+/// Record where the last response failed to parse
+#[cfg(feature = "parse-diagnostics")]
+pub(crate) fn set_parse_diagnostic(diagnostic: ParseDiagnostic) {
+    PARSE_DIAGNOSTIC.lock(|d| d.set(Some(diagnostic)));
+}
+
+/// Take the details on where the last response failed to parse with
+/// [`Error::Parse`], if any.
+#[cfg(feature = "parse-diagnostics")]
+pub fn take_parse_diagnostic() -> Option<ParseDiagnostic> {
+    PARSE_DIAGNOSTIC.lock(Cell::take)
+}
+
+impl Error {
+    /// Whether the command may succeed when retried, ie. it timed out or
+    /// failed with a transient `+CME ERROR`, see [`CmeError::is_transient`].
+    #[must_use]
+    pub const fn is_transient(&self) -> bool {
+        match self {
+            Self::Timeout => true,
+            Self::CmeError(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Map a vendor specific `+CME ERROR` code to a user defined type, eg. an
+    /// enum of the proprietary codes of the device. Returns `None` for other
+    /// errors, and codes the type does not cover.
+    pub fn vendor_cme<E: TryFrom<u16>>(&self) -> Option<E> {
+        match self {
+            Self::CmeError(CmeError::Vendor(code)) => E::try_from(*code).ok(),
+            _ => None,
+        }
+    }
+
+    /// Map a vendor specific `+CMS ERROR` code to a user defined type, see
+    /// [`Error::vendor_cme`].
+    pub fn vendor_cms<E: TryFrom<u16>>(&self) -> Option<E> {
+        match self {
+            Self::CmsError(CmsError::Vendor(code)) => E::try_from(*code).ok(),
+            _ => None,
+        }
+    }
+}
+
 impl<'a> From<InternalError<'a>> for Error {
     fn from(ie: InternalError) -> Self {
         match ie {
@@ -94,6 +192,9 @@ impl<'a> From<InternalError<'a>> for Error {
             InternalError::Timeout => Self::Timeout,
             InternalError::InvalidResponse => Self::InvalidResponse,
             InternalError::Aborted => Self::Aborted,
+            InternalError::Desync => Self::Desync,
+            InternalError::ModemRestarted => Self::ModemRestarted,
+            InternalError::ResponseTooLarge => Self::ResponseTooLarge,
             InternalError::Parse => Self::Parse,
             InternalError::Error => Self::Error,
             InternalError::CmeError(e) => Self::CmeError(e),
@@ -1,5 +1,20 @@
+//! Formatting helpers for logging AT traffic.
+//!
+//! These are used by atat itself, and are part of the public API so drivers
+//! can log commands and responses the same way.
+//!
+//! ```
+//! use atat::helpers::{EscapedBytes, LossyStr};
+//!
+//! let response = b"+CSQ: 19,99\r\nOK\r\n";
+//!
+//! assert_eq!(format!("{:?}", LossyStr(response)), "\"+CSQ: 19,99\\r\\nOK\\r\\n\"");
+//! assert_eq!(format!("{}", EscapedBytes(b"\x1a>\r\n")), "\\x1a>\\r\\n");
+//! ```
+
 /// Wrapper for a byte-slice that formats it as a string if possible and as
 /// bytes otherwise.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct LossyStr<'a>(pub &'a [u8]);
 
 impl<'a> core::fmt::Debug for LossyStr<'a> {
@@ -17,3 +32,119 @@ impl<'a> defmt::Format for LossyStr<'a> {
         defmt::write!(fmt, "{=[u8]:a}", self.0)
     }
 }
+
+/// Wrapper for a byte-slice that formats it as text on a single line,
+/// escaping line endings, tabs and any byte that is not printable ASCII, eg.
+/// `AT+CMGS=1\r\x1a`.
+///
+/// Unlike [`LossyStr`], a single invalid byte does not turn the whole slice
+/// into a list of numbers, which keeps binary payloads readable.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct EscapedBytes<'a>(pub &'a [u8]);
+
+impl<'a> core::fmt::Display for EscapedBytes<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for &b in self.0 {
+            match b {
+                b'\r' => f.write_str("\\r")?,
+                b'\n' => f.write_str("\\n")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\\' => f.write_str("\\\\")?,
+                b' '..=b'~' => core::fmt::Write::write_char(f, b as char)?,
+                _ => write!(f, "\\x{b:02x}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> core::fmt::Debug for EscapedBytes<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "\"{self}\"")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for EscapedBytes<'a> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=[u8]:a}", self.0)
+    }
+}
+
+/// Amount of detail logged about the AT traffic. It is adjustable at runtime,
+/// see eg. [`Ingress::set_trace`], to enable deep tracing on a unit in the
+/// field without shipping a separate build.
+///
+/// [`Ingress::set_trace`]: crate::Ingress::set_trace
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TraceLevel {
+    /// Log the kind and length of commands and responses, without their
+    /// contents
+    Summary,
+    /// Also log the contents of commands and responses
+    #[default]
+    Frames,
+    /// Also dump every chunk of bytes received, and commands with long
+    /// payloads
+    Bytes,
+}
+
+/// Wrapper for a byte-slice logged at a [`TraceLevel`]. It formats like
+/// [`LossyStr`] if the level includes the contents of frames, and as its
+/// length otherwise.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Traced<'a>(pub TraceLevel, pub &'a [u8]);
+
+impl<'a> core::fmt::Debug for Traced<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0 >= TraceLevel::Frames {
+            write!(f, "{:?}", LossyStr(self.1))
+        } else {
+            write!(f, "<{} bytes>", self.1.len())
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for Traced<'a> {
+    fn format(&self, fmt: defmt::Formatter) {
+        if self.0 >= TraceLevel::Frames {
+            defmt::write!(fmt, "{=[u8]:a}", self.1)
+        } else {
+            defmt::write!(fmt, "<{=usize} bytes>", self.1.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossy_str() {
+        assert_eq!(format!("{:?}", LossyStr(b"AT\r\n")), "\"AT\\r\\n\"");
+        assert_eq!(format!("{:?}", LossyStr(b"A\xff")), "[65, 255]");
+    }
+
+    #[test]
+    fn escaped_bytes() {
+        assert_eq!(
+            format!("{}", EscapedBytes(b"AT+CMGS=1\r\x1a\\\t")),
+            "AT+CMGS=1\\r\\x1a\\\\\\t"
+        );
+        assert_eq!(format!("{:?}", EscapedBytes(b"A\xff")), "\"A\\xff\"");
+    }
+
+    #[test]
+    fn traced() {
+        assert_eq!(
+            format!("{:?}", Traced(TraceLevel::Frames, b"AT\r\n")),
+            "\"AT\\r\\n\""
+        );
+        assert_eq!(
+            format!("{:?}", Traced(TraceLevel::Summary, b"AT\r\n")),
+            "<4 bytes>"
+        );
+    }
+}
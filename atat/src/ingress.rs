@@ -1,9 +1,12 @@
 use crate::{
-    helpers::LossyStr, urc_channel::UrcPublisher, AtatUrc, DigestResult, Digester, ResponseSlot,
-    UrcChannel,
+    config::TraceHook,
+    helpers::{LossyStr, Traced},
+    urc_channel::UrcPublisher,
+    AtatUrc, DigestResult, Digester, InternalError, ResponseSlot, TraceLevel, UrcChannel,
 };
+use embassy_time::{Duration, Instant};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     ResponseSlotBusy,
@@ -17,15 +20,26 @@ pub trait AtatIngress {
     fn write_buf(&mut self) -> &mut [u8];
 
     /// Commit a given number of written bytes to the ingress and make them visible to the digester.
+    ///
+    /// Fails with [`Error::UrcChannelFull`] if a URC could not be published,
+    /// in which case it is retried on the next call, and with
+    /// [`Error::ResponseSlotBusy`] if a response was dropped because the
+    /// previous one was never picked up by the client.
     fn try_advance(&mut self, commit: usize) -> Result<(), Error>;
 
     /// Commit a given number of written bytes to the ingress and make them visible to the digester.
     async fn advance(&mut self, commit: usize);
 
     /// Write a buffer to the ingress and return how many bytes were written.
+    ///
+    /// Bytes are committed even if [`AtatIngress::try_advance`] fails, so
+    /// writing continues past an error. The error is only returned if all of
+    /// `buf` was written, and the count is returned otherwise, such that the
+    /// caller never writes the same bytes twice.
     fn try_write(&mut self, buf: &[u8]) -> Result<usize, Error> {
         let mut buf = buf;
         let mut written = 0;
+        let mut error = None;
         while !buf.is_empty() {
             let ingress_buf = self.write_buf();
             if ingress_buf.is_empty() {
@@ -33,17 +47,26 @@ pub trait AtatIngress {
             }
             let len = usize::min(buf.len(), ingress_buf.len());
             ingress_buf[..len].copy_from_slice(&buf[..len]);
-            self.try_advance(len)?;
+            if let Err(e) = self.try_advance(len) {
+                error.get_or_insert(e);
+            }
             buf = &buf[len..];
             written += len;
         }
-        Ok(written)
+        match error {
+            Some(e) => Err(e),
+            None => Ok(written),
+        }
     }
 
     /// Write a buffer to the ingress
     async fn write(&mut self, buf: &[u8]) {
         let mut buf = buf;
         while !buf.is_empty() {
+            if self.write_buf().is_empty() {
+                warn!("Ingress buffer full, clearing");
+                self.clear_overflow();
+            }
             let ingress_buf = self.write_buf();
             let len = usize::min(buf.len(), ingress_buf.len());
             ingress_buf[..len].copy_from_slice(&buf[..len]);
@@ -60,7 +83,7 @@ pub trait AtatIngress {
             let buf = self.write_buf();
             if buf.is_empty() {
                 warn!("Ingress buffer full, clearing");
-                self.clear();
+                self.clear_overflow();
                 continue;
             }
             match serial.read(buf).await {
@@ -78,8 +101,27 @@ pub trait AtatIngress {
     }
 
     fn clear(&mut self);
+
+    /// Clear the ingress because its buffer is full. The in-flight command,
+    /// if any, is failed with [`Error::ResponseTooLarge`] rather than left
+    /// waiting for a timeout.
+    ///
+    /// [`Error::ResponseTooLarge`]: crate::Error::ResponseTooLarge
+    fn clear_overflow(&mut self) {
+        self.clear();
+    }
 }
 
+/// Default number of consecutive reads a digester may make no progress in,
+/// before the ingress reports it as stalled, see
+/// [`Ingress::with_stall_limit`].
+#[cfg(feature = "stall-check")]
+pub const DEFAULT_STALL_LIMIT: usize = 32;
+
+/// Bytes of the ingress buffer logged when the digester stalled
+#[cfg(feature = "stall-check")]
+const STALL_HEAD_LEN: usize = 32;
+
 pub struct Ingress<
     'a,
     D: Digester,
@@ -93,6 +135,67 @@ pub struct Ingress<
     pos: usize,
     res_slot: &'a ResponseSlot<RES_BUF_SIZE>,
     urc_publisher: UrcPublisher<'a, Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
+    urc_handler: Option<&'a mut (dyn FnMut(&[u8]) -> bool + Send)>,
+    restart_handler: Option<&'a mut (dyn FnMut() + Send)>,
+    /// Grace period after startup and restarts, and when the current one ends
+    grace_period: Option<Duration>,
+    grace_until: Instant,
+    trace: TraceLevel,
+    rx_hook: Option<TraceHook>,
+    #[cfg(feature = "memory-stats")]
+    stats: MemoryStats,
+    #[cfg(feature = "stall-check")]
+    stall_limit: usize,
+    /// Consecutive digests making no progress on a non-empty buffer
+    #[cfg(feature = "stall-check")]
+    stalled: usize,
+    /// Buffer length at the last digest making no progress
+    #[cfg(feature = "stall-check")]
+    stalled_pos: usize,
+}
+
+/// Usage of the buffers of an [`Ingress`] since it was created, to size
+/// their const generic parameters from field data rather than guesses.
+#[cfg(feature = "memory-stats")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MemoryStats {
+    /// Bytes currently held in the ingress buffer
+    pub ingress_used: usize,
+    /// Most bytes held in the ingress buffer at once
+    pub ingress_high_water: usize,
+    /// Size of the ingress buffer
+    pub ingress_capacity: usize,
+    /// Largest frame digested at once, including echo and line endings
+    pub max_frame: usize,
+    /// Largest response or page signalled, bounded by `RES_BUF_SIZE`
+    pub max_response: usize,
+    /// Largest URC line received
+    pub max_urc: usize,
+    /// URC's currently queued in the URC channel
+    pub urc_queued: usize,
+    /// Most URC's queued in the URC channel at once, bounded by
+    /// `URC_CAPACITY`
+    pub urc_high_water: usize,
+}
+
+#[cfg(feature = "memory-stats")]
+impl MemoryStats {
+    fn record(&mut self, res: &DigestResult<'_>, swallowed: usize) {
+        self.max_frame = self.max_frame.max(swallowed);
+        match res {
+            DigestResult::Response(Ok(r) | Err(InternalError::Custom(r)))
+            | DigestResult::Continuation(r) => {
+                self.max_response = self.max_response.max(r.len());
+            }
+            DigestResult::Urc(line) => self.max_urc = self.max_urc.max(line.len()),
+            _ => {}
+        }
+    }
+
+    fn record_urc_queue(&mut self, queued: usize) {
+        self.urc_high_water = self.urc_high_water.max(queued);
+    }
 }
 
 impl<
@@ -116,8 +219,266 @@ impl<
             pos: 0,
             res_slot,
             urc_publisher: urc_channel.0.publisher().unwrap(),
+            urc_handler: None,
+            restart_handler: None,
+            grace_period: None,
+            grace_until: Instant::MIN,
+            trace: TraceLevel::Frames,
+            rx_hook: None,
+            #[cfg(feature = "memory-stats")]
+            stats: MemoryStats::default(),
+            #[cfg(feature = "stall-check")]
+            stall_limit: DEFAULT_STALL_LIMIT,
+            #[cfg(feature = "stall-check")]
+            stalled: 0,
+            #[cfg(feature = "stall-check")]
+            stalled_pos: 0,
+        }
+    }
+
+    /// Create an ingress with a buffer of a size known at compile time,
+    /// checking that it holds a response of up to `RES_BUF_SIZE` bytes.
+    ///
+    /// The ingress buffer bounds the length of any received frame, while
+    /// `RES_BUF_SIZE` only bounds responses, and `URC_CAPACITY` the number of
+    /// queued URC's. To receive large data carrying URC's, eg. `+NSONMI`
+    /// with a 512 byte payload, only the ingress buffer needs to grow.
+    pub fn new_sized<const BUF_SIZE: usize>(
+        digester: D,
+        buf: &'a mut [u8; BUF_SIZE],
+        res_slot: &'a ResponseSlot<RES_BUF_SIZE>,
+        urc_channel: &'a UrcChannel<Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
+    ) -> Self {
+        const {
+            core::assert!(
+                BUF_SIZE >= RES_BUF_SIZE,
+                "the ingress buffer must hold the largest response of the response slot"
+            )
+        };
+        Self::new(digester, buf, res_slot, urc_channel)
+    }
+
+    /// Hand every URC line to `handler` straight from the ingress buffer,
+    /// before it is parsed into `Urc`. Returning `true` consumes the line, so
+    /// it is never parsed nor published to the URC channel.
+    ///
+    /// This allows large data carrying URC's to be processed in place, without
+    /// copying them into a `Urc` and being bound by its size. The handler runs
+    /// on the ingress task, and should return quickly.
+    #[must_use]
+    pub fn with_urc_handler(self, handler: &'a mut (dyn FnMut(&[u8]) -> bool + Send)) -> Self {
+        Self {
+            urc_handler: Some(handler),
+            ..self
+        }
+    }
+
+    /// Call `handler` when the digester detects that the device restarted, eg.
+    /// by [`AtDigester::with_boot_banners`], such that the application can
+    /// restore the device configuration. The in-flight command, if any, fails
+    /// with [`Error::ModemRestarted`] regardless, and the ingress buffer is
+    /// cleared, discarding any partial frame received along with the restart.
+    ///
+    /// [`AtDigester::with_boot_banners`]: crate::AtDigester::with_boot_banners
+    /// [`Error::ModemRestarted`]: crate::Error::ModemRestarted
+    #[must_use]
+    pub fn with_restart_handler(self, handler: &'a mut (dyn FnMut() + Send)) -> Self {
+        Self {
+            restart_handler: Some(handler),
+            ..self
+        }
+    }
+
+    /// Treat input as boot noise for `duration` from now, and from every
+    /// restart detected by the digester. URC's are still published, while
+    /// responses received when no command is in flight, and strict mode
+    /// desyncs, are dropped rather than signalled to the client.
+    #[must_use]
+    pub fn with_grace_period(self, duration: Duration) -> Self {
+        Self {
+            grace_period: Some(duration),
+            grace_until: Instant::now() + duration,
+            ..self
         }
     }
+
+    /// Report a stalled digester once it made no progress on a non-empty
+    /// buffer for `limit` consecutive reads, see [`DEFAULT_STALL_LIMIT`].
+    /// The stall is logged as an error, and panics in debug builds.
+    /// Reads growing an incomplete line are not counted, so the limit only
+    /// needs to exceed the number of reads the lines after the first one of
+    /// a multi-line response may arrive in.
+    #[cfg(feature = "stall-check")]
+    #[must_use]
+    pub fn with_stall_limit(self, limit: usize) -> Self {
+        Self {
+            stall_limit: limit,
+            ..self
+        }
+    }
+
+    /// Usage of the ingress buffer, the response slot and the URC channel
+    /// since the ingress was created.
+    #[cfg(feature = "memory-stats")]
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            ingress_used: self.pos,
+            ingress_capacity: self.buf.len(),
+            urc_queued: URC_CAPACITY - self.urc_publisher.free_capacity(),
+            ..self.stats
+        }
+    }
+
+    /// Discard everything received before the last `commit` bytes, if the
+    /// client is resynchronizing.
+    fn take_clear_request(&mut self, commit: usize) {
+        if self.res_slot.take_clear_request() {
+            debug!("Clearing {} bytes to resynchronize", self.pos - commit);
+            self.buf.copy_within(self.pos - commit..self.pos, 0);
+            self.pos = commit;
+            self.digester.reset();
+        }
+    }
+
+    /// Let the digester recognize the final result codes of the command in
+    /// flight, if any.
+    fn set_result_codes(&mut self) {
+        match self.res_slot.state() {
+            Some(state) => self
+                .digester
+                .set_result_codes(state.success_codes, state.error_codes),
+            None => self.digester.set_result_codes(&[], &[]),
+        }
+    }
+
+    /// Whether a digested result is noise of a device which is still booting
+    fn is_boot_noise(&self, res: &DigestResult<'_>) -> bool {
+        if self.grace_period.is_none() || Instant::now() >= self.grace_until {
+            return false;
+        }
+        match res {
+            DigestResult::Response(Err(InternalError::Desync)) => true,
+            DigestResult::Response(_) | DigestResult::Prompt(_) | DigestResult::Continuation(_) => {
+                self.res_slot.state().is_none()
+            }
+            _ => false,
+        }
+    }
+
+    /// Count digests making no progress on a non-empty buffer, to surface a
+    /// digester which never recognizes what it is given, eg. an unknown URC,
+    /// rather than silently waiting for the buffer to overflow.
+    ///
+    /// A frame arriving in many reads is not a stall: a digest only counts
+    /// if the buffer did not grow since the last one, or if it holds a
+    /// complete line the digester refused.
+    #[cfg(feature = "stall-check")]
+    fn check_stall(&mut self, swallowed: usize) {
+        if swallowed > 0 {
+            self.stalled = 0;
+            self.stalled_pos = 0;
+            return;
+        }
+        let grew = self.pos > self.stalled_pos;
+        self.stalled_pos = self.pos;
+        let data = &self.buf[..self.pos];
+        let start = data
+            .iter()
+            .position(|b| !matches!(b, b'\r' | b'\n'))
+            .unwrap_or(data.len());
+        if grew && !data[start..].contains(&b'\n') {
+            return;
+        }
+        self.stalled += 1;
+        if self.stalled == self.stall_limit {
+            error!(
+                "Digester made no progress in {} reads of {} bytes: {:?}",
+                self.stalled,
+                self.pos,
+                LossyStr(&self.buf[..self.pos.min(STALL_HEAD_LEN)])
+            );
+            #[cfg(debug_assertions)]
+            panic!("digester stalled");
+        }
+    }
+
+    fn restart_grace_period(&mut self) {
+        if let Some(duration) = self.grace_period {
+            self.grace_until = Instant::now() + duration;
+        }
+    }
+
+    /// Set the amount of detail logged about the received frames, eg. to
+    /// enable deep tracing temporarily from a debug channel.
+    pub fn set_trace(&mut self, level: TraceLevel) {
+        self.trace = level;
+    }
+
+    /// Mirror the received bytes, as they are, to `hook`, eg. the receive
+    /// hook of [`Config::trace_hooks`]. The hook runs on the ingress task,
+    /// and should return quickly.
+    ///
+    /// [`Config::trace_hooks`]: crate::Config::trace_hooks
+    pub fn set_rx_hook(&mut self, hook: Option<TraceHook>) {
+        self.rx_hook = hook;
+    }
+
+    /// Access the digester, eg. to change its mode at runtime.
+    pub fn digester_mut(&mut self) -> &mut D {
+        &mut self.digester
+    }
+
+    /// Tear down the ingress, discarding any partially received frame and
+    /// clearing the response slot, and return the digester and the ingress
+    /// buffer, eg. to hand the serial port to another peripheral.
+    ///
+    /// Once the client is released as well, a new pair can be created on the
+    /// same response slot and URC channel. URCs already queued are kept until
+    /// the channel is cleared with [`UrcChannel::clear`].
+    pub fn release(mut self) -> (D, &'a mut [u8]) {
+        debug!("Releasing ingress");
+        self.digester.reset();
+        self.res_slot.clear();
+        (self.digester, self.buf)
+    }
+}
+
+impl<
+        D: Digester,
+        Urc: AtatUrc,
+        const RES_BUF_SIZE: usize,
+        const URC_CAPACITY: usize,
+        const URC_SUBSCRIBERS: usize,
+    > core::fmt::Debug for Ingress<'_, D, Urc, RES_BUF_SIZE, URC_CAPACITY, URC_SUBSCRIBERS>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Ingress")
+            .field("pos", &self.pos)
+            .field("capacity", &self.buf.len())
+            .field("urc_handler", &self.urc_handler.is_some())
+            .field("restart_handler", &self.restart_handler.is_some())
+            .field("grace_period", &self.grace_period)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+        D: Digester,
+        Urc: AtatUrc,
+        const RES_BUF_SIZE: usize,
+        const URC_CAPACITY: usize,
+        const URC_SUBSCRIBERS: usize,
+    > defmt::Format for Ingress<'_, D, Urc, RES_BUF_SIZE, URC_CAPACITY, URC_SUBSCRIBERS>
+{
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Ingress {{ pos: {=usize}, capacity: {=usize}, .. }}",
+            self.pos,
+            self.buf.len()
+        )
+    }
 }
 
 impl<
@@ -135,16 +496,44 @@ impl<
     fn try_advance(&mut self, commit: usize) -> Result<(), Error> {
         self.pos += commit;
         assert!(self.pos <= self.buf.len());
+        if let Some(hook) = self.rx_hook {
+            hook(&self.buf[self.pos - commit..self.pos]);
+        }
+        if self.trace == TraceLevel::Bytes {
+            trace!(
+                "Received chunk: {:?}",
+                LossyStr(&self.buf[self.pos - commit..self.pos])
+            );
+        }
+        self.take_clear_request(commit);
+        self.pos = self.digester.normalize(self.buf, self.pos, commit);
+        self.set_result_codes();
+        #[cfg(feature = "memory-stats")]
+        {
+            self.stats.ingress_high_water = self.stats.ingress_high_water.max(self.pos);
+        }
+
+        // Responses are dropped rather than retried when the slot is busy, as
+        // a pending response means the client has lost track of the exchange
+        // anyway. Processing continues, and the loss is reported at the end.
+        let mut slot_busy = false;
 
         while self.pos > 0 {
-            let swallowed = match self.digester.digest(&self.buf[..self.pos]) {
+            let digested = self.digester.digest(&self.buf[..self.pos]);
+            #[cfg(feature = "memory-stats")]
+            self.stats.record(&digested.0, digested.1);
+            let swallowed = match digested {
+                (res, swallowed) if self.is_boot_noise(&res) => {
+                    trace!("Dropping boot noise ({}/{})", swallowed, self.pos);
+                    swallowed
+                }
                 (DigestResult::None, swallowed) => {
                     if swallowed > 0 {
                         debug!(
                             "Received echo or space ({}/{}): {:?}",
                             swallowed,
                             self.pos,
-                            LossyStr(&self.buf[..self.pos])
+                            Traced(self.trace, &self.buf[..self.pos])
                         );
                     }
 
@@ -155,28 +544,81 @@ impl<
 
                     if self.res_slot.signal_prompt(prompt).is_err() {
                         error!("Received prompt but a response is already pending");
+                        slot_busy = true;
                     }
 
                     swallowed
                 }
+                (DigestResult::Continuation(page), swallowed) => {
+                    debug!(
+                        "Received page of response ({}/{}): {:?}",
+                        swallowed,
+                        self.pos,
+                        Traced(self.trace, page)
+                    );
+
+                    if self.res_slot.signal_continuation(page).is_err() {
+                        error!("Received page of response but a response is already pending");
+                        slot_busy = true;
+                    }
+                    swallowed
+                }
                 (DigestResult::Urc(urc_line), swallowed) => {
-                    if let Some(urc) = Urc::parse(urc_line) {
+                    // Progress of the command in flight is handled by the client
+                    if self.res_slot.try_signal_progress(urc_line)
+                        || self.urc_handler.as_mut().is_some_and(|h| h(urc_line))
+                    {
+                        debug!(
+                            "Handled URC in place ({}/{}): {:?}",
+                            swallowed,
+                            self.pos,
+                            Traced(self.trace, urc_line)
+                        );
+                    } else if let Some(urc) = Urc::parse(urc_line) {
                         debug!(
                             "Received URC/{} ({}/{}): {:?}",
                             self.urc_publisher.free_capacity(),
                             swallowed,
                             self.pos,
-                            LossyStr(urc_line)
+                            Traced(self.trace, urc_line)
                         );
 
                         self.urc_publisher
                             .try_publish(urc)
                             .map_err(|_| Error::UrcChannelFull)?;
+                        #[cfg(feature = "memory-stats")]
+                        self.stats
+                            .record_urc_queue(URC_CAPACITY - self.urc_publisher.free_capacity());
                     } else {
                         error!("Parsing URC FAILED: {:?}", LossyStr(urc_line));
                     }
                     swallowed
                 }
+                (DigestResult::ModemRestarted, swallowed) => {
+                    self.restart_grace_period();
+                    self.res_slot.signal_booted();
+                    if let Some(handler) = self.restart_handler.as_mut() {
+                        handler();
+                    }
+                    // A command sent before the restart is never answered
+                    self.res_slot.swallow_response(None);
+
+                    // Fail the in-flight command, if any. A busy slot holds a
+                    // response from before the restart, which is kept.
+                    if self
+                        .res_slot
+                        .signal_response(Err(InternalError::ModemRestarted))
+                        .is_err()
+                    {
+                        debug!("Device restarted while a response is pending");
+                    }
+
+                    // Bytes received along with the restart predate the
+                    // clean state of the device, discard them with the banner
+                    debug!("Clearing {} bytes after restart", self.pos - swallowed);
+                    self.digester.reset();
+                    self.pos
+                }
                 (DigestResult::Response(resp), swallowed) => {
                     match &resp {
                         Ok(r) => {
@@ -187,7 +629,7 @@ impl<
                                     "Received response ({}/{}): {:?}",
                                     swallowed,
                                     self.pos,
-                                    LossyStr(r)
+                                    Traced(self.trace, r)
                                 );
                             }
                         }
@@ -199,13 +641,19 @@ impl<
                         }
                     }
 
-                    if self.res_slot.signal_response(resp).is_err() {
+                    if self.res_slot.take_swallow() {
+                        debug!("Swallowed response of a command sent without awaiting it");
+                    } else if self.res_slot.signal_response_in_order(resp).is_err() {
                         error!("Received response but a response is already pending");
+                        slot_busy = true;
                     }
                     swallowed
                 }
             };
 
+            #[cfg(feature = "stall-check")]
+            self.check_stall(swallowed);
+
             if swallowed == 0 {
                 break;
             }
@@ -214,22 +662,48 @@ impl<
             self.pos -= swallowed;
         }
 
+        if slot_busy {
+            return Err(Error::ResponseSlotBusy);
+        }
         Ok(())
     }
 
     async fn advance(&mut self, commit: usize) {
         self.pos += commit;
         assert!(self.pos <= self.buf.len());
+        if let Some(hook) = self.rx_hook {
+            hook(&self.buf[self.pos - commit..self.pos]);
+        }
+        if self.trace == TraceLevel::Bytes {
+            trace!(
+                "Received chunk: {:?}",
+                LossyStr(&self.buf[self.pos - commit..self.pos])
+            );
+        }
+        self.take_clear_request(commit);
+        self.pos = self.digester.normalize(self.buf, self.pos, commit);
+        self.set_result_codes();
+        #[cfg(feature = "memory-stats")]
+        {
+            self.stats.ingress_high_water = self.stats.ingress_high_water.max(self.pos);
+        }
 
         while self.pos > 0 {
-            let swallowed = match self.digester.digest(&self.buf[..self.pos]) {
+            let digested = self.digester.digest(&self.buf[..self.pos]);
+            #[cfg(feature = "memory-stats")]
+            self.stats.record(&digested.0, digested.1);
+            let swallowed = match digested {
+                (res, swallowed) if self.is_boot_noise(&res) => {
+                    trace!("Dropping boot noise ({}/{})", swallowed, self.pos);
+                    swallowed
+                }
                 (DigestResult::None, swallowed) => {
                     if swallowed > 0 {
                         debug!(
                             "Received echo or whitespace ({}/{}): {:?}",
                             swallowed,
                             self.pos,
-                            LossyStr(&self.buf[..self.pos])
+                            Traced(self.trace, &self.buf[..self.pos])
                         );
                     }
 
@@ -243,24 +717,75 @@ impl<
                     }
                     swallowed
                 }
+                (DigestResult::Continuation(page), swallowed) => {
+                    debug!(
+                        "Received page of response ({}/{}): {:?}",
+                        swallowed,
+                        self.pos,
+                        Traced(self.trace, page)
+                    );
+
+                    if self.res_slot.signal_continuation(page).is_err() {
+                        error!("Received page of response but a response is already pending");
+                    }
+                    swallowed
+                }
                 (DigestResult::Urc(urc_line), swallowed) => {
-                    if let Some(urc) = Urc::parse(urc_line) {
+                    // Progress of the command in flight is handled by the client
+                    if self.res_slot.try_signal_progress(urc_line)
+                        || self.urc_handler.as_mut().is_some_and(|h| h(urc_line))
+                    {
+                        debug!(
+                            "Handled URC in place ({}/{}): {:?}",
+                            swallowed,
+                            self.pos,
+                            Traced(self.trace, urc_line)
+                        );
+                    } else if let Some(urc) = Urc::parse(urc_line) {
                         debug!(
                             "Received URC/{} ({}/{}): {:?}",
                             self.urc_publisher.free_capacity(),
                             swallowed,
                             self.pos,
-                            LossyStr(urc_line)
+                            Traced(self.trace, urc_line)
                         );
 
                         if let Err(urc) = self.urc_publisher.try_publish(urc) {
                             self.urc_publisher.publish(urc).await;
                         }
+                        #[cfg(feature = "memory-stats")]
+                        self.stats
+                            .record_urc_queue(URC_CAPACITY - self.urc_publisher.free_capacity());
                     } else {
                         error!("Parsing URC FAILED: {:?}", LossyStr(urc_line));
                     }
                     swallowed
                 }
+                (DigestResult::ModemRestarted, swallowed) => {
+                    self.restart_grace_period();
+                    self.res_slot.signal_booted();
+                    if let Some(handler) = self.restart_handler.as_mut() {
+                        handler();
+                    }
+                    // A command sent before the restart is never answered
+                    self.res_slot.swallow_response(None);
+
+                    // Fail the in-flight command, if any. A busy slot holds a
+                    // response from before the restart, which is kept.
+                    if self
+                        .res_slot
+                        .signal_response(Err(InternalError::ModemRestarted))
+                        .is_err()
+                    {
+                        debug!("Device restarted while a response is pending");
+                    }
+
+                    // Bytes received along with the restart predate the
+                    // clean state of the device, discard them with the banner
+                    debug!("Clearing {} bytes after restart", self.pos - swallowed);
+                    self.digester.reset();
+                    self.pos
+                }
                 (DigestResult::Response(resp), swallowed) => {
                     match &resp {
                         Ok(r) => {
@@ -271,7 +796,7 @@ impl<
                                     "Received response ({}/{}): {:?}",
                                     swallowed,
                                     self.pos,
-                                    LossyStr(r)
+                                    Traced(self.trace, r)
                                 );
                             }
                         }
@@ -283,13 +808,18 @@ impl<
                         }
                     }
 
-                    if self.res_slot.signal_response(resp).is_err() {
+                    if self.res_slot.take_swallow() {
+                        debug!("Swallowed response of a command sent without awaiting it");
+                    } else if self.res_slot.signal_response_in_order(resp).is_err() {
                         error!("Received response but a response is already pending");
                     }
                     swallowed
                 }
             };
 
+            #[cfg(feature = "stall-check")]
+            self.check_stall(swallowed);
+
             if swallowed == 0 {
                 break;
             }
@@ -301,6 +831,23 @@ impl<
 
     fn clear(&mut self) {
         self.pos = 0;
+        self.digester.reset();
+        #[cfg(feature = "stall-check")]
+        {
+            self.stalled = 0;
+            self.stalled_pos = 0;
+        }
+    }
+
+    fn clear_overflow(&mut self) {
+        self.clear();
+        if self
+            .res_slot
+            .signal_response(Err(InternalError::ResponseTooLarge))
+            .is_err()
+        {
+            debug!("Ingress overflow while a response is pending");
+        }
     }
 }
 
@@ -431,6 +978,467 @@ mod tests {
         );
     }
 
+    #[test]
+    fn modem_restarted() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+
+        let mut restarts = 0;
+        let mut handler = || restarts += 1;
+        let digester = AtDigester::<Urc>::new().with_boot_banners(crate::digest::BOOT_BANNERS);
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(digester, &mut buf, &res_slot, &urc_channel)
+                .with_restart_handler(&mut handler);
+
+        ingress.try_write(b"AT+CSQ\r\r\n\r\nRDY\r\n").unwrap();
+        assert_eq!(
+            Response::ModemRestartedError,
+            *res_slot.try_get().unwrap().borrow()
+        );
+
+        res_slot.reset();
+        ingress.try_write(b"\r\n+PBREADY\r\n").unwrap();
+        assert_eq!(
+            Response::ModemRestartedError,
+            *res_slot.try_get().unwrap().borrow()
+        );
+
+        // The partial frame received along with the restart is discarded,
+        // and the next command is answered as usual
+        res_slot.reset();
+        ingress.try_write(b"\r\nRDY\r\n\r\n+CS").unwrap();
+        res_slot.reset();
+        ingress.try_write(b"\r\nOK\r\n").unwrap();
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+        drop(ingress);
+        assert_eq!(restarts, 3);
+    }
+
+    #[test]
+    fn release() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let _subscription = urc_channel.subscribe().unwrap();
+        let mut buf = [0; 100];
+
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+        ingress
+            .try_write(b"\r\nCONNECT OK\r\n\r\nOK\r\n\r\n+CRE")
+            .unwrap();
+        assert!(res_slot.try_get().is_some());
+        assert_eq!(urc_channel.free_capacity(), 9);
+
+        let (digester, buf) = ingress.release();
+        assert!(res_slot.try_get().is_none());
+        urc_channel.clear();
+        assert_eq!(urc_channel.free_capacity(), 10);
+
+        // A new ingress publishes to the same channel, without the partial
+        // frame of the released one
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(digester, buf, &res_slot, &urc_channel);
+        ingress.try_write(b"\r\nCONNECT FAIL\r\n").unwrap();
+        assert_eq!(urc_channel.free_capacity(), 9);
+        assert!(res_slot.try_get().is_none());
+    }
+
+    #[test]
+    fn grace_period() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+
+        let digester = AtDigester::<Urc>::new().with_boot_banners(crate::digest::BOOT_BANNERS);
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(digester, &mut buf, &res_slot, &urc_channel)
+                .with_grace_period(Duration::from_millis(50));
+        let mut sub = urc_channel.subscribe().unwrap();
+
+        // Noise is dropped, while URC's are still published
+        ingress
+            .try_write(b"\r\nboot rom v2\r\n\r\nERROR\r\n\r\nCONNECT OK\r\n")
+            .unwrap();
+        assert!(res_slot.try_get().is_none());
+        assert_eq!(Urc::ConnectOk, sub.try_next_message_pure().unwrap());
+
+        // Responses to a command in flight are delivered
+        res_slot.set_state(Some(crate::CommandState::new::<crate::init::InitStep>(1)));
+        ingress.try_write(b"\r\nOK\r\n").unwrap();
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+        res_slot.set_state(None);
+        res_slot.reset();
+
+        std::thread::sleep(std::time::Duration::from_millis(60));
+        ingress.try_write(b"\r\nERROR\r\n").unwrap();
+        assert_eq!(Response::OtherError, *res_slot.try_get().unwrap().borrow());
+
+        // A restart starts another grace period
+        res_slot.reset();
+        ingress.try_write(b"\r\nRDY\r\n").unwrap();
+        res_slot.reset();
+        ingress.try_write(b"\r\nERROR\r\n").unwrap();
+        assert!(res_slot.try_get().is_none());
+    }
+
+    #[test]
+    fn clear_request() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+
+        res_slot.set_state(Some(crate::CommandState::new::<crate::init::InitStep>(1)));
+        // Half a response received before the device was unplugged
+        ingress.try_write(b"\r\n+CSQ: 1").unwrap();
+        res_slot.request_clear();
+        ingress.try_write(b"\r\nOK\r\n").unwrap();
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+        assert!(!res_slot.take_clear_request());
+    }
+
+    #[test]
+    fn result_codes() {
+        #[derive(Clone, atat::atat_derive::AtatCmd)]
+        #[at_cmd(
+            "+CIPSEND",
+            heapless::String<64>,
+            success_code = "SEND OK",
+            error_code = "SEND FAIL"
+        )]
+        struct SendData;
+
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+
+        res_slot.set_state(Some(crate::CommandState::new::<SendData>(1)));
+        ingress
+            .try_write(b"\r\nRecv 4 bytes\r\n\r\nSEND OK\r\n")
+            .unwrap();
+        assert_eq!(
+            Response::ok(b"Recv 4 bytes"),
+            *res_slot.try_get().unwrap().borrow()
+        );
+        res_slot.reset();
+
+        ingress.try_write(b"\r\nSEND FAIL\r\n").unwrap();
+        assert_eq!(
+            Response::CustomError(heapless::Vec::from_slice(b"SEND FAIL").unwrap()),
+            *res_slot.try_get().unwrap().borrow()
+        );
+        res_slot.reset();
+
+        // The result codes only apply to the command in flight
+        res_slot.set_state(Some(crate::CommandState::new::<crate::init::InitStep>(1)));
+        ingress.try_write(b"\r\nSEND OK\r\n\r\nOK\r\n").unwrap();
+        assert_eq!(
+            Response::ok(b"SEND OK"),
+            *res_slot.try_get().unwrap().borrow()
+        );
+    }
+
+    #[cfg(feature = "memory-stats")]
+    #[test]
+    fn memory_stats() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+        let _sub = urc_channel.subscribe().unwrap();
+
+        ingress
+            .try_write(b"\r\nCONNECT OK\r\n\r\nCONNECT OK\r\n")
+            .unwrap();
+        ingress
+            .try_write(b"\r\n+CGMI: u-blox\r\n\r\nOK\r\n\r\n+CG")
+            .unwrap();
+        assert_eq!(
+            ingress.memory_stats(),
+            MemoryStats {
+                ingress_used: 5,
+                ingress_high_water: 28,
+                ingress_capacity: 100,
+                max_frame: 23,
+                max_response: 13,
+                max_urc: 10,
+                urc_queued: 2,
+                urc_high_water: 2,
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "stall-check", debug_assertions))]
+    #[should_panic(expected = "digester stalled")]
+    fn stall_check() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel)
+                .with_stall_limit(3);
+
+        // Progress resets the count
+        ingress.try_write(b"\r\nCONN").unwrap();
+        ingress.try_write(b"ECT OK\r\n").unwrap();
+        ingress.try_write(b"\r\nRI").unwrap();
+        ingress.try_write(b"NG").unwrap();
+        // The digester never recognizes the line
+        ingress.try_write(b"\r\n").unwrap();
+        ingress.try_write(b"\r\n").unwrap();
+        ingress.try_write(b"\r\n").unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "stall-check")]
+    fn stall_check_byte_at_a_time() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+
+        let frame = b"\r\n+CGDCONT: 1,\"IP\",\"internet\",\"0.0.0.0\",0,0\r\n\r\nOK\r\n";
+        assert!(frame.len() > DEFAULT_STALL_LIMIT);
+        for b in frame {
+            ingress.try_write(core::slice::from_ref(b)).unwrap();
+        }
+
+        assert_eq!(
+            Response::Ok(
+                heapless::Vec::from_slice(b"+CGDCONT: 1,\"IP\",\"internet\",\"0.0.0.0\",0,0")
+                    .unwrap()
+            ),
+            *res_slot.try_get().unwrap().borrow()
+        );
+    }
+
+    #[test]
+    fn trace_rx() {
+        static RX: std::sync::Mutex<std::vec::Vec<u8>> =
+            std::sync::Mutex::new(std::vec::Vec::new());
+
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+        ingress.set_rx_hook(Some(|bytes| RX.lock().unwrap().extend_from_slice(bytes)));
+
+        ingress.try_write(b"\r\nCONNECT").unwrap();
+        ingress.try_write(b" OK\r\n").unwrap();
+        assert_eq!(RX.lock().unwrap().as_slice(), b"\r\nCONNECT OK\r\n");
+    }
+
+    #[test]
+    fn swallow_response() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let digester = AtDigester::<Urc>::new().with_boot_banners(crate::digest::BOOT_BANNERS);
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(digester, &mut buf, &res_slot, &urc_channel);
+
+        // Only the next final result code is swallowed
+        res_slot.swallow_response(Some(Instant::now() + Duration::from_secs(1)));
+        ingress.try_write(b"AT+CFUN=16\r\r\nOK\r\n").unwrap();
+        assert!(res_slot.try_get().is_none());
+        ingress.try_write(b"\r\nOK\r\n").unwrap();
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+
+        // The device restarting before answering cancels the swallowing
+        res_slot.reset();
+        res_slot.swallow_response(Some(Instant::now() + Duration::from_secs(1)));
+        ingress.try_write(b"\r\nRDY\r\n").unwrap();
+        res_slot.reset();
+        ingress.try_write(b"\r\nOK\r\n").unwrap();
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+    }
+
+    #[test]
+    fn urc_handler_in_place() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+
+        let mut handled = 0;
+        let mut handler = |urc: &[u8]| {
+            if urc.starts_with(b"CUSTOM") {
+                handled += urc.len();
+                true
+            } else {
+                false
+            }
+        };
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel)
+                .with_urc_handler(&mut handler);
+
+        let mut sub = urc_channel.subscribe().unwrap();
+
+        let buf = ingress.write_buf();
+        let data = b"\r\nCUSTOM: 1,5, true\r\n\r\nCONNECT OK\r\n";
+        buf[..data.len()].copy_from_slice(data);
+        ingress.try_advance(data.len()).unwrap();
+        drop(ingress);
+
+        assert_eq!(Urc::ConnectOk, sub.try_next_message_pure().unwrap());
+        assert!(sub.try_next_message_pure().is_none());
+        assert_eq!(handled, b"CUSTOM: 1,5, true".len());
+    }
+
+    #[tokio::test]
+    async fn progress_of_command_in_flight() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+
+        let mut sub = urc_channel.subscribe().unwrap();
+
+        res_slot.set_progress_filter(Some(|line| line.starts_with(b"CONNECT")));
+        ingress
+            .try_write(b"\r\nCONNECT FAIL\r\n\r\nCONNECT OK\r\n\r\nOK\r\n")
+            .unwrap();
+
+        // Back-to-back progress lines are queued
+        assert_eq!(&res_slot.wait_progress().await[..], b"CONNECT FAIL");
+        assert_eq!(&res_slot.wait_progress().await[..], b"CONNECT OK");
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+        assert!(sub.try_next_message_pure().is_none());
+
+        res_slot.set_progress_filter(None);
+        ingress.try_write(b"\r\nCONNECT OK\r\n").unwrap();
+        assert_eq!(Urc::ConnectOk, sub.try_next_message_pure().unwrap());
+    }
+
+    #[tokio::test]
+    async fn response_too_large() {
+        let res_slot = ResponseSlot::<8>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 32];
+        let mut ingress: Ingress<_, Urc, 8, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+
+        // Fits the ingress buffer, but not the response slot
+        ingress.try_write(b"\r\n+CGMR: 1.2\r\nOK\r\n").unwrap();
+        assert_eq!(
+            Response::ResponseTooLargeError,
+            *res_slot.try_get().unwrap().borrow()
+        );
+
+        // Exceeds the ingress buffer before completing
+        res_slot.reset();
+        ingress
+            .write(b"\r\n+CGMR: 1.2.3.4.5.6.7.8.9.10.11.12\r\nOK\r\n")
+            .await;
+        assert_eq!(
+            Response::ResponseTooLargeError,
+            *res_slot.try_get().unwrap().borrow()
+        );
+    }
+
+    #[test]
+    fn urc_larger_than_response_slot() {
+        let res_slot = ResponseSlot::<8>::new();
+        let urc_channel = UrcChannel::<Urc, 1, 1>::new();
+        let mut sub = urc_channel.subscribe().unwrap();
+        let mut buf = [0; 64];
+        let mut ingress: Ingress<_, Urc, 8, 1, 1> =
+            Ingress::new_sized(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+
+        ingress
+            .try_write(b"\r\n+CREG: 1,\"0123456789ABCDEF0123456789\"\r\n")
+            .unwrap();
+        assert_eq!(Urc::Creg, sub.try_next_message_pure().unwrap());
+    }
+
+    #[test]
+    fn response_slot_busy_after_timeout() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+        let mut sub = urc_channel.subscribe().unwrap();
+
+        // A late response arrives after the client timed out, and is left in
+        // the slot. Any further response is dropped and reported, while the
+        // rest of the input is still processed.
+        assert_eq!(ingress.try_write(b"\r\nOK\r\n"), Ok(6));
+        assert_eq!(
+            ingress.try_write(b"\r\nERROR\r\n\r\nCONNECT OK\r\n"),
+            Err(Error::ResponseSlotBusy)
+        );
+        assert_eq!(Urc::ConnectOk, sub.try_next_message_pure().unwrap());
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+
+        // The client resets the slot when sending its next request
+        res_slot.reset();
+        assert_eq!(ingress.try_write(b"\r\nERROR\r\n"), Ok(9));
+        assert_eq!(Response::OtherError, *res_slot.try_get().unwrap().borrow());
+    }
+
+    #[test]
+    fn runtime_selected_digester() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+
+        let mut generic = AtDigester::<Urc>::new();
+        let mut vendor = AtDigester::<Urc>::new().with_custom_success(|buf| {
+            let (_, (matched, len)) =
+                atat::digest::parser::urc_helper::<_, nom::error::Error<_>>(&b"SEND OK"[..])(buf)?;
+            Ok((&matched[..0], len))
+        });
+
+        let detected_vendor = true;
+        let digester: &mut dyn Digester = if detected_vendor {
+            &mut vendor
+        } else {
+            &mut generic
+        };
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(digester, &mut buf, &res_slot, &urc_channel);
+
+        ingress.try_write(b"\r\nSEND OK\r\n").unwrap();
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+    }
+
+    #[test]
+    fn pipelined_responses_queued() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+
+        let mut ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+        let mut sub = urc_channel.subscribe().unwrap();
+
+        // Responses of later commands are queued, without holding up URC's
+        res_slot.set_pipelined(2);
+        assert_eq!(
+            ingress.try_write(b"\r\nOK\r\n\r\nERROR\r\n\r\nCONNECT OK\r\n"),
+            Ok(29)
+        );
+        assert_eq!(Urc::ConnectOk, sub.try_next_message_pure().unwrap());
+        assert_eq!(Response::default(), *res_slot.try_get().unwrap().borrow());
+
+        res_slot.take_pipelined();
+        assert_eq!(Response::OtherError, *res_slot.try_get().unwrap().borrow());
+        res_slot.take_pipelined();
+        assert!(res_slot.try_get().is_none());
+        assert_eq!(res_slot.pipelined(), 0);
+    }
+
     #[test]
     fn advance_can_processes_multiple_digest_results() {
         let res_slot = ResponseSlot::<100>::new();
@@ -476,7 +1484,7 @@ mod tests {
         }
         impl embedded_io_async::Read for Reader {
             async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-                assert!(buf.len() > 0);
+                assert!(!buf.is_empty());
                 if self.pos >= self.data.len() {
                     // Simulate waiting on more data.
                     loop {
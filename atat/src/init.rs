@@ -0,0 +1,212 @@
+//! The initialization handshake and resynchronization performed by the
+//! clients' `init` and `resync`.
+use core::fmt::Write;
+
+use embassy_time::Duration;
+use heapless::String;
+
+use crate::{AtatCmd, AtatResp, Config, Error, InternalError, Response};
+
+/// Empty response to an initialization step
+pub(crate) struct Ack;
+
+impl AtatResp for Ack {}
+
+/// A single step of the initialization handshake, in order of execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum InitStep {
+    /// `AT`, until the device responds
+    Sync,
+    /// `ATE0`, disable echo
+    EchoOff,
+    /// `AT+CMEE=1`, report `+CME ERROR` result codes
+    ReportErrors,
+    /// `ATS4=<c>`, response formatting character
+    ResponseFormatting(u8),
+    /// `ATS3=<c>`, command line termination character. Applied last, as the
+    /// response to it is the first to use the new termination.
+    LineTermination(u8),
+}
+
+impl InitStep {
+    pub(crate) fn sequence(config: &Config) -> impl Iterator<Item = Self> {
+        [Self::Sync, Self::EchoOff, Self::ReportErrors]
+            .into_iter()
+            .chain(config.response_formatting.map(Self::ResponseFormatting))
+            .chain(config.command_termination.map(Self::LineTermination))
+    }
+}
+
+impl AtatCmd for InitStep {
+    type Response = Ack;
+
+    const MAX_LEN: usize = 16;
+
+    fn write(&self, buf: &mut [u8]) -> usize {
+        let mut cmd = String::<16>::new();
+        // Longest command, `ATS3=255\r\n`, fits the buffer
+        match self {
+            Self::Sync => write!(cmd, "AT\r\n"),
+            Self::EchoOff => write!(cmd, "ATE0\r\n"),
+            Self::ReportErrors => write!(cmd, "AT+CMEE=1\r\n"),
+            Self::ResponseFormatting(c) => write!(cmd, "ATS4={}\r\n", c),
+            Self::LineTermination(c) => write!(cmd, "ATS3={}\r\n", c),
+        }
+        .unwrap();
+        buf[..cmd.len()].copy_from_slice(cmd.as_bytes());
+        cmd.len()
+    }
+
+    fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error> {
+        resp?;
+        Ok(Ack)
+    }
+}
+
+/// Drives the initialization handshake of a client: which step to send next,
+/// and whether a failed step is retried.
+pub(crate) struct Handshake {
+    steps: heapless::Vec<InitStep, 5>,
+    next: usize,
+    attempt: u8,
+    attempts: u8,
+}
+
+impl Handshake {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            steps: InitStep::sequence(config).collect(),
+            next: 0,
+            attempt: 1,
+            attempts: config.init_attempts,
+        }
+    }
+
+    /// The step to send next, or `None` once the handshake completed.
+    pub(crate) fn step(&self) -> Option<InitStep> {
+        self.steps.get(self.next).copied()
+    }
+
+    /// Record the result of the current step. Fails once a step failed
+    /// [`Config::init_attempts`] times.
+    pub(crate) fn record(&mut self, res: Result<Ack, Error>) -> Result<(), Error> {
+        let step = self.steps[self.next];
+        match res {
+            Ok(Ack) => {
+                self.next += 1;
+                self.attempt = 1;
+            }
+            Err(e) if self.attempt >= self.attempts => {
+                error!("Initialization failed at {:?}", step);
+                return Err(e);
+            }
+            Err(_) => {
+                self.attempt += 1;
+                debug!("Retrying {:?}, attempt {}", step, self.attempt);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drives the resynchronization of a client: how often the
+/// [`Config::attention_sequence`] is written, and which response ends it.
+pub(crate) struct Resync {
+    attempt: u8,
+    attempts: u8,
+}
+
+impl Resync {
+    /// Timeout of each response to the attention sequence
+    pub(crate) const TIMEOUT: Duration = Duration::from_millis(InitStep::MAX_TIMEOUT_MS as u64);
+
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            attempt: 0,
+            attempts: config.init_attempts.max(1),
+        }
+    }
+
+    /// The next attempt at writing the attention sequence, or `None` once
+    /// all attempts went without a clean `OK`.
+    pub(crate) fn next_attempt(&mut self) -> Option<u8> {
+        if self.attempt > 0 {
+            debug!("No clean OK to resync, attempt {}", self.attempt);
+        }
+        if self.attempt >= self.attempts {
+            return None;
+        }
+        self.attempt += 1;
+        Some(self.attempt)
+    }
+
+    /// The result of writing the attention sequence. A partial write is
+    /// fine, as the sequence starts by terminating any half written line.
+    pub(crate) fn written(res: Result<(), Error>) -> Result<(), Error> {
+        match res {
+            Ok(()) | Err(Error::PartialWrite(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether `response` is the clean `OK` ending the resynchronization.
+    pub(crate) fn is_clean<const N: usize>(response: &Response<N>) -> bool {
+        matches!(response, Response::Ok(r) if r.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence() {
+        let mut buf = [0; 16];
+        let written: heapless::Vec<_, 5> = InitStep::sequence(&Config::new())
+            .map(|step| {
+                let len = step.write(&mut buf);
+                String::<16>::try_from(core::str::from_utf8(&buf[..len]).unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(written, ["AT\r\n", "ATE0\r\n", "AT+CMEE=1\r\n"]);
+
+        let steps: heapless::Vec<_, 5> =
+            InitStep::sequence(&Config::new().line_termination(b'\r', b'\n')).collect();
+        assert_eq!(
+            &steps[3..],
+            [
+                InitStep::ResponseFormatting(10),
+                InitStep::LineTermination(13)
+            ]
+        );
+
+        // Terminating commands with a bare `\r` leaves the responses as is
+        let steps: heapless::Vec<_, 5> =
+            InitStep::sequence(&Config::new().command_termination(b'\r')).collect();
+        assert_eq!(&steps[3..], [InitStep::LineTermination(13)]);
+    }
+
+    #[test]
+    fn handshake() {
+        let mut handshake = Handshake::new(&Config::new().init_attempts(2));
+        assert_eq!(handshake.step(), Some(InitStep::Sync));
+        assert_eq!(handshake.record(Err(Error::Timeout)), Ok(()));
+        assert_eq!(handshake.step(), Some(InitStep::Sync));
+        assert_eq!(handshake.record(Ok(Ack)), Ok(()));
+        assert_eq!(handshake.step(), Some(InitStep::EchoOff));
+        assert_eq!(handshake.record(Err(Error::Error)), Ok(()));
+        assert_eq!(handshake.record(Err(Error::Timeout)), Err(Error::Timeout));
+    }
+
+    #[test]
+    fn resync() {
+        let mut resync = Resync::new(&Config::new().init_attempts(2));
+        assert_eq!(resync.next_attempt(), Some(1));
+        assert_eq!(resync.next_attempt(), Some(2));
+        assert_eq!(resync.next_attempt(), None);
+
+        assert!(Resync::is_clean(&Response::<16>::ok(b"")));
+        assert!(!Resync::is_clean(&Response::<16>::ok(b"+CSQ: 1,2")));
+    }
+}
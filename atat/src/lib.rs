@@ -98,119 +98,64 @@
 //! ```
 //!
 //! ### Basic usage example (More available in examples folder):
-//! ```ignore
-//!
-//! use cortex_m::asm;
-//! use hal::{
-//!     gpio::{
-//!         gpioa::{PA2, PA3},
-//!         Alternate, Floating, Input, AF7,
-//!     },
-//!     pac::{interrupt, Peripherals, USART2},
-//!     prelude::*,
-//!     serial::{Config, Event::Rxne, Rx, Serial},
-//!     timer::{Event, Timer},
-//! };
-//!
-//! use atat::{atat_derive::{AtatResp, AtatCmd}};
-//!
-//! use heapless::{spsc::Queue, String};
-//!
-//! use crate::rt::entry;
-//! static mut INGRESS: Option<atat::IngressManager> = None;
-//! static mut RX: Option<Rx<USART2>> = None;
-//!
-//!
-//! #[derive(Clone, AtatResp)]
-//! pub struct NoResponse;
-//!
-//! #[derive(Clone, AtatCmd)]
-//! #[at_cmd("", NoResponse, timeout_ms = 1000)]
-//! pub struct AT;
-//!
-//! #[entry]
-//! fn main() -> ! {
-//!     let p = Peripherals::take().unwrap();
 //!
-//!     let mut flash = p.FLASH.constrain();
-//!     let mut rcc = p.RCC.constrain();
-//!     let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+//! Received bytes are digested by the [`Ingress`], which hands responses to
+//! the client through a [`ResponseSlot`], and publishes URC's to the
+//! subscribers of a [`UrcChannel`]. Both are built on `embassy-sync`, such
+//! that waiting tasks are woken when a response or URC arrives, rather than
+//! polling for it.
 //!
-//!     let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+//! ```ignore
+//! use atat::{asynch::{AtatClient, Client}, AtatIngress, DefaultDigester, Ingress, ResponseSlot, UrcChannel};
 //!
-//!     let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+//! const INGRESS_BUF_SIZE: usize = 1024;
+//! const RES_SLOT_SIZE: usize = 256;
 //!
-//!     let tx = gpioa.pa2.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
-//!     let rx = gpioa.pa3.into_af7(&mut gpioa.moder, &mut gpioa.afrl);
+//! static RES_SLOT: ResponseSlot<RES_SLOT_SIZE> = ResponseSlot::new();
+//! static URC_CHANNEL: UrcChannel<Urc, 128, 3> = UrcChannel::new();
 //!
-//!     let mut timer = Timer::tim7(p.TIM7, 1.hz(), clocks, &mut rcc.apb1r1);
-//!     let at_timer = Timer::tim6(p.TIM6, 100.hz(), clocks, &mut rcc.apb1r1);
+//! #[embassy_executor::main]
+//! async fn main(spawner: Spawner) {
+//!     let (reader, writer) = uart.split();
 //!
-//!     let mut serial = Serial::usart2(
-//!         p.USART2,
-//!         (tx, rx),
-//!         Config::default().baudrate(115_200.bps()),
-//!         clocks,
-//!         &mut rcc.apb1r1,
+//!     static INGRESS_BUF: StaticCell<[u8; INGRESS_BUF_SIZE]> = StaticCell::new();
+//!     let ingress = Ingress::new_sized(
+//!         DefaultDigester::<Urc>::default(),
+//!         INGRESS_BUF.init([0; INGRESS_BUF_SIZE]),
+//!         &RES_SLOT,
+//!         &URC_CHANNEL,
 //!     );
+//!     spawner.spawn(ingress_task(ingress, reader)).unwrap();
 //!
-//!     serial.listen(Rxne);
-//!
-//!     static mut RES_QUEUE: ResQueue<256> = Queue::new();
-//!     static mut URC_QUEUE: UrcQueue<256, 10> = Queue::new();
-//!     static mut COM_QUEUE: ComQueue = Queue::new();
-//!
-//!     let queues = Queues {
-//!         res_queue: unsafe { RES_QUEUE.split() },
-//!         urc_queue: unsafe { URC_QUEUE.split() },
-//!         com_queue: unsafe { COM_QUEUE.split() },
-//!     };
-//!
-//!     let (tx, rx) = serial.split();
-//!     let (mut client, ingress) =
-//!         ClientBuilder::new(tx, timer, atat::Config::new(atat::Mode::Timeout)).build(queues);
-//!
-//!     unsafe { INGRESS = Some(ingress) };
-//!     unsafe { RX = Some(rx) };
-//!
-//!     // configure NVIC interrupts
-//!     unsafe { cortex_m::peripheral::NVIC::unmask(hal::stm32::Interrupt::TIM7) };
-//!     timer.listen(Event::TimeOut);
+//!     static BUF: StaticCell<[u8; 1024]> = StaticCell::new();
+//!     let mut client = Client::new(writer, &RES_SLOT, BUF.init([0; 1024]), atat::Config::default());
 //!
-//!     // if all goes well you should reach this breakpoint
-//!     asm::bkpt();
-//!
-//!     loop {
-//!         asm::wfi();
-//!
-//!         match client.send(&AT) {
-//!             Ok(response) => {
-//!                 // Do something with response here
-//!             }
-//!             Err(e) => {}
+//!     let mut urcs = URC_CHANNEL.subscribe().unwrap();
+//!     match client.send(&AT).await {
+//!         Ok(response) => {
+//!             // Do something with response here
 //!         }
+//!         Err(e) => {}
 //!     }
+//!     let urc = urcs.next_message_pure().await;
 //! }
 //!
-//! #[interrupt]
-//! fn TIM7() {
-//!     let ingress = unsafe { INGRESS.as_mut().unwrap() };
-//!     ingress.digest();
-//! }
-//!
-//! #[interrupt]
-//! fn USART2() {
-//!     let ingress = unsafe { INGRESS.as_mut().unwrap() };
-//!     let rx = unsafe { RX.as_mut().unwrap() };
-//!     if let Ok(d) = nb::block!(rx.read()) {
-//!         ingress.write(&[d]);
-//!     }
+//! #[embassy_executor::task]
+//! async fn ingress_task(mut ingress: Ingress<'static, DefaultDigester<Urc>, Urc, RES_SLOT_SIZE, 128, 3>, mut reader: BufferedUartRx<'static, UART0>) -> ! {
+//!     ingress.read_from(&mut reader).await
 //! }
 //! ```
 //! # Optional Cargo Features
 //!
 //! - **`derive`** *(enabled by default)* - Re-exports [`atat_derive`] to allow
 //!   deriving `Atat__` traits.
+//! - **`console`** - Enables the [`console`] module, an interactive debug
+//!   console for sending raw AT commands during development.
+//! - **`rtt`** - Enables the [`rtt`] module, a transport running the AT byte
+//!   stream over RTT channels, to exercise the pipeline on a dev board without
+//!   a modem.
+//! - **`std`** - Enables the [`replay`] module, replaying captured serial
+//!   traffic through the ingress in host-side regression tests.
 
 // #![deny(warnings)]
 #![allow(clippy::multiple_crate_versions)]
@@ -228,17 +173,37 @@
 // This mod MUST go first, so that the others see its macros.
 pub(crate) mod fmt;
 
+pub mod binary;
+pub mod capabilities;
+pub mod chained;
 mod config;
+#[cfg(feature = "console")]
+pub mod console;
 pub mod digest;
+pub mod dma;
 mod error;
 pub mod helpers;
 mod ingress;
+mod init;
+pub mod loopback;
+pub mod reassembly;
+#[cfg(any(test, feature = "std"))]
+pub mod replay;
+pub mod report;
 mod response;
 pub mod response_slot;
+pub mod router;
+#[cfg(feature = "rtt")]
+pub mod rtt;
+#[cfg(feature = "stk")]
+pub mod stk;
+#[cfg(test)]
+mod test_ingress;
 mod traits;
 #[cfg(test)]
 mod tx_mock;
 pub mod urc_channel;
+pub mod urc_limiter;
 pub use nom;
 
 pub mod asynch;
@@ -264,14 +229,27 @@ pub use serde_at;
 #[cfg(feature = "derive")]
 pub use heapless;
 
-pub use config::Config;
-pub use digest::{AtDigester, AtDigester as DefaultDigester, DigestResult, Digester, Parser};
+pub use capabilities::{Capabilities, TestCmd};
+pub use chained::Chained;
+pub use config::{Config, ConfigDelta};
+pub use digest::{
+    AtDigester, AtDigester as DefaultDigester, CmeMode, DigestResult, Digester, Parser, UrcMatcher,
+};
+#[cfg(feature = "parse-diagnostics")]
+pub use error::{take_parse_diagnostic, ParseDiagnostic};
 pub use error::{CmeError, CmsError, ConnectionError, Error, InternalError};
+pub use helpers::{EscapedBytes, LossyStr, TraceLevel};
+#[cfg(feature = "memory-stats")]
+pub use ingress::MemoryStats;
+#[cfg(feature = "stall-check")]
+pub use ingress::DEFAULT_STALL_LIMIT;
 pub use ingress::{AtatIngress, Error as IngressError, Ingress};
 pub use response::Response;
-pub use response_slot::ResponseSlot;
+#[cfg(feature = "command-stats")]
+pub use response_slot::CommandStats;
+pub use response_slot::{CommandState, ResponseSlot, COMMAND_STATS_LEN};
 pub use traits::{AtatCmd, AtatResp, AtatUrc};
-pub use urc_channel::{UrcChannel, UrcSubscription};
+pub use urc_channel::{RawUrc, UrcChannel, UrcSink, UrcStream, UrcSubscription, WithRaw};
 
 #[cfg(test)]
 #[cfg(feature = "defmt")]
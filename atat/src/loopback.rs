@@ -0,0 +1,251 @@
+//! Loopback facilities for self-testing the AT pipeline without a device
+//! attached.
+//!
+//! There are two ways of looping requests back:
+//!
+//! - Enabling [`Config::loopback`], where the client signals each written
+//!   request straight back as its own response, bypassing the ingress and
+//!   digester.
+//! - Using [`Loopback`] as the client writer, which feeds each written
+//!   request, followed by a canned response, into an [`AtatIngress`], such
+//!   that it passes through the digester as if it was echoed by a device.
+//!
+//! In both cases, sending [`SelfTest`] yields a [`SelfTestReport`] telling
+//! whether the request made it back through the pipeline.
+//!
+//! [`Config::loopback`]: crate::Config::loopback
+use crate::{AtatCmd, AtatIngress, AtatResp, Error, InternalError};
+
+const SELF_TEST_CMD: &[u8] = b"AT\r\n";
+
+/// Power-on self-test command, sending a bare `AT`.
+///
+/// With a device attached, this passes when the device responds `OK`. With
+/// [`Config::loopback`] enabled, it passes when the request is received back
+/// unaltered.
+///
+/// [`Config::loopback`]: crate::Config::loopback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelfTest;
+
+/// Result of a [`SelfTest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelfTestReport {
+    /// Whether the response matched the request
+    pub passed: bool,
+    /// Whether the response was the request itself, as opposed to an empty
+    /// `OK` response
+    pub looped_back: bool,
+    /// Number of response bytes received
+    pub received: usize,
+}
+
+impl AtatResp for SelfTestReport {}
+
+impl AtatCmd for SelfTest {
+    type Response = SelfTestReport;
+
+    const MAX_LEN: usize = SELF_TEST_CMD.len();
+
+    const WIRE_IMAGE: Option<&'static [u8]> = Some(SELF_TEST_CMD);
+
+    fn write(&self, buf: &mut [u8]) -> usize {
+        buf[..SELF_TEST_CMD.len()].copy_from_slice(SELF_TEST_CMD);
+        SELF_TEST_CMD.len()
+    }
+
+    fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error> {
+        let resp = resp?;
+        let looped_back = resp == SELF_TEST_CMD;
+        Ok(SelfTestReport {
+            passed: looped_back || resp.is_empty(),
+            looped_back,
+            received: resp.len(),
+        })
+    }
+}
+
+/// Writer feeding everything written to it into an ingress, followed by a
+/// canned `response` on every flush.
+///
+/// With the default `"\r\nOK\r\n"` response, the digester treats the written
+/// request as an echo, and the response completes the request with an empty
+/// `OK`.
+pub struct Loopback<'a, I: AtatIngress> {
+    ingress: &'a mut I,
+    response: &'a [u8],
+}
+
+impl<'a, I: AtatIngress> Loopback<'a, I> {
+    pub fn new(ingress: &'a mut I) -> Self {
+        Self {
+            ingress,
+            response: b"\r\nOK\r\n",
+        }
+    }
+
+    /// Use a different canned response, eg. an error code or a URC, to test
+    /// how those pass through the pipeline.
+    #[must_use]
+    pub fn with_response(self, response: &'a [u8]) -> Self {
+        Self { response, ..self }
+    }
+}
+
+impl<I: AtatIngress> core::fmt::Debug for Loopback<'_, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Loopback")
+            .field("response", &crate::helpers::LossyStr(self.response))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<I: AtatIngress> defmt::Format for Loopback<'_, I> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Loopback {{ response: {=[u8]:a}, .. }}", self.response)
+    }
+}
+
+impl<I: AtatIngress> embedded_io::ErrorType for Loopback<'_, I> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl<I: AtatIngress> embedded_io::Write for Loopback<'_, I> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        match self.ingress.try_write(buf) {
+            Ok(0) if !buf.is_empty() => Err(embedded_io::ErrorKind::OutOfMemory),
+            Ok(written) => Ok(written),
+            Err(_) => Err(embedded_io::ErrorKind::Other),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        embedded_io::Write::write_all(self, self.response)
+    }
+}
+
+impl<I: AtatIngress> embedded_io_async::Write for Loopback<'_, I> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.ingress.write(buf).await;
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.ingress.write(self.response).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asynch::{AtatClient, Client},
+        AtDigester, Config, Ingress, ResponseSlot, UrcChannel,
+    };
+
+    #[derive(Clone, Debug)]
+    enum Urc {}
+
+    impl crate::AtatUrc for Urc {
+        type Response = Self;
+
+        fn parse(_resp: &[u8]) -> Option<Self::Response> {
+            None
+        }
+    }
+
+    impl crate::Parser for Urc {
+        fn parse(_buf: &[u8]) -> Result<(&[u8], usize), crate::digest::ParseError> {
+            Err(crate::digest::ParseError::NoMatch)
+        }
+    }
+
+    #[test]
+    fn parse_report() {
+        assert_eq!(
+            SelfTest.parse(Ok(b"AT\r\n")),
+            Ok(SelfTestReport {
+                passed: true,
+                looped_back: true,
+                received: 4
+            })
+        );
+        assert_eq!(
+            SelfTest.parse(Ok(b"")),
+            Ok(SelfTestReport {
+                passed: true,
+                looped_back: false,
+                received: 0
+            })
+        );
+        assert!(!SelfTest.parse(Ok(b"A")).unwrap().passed);
+        assert_eq!(SelfTest.parse(Err(InternalError::Error)), Err(Error::Error));
+    }
+
+    #[tokio::test]
+    async fn config_loopback() {
+        static RES_SLOT: ResponseSlot<64> = ResponseSlot::new();
+        let mut tx = [0; 64];
+        let mut buf = [0; 64];
+        let mut client = Client::new(
+            &mut tx[..],
+            &RES_SLOT,
+            &mut buf,
+            Config::new().loopback(true),
+        );
+
+        let report = client.send(&SelfTest).await.unwrap();
+        assert!(report.passed);
+        assert!(report.looped_back);
+    }
+
+    #[tokio::test]
+    async fn ingress_loopback() {
+        static RES_SLOT: ResponseSlot<64> = ResponseSlot::new();
+        static URC_CHANNEL: UrcChannel<Urc, 1, 1> = UrcChannel::new();
+        let mut ingress_buf = [0; 64];
+        let mut ingress = Ingress::new(
+            AtDigester::<Urc>::new(),
+            &mut ingress_buf,
+            &RES_SLOT,
+            &URC_CHANNEL,
+        );
+        let mut buf = [0; 64];
+        let mut client = Client::new(
+            Loopback::new(&mut ingress),
+            &RES_SLOT,
+            &mut buf,
+            Config::new(),
+        );
+
+        let report = client.send(&SelfTest).await.unwrap();
+        assert!(report.passed);
+        assert!(!report.looped_back);
+    }
+
+    #[tokio::test]
+    async fn ingress_loopback_error() {
+        static RES_SLOT: ResponseSlot<64> = ResponseSlot::new();
+        static URC_CHANNEL: UrcChannel<Urc, 1, 1> = UrcChannel::new();
+        let mut ingress_buf = [0; 64];
+        let mut ingress = Ingress::new(
+            AtDigester::<Urc>::new(),
+            &mut ingress_buf,
+            &RES_SLOT,
+            &URC_CHANNEL,
+        );
+        let mut buf = [0; 64];
+        let mut client = Client::new(
+            Loopback::new(&mut ingress).with_response(b"\r\nERROR\r\n"),
+            &RES_SLOT,
+            &mut buf,
+            Config::new(),
+        );
+
+        assert_eq!(client.send(&SelfTest).await, Err(Error::Error));
+    }
+}
@@ -0,0 +1,275 @@
+//! Reassembly of fragmented transports, eg. BLE Nordic UART Service (NUS),
+//! in front of an ingress.
+//!
+//! BLE delivers data in MTU sized fragments, which may split lines anywhere.
+//! Splitting alone is handled by the ingress, which buffers partial lines, so
+//! fragments of an ordered link can be written to it directly. Links using
+//! write without response may however reorder fragments, in which case the
+//! peer can prefix each fragment with a wrapping sequence number, and the
+//! [`Reassembler`] restores the byte stream before it is digested.
+//!
+//! ```
+//! use atat::{reassembly::Reassembler, AtatIngress};
+//!
+//! /// Called from the NUS RX characteristic write handler, where the first
+//! /// byte of each fragment is its sequence number.
+//! fn on_nus_rx<I: AtatIngress>(
+//!     reassembler: &mut Reassembler<'_, I, 4, 20>,
+//!     data: &[u8],
+//! ) {
+//!     if let Some((&seq, fragment)) = data.split_first() {
+//!         if reassembler.try_write_fragment(seq, fragment).is_err() {
+//!             // A fragment was lost, give up waiting for it
+//!             reassembler.resync().ok();
+//!         }
+//!     }
+//! }
+//! ```
+use core::cmp::Reverse;
+
+use heapless::Vec;
+
+use crate::{AtatIngress, IngressError};
+
+/// Error reassembling a fragmented transport.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A fragment is larger than the configured MTU
+    FragmentTooLarge,
+    /// Too many fragments are waiting for a missing one. Either it was lost,
+    /// in which case [`Reassembler::resync`] skips it, or the window is too
+    /// small for the link.
+    WindowFull,
+    /// The ingress failed to accept the reassembled bytes
+    Ingress(IngressError),
+}
+
+impl From<IngressError> for Error {
+    fn from(e: IngressError) -> Self {
+        Self::Ingress(e)
+    }
+}
+
+/// Restores the order of sequenced fragments before writing them to an
+/// ingress.
+///
+/// Up to `WINDOW` fragments of at most `MTU` bytes are held back while
+/// waiting for a missing fragment. Duplicates of already written fragments
+/// are dropped.
+pub struct Reassembler<'a, I: AtatIngress, const WINDOW: usize, const MTU: usize> {
+    ingress: &'a mut I,
+    next_seq: u8,
+    pending: Vec<(u8, Vec<u8, MTU>), WINDOW>,
+}
+
+impl<'a, I: AtatIngress, const WINDOW: usize, const MTU: usize> Reassembler<'a, I, WINDOW, MTU> {
+    pub fn new(ingress: &'a mut I) -> Self {
+        Self {
+            ingress,
+            next_seq: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Forget any held back fragments, and expect `seq` next. Call this when
+    /// the BLE connection is re-established.
+    pub fn reset(&mut self, seq: u8) {
+        self.pending.clear();
+        self.next_seq = seq;
+    }
+
+    /// Write a fragment to the ingress, along with any held back fragments
+    /// following it.
+    pub async fn write_fragment(&mut self, seq: u8, fragment: &[u8]) -> Result<(), Error> {
+        if !self.accept(seq, fragment)? {
+            return Ok(());
+        }
+
+        self.ingress.write(fragment).await;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        while let Some(fragment) = self.take_next() {
+            self.ingress.write(&fragment).await;
+        }
+        Ok(())
+    }
+
+    /// Non-blocking variant of [`Reassembler::write_fragment`], eg. for use
+    /// in a BLE event handler.
+    pub fn try_write_fragment(&mut self, seq: u8, fragment: &[u8]) -> Result<(), Error> {
+        if !self.accept(seq, fragment)? {
+            return Ok(());
+        }
+
+        // A fragment is committed to the ingress even if it fails, so only the
+        // held back fragments not yet written are kept
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.ingress.try_write(fragment)?;
+        while let Some(fragment) = self.take_next() {
+            self.ingress.try_write(&fragment)?;
+        }
+        Ok(())
+    }
+
+    /// Give up waiting for missing fragments, and write all held back
+    /// fragments in sequence order.
+    pub fn resync(&mut self) -> Result<(), Error> {
+        // Last in sequence first, so fragments are popped in order, and the
+        // ones not yet written are kept if the ingress fails
+        let next_seq = self.next_seq;
+        self.pending
+            .sort_unstable_by_key(|(seq, _)| Reverse(seq.wrapping_sub(next_seq)));
+
+        while let Some((seq, fragment)) = self.pending.pop() {
+            if seq != self.next_seq {
+                warn!("Skipping missing fragments {} to {}", self.next_seq, seq);
+            }
+            self.next_seq = seq.wrapping_add(1);
+            self.ingress.try_write(&fragment)?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the fragment is next in sequence, holding it back if
+    /// it is ahead, and dropping it if it is a duplicate.
+    fn accept(&mut self, seq: u8, fragment: &[u8]) -> Result<bool, Error> {
+        if fragment.len() > MTU {
+            return Err(Error::FragmentTooLarge);
+        }
+
+        match seq.wrapping_sub(self.next_seq) {
+            0 => Ok(true),
+            // Behind, already written
+            128.. => {
+                debug!("Dropping duplicate fragment {}", seq);
+                Ok(false)
+            }
+            _ if self.pending.iter().any(|(s, _)| *s == seq) => Ok(false),
+            _ => {
+                trace!("Holding back fragment {}, expecting {}", seq, self.next_seq);
+                // Length is checked against `MTU` above
+                let fragment = Vec::from_slice(fragment).unwrap();
+                self.pending
+                    .push((seq, fragment))
+                    .map_err(|_| Error::WindowFull)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Take the held back fragment that is next in sequence, if any.
+    fn take_next(&mut self) -> Option<Vec<u8, MTU>> {
+        let i = self
+            .pending
+            .iter()
+            .position(|(seq, _)| *seq == self.next_seq)?;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        Some(self.pending.swap_remove(i).1)
+    }
+}
+
+impl<I: AtatIngress, const WINDOW: usize, const MTU: usize> core::fmt::Debug
+    for Reassembler<'_, I, WINDOW, MTU>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Reassembler")
+            .field("next_seq", &self.next_seq)
+            .field("pending", &self.pending.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<I: AtatIngress, const WINDOW: usize, const MTU: usize> defmt::Format
+    for Reassembler<'_, I, WINDOW, MTU>
+{
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Reassembler {{ next_seq: {=u8}, pending: {=usize}, .. }}",
+            self.next_seq,
+            self.pending.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_ingress::ingress;
+    use crate::{Response, ResponseSlot, UrcChannel};
+
+    #[test]
+    fn reorder() {
+        let res_slot = ResponseSlot::<64>::new();
+        let urc_channel = UrcChannel::new();
+        let mut buf = [0; 64];
+        let mut ingress = ingress(&mut buf, &res_slot, &urc_channel);
+        let mut reassembler = Reassembler::<_, 4, 4>::new(&mut ingress);
+
+        reassembler.reset(254);
+        reassembler.try_write_fragment(0, b"\r\n").unwrap();
+        reassembler.try_write_fragment(255, b"RSSI").unwrap();
+        assert!(res_slot.try_get().is_none());
+
+        // Duplicate of a written fragment
+        reassembler.try_write_fragment(255, b"RSSI").unwrap();
+        reassembler.try_write_fragment(1, b"OK\r\n").unwrap();
+        assert_eq!(
+            reassembler.try_write_fragment(2, b"ERROR"),
+            Err(Error::FragmentTooLarge)
+        );
+        assert!(res_slot.try_get().is_none());
+
+        reassembler.try_write_fragment(254, b"\r\n+C").unwrap();
+        assert_eq!(
+            *res_slot.try_get().unwrap().borrow(),
+            Response::Ok(Vec::from_slice(b"+CRSSI").unwrap())
+        );
+    }
+
+    #[test]
+    fn window_full_resync() {
+        let res_slot = ResponseSlot::<64>::new();
+        let urc_channel = UrcChannel::new();
+        let mut buf = [0; 64];
+        let mut ingress = ingress(&mut buf, &res_slot, &urc_channel);
+        let mut reassembler = Reassembler::<_, 2, 8>::new(&mut ingress);
+
+        // Fragment 0 is lost
+        reassembler.try_write_fragment(2, b"OK\r\n").unwrap();
+        reassembler.try_write_fragment(1, b"\r\n").unwrap();
+        assert_eq!(
+            reassembler.try_write_fragment(3, b"\r\n"),
+            Err(Error::WindowFull)
+        );
+
+        reassembler.resync().unwrap();
+        assert_eq!(*res_slot.try_get().unwrap().borrow(), Response::default());
+
+        reassembler.try_write_fragment(3, b"\r\nOK").unwrap();
+    }
+
+    #[test]
+    fn resync_ingress_error() {
+        let res_slot = ResponseSlot::<64>::new();
+        let urc_channel = UrcChannel::new();
+        let mut buf = [0; 64];
+        let mut ingress = ingress(&mut buf, &res_slot, &urc_channel);
+        let mut reassembler = Reassembler::<_, 4, 8>::new(&mut ingress);
+
+        // The response is never picked up, and fragment 1 is lost
+        reassembler.try_write_fragment(0, b"\r\nOK\r\n").unwrap();
+        reassembler.try_write_fragment(2, b"\r\nOK\r\n").unwrap();
+        reassembler.try_write_fragment(3, b"\r\nOK\r\n").unwrap();
+        assert_eq!(
+            reassembler.resync(),
+            Err(Error::Ingress(IngressError::ResponseSlotBusy))
+        );
+
+        // Fragments not yet written are kept
+        res_slot.reset();
+        reassembler.resync().unwrap();
+        assert_eq!(*res_slot.try_get().unwrap().borrow(), Response::default());
+    }
+}
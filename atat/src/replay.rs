@@ -0,0 +1,353 @@
+//! Replay of captured serial traffic through an [`Ingress`], to turn a raw
+//! capture of a misbehaving device into a regression test.
+//!
+//! A trace holds one chunk per line, in the order it was captured:
+//!
+//! ```text
+//! # SIMCom voice firmware, RING preceded by a stray carriage return
+//! < "ATD+4512345678;\r\n"
+//! +12ms "\r\nOK\r\n"
+//! +1500ms "\r\r\nRING\r\n"
+//! ```
+//!
+//! - `> "..."`, or `+<delay>ms "..."`, is a chunk received from the device,
+//!   written to the ingress as a single chunk, optionally after a delay.
+//! - `< "..."` is a request written by the client, which puts a command in
+//!   flight, resetting the response slot like the client does.
+//! - Empty lines and lines starting with `#` are ignored.
+//!
+//! Chunks are given as quoted strings, with the escapes `\r`, `\n`, `\t`,
+//! `\"`, `\\` and `\xHH`.
+//!
+//! [`Ingress`]: crate::Ingress
+
+use std::vec::Vec;
+
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::{
+    ingress,
+    urc_channel::{self, UrcSubscription},
+    AtatIngress, AtatUrc, CommandState, Response, ResponseSlot, UrcChannel,
+};
+
+/// Error in a trace, with the line number it was found on, starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceError {
+    pub line: usize,
+}
+
+/// Direction of a captured chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Written by the client to the device
+    Tx,
+    /// Received from the device
+    Rx,
+}
+
+/// A chunk of a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub direction: Direction,
+    /// Time elapsed since the previous chunk, when it was captured
+    pub delay: Duration,
+    pub bytes: Vec<u8>,
+}
+
+/// Parse a trace into its chunks.
+pub fn parse(trace: &str) -> Result<Vec<Chunk>, TraceError> {
+    trace
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(i, line)| parse_chunk(line.trim()).ok_or(TraceError { line: i + 1 }))
+        .collect()
+}
+
+fn parse_chunk(line: &str) -> Option<Chunk> {
+    let (head, data) = line.split_once(' ')?;
+    let (direction, delay) = match head {
+        "<" => (Direction::Tx, Duration::from_ticks(0)),
+        ">" => (Direction::Rx, Duration::from_ticks(0)),
+        _ => {
+            let ms = head.strip_prefix('+')?.strip_suffix("ms")?.parse().ok()?;
+            (Direction::Rx, Duration::from_millis(ms))
+        }
+    };
+    let data = data.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some(Chunk {
+        direction,
+        delay,
+        bytes: unescape(data)?,
+    })
+}
+
+fn unescape(data: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut chars = data.bytes();
+    while let Some(c) = chars.next() {
+        if c != b'\\' {
+            bytes.push(c);
+            continue;
+        }
+        bytes.push(match chars.next()? {
+            b'r' => b'\r',
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'"' => b'"',
+            b'\\' => b'\\',
+            b'x' => {
+                let hex = [chars.next()?, chars.next()?];
+                u8::from_str_radix(core::str::from_utf8(&hex).ok()?, 16).ok()?
+            }
+            _ => return None,
+        });
+    }
+    Some(bytes)
+}
+
+/// What the client would observe while a trace is replayed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<U, const N: usize> {
+    /// A response, prompt or page signalled to the client
+    Response(Response<N>),
+    /// A URC published to the URC channel
+    Urc(U),
+    /// The ingress failed to process a chunk
+    Error(ingress::Error),
+}
+
+/// Replays traces through an ingress, collecting the events observed by the
+/// client.
+pub struct Replay<
+    'a,
+    I,
+    Urc: AtatUrc,
+    const N: usize,
+    const CAPACITY: usize,
+    const SUBSCRIBERS: usize,
+> {
+    ingress: I,
+    res_slot: &'a ResponseSlot<N>,
+    urcs: UrcSubscription<'a, Urc, CAPACITY, SUBSCRIBERS>,
+}
+
+impl<'a, I, Urc, const N: usize, const CAPACITY: usize, const SUBSCRIBERS: usize>
+    Replay<'a, I, Urc, N, CAPACITY, SUBSCRIBERS>
+where
+    I: AtatIngress,
+    Urc: AtatUrc,
+{
+    /// Replay through `ingress`, which must signal to `res_slot` and publish
+    /// to `urc_channel`. Fails if all subscribers of the channel are taken.
+    pub fn new(
+        ingress: I,
+        res_slot: &'a ResponseSlot<N>,
+        urc_channel: &'a UrcChannel<Urc, CAPACITY, SUBSCRIBERS>,
+    ) -> Result<Self, urc_channel::Error> {
+        Ok(Self {
+            ingress,
+            res_slot,
+            urcs: urc_channel.subscribe()?,
+        })
+    }
+
+    /// Replay `trace`, waiting out the delays between chunks.
+    pub async fn run(&mut self, trace: &str) -> Result<Vec<Event<Urc::Response, N>>, TraceError> {
+        let mut events = Vec::new();
+        for chunk in parse(trace)? {
+            if chunk.delay > Duration::from_ticks(0) {
+                Timer::after(chunk.delay).await;
+            }
+            self.feed(&chunk, &mut events);
+        }
+        Ok(events)
+    }
+
+    /// Replay `trace` without delays between chunks.
+    pub fn run_immediate(
+        &mut self,
+        trace: &str,
+    ) -> Result<Vec<Event<Urc::Response, N>>, TraceError> {
+        let mut events = Vec::new();
+        for chunk in parse(trace)? {
+            self.feed(&chunk, &mut events);
+        }
+        Ok(events)
+    }
+
+    fn feed(&mut self, chunk: &Chunk, events: &mut Vec<Event<Urc::Response, N>>) {
+        match chunk.direction {
+            Direction::Tx => {
+                self.res_slot.reset();
+                self.res_slot.set_state(Some(CommandState {
+                    name: "replay",
+                    sent: Instant::now(),
+                    attempt: 1,
+                    abortable: false,
+                    success_codes: &[],
+                    error_codes: &[],
+                    stats_id: None,
+                }));
+            }
+            Direction::Rx => {
+                if let Err(e) = self.ingress.try_write(&chunk.bytes) {
+                    events.push(Event::Error(e));
+                }
+            }
+        }
+
+        while let Some(urc) = self.urcs.try_next_message_pure() {
+            events.push(Event::Urc(urc));
+        }
+        if let Some(response) = self.res_slot.try_get() {
+            events.push(Event::Response(response.borrow().clone()));
+            drop(response);
+            // The client picks up the response, ending the command
+            self.res_slot.reset();
+            self.res_slot.set_state(None);
+        }
+    }
+
+    /// The ingress, eg. to inspect its digester after a replay.
+    pub fn ingress_mut(&mut self) -> &mut I {
+        &mut self.ingress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{self as atat, atat_derive::AtatUrc, AtDigester, Ingress, UrcChannel};
+
+    #[derive(AtatUrc, Clone, PartialEq, Debug)]
+    enum Urc {
+        #[at_urc(b"RING")]
+        Ring,
+        #[at_urc(b"NO CARRIER")]
+        NoCarrier,
+    }
+
+    #[test]
+    fn parse_trace() {
+        let chunks =
+            parse("# comment\n\n< \"AT\\r\\n\"\n+20ms \"\\r\\nOK\\x0d\\x0a\"\n> \"\\\"\\\\\"")
+                .unwrap();
+        assert_eq!(
+            chunks,
+            [
+                Chunk {
+                    direction: Direction::Tx,
+                    delay: Duration::from_ticks(0),
+                    bytes: b"AT\r\n".to_vec(),
+                },
+                Chunk {
+                    direction: Direction::Rx,
+                    delay: Duration::from_millis(20),
+                    bytes: b"\r\nOK\r\n".to_vec(),
+                },
+                Chunk {
+                    direction: Direction::Rx,
+                    delay: Duration::from_ticks(0),
+                    bytes: b"\"\\".to_vec(),
+                },
+            ]
+        );
+
+        assert_eq!(parse("> \"\\q\""), Err(TraceError { line: 1 }));
+        assert_eq!(parse("\n+5 \"OK\""), Err(TraceError { line: 2 }));
+    }
+
+    #[tokio::test]
+    async fn replay() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel);
+        let mut replay = Replay::new(ingress, &res_slot, &urc_channel).unwrap();
+
+        let events = replay
+            .run(
+                "< \"ATD+4512345678;\\r\\n\"\n\
+                 +5ms \"\\r\\nO\"\n\
+                 > \"K\\r\\n\"\n\
+                 +5ms \"\\r\\nRING\\r\\n\\r\\nNO CARRIER\\r\\n\"\n\
+                 < \"ATH\\r\\n\"\n\
+                 > \"\\r\\nERROR\\r\\n\"",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            events,
+            [
+                Event::Response(Response::default()),
+                Event::Urc(Urc::Ring),
+                Event::Urc(Urc::NoCarrier),
+                Event::Response(Response::OtherError),
+            ]
+        );
+    }
+
+    #[test]
+    fn urc_inside_echo() {
+        for strict in [false, true] {
+            let res_slot = ResponseSlot::<100>::new();
+            let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+            let mut buf = [0; 100];
+            let digester = AtDigester::<Urc>::new().with_strict(strict);
+            let ingress: Ingress<_, Urc, 100, 10, 1> =
+                Ingress::new(digester, &mut buf, &res_slot, &urc_channel);
+            let mut replay = Replay::new(ingress, &res_slot, &urc_channel).unwrap();
+
+            // The device sends a URC while echoing the command
+            let events = replay
+                .run_immediate(
+                    "< \"ATD+4512345678;\\r\"\n\
+                     > \"ATD+451\"\n\
+                     > \"\\r\\nRING\\r\\n23\"\n\
+                     > \"45678;\\r\\r\\nOK\\r\\n\"",
+                )
+                .unwrap();
+
+            assert_eq!(
+                events,
+                [Event::Urc(Urc::Ring), Event::Response(Response::default())]
+            );
+        }
+    }
+
+    #[test]
+    fn quirky_line_endings() {
+        let res_slot = ResponseSlot::<100>::new();
+        let urc_channel = UrcChannel::<Urc, 10, 1>::new();
+        let mut buf = [0; 100];
+        let digester = AtDigester::<Urc>::new().with_line_normalization(true);
+        let ingress: Ingress<_, Urc, 100, 10, 1> =
+            Ingress::new(digester, &mut buf, &res_slot, &urc_channel);
+        let mut replay = Replay::new(ingress, &res_slot, &urc_channel).unwrap();
+
+        let events = replay
+            .run_immediate(
+                "> \"\\r\\r\\nRING\\r\\r\"\n\
+                 > \"\\n\\nRING\\n\"\n\
+                 < \"ATA\\r\"\n\
+                 > \"\\nOK\\n\"",
+            )
+            .unwrap();
+
+        assert_eq!(
+            events,
+            [
+                Event::Urc(Urc::Ring),
+                Event::Urc(Urc::Ring),
+                Event::Response(Response::default()),
+            ]
+        );
+    }
+}
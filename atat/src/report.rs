@@ -0,0 +1,241 @@
+//! Size report of a command set, to audit the flash and RAM contribution of
+//! the AT layer of a driver, and to spot command types that are never sent.
+//!
+//! The report is a const table built by [`command_report!`] from the
+//! commands derived with [`atat_derive`]:
+//!
+//! ```
+//! use atat::{atat_derive::{AtatCmd, AtatResp}, command_report, report::CommandReport};
+//!
+//! #[derive(Clone, AtatResp)]
+//! pub struct SignalQuality {
+//!     #[at_arg(position = 0)]
+//!     pub rssi: u8,
+//!     #[at_arg(position = 1)]
+//!     pub ber: u8,
+//! }
+//!
+//! #[derive(Clone, AtatCmd)]
+//! #[at_cmd("+CSQ", SignalQuality)]
+//! pub struct GetSignalQuality;
+//!
+//! #[derive(Clone, AtatResp)]
+//! pub struct NoResponse;
+//!
+//! #[derive(Clone, AtatCmd)]
+//! #[at_cmd("+CFUN", NoResponse)]
+//! pub struct SetModuleFunctionality {
+//!     #[at_arg(position = 0)]
+//!     pub fun: u8,
+//! }
+//!
+//! const COMMANDS: CommandReport = command_report![GetSignalQuality, SetModuleFunctionality];
+//!
+//! // The client buffer must hold the longest command
+//! const _: () = core::assert!(COMMANDS.max_len() <= 32);
+//!
+//! let unused: Vec<_> = COMMANDS
+//!     .unused(["my_driver::GetSignalQuality"])
+//!     .map(|info| info.name)
+//!     .collect();
+//! assert_eq!(unused, ["SetModuleFunctionality"]);
+//! ```
+//!
+//! [`atat_derive`]: https://crates.io/crates/atat_derive
+//! [`command_report!`]: crate::command_report
+
+use core::fmt;
+
+use crate::AtatCmd;
+
+/// Sizes of a single command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CommandInfo {
+    /// Name of the command type, see [`AtatCmd::NAME`]
+    pub name: &'static str,
+    /// See [`AtatCmd::WIRE_FORMAT`]
+    pub wire_format: &'static str,
+    /// Maximum length of the serialized command, see [`AtatCmd::MAX_LEN`]
+    pub max_len: usize,
+    /// Size in memory of the parsed response
+    pub response_size: usize,
+}
+
+impl CommandInfo {
+    #[must_use]
+    pub const fn of<Cmd: AtatCmd>() -> Self {
+        Self {
+            name: Cmd::NAME,
+            wire_format: Cmd::WIRE_FORMAT,
+            max_len: Cmd::MAX_LEN,
+            response_size: core::mem::size_of::<Cmd::Response>(),
+        }
+    }
+
+    /// Whether `type_name`, eg. [`CommandState::name`], names this command,
+    /// ignoring its module path and generic parameters.
+    ///
+    /// [`CommandState::name`]: crate::CommandState::name
+    #[must_use]
+    pub fn is(&self, type_name: &str) -> bool {
+        let type_name = type_name.split('<').next().unwrap_or(type_name);
+        type_name.rsplit("::").next() == Some(self.name)
+    }
+}
+
+/// Sizes of a set of commands, built by [`command_report!`].
+///
+/// Prints as a table, with the totals of the set on the last line.
+///
+/// [`command_report!`]: crate::command_report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandReport(pub &'static [CommandInfo]);
+
+impl CommandReport {
+    /// The commands of the set
+    #[must_use]
+    pub const fn commands(&self) -> &'static [CommandInfo] {
+        self.0
+    }
+
+    /// Length of the longest serialized command, ie. the minimum size of the
+    /// client buffer.
+    #[must_use]
+    pub const fn max_len(&self) -> usize {
+        let mut max = 0;
+        let mut i = 0;
+        while i < self.0.len() {
+            if self.0[i].max_len > max {
+                max = self.0[i].max_len;
+            }
+            i += 1;
+        }
+        max
+    }
+
+    /// Size of the largest parsed response.
+    #[must_use]
+    pub const fn max_response_size(&self) -> usize {
+        let mut max = 0;
+        let mut i = 0;
+        while i < self.0.len() {
+            if self.0[i].response_size > max {
+                max = self.0[i].response_size;
+            }
+            i += 1;
+        }
+        max
+    }
+
+    /// The commands none of `sent` names, eg. the [`CommandState::name`] of
+    /// every command sent during a test run.
+    ///
+    /// [`CommandState::name`]: crate::CommandState::name
+    pub fn unused<'a, I>(self, sent: I) -> impl Iterator<Item = &'static CommandInfo> + 'a
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: Clone + 'a,
+    {
+        let sent = sent.into_iter();
+        let commands = self.0;
+        commands
+            .iter()
+            .filter(move |info| !sent.clone().any(|name| info.is(name)))
+    }
+}
+
+impl fmt::Display for CommandReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|info| info.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Command".len());
+        writeln!(
+            f,
+            "{:width$} {:>8} {:>9}  Format",
+            "Command", "Max len", "Response"
+        )?;
+        for info in self.0 {
+            writeln!(
+                f,
+                "{:width$} {:>8} {:>9}  {}",
+                info.name, info.max_len, info.response_size, info.wire_format
+            )?;
+        }
+        write!(
+            f,
+            "{:width$} {:>8} {:>9}  {} commands",
+            "Max",
+            self.max_len(),
+            self.max_response_size(),
+            self.0.len()
+        )
+    }
+}
+
+/// Build the [`CommandReport`] of a set of commands, in a const context.
+///
+/// Generic commands are listed with their parameters, eg.
+/// `ConnectSocket<'static>`.
+///
+/// [`CommandReport`]: crate::report::CommandReport
+#[macro_export]
+macro_rules! command_report {
+    ($($cmd:ty),* $(,)?) => {
+        $crate::report::CommandReport(&[$($crate::report::CommandInfo::of::<$cmd>()),*])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        self as atat,
+        atat_derive::{AtatCmd, AtatResp},
+    };
+
+    #[derive(Clone, AtatResp)]
+    struct NoResponse;
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CSQ", NoResponse)]
+    struct GetSignalQuality;
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+USOWR", NoResponse)]
+    struct WriteSocket<'a> {
+        #[at_arg(position = 0)]
+        socket: u8,
+        #[at_arg(position = 1, len = 16)]
+        data: &'a str,
+    }
+
+    const COMMANDS: CommandReport = command_report![GetSignalQuality, WriteSocket<'static>];
+
+    #[test]
+    fn report() {
+        assert_eq!(COMMANDS.commands()[0].name, "GetSignalQuality");
+        assert_eq!(COMMANDS.commands()[0].max_len, GetSignalQuality::MAX_LEN);
+        assert_eq!(COMMANDS.max_len(), WriteSocket::MAX_LEN);
+        assert_eq!(COMMANDS.max_response_size(), 0);
+
+        let mut table = heapless::String::<256>::new();
+        core::fmt::write(&mut table, format_args!("{COMMANDS}")).unwrap();
+        let mut lines = table.lines();
+        assert!(lines.next().unwrap().starts_with("Command "));
+        assert!(lines.next().unwrap().ends_with("AT+CSQ\\r\\n"));
+        assert!(lines.last().unwrap().ends_with("2 commands"));
+    }
+
+    #[test]
+    fn unused() {
+        let sent = [core::any::type_name::<WriteSocket<'static>>()];
+        let unused: heapless::Vec<_, 2> = COMMANDS.unused(sent).map(|info| info.name).collect();
+        assert_eq!(unused, ["GetSignalQuality"]);
+        assert_eq!(COMMANDS.unused([]).count(), 2);
+    }
+}
@@ -1,15 +1,19 @@
-use crate::InternalError;
+use crate::{helpers::LossyStr, AtatCmd, AtatResp, Error, InternalError};
 use heapless::Vec;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Response<const N: usize> {
     Ok(Vec<u8, N>),
+    Continuation(Vec<u8, N>),
     Prompt(u8),
     ReadError,
     WriteError,
     TimeoutError,
     InvalidResponseError,
     AbortedError,
+    DesyncError,
+    ModemRestartedError,
+    ResponseTooLargeError,
     ParseError,
     OtherError,
     CmeError(u16),
@@ -18,10 +22,99 @@ pub enum Response<const N: usize> {
     CustomError(Vec<u8, N>),
 }
 
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for Response<N> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Response::Ok(v) => defmt::write!(f, "Response::Ok({=[u8]:a})", &v[..]),
+            Response::Continuation(v) => {
+                defmt::write!(f, "Response::Continuation({=[u8]:a})", &v[..])
+            }
+            Response::Prompt(p) => defmt::write!(f, "Response::Prompt({=u8})", p),
+            Response::ReadError => defmt::write!(f, "Response::ReadError"),
+            Response::WriteError => defmt::write!(f, "Response::WriteError"),
+            Response::TimeoutError => defmt::write!(f, "Response::TimeoutError"),
+            Response::InvalidResponseError => defmt::write!(f, "Response::InvalidResponseError"),
+            Response::AbortedError => defmt::write!(f, "Response::AbortedError"),
+            Response::DesyncError => defmt::write!(f, "Response::DesyncError"),
+            Response::ModemRestartedError => defmt::write!(f, "Response::ModemRestartedError"),
+            Response::ResponseTooLargeError => defmt::write!(f, "Response::ResponseTooLargeError"),
+            Response::ParseError => defmt::write!(f, "Response::ParseError"),
+            Response::OtherError => defmt::write!(f, "Response::OtherError"),
+            Response::CmeError(e) => defmt::write!(f, "Response::CmeError({=u16})", e),
+            Response::CmsError(e) => defmt::write!(f, "Response::CmsError({=u16})", e),
+            Response::ConnectionError(e) => defmt::write!(f, "Response::ConnectionError({=u8})", e),
+            Response::CustomError(v) => {
+                defmt::write!(f, "Response::CustomError({=[u8]:a})", &v[..])
+            }
+        }
+    }
+}
+
 impl<const N: usize> Response<N> {
     pub fn ok(value: &[u8]) -> Self {
         Response::Ok(Vec::from_slice(value).unwrap())
     }
+
+    /// A page of a paginated response, failing the command if it does not
+    /// fit.
+    pub(crate) fn continuation(page: &[u8]) -> Self {
+        Vec::from_slice(page).map_or_else(|_| Self::too_large(page), Response::Continuation)
+    }
+
+    fn too_large(response: &[u8]) -> Self {
+        error!(
+            "Response of {} bytes exceeds the {} byte response buffer",
+            response.len(),
+            N
+        );
+        Response::ResponseTooLargeError
+    }
+}
+
+/// Reject a response not starting with the command's [`AtatCmd::RESP_PREFIX`]
+/// as belonging to another command.
+pub(crate) fn anchor_response<'a, Cmd: AtatCmd>(
+    response: Result<&'a [u8], InternalError<'a>>,
+) -> Result<&'a [u8], InternalError<'a>> {
+    match (response, Cmd::RESP_PREFIX) {
+        (Ok(r), Some(prefix)) if !r.is_empty() && !r.starts_with(prefix) => {
+            warn!(
+                "Response does not start with {:?}: {:?}",
+                LossyStr(prefix),
+                LossyStr(r)
+            );
+            Err(InternalError::Desync)
+        }
+        (response, _) => response,
+    }
+}
+
+/// Parse a response, and check it with [`AtatResp::validate`].
+pub(crate) fn parse_validated<Cmd: AtatCmd>(
+    cmd: &Cmd,
+    response: Result<&[u8], InternalError>,
+) -> Result<Cmd::Response, Error> {
+    let mut response = cmd.parse(response)?;
+    response.validate()?;
+    Ok(response)
+}
+
+/// Append a page of a paginated response to `buf`, separating pages by
+/// `\r\n`.
+pub(crate) fn append_page(buf: &mut [u8], pos: &mut usize, page: &[u8]) -> Result<(), Error> {
+    if page.is_empty() {
+        return Ok(());
+    }
+    let sep: &[u8] = if *pos > 0 { b"\r\n" } else { b"" };
+    let end = *pos + sep.len() + page.len();
+    if end > buf.len() {
+        return Err(Error::InvalidResponse);
+    }
+    buf[*pos..*pos + sep.len()].copy_from_slice(sep);
+    buf[end - page.len()..end].copy_from_slice(page);
+    *pos = end;
+    Ok(())
 }
 
 impl<const N: usize> Default for Response<N> {
@@ -33,7 +126,9 @@ impl<const N: usize> Default for Response<N> {
 impl<'a, const N: usize> From<Result<&'a [u8], InternalError<'a>>> for Response<N> {
     fn from(value: Result<&'a [u8], InternalError<'a>>) -> Self {
         match value {
-            Ok(slice) => Response::Ok(Vec::from_slice(slice).unwrap()),
+            Ok(slice) => {
+                Vec::from_slice(slice).map_or_else(|_| Self::too_large(slice), Response::Ok)
+            }
             Err(error) => error.into(),
         }
     }
@@ -47,12 +142,17 @@ impl<'a, const N: usize> From<InternalError<'a>> for Response<N> {
             InternalError::Timeout => Response::TimeoutError,
             InternalError::InvalidResponse => Response::InvalidResponseError,
             InternalError::Aborted => Response::AbortedError,
+            InternalError::Desync => Response::DesyncError,
+            InternalError::ModemRestarted => Response::ModemRestartedError,
+            InternalError::ResponseTooLarge => Response::ResponseTooLargeError,
             InternalError::Parse => Response::ParseError,
             InternalError::Error => Response::OtherError,
-            InternalError::CmeError(e) => Response::CmeError(e as u16),
-            InternalError::CmsError(e) => Response::CmsError(e as u16),
+            InternalError::CmeError(e) => Response::CmeError(e.code()),
+            InternalError::CmsError(e) => Response::CmsError(e.code()),
             InternalError::ConnectionError(e) => Response::ConnectionError(e as u8),
-            InternalError::Custom(e) => Response::CustomError(Vec::from_slice(e).unwrap()),
+            InternalError::Custom(e) => {
+                Vec::from_slice(e).map_or_else(|_| Self::too_large(e), Response::CustomError)
+            }
         }
     }
 }
@@ -61,19 +161,22 @@ impl<'a, const N: usize> From<&'a Response<N>> for Result<&'a [u8], InternalErro
     fn from(value: &'a Response<N>) -> Self {
         match value {
             Response::Ok(slice) => Ok(slice),
+            // Only commands with a continuation token collect pages
+            Response::Continuation(_) => Err(InternalError::InvalidResponse),
             Response::Prompt(_) => Ok(&[]),
             Response::ReadError => Err(InternalError::Read),
             Response::WriteError => Err(InternalError::Write),
             Response::TimeoutError => Err(InternalError::Timeout),
             Response::InvalidResponseError => Err(InternalError::InvalidResponse),
             Response::AbortedError => Err(InternalError::Aborted),
+            Response::DesyncError => Err(InternalError::Desync),
+            Response::ModemRestartedError => Err(InternalError::ModemRestarted),
+            Response::ResponseTooLargeError => Err(InternalError::ResponseTooLarge),
             Response::ParseError => Err(InternalError::Parse),
             Response::OtherError => Err(InternalError::Error),
-            Response::CmeError(e) => Err(InternalError::CmeError((*e).try_into().unwrap())),
-            Response::CmsError(e) => Err(InternalError::CmsError((*e).try_into().unwrap())),
-            Response::ConnectionError(e) => {
-                Err(InternalError::ConnectionError((*e).try_into().unwrap()))
-            }
+            Response::CmeError(e) => Err(InternalError::CmeError((*e).into())),
+            Response::CmsError(e) => Err(InternalError::CmsError((*e).into())),
+            Response::ConnectionError(e) => Err(InternalError::ConnectionError((*e).into())),
             Response::CustomError(e) => Err(InternalError::Custom(e)),
         }
     }
@@ -1,89 +1,594 @@
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use embassy_sync::{
-    blocking_mutex::raw::CriticalSectionRawMutex,
+    blocking_mutex::{self, raw::CriticalSectionRawMutex},
+    channel::Channel,
     mutex::{Mutex, MutexGuard},
     signal::Signal,
 };
-use heapless::Vec;
+use embassy_time::{Duration, Instant};
+use heapless::{Deque, Vec};
 
-use crate::{InternalError, Response};
+use crate::{AtatCmd, InternalError, Response};
 
-pub struct ResponseSlot<const N: usize>(
-    Mutex<CriticalSectionRawMutex, RefCell<Response<N>>>,
-    Signal<CriticalSectionRawMutex, ()>,
-);
+/// Maximum length of a progress line, longer lines are dropped.
+pub const PROGRESS_LEN: usize = 64;
+
+/// Number of progress lines queued for the client, further lines are dropped
+/// until it catches up.
+pub const PROGRESS_DEPTH: usize = 4;
+
+/// Number of responses of pipelined commands queued while the client has not
+/// taken the pending one, further responses are dropped. See
+/// [`Pipeline`](crate::asynch::Pipeline).
+pub const PIPELINE_DEPTH: usize = 2;
+
+/// Number of command ids tracked by [`ResponseSlot::command_stats`].
+pub const COMMAND_STATS_LEN: usize = 16;
+
+/// Filter recognizing progress lines of the command in flight.
+pub(crate) type ProgressFilter = fn(&[u8]) -> bool;
+
+/// State of the command in flight, see [`ResponseSlot::state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandState {
+    /// Name of the command type, as given by [`core::any::type_name`]
+    pub name: &'static str,
+    /// When writing the request started
+    pub sent: Instant,
+    /// The attempt of the command, starting at 1
+    pub attempt: u8,
+    /// Whether the command can be aborted, see [`AtatCmd::CAN_ABORT`]
+    pub abortable: bool,
+    /// Final result codes besides `OK`, see [`AtatCmd::SUCCESS_CODES`]
+    pub success_codes: &'static [&'static [u8]],
+    /// Final result codes besides `ERROR`, see [`AtatCmd::ERROR_CODES`]
+    pub error_codes: &'static [&'static [u8]],
+    /// Id of the round-trip time statistics, see [`AtatCmd::STATS_ID`]
+    pub stats_id: Option<u8>,
+}
+
+impl CommandState {
+    pub(crate) fn new<Cmd: AtatCmd>(attempt: u8) -> Self {
+        Self {
+            name: core::any::type_name::<Cmd>(),
+            sent: Instant::now(),
+            attempt,
+            abortable: Cmd::CAN_ABORT,
+            success_codes: Cmd::SUCCESS_CODES,
+            error_codes: Cmd::ERROR_CODES,
+            stats_id: Cmd::STATS_ID,
+        }
+    }
+
+    /// State of raw bytes sent with `send_raw`, eg.
+    /// [`asynch::Client::send_raw`](crate::asynch::Client::send_raw)
+    pub(crate) fn raw() -> Self {
+        Self {
+            name: "raw",
+            sent: Instant::now(),
+            attempt: 1,
+            abortable: false,
+            success_codes: &[],
+            error_codes: &[],
+            stats_id: None,
+        }
+    }
+
+    /// Time elapsed since the request was written
+    pub fn elapsed(&self) -> Duration {
+        self.sent.elapsed()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for CommandState {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "CommandState {{ name: {=str}, elapsed: {=u64} ms, attempt: {=u8}, abortable: {=bool} }}",
+            self.name,
+            self.elapsed().as_millis(),
+            self.attempt,
+            self.abortable
+        )
+    }
+}
+
+/// Round-trip times of a command type, from the request being written to
+/// its final result code being received, see [`ResponseSlot::command_stats`].
+#[cfg(feature = "command-stats")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandStats {
+    /// Number of responses received
+    pub count: u32,
+    /// Exponentially weighted moving average, weighting each new round-trip
+    /// time by 1/8
+    pub average: Duration,
+    /// Longest round-trip time
+    pub max: Duration,
+    /// Latest round-trip time
+    pub last: Duration,
+}
+
+#[cfg(feature = "command-stats")]
+impl CommandStats {
+    const fn new(rtt: Duration) -> Self {
+        Self {
+            count: 1,
+            average: rtt,
+            max: rtt,
+            last: rtt,
+        }
+    }
+
+    fn record(&mut self, rtt: Duration) {
+        let average = self.average.as_ticks();
+        self.average = if rtt.as_ticks() > average {
+            Duration::from_ticks(average + (rtt.as_ticks() - average) / 8)
+        } else {
+            Duration::from_ticks(average - (average - rtt.as_ticks()) / 8)
+        };
+        self.count = self.count.saturating_add(1);
+        self.max = self.max.max(rtt);
+        self.last = rtt;
+    }
+}
+
+#[cfg(all(feature = "command-stats", feature = "defmt"))]
+impl defmt::Format for CommandStats {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "CommandStats {{ count: {=u32}, average: {=u64} ms, max: {=u64} ms, last: {=u64} ms }}",
+            self.count,
+            self.average.as_millis(),
+            self.max.as_millis(),
+            self.last.as_millis()
+        )
+    }
+}
+
+#[cfg(feature = "command-stats")]
+type StatsTable = [Option<CommandStats>; COMMAND_STATS_LEN];
+
+/// Progress lines are delivered through a separate queue, such that they
+/// never occupy the slot needed by the final response. Up to
+/// [`PROGRESS_DEPTH`] progress lines are kept.
+pub struct ResponseSlot<const N: usize> {
+    response: Mutex<CriticalSectionRawMutex, RefCell<Response<N>>>,
+    signal: Signal<CriticalSectionRawMutex, ()>,
+    progress_filter: blocking_mutex::Mutex<CriticalSectionRawMutex, Cell<Option<ProgressFilter>>>,
+    progress: Channel<CriticalSectionRawMutex, Vec<u8, PROGRESS_LEN>, PROGRESS_DEPTH>,
+    state: blocking_mutex::Mutex<CriticalSectionRawMutex, Cell<Option<CommandState>>>,
+    clear_request: blocking_mutex::Mutex<CriticalSectionRawMutex, Cell<bool>>,
+    pipelined: blocking_mutex::Mutex<CriticalSectionRawMutex, Cell<usize>>,
+    /// Responses of pipelined commands received while one is pending
+    queued:
+        blocking_mutex::Mutex<CriticalSectionRawMutex, RefCell<Deque<Response<N>, PIPELINE_DEPTH>>>,
+    booted: Signal<CriticalSectionRawMutex, ()>,
+    swallow_until: blocking_mutex::Mutex<CriticalSectionRawMutex, Cell<Option<Instant>>>,
+    #[cfg(feature = "command-stats")]
+    stats: blocking_mutex::Mutex<CriticalSectionRawMutex, RefCell<StatsTable>>,
+}
+
+/// Clears the command state when dropped, such that a command is not
+/// reported in flight after its send future is dropped.
+pub(crate) struct CommandStateGuard<'a, const N: usize>(&'a ResponseSlot<N>);
+
+impl<const N: usize> CommandStateGuard<'_, N> {
+    /// Keep reporting the state once the guard is dropped, until it is
+    /// cleared with [`ResponseSlot::set_state`].
+    pub(crate) fn keep(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl<const N: usize> Drop for CommandStateGuard<'_, N> {
+    fn drop(&mut self) {
+        self.0.set_state(None);
+    }
+}
+
+/// Clears the progress filter when dropped, such that URC's are published
+/// again once the send future routing them is dropped, eg. by a timeout.
+pub(crate) struct ProgressFilterGuard<'a, const N: usize>(&'a ResponseSlot<N>);
+
+impl<const N: usize> Drop for ProgressFilterGuard<'_, N> {
+    fn drop(&mut self) {
+        self.0.set_progress_filter(None);
+    }
+}
 
 pub type ResponseSlotGuard<'a, const N: usize> =
     MutexGuard<'a, CriticalSectionRawMutex, RefCell<Response<N>>>;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct SlotInUseError;
 
+impl<const N: usize> core::fmt::Debug for ResponseSlot<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ResponseSlot")
+            .field("signaled", &self.signal.signaled())
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for ResponseSlot<N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "ResponseSlot {{ signaled: {=bool} }}",
+            self.signal.signaled()
+        )
+    }
+}
+
 impl<const N: usize> ResponseSlot<N> {
+    #[allow(clippy::new_without_default)]
     pub const fn new() -> Self {
-        Self(
-            Mutex::new(RefCell::new(Response::Ok(Vec::new()))),
-            Signal::new(),
-        )
+        Self {
+            response: Mutex::new(RefCell::new(Response::Ok(Vec::new()))),
+            signal: Signal::new(),
+            progress_filter: blocking_mutex::Mutex::new(Cell::new(None)),
+            progress: Channel::new(),
+            state: blocking_mutex::Mutex::new(Cell::new(None)),
+            clear_request: blocking_mutex::Mutex::new(Cell::new(false)),
+            pipelined: blocking_mutex::Mutex::new(Cell::new(0)),
+            queued: blocking_mutex::Mutex::new(RefCell::new(Deque::new())),
+            booted: Signal::new(),
+            swallow_until: blocking_mutex::Mutex::new(Cell::new(None)),
+            #[cfg(feature = "command-stats")]
+            stats: blocking_mutex::Mutex::new(RefCell::new([None; COMMAND_STATS_LEN])),
+        }
+    }
+
+    /// The command currently in flight, if any.
+    ///
+    /// As the slot is shared with the client, this can be queried from
+    /// another task while the client is awaiting a response, eg. by a
+    /// supervisor deciding whether to wait or reset the modem.
+    pub fn state(&self) -> Option<CommandState> {
+        self.state.lock(Cell::get)
+    }
+
+    pub(crate) fn set_state(&self, state: Option<CommandState>) {
+        self.state.lock(|s| s.set(state));
+    }
+
+    /// Report `state` until the returned guard is dropped.
+    pub(crate) fn start_command(&self, state: CommandState) -> CommandStateGuard<'_, N> {
+        self.set_state(Some(state));
+        CommandStateGuard(self)
+    }
+
+    /// Round-trip time statistics of the commands with [`AtatCmd::STATS_ID`]
+    /// `id`, or `None` if no response to them was received yet.
+    ///
+    /// Queried eg. by a supervisor detecting a degraded network, which makes
+    /// `+COPS?` slow, to adapt timeouts. Only commands sent through a client
+    /// sharing this slot are tracked.
+    #[cfg(feature = "command-stats")]
+    pub fn command_stats(&self, id: u8) -> Option<CommandStats> {
+        self.stats
+            .lock(|t| t.borrow().get(usize::from(id)).copied().flatten())
+    }
+
+    /// Clear the round-trip time statistics of all commands.
+    #[cfg(feature = "command-stats")]
+    pub fn reset_command_stats(&self) {
+        self.stats.lock(|t| t.replace([None; COMMAND_STATS_LEN]));
+    }
+
+    /// Record the round-trip time of the command in flight, if it has an id.
+    #[cfg(feature = "command-stats")]
+    fn record_rtt(&self) {
+        let Some(state) = self.state() else {
+            return;
+        };
+        let Some(id) = state.stats_id.map(usize::from) else {
+            return;
+        };
+        let rtt = state.elapsed();
+        self.stats.lock(|t| match t.borrow_mut().get_mut(id) {
+            Some(Some(stats)) => stats.record(rtt),
+            Some(stats) => *stats = Some(CommandStats::new(rtt)),
+            None => warn!("Command stats id {} out of range", id),
+        });
     }
 
-    /// Reset the current response slot
+    /// Have the ingress discard everything it holds before the next bytes it
+    /// receives, eg. half a frame left over from before a hot-plug.
+    pub(crate) fn request_clear(&self) {
+        self.clear_request.lock(|c| c.set(true));
+    }
+
+    /// Whether the client requested the ingress to be cleared, see
+    /// [`ResponseSlot::request_clear`].
+    pub(crate) fn take_clear_request(&self) -> bool {
+        self.clear_request.lock(|c| c.replace(false))
+    }
+
+    /// Number of pipelined commands written and awaiting their response, see
+    /// [`Pipeline`].
+    ///
+    /// [`Pipeline`]: crate::asynch::Pipeline
+    pub fn pipelined(&self) -> usize {
+        self.pipelined.lock(Cell::get)
+    }
+
+    /// Number of responses received and not yet taken by the client,
+    /// including the responses of pipelined commands queued behind the
+    /// pending one.
+    pub(crate) fn pending_frames(&self) -> usize {
+        self.queued
+            .lock(|q| usize::from(self.signal.signaled()) + q.borrow().len())
+    }
+
+    /// Have the ingress swallow the next final result code received before
+    /// `until`, answering a command sent without awaiting its response.
+    ///
+    /// See [`asynch::Client::send_no_response`].
+    ///
+    /// [`asynch::Client::send_no_response`]: crate::asynch::Client::send_no_response
+    pub(crate) fn swallow_response(&self, until: Option<Instant>) {
+        self.swallow_until.lock(|s| s.set(until));
+    }
+
+    /// Whether the ingress is to swallow a final result code received now,
+    /// consuming the request.
+    pub(crate) fn take_swallow(&self) -> bool {
+        self.swallow_until
+            .lock(|s| s.take())
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    pub(crate) fn set_pipelined(&self, pipelined: usize) {
+        self.pipelined.lock(|p| p.set(pipelined));
+    }
+
+    /// Return the slot to its initial state, dropping any response, command
+    /// state or progress filter left behind, so it can be shared by a new
+    /// client and ingress pair. See [`asynch::Client::release`].
+    ///
+    /// [`asynch::Client::release`]: crate::asynch::Client::release
+    pub fn clear(&self) {
+        self.set_state(None);
+        self.set_progress_filter(None);
+        self.take_clear_request();
+        self.set_pipelined(0);
+        self.swallow_response(None);
+        if let Ok(response) = self.response.try_lock() {
+            response.replace(Response::Ok(Vec::new()));
+        }
+        self.reset();
+        self.booted.reset();
+    }
+
+    /// Signal that the device printed a boot banner, see
+    /// [`Config::wait_ready`](crate::Config::wait_ready).
+    pub(crate) fn signal_booted(&self) {
+        self.booted.signal(());
+    }
+
+    /// Wait for the device to print a boot banner.
+    pub(crate) async fn wait_booted(&self) {
+        self.booted.wait().await;
+    }
+
+    /// Whether the device printed a boot banner, consuming the signal.
+    pub(crate) fn take_booted(&self) -> bool {
+        self.booted.try_take().is_some()
+    }
+
+    /// Reset the current response slot, dropping any queued responses of
+    /// pipelined commands.
     pub fn reset(&self) {
-        self.1.reset();
+        self.queued.lock(|q| {
+            q.borrow_mut().clear();
+            self.signal.reset();
+        });
+    }
+
+    /// Reset the response slot once the client took the response of a
+    /// pipelined command, and signal the response of the next one, if it was
+    /// queued meanwhile.
+    pub(crate) fn take_pipelined(&self) {
+        // In one critical section, such that the ingress never finds the
+        // slot free while responses are queued
+        self.queued.lock(|q| {
+            self.signal.reset();
+            self.set_pipelined(self.pipelined().saturating_sub(1));
+            if let Some(next) = q.borrow_mut().pop_front() {
+                // Not signaled: the client is not holding the response guard
+                self.response.try_lock().unwrap().replace(next);
+                self.signal.signal(());
+            }
+        });
     }
 
     /// Wait for a response to be signaled and get a guard to the response
-    pub async fn get<'a>(&'a self) -> ResponseSlotGuard<'a, N> {
-        self.1.wait().await;
+    pub async fn get(&self) -> ResponseSlotGuard<'_, N> {
+        self.signal.wait().await;
 
         // The mutex is not locked when signal is emitted
-        self.0.try_lock().unwrap()
+        self.response.try_lock().unwrap()
     }
 
     /// If signaled, get a guard to the response
-    pub fn try_get<'a>(&'a self) -> Option<ResponseSlotGuard<'a, N>> {
-        if self.1.signaled() {
+    pub fn try_get(&self) -> Option<ResponseSlotGuard<'_, N>> {
+        if self.signal.signaled() {
             // The mutex is not locked when signal is emitted
-            Some(self.0.try_lock().unwrap())
+            Some(self.response.try_lock().unwrap())
         } else {
             None
         }
     }
 
+    /// Route lines matching `filter` to the progress queue rather than the
+    /// URC channel, until cleared with `None`.
+    pub(crate) fn set_progress_filter(&self, filter: Option<ProgressFilter>) {
+        self.progress_filter.lock(|f| f.set(filter));
+        self.progress.clear();
+    }
+
+    /// Route lines matching `filter` to the progress queue until the
+    /// returned guard is dropped.
+    pub(crate) fn route_progress(&self, filter: ProgressFilter) -> ProgressFilterGuard<'_, N> {
+        self.set_progress_filter(Some(filter));
+        ProgressFilterGuard(self)
+    }
+
+    /// Signal a progress line of the command in flight, returning whether
+    /// the line was consumed as such.
+    pub(crate) fn try_signal_progress(&self, line: &[u8]) -> bool {
+        let Some(filter) = self.progress_filter.lock(Cell::get) else {
+            return false;
+        };
+        if !filter(line) {
+            return false;
+        }
+
+        match Vec::from_slice(line) {
+            Ok(line) => {
+                if self.progress.try_send(line).is_err() {
+                    warn!("Dropping progress line, {} are queued", PROGRESS_DEPTH);
+                }
+            }
+            Err(_) => warn!("Dropping progress line longer than {}", PROGRESS_LEN),
+        }
+        true
+    }
+
+    /// Wait for the next progress line of the command in flight
+    pub(crate) async fn wait_progress(&self) -> Vec<u8, PROGRESS_LEN> {
+        self.progress.receive().await
+    }
+
     pub(crate) fn signal_prompt(&self, prompt: u8) -> Result<(), SlotInUseError> {
-        if self.1.signaled() {
+        if self.signal.signaled() {
             return Err(SlotInUseError);
         }
 
         // Not currently signaled: We know that the client is not currently holding the response slot guard
         {
-            let buf = self.0.try_lock().unwrap();
+            let buf = self.response.try_lock().unwrap();
             let mut res = buf.borrow_mut();
             *res = Response::Prompt(prompt);
         }
 
         // Mutex is unlocked before we signal
-        self.1.signal(());
+        self.signal.signal(());
         Ok(())
     }
 
+    pub(crate) fn signal_continuation(&self, page: &[u8]) -> Result<(), SlotInUseError> {
+        if self.signal.signaled() {
+            return Err(SlotInUseError);
+        }
+
+        // Not currently signaled: We know that the client is not currently holding the response slot guard
+        {
+            let buf = self.response.try_lock().unwrap();
+            let mut res = buf.borrow_mut();
+            *res = Response::continuation(page);
+        }
+
+        // Mutex is unlocked before we signal
+        self.signal.signal(());
+        Ok(())
+    }
+
+    /// Signal the response of a pipelined command. If the response of an
+    /// earlier one was not taken by the client yet, it is queued instead, see
+    /// [`PIPELINE_DEPTH`].
+    pub(crate) fn signal_response_in_order(
+        &self,
+        response: Result<&[u8], InternalError<'_>>,
+    ) -> Result<(), SlotInUseError> {
+        self.queued.lock(|q| {
+            let mut q = q.borrow_mut();
+            if q.is_empty() && !(self.signal.signaled() && self.pipelined() > 1) {
+                return self.signal_response(response);
+            }
+            q.push_back(response.into()).map_err(|_| SlotInUseError)
+        })
+    }
+
     pub(crate) fn signal_response(
         &self,
         response: Result<&[u8], InternalError>,
     ) -> Result<(), SlotInUseError> {
-        if self.1.signaled() {
+        if self.signal.signaled() {
             return Err(SlotInUseError);
         }
 
+        // A restart is not an answer to the command
+        #[cfg(feature = "command-stats")]
+        if !matches!(response, Err(InternalError::ModemRestarted)) {
+            self.record_rtt();
+        }
+
         // Not currently signaled: We know that the client is not currently holding the response slot guard
         {
-            let buf = self.0.try_lock().unwrap();
+            let buf = self.response.try_lock().unwrap();
             let mut res = buf.borrow_mut();
             *res = response.into();
         }
 
         // Mutex is unlocked before we signal
-        self.1.signal(());
+        self.signal.signal(());
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "command-stats")]
+mod tests {
+    use super::*;
+    use crate::{
+        self as atat,
+        atat_derive::{AtatCmd, AtatResp},
+    };
+
+    #[derive(Clone, AtatResp)]
+    struct NoResponse;
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+COPS?", NoResponse, stats_id = 3)]
+    struct GetOperatorSelection;
+
+    #[test]
+    fn command_stats() {
+        let slot = ResponseSlot::<16>::new();
+        assert_eq!(slot.command_stats(3), None);
+
+        for _ in 0..2 {
+            let _state = slot.start_command(CommandState::new::<GetOperatorSelection>(1));
+            slot.signal_response(Ok(b"")).unwrap();
+            slot.reset();
+        }
+        assert_eq!(slot.command_stats(3).map(|s| s.count), Some(2));
+
+        // Restarts and commands without an id are not recorded
+        let _state = slot.start_command(CommandState::new::<GetOperatorSelection>(1));
+        slot.signal_response(Err(InternalError::ModemRestarted))
+            .unwrap();
+        assert_eq!(slot.command_stats(3).map(|s| s.count), Some(2));
+
+        slot.reset_command_stats();
+        assert_eq!(slot.command_stats(3), None);
+    }
+
+    #[test]
+    fn average() {
+        let mut stats = CommandStats::new(Duration::from_millis(800));
+        stats.record(Duration::from_millis(1600));
+        assert_eq!(stats.average, Duration::from_millis(900));
+        stats.record(Duration::from_millis(100));
+        assert_eq!(stats.average, Duration::from_millis(800));
+        assert_eq!(stats.max, Duration::from_millis(1600));
+        assert_eq!(stats.last, Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,144 @@
+//! Routing of responses by sequence id, for AT-like protocols tagging their
+//! responses, eg. some GNSS or proprietary modules, which may answer
+//! pipelined commands out of order.
+//!
+//! Responses are matched to the commands of a [`Pipeline`] in the order the
+//! commands were written by default. Commands of such a protocol instead
+//! implement [`AtatCmd::ID_EXTRACTOR`] and [`AtatCmd::sequence_id`], and
+//! their responses are read with [`Pipeline::read_routed`]: a response
+//! tagged with the id of another command is set aside in the
+//! [`ResponseRouter`] until that command is read.
+//!
+//! Error responses carry no id, so they are taken to answer the command
+//! being read.
+//!
+//! ```
+//! use atat::{AtatCmd, AtatResp, Error, InternalError};
+//!
+//! pub struct Position;
+//!
+//! impl AtatResp for Position {}
+//!
+//! /// `AT$POS=<seq>`, answered by `$POS:<seq>,<lat>,<lon>`
+//! pub struct GetPosition {
+//!     pub seq: u8,
+//! }
+//!
+//! impl AtatCmd for GetPosition {
+//!     type Response = Position;
+//!     const MAX_LEN: usize = 16;
+//!     const ID_EXTRACTOR: Option<fn(&[u8]) -> Option<u32>> = Some(|resp| {
+//!         let seq = resp.strip_prefix(b"$POS:")?.split(|&b| b == b',').next()?;
+//!         core::str::from_utf8(seq).ok()?.parse().ok()
+//!     });
+//!
+//!     fn sequence_id(&self) -> Option<u32> {
+//!         Some(self.seq.into())
+//!     }
+//!
+//!     fn write(&self, mut buf: &mut [u8]) -> usize {
+//!         let len = buf.len();
+//!         use embedded_io::Write;
+//!         write!(buf, "AT$POS={}\r\n", self.seq).unwrap();
+//!         len - buf.len()
+//!     }
+//!
+//!     fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Position, Error> {
+//!         resp?;
+//!         Ok(Position)
+//!     }
+//! }
+//! ```
+//!
+//! [`Pipeline`]: crate::asynch::Pipeline
+//! [`Pipeline::read_routed`]: crate::asynch::Pipeline::read_routed
+//! [`AtatCmd::ID_EXTRACTOR`]: crate::AtatCmd::ID_EXTRACTOR
+//! [`AtatCmd::sequence_id`]: crate::AtatCmd::sequence_id
+use heapless::Vec;
+
+use crate::{helpers::LossyStr, Error};
+
+/// Extracts the sequence id tagging a response, see
+/// [`AtatCmd::ID_EXTRACTOR`](crate::AtatCmd::ID_EXTRACTOR).
+pub type IdExtractor = fn(&[u8]) -> Option<u32>;
+
+/// Holds up to `N` responses of up to `LEN` bytes which arrived ahead of
+/// their command being read.
+#[derive(Debug, Default)]
+pub struct ResponseRouter<const N: usize, const LEN: usize> {
+    pending: Vec<(u32, Vec<u8, LEN>), N>,
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize, const LEN: usize> defmt::Format for ResponseRouter<N, LEN> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "ResponseRouter {{ pending: {=usize} }}",
+            self.pending.len()
+        )
+    }
+}
+
+impl<const N: usize, const LEN: usize> ResponseRouter<N, LEN> {
+    /// Create an empty router.
+    pub const fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    /// Number of responses set aside.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no response is set aside.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Discard the responses set aside, eg. after resynchronizing.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Set aside the response tagged with `id`.
+    ///
+    /// Fails with [`Error::ResponseTooLarge`] if the response does not fit,
+    /// or with [`Error::RouterFull`] if no more responses can be held.
+    pub(crate) fn hold(&mut self, id: u32, response: &[u8]) -> Result<(), Error> {
+        debug!("Holding response to #{}: {:?}", id, LossyStr(response));
+        let response = Vec::from_slice(response).map_err(|_| Error::ResponseTooLarge)?;
+        self.pending
+            .push((id, response))
+            .map_err(|_| Error::RouterFull)
+    }
+
+    /// Take the response tagged with `id`, if it was set aside.
+    pub(crate) fn take(&mut self, id: u32) -> Option<Vec<u8, LEN>> {
+        let index = self.pending.iter().position(|(i, _)| *i == id)?;
+        Some(self.pending.swap_remove(index).1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hold_and_take() {
+        let mut router = ResponseRouter::<2, 8>::new();
+        router.hold(2, b"$POS:2").unwrap();
+        router.hold(3, b"$POS:3").unwrap();
+        assert_eq!(router.hold(4, b"$POS:4"), Err(Error::RouterFull));
+
+        assert_eq!(router.take(1), None);
+        assert_eq!(router.take(3).as_deref(), Some(&b"$POS:3"[..]));
+        assert_eq!(router.len(), 1);
+        assert_eq!(
+            router.hold(5, b"$POS:5,too long"),
+            Err(Error::ResponseTooLarge)
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Transport running the AT byte stream over a pair of RTT channels, to
+//! exercise the AT pipeline on a dev board without a modem, with the device
+//! side played by the host through the debug probe.
+//!
+//! RTT has no interrupts, so the channels are polled. Only the transport
+//! differs from a production build: the reader half feeds the ingress, and
+//! the writer half is handed to the client, like the halves of a UART.
+//!
+//! The channels are abstracted by [`UpChannel`] and [`DownChannel`], whose
+//! methods match those of eg. the `rtt-target` crate:
+//!
+//! ```
+//! use atat::rtt::{DownChannel, RttTransport, UpChannel};
+//! # struct RttUp;
+//! # impl RttUp { fn write(&mut self, buf: &[u8]) -> usize { buf.len() } }
+//! # struct RttDown;
+//! # impl RttDown { fn read(&mut self, _buf: &mut [u8]) -> usize { 0 } }
+//!
+//! struct Up(RttUp);
+//!
+//! impl UpChannel for Up {
+//!     fn write(&mut self, buf: &[u8]) -> usize {
+//!         self.0.write(buf)
+//!     }
+//! }
+//!
+//! struct Down(RttDown);
+//!
+//! impl DownChannel for Down {
+//!     fn read(&mut self, buf: &mut [u8]) -> usize {
+//!         self.0.read(buf)
+//!     }
+//! }
+//!
+//! let (reader, writer) = RttTransport::new(Up(RttUp), Down(RttDown)).split();
+//! ```
+use core::convert::Infallible;
+
+use embassy_time::{Duration, Timer};
+
+/// Channel from the target to the host.
+pub trait UpChannel {
+    /// Write as much of `buf` as fits in the channel, returning the number of
+    /// bytes written.
+    fn write(&mut self, buf: &[u8]) -> usize;
+}
+
+/// Channel from the host to the target.
+pub trait DownChannel {
+    /// Read the bytes available in the channel into `buf`, returning the
+    /// number of bytes read.
+    fn read(&mut self, buf: &mut [u8]) -> usize;
+}
+
+/// A pair of RTT channels, carrying the AT byte stream as a UART would.
+pub struct RttTransport<U: UpChannel, D: DownChannel> {
+    reader: RttReader<D>,
+    writer: RttWriter<U>,
+}
+
+impl<U: UpChannel, D: DownChannel> RttTransport<U, D> {
+    pub const fn new(up: U, down: D) -> Self {
+        Self {
+            reader: RttReader {
+                down,
+                poll_interval: Duration::from_millis(1),
+            },
+            writer: RttWriter {
+                up,
+                poll_interval: Duration::from_millis(1),
+            },
+        }
+    }
+
+    /// Interval at which the async reader and writer poll the channels, while
+    /// there is nothing to read or no room to write.
+    ///
+    /// **default**: 1 ms
+    #[must_use]
+    pub const fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.reader.poll_interval = interval;
+        self.writer.poll_interval = interval;
+        self
+    }
+
+    /// Split into the reader, feeding the ingress, and the writer, handed to
+    /// the client.
+    pub fn split(self) -> (RttReader<D>, RttWriter<U>) {
+        (self.reader, self.writer)
+    }
+}
+
+/// Reader half of an [`RttTransport`].
+pub struct RttReader<D: DownChannel> {
+    down: D,
+    poll_interval: Duration,
+}
+
+/// Writer half of an [`RttTransport`].
+pub struct RttWriter<U: UpChannel> {
+    up: U,
+    poll_interval: Duration,
+}
+
+impl<D: DownChannel> embedded_io::ErrorType for RttReader<D> {
+    type Error = Infallible;
+}
+
+impl<D: DownChannel> embedded_io::Read for RttReader<D> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        loop {
+            let n = self.down.read(buf);
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+        }
+    }
+}
+
+impl<D: DownChannel> embedded_io_async::Read for RttReader<D> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        loop {
+            let n = self.down.read(buf);
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            Timer::after(self.poll_interval).await;
+        }
+    }
+}
+
+impl<U: UpChannel> embedded_io::ErrorType for RttWriter<U> {
+    type Error = Infallible;
+}
+
+impl<U: UpChannel> embedded_io::Write for RttWriter<U> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        loop {
+            let n = self.up.write(buf);
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        // The host reads the channel buffer directly
+        Ok(())
+    }
+}
+
+impl<U: UpChannel> embedded_io_async::Write for RttWriter<U> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        loop {
+            let n = self.up.write(buf);
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            Timer::after(self.poll_interval).await;
+        }
+    }
+}
+
+impl<U: UpChannel, D: DownChannel> embedded_io::ErrorType for RttTransport<U, D> {
+    type Error = Infallible;
+}
+
+impl<U: UpChannel, D: DownChannel> embedded_io::Read for RttTransport<U, D> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io::Read::read(&mut self.reader, buf)
+    }
+}
+
+impl<U: UpChannel, D: DownChannel> embedded_io_async::Read for RttTransport<U, D> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io_async::Read::read(&mut self.reader, buf).await
+    }
+}
+
+impl<U: UpChannel, D: DownChannel> embedded_io::Write for RttTransport<U, D> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        embedded_io::Write::write(&mut self.writer, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        embedded_io::Write::flush(&mut self.writer)
+    }
+}
+
+impl<U: UpChannel, D: DownChannel> embedded_io_async::Write for RttTransport<U, D> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        embedded_io_async::Write::write(&mut self.writer, buf).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asynch::{AtatClient, SimpleClient},
+        test_ingress::NoUrc,
+        AtDigester, Config,
+    };
+    use std::{collections::VecDeque, vec::Vec};
+
+    /// Up channel with room for `capacity` bytes, drained by the test
+    struct Up {
+        data: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl UpChannel for Up {
+        fn write(&mut self, buf: &[u8]) -> usize {
+            let n = buf.len().min(self.capacity - self.data.len());
+            self.data.extend_from_slice(&buf[..n]);
+            n
+        }
+    }
+
+    struct Down(VecDeque<u8>);
+
+    impl DownChannel for Down {
+        fn read(&mut self, buf: &mut [u8]) -> usize {
+            let n = buf.len().min(self.0.len());
+            for (b, c) in buf.iter_mut().zip(self.0.drain(..n)) {
+                *b = c;
+            }
+            n
+        }
+    }
+
+    #[tokio::test]
+    async fn simple_client() {
+        let up = Up {
+            data: Vec::new(),
+            capacity: 64,
+        };
+        let down = Down(b"\r\nOK\r\n".iter().copied().collect());
+        let mut buf = [0; 64];
+        let mut client = SimpleClient::new(
+            RttTransport::new(up, down),
+            AtDigester::<NoUrc>::new(),
+            &mut buf,
+            Config::new(),
+        );
+
+        assert_eq!(
+            client
+                .send(&crate::loopback::SelfTest)
+                .await
+                .map(|r| r.passed),
+            Ok(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn simple_client_retry() {
+        let up = Up {
+            data: Vec::new(),
+            capacity: 64,
+        };
+        let down = Down(
+            b"\r\n+CME ERROR: 14\r\n\r\nOK\r\n"
+                .iter()
+                .copied()
+                .collect(),
+        );
+        let mut buf = [0; 64];
+        let mut client = SimpleClient::new(
+            RttTransport::new(up, down),
+            AtDigester::<NoUrc>::new(),
+            &mut buf,
+            Config::new().retry(2, 0),
+        );
+
+        assert!(client.send(&crate::init::InitStep::Sync).await.is_ok());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Listing(heapless::Vec<u8, 32>);
+
+    impl crate::AtatResp for Listing {}
+
+    struct ListMessages;
+
+    impl crate::AtatCmd for ListMessages {
+        type Response = Listing;
+        const MAX_LEN: usize = 9;
+        const CONTINUATION: Option<&'static [u8]> = Some(b"\r");
+
+        fn write(&self, buf: &mut [u8]) -> usize {
+            buf[..9].copy_from_slice(b"AT+CMGL\r\n");
+            9
+        }
+
+        fn parse(
+            &self,
+            resp: Result<&[u8], crate::InternalError>,
+        ) -> Result<Listing, crate::Error> {
+            Ok(Listing(heapless::Vec::from_slice(resp?).unwrap()))
+        }
+    }
+
+    fn paging_client<'a>(
+        down: &[u8],
+        buf: &'a mut [u8],
+    ) -> SimpleClient<'a, RttTransport<Up, Down>, AtDigester<NoUrc>> {
+        let up = Up {
+            data: Vec::new(),
+            capacity: 64,
+        };
+        let digester = AtDigester::<NoUrc>::new().with_custom_continuation(|buf| {
+            let (_, (data, tag)) =
+                crate::digest::parser::take_until_including::<_, _, nom::error::Error<_>>(
+                    "\r\n-- More --",
+                )(buf)?;
+            Ok((
+                data.strip_prefix(b"\r\n").unwrap_or(data),
+                data.len() + tag.len(),
+            ))
+        });
+        let down = Down(down.iter().copied().collect());
+        SimpleClient::new(RttTransport::new(up, down), digester, buf, Config::new())
+    }
+
+    #[tokio::test]
+    async fn simple_client_pages() {
+        // The pages are collected in the client buffer
+        let mut buf = [0; 32];
+        let mut client = paging_client(
+            b"\r\n+CMGL: 1\r\n-- More --\r\n+CMGL: 2\r\nOK\r\n",
+            &mut buf,
+        );
+        assert_eq!(
+            client.send(&ListMessages).await,
+            Ok(Listing(
+                heapless::Vec::from_slice(b"+CMGL: 1\r\n+CMGL: 2").unwrap()
+            ))
+        );
+
+        // A page is not taken as the response of other commands
+        let mut buf = [0; 32];
+        let mut client = paging_client(b"\r\n+CMGL: 1\r\n-- More --", &mut buf);
+        assert_eq!(
+            client.send(&crate::init::InitStep::Sync).await.err(),
+            Some(crate::Error::InvalidResponse)
+        );
+    }
+
+    #[test]
+    fn write_waits_for_room() {
+        let up = Up {
+            data: Vec::new(),
+            capacity: 2,
+        };
+        let (_, mut writer) = RttTransport::new(up, Down(VecDeque::new())).split();
+        assert_eq!(embedded_io::Write::write(&mut writer, b"AT\r\n"), Ok(2));
+        assert_eq!(writer.up.data, b"AT");
+    }
+}
@@ -0,0 +1,266 @@
+//! Scaffolding for SIM toolkit (STK) proactive commands, announced by URCs
+//! like `+STKPCI: 0,"D0..."` or `+CUSATP: "D0..."`.
+//!
+//! These carry a BER-TLV encoded command of up to 255 bytes as hex, ie. URC
+//! lines of over 500 bytes, exceeding the size of typical URC types. Rather
+//! than parsing them into the URC type of the [`UrcChannel`], handle them in
+//! place with [`Ingress::with_urc_handler`], and decode the command into a
+//! buffer of the application with [`payload`] and [`decode_hex`]. Only the
+//! ingress buffer must hold the whole line.
+//!
+//! Staged delivery, passing the line on in parts as it is received, is not
+//! supported: the ingress only hands complete lines to the URC handler, so
+//! its buffer must be sized for the longest proactive command.
+//!
+//! ```
+//! use atat::stk::{self, ProactiveCommand};
+//!
+//! // DISPLAY TEXT "Hi!" to the display
+//! let line = b"+CUSATP: \"D00F8103012180820281028D0404486921\"";
+//!
+//! let mut buf = [0; stk::MAX_COMMAND_LEN];
+//! let data = stk::decode_hex(stk::payload(line).unwrap(), &mut buf).unwrap();
+//! let cmd = ProactiveCommand::parse(data).unwrap();
+//! assert_eq!(cmd.kind, stk::DISPLAY_TEXT);
+//!
+//! // The text string starts with its data coding scheme, 0x04 for 8-bit data
+//! let text = cmd.find(stk::TEXT_STRING).unwrap();
+//! assert_eq!(text, b"\x04Hi!");
+//! ```
+//!
+//! [`UrcChannel`]: crate::UrcChannel
+//! [`Ingress::with_urc_handler`]: crate::Ingress::with_urc_handler
+
+/// Largest proactive command, in bytes once decoded.
+pub const MAX_COMMAND_LEN: usize = 255;
+
+/// Tag of the proactive command wrapping all other objects.
+pub const PROACTIVE_COMMAND: u8 = 0xD0;
+/// Tag of the command details, holding the type of the command.
+pub const COMMAND_DETAILS: u8 = 0x01;
+/// Tag of the device identities, holding the source and destination.
+pub const DEVICE_IDENTITIES: u8 = 0x02;
+/// Tag of a text string, starting with its data coding scheme.
+pub const TEXT_STRING: u8 = 0x0D;
+
+/// Type of the DISPLAY TEXT command.
+pub const DISPLAY_TEXT: u8 = 0x21;
+/// Type of the GET INKEY command.
+pub const GET_INKEY: u8 = 0x22;
+/// Type of the GET INPUT command.
+pub const GET_INPUT: u8 = 0x23;
+/// Type of the SET UP MENU command.
+pub const SET_UP_MENU: u8 = 0x25;
+
+/// Error decoding a proactive command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The payload is not an even number of hex digits
+    InvalidHex,
+    /// The decoded payload does not fit the buffer
+    BufferTooSmall,
+    /// A TLV object is truncated or has an unsupported length encoding
+    InvalidTlv,
+    /// The data is not a proactive command
+    NotProactive,
+}
+
+/// The hex payload of an STK URC line, ie. its last quoted parameter, eg.
+/// `D0..` of `+STKPCI: 0,"D0.."`.
+pub fn payload(urc_line: &[u8]) -> Option<&[u8]> {
+    let end = urc_line.iter().rposition(|&b| b == b'"')?;
+    let start = urc_line[..end].iter().rposition(|&b| b == b'"')? + 1;
+    Some(&urc_line[start..end])
+}
+
+/// Decode `hex` into `buf`, returning the decoded bytes.
+pub fn decode_hex<'b>(hex: &[u8], buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+    if hex.len() % 2 != 0 {
+        return Err(Error::InvalidHex);
+    }
+    let len = hex.len() / 2;
+    if len > buf.len() {
+        return Err(Error::BufferTooSmall);
+    }
+    for (byte, pair) in buf.iter_mut().zip(hex.chunks_exact(2)) {
+        *byte = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+    }
+    Ok(&buf[..len])
+}
+
+fn nibble(digit: u8) -> Result<u8, Error> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(Error::InvalidHex),
+    }
+}
+
+/// A BER-TLV object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Tlv<'a> {
+    /// The tag, including the comprehension required bit `0x80` of simple
+    /// TLV objects
+    pub tag: u8,
+    /// The value, of the length encoded in the object
+    pub value: &'a [u8],
+}
+
+impl Tlv<'_> {
+    /// Whether the object has `tag`, ignoring the comprehension required
+    /// bit.
+    pub fn is(&self, tag: u8) -> bool {
+        self.tag & 0x7F == tag & 0x7F
+    }
+}
+
+/// Iterator over consecutive TLV objects, see [`tlvs`].
+#[derive(Debug, Clone)]
+pub struct Tlvs<'a> {
+    data: &'a [u8],
+}
+
+/// Iterate the TLV objects of `data`. Iteration stops after the first
+/// error.
+pub fn tlvs(data: &[u8]) -> Tlvs<'_> {
+    Tlvs { data }
+}
+
+impl<'a> Iterator for Tlvs<'a> {
+    type Item = Result<Tlv<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&tag, rest) = self.data.split_first()?;
+        let res = match rest {
+            [0x81, len, value @ ..] if *len >= 0x80 => Ok((*len, value)),
+            [len, value @ ..] if *len < 0x80 => Ok((*len, value)),
+            _ => Err(Error::InvalidTlv),
+        }
+        .and_then(|(len, value)| {
+            let len = usize::from(len);
+            if value.len() < len {
+                return Err(Error::InvalidTlv);
+            }
+            let (value, rest) = value.split_at(len);
+            self.data = rest;
+            Ok(Tlv { tag, value })
+        });
+        if res.is_err() {
+            self.data = &[];
+        }
+        Some(res)
+    }
+}
+
+/// A proactive command, with its command details parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ProactiveCommand<'a> {
+    /// Number identifying the command in the terminal response
+    pub number: u8,
+    /// Type of the command, eg. [`DISPLAY_TEXT`]
+    pub kind: u8,
+    /// Qualifier of the command, whose meaning depends on the type
+    pub qualifier: u8,
+    objects: &'a [u8],
+}
+
+impl<'a> ProactiveCommand<'a> {
+    /// Parse a decoded proactive command.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let command = match tlvs(data).next() {
+            Some(Ok(tlv)) if tlv.tag == PROACTIVE_COMMAND => tlv,
+            Some(Err(e)) => return Err(e),
+            _ => return Err(Error::NotProactive),
+        };
+        let (number, kind, qualifier) = match tlvs(command.value)
+            .find_map(|tlv| tlv.ok().filter(|tlv| tlv.is(COMMAND_DETAILS)))
+        {
+            Some(Tlv {
+                value: [number, kind, qualifier, ..],
+                ..
+            }) => (*number, *kind, *qualifier),
+            _ => return Err(Error::NotProactive),
+        };
+        Ok(Self {
+            number,
+            kind,
+            qualifier,
+            objects: command.value,
+        })
+    }
+
+    /// The objects of the command, including its command details.
+    pub fn objects(&self) -> Tlvs<'a> {
+        tlvs(self.objects)
+    }
+
+    /// The value of the first object with `tag`, eg. [`TEXT_STRING`],
+    /// ignoring the comprehension required bit.
+    pub fn find(&self, tag: u8) -> Option<&'a [u8]> {
+        self.objects()
+            .map_while(Result::ok)
+            .find(|tlv| tlv.is(tag))
+            .map(|tlv| tlv.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_of_urcs() {
+        assert_eq!(payload(b"+STKPCI: 0,\"D003\""), Some(&b"D003"[..]));
+        assert_eq!(payload(b"+CUSATP: \"d003\""), Some(&b"d003"[..]));
+        assert_eq!(payload(b"+STKPCI: 0"), None);
+    }
+
+    #[test]
+    fn hex() {
+        let mut buf = [0; 2];
+        assert_eq!(decode_hex(b"D0aF", &mut buf), Ok(&[0xD0, 0xAF][..]));
+        assert_eq!(decode_hex(b"D0a", &mut buf), Err(Error::InvalidHex));
+        assert_eq!(decode_hex(b"D0aG", &mut buf), Err(Error::InvalidHex));
+        assert_eq!(decode_hex(b"D0aF00", &mut buf), Err(Error::BufferTooSmall));
+    }
+
+    #[test]
+    fn long_tlv() {
+        let mut data = [0xAA; 3 + 200];
+        data[..3].copy_from_slice(&[0x8D, 0x81, 200]);
+        let mut objects = tlvs(&data);
+        assert_eq!(
+            objects.next().map(|tlv| tlv.map(|t| t.value.len())),
+            Some(Ok(200))
+        );
+        assert_eq!(objects.next(), None);
+
+        // Truncated
+        let mut objects = tlvs(&data[..100]);
+        assert_eq!(objects.next(), Some(Err(Error::InvalidTlv)));
+        assert_eq!(objects.next(), None);
+    }
+
+    #[test]
+    fn display_text() {
+        let mut buf = [0; MAX_COMMAND_LEN];
+        let data = decode_hex(b"D00F8103012180820281028D0404486921", &mut buf).unwrap();
+        let cmd = ProactiveCommand::parse(data).unwrap();
+        assert_eq!(
+            (cmd.number, cmd.kind, cmd.qualifier),
+            (1, DISPLAY_TEXT, 0x80)
+        );
+        assert_eq!(cmd.find(DEVICE_IDENTITIES), Some(&[0x81, 0x02][..]));
+        assert_eq!(cmd.find(TEXT_STRING), Some(&b"\x04Hi!"[..]));
+        assert_eq!(cmd.objects().count(), 3);
+
+        assert_eq!(
+            ProactiveCommand::parse(&[0x81, 0x03, 0x01, 0x21, 0x80]),
+            Err(Error::NotProactive)
+        );
+    }
+}
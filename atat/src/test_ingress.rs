@@ -0,0 +1,31 @@
+//! Ingress shared by the tests of the transports feeding one, eg. DMA or
+//! fragmented links.
+use crate::{AtDigester, Ingress, ResponseSlot, UrcChannel};
+
+/// URC's of a device which sends none
+#[derive(Clone, Debug)]
+pub enum NoUrc {}
+
+impl crate::AtatUrc for NoUrc {
+    type Response = Self;
+
+    fn parse(_resp: &[u8]) -> Option<Self::Response> {
+        None
+    }
+}
+
+impl crate::Parser for NoUrc {
+    fn parse(_buf: &[u8]) -> Result<(&[u8], usize), crate::digest::ParseError> {
+        Err(crate::digest::ParseError::NoMatch)
+    }
+}
+
+pub type TestIngress<'a> = Ingress<'a, AtDigester<NoUrc>, NoUrc, 64, 1, 1>;
+
+pub fn ingress<'a>(
+    buf: &'a mut [u8],
+    res_slot: &'a ResponseSlot<64>,
+    urc_channel: &'a UrcChannel<NoUrc, 1, 1>,
+) -> TestIngress<'a> {
+    Ingress::new(AtDigester::new(), buf, res_slot, urc_channel)
+}
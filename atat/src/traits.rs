@@ -1,6 +1,10 @@
 use crate::error::{Error, InternalError};
+use crate::router::IdExtractor;
 use heapless::{String, Vec};
 
+/// Default of [`AtatCmd::MAX_TIMEOUT_MS`]
+pub(crate) const DEFAULT_TIMEOUT_MS: u32 = 1000;
+
 /// This trait needs to be implemented for every response type.
 ///
 /// Example:
@@ -13,7 +17,42 @@ use heapless::{String, Vec};
 ///
 /// impl AtatResp for GreetingText {}
 /// ```
-pub trait AtatResp {}
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a response type",
+    label = "`{Self}` does not implement `AtatResp`",
+    note = "derive `AtatResp` for `{Self}`"
+)]
+pub trait AtatResp {
+    /// Check the parsed response, eg. for values out of range, before it is
+    /// returned by the client.
+    ///
+    /// The response may be normalized in place, eg. mapping a value reserved
+    /// for "unknown" to `None`. Returning [`Error::Parse`] treats the response
+    /// like one failing to parse, so the command is reattempted.
+    ///
+    /// ```
+    /// use atat::{AtatResp, Error};
+    ///
+    /// pub struct SignalQuality {
+    ///     /// RSRP in dBm, with 0 reported when not known
+    ///     pub rsrp: Option<i16>,
+    /// }
+    ///
+    /// impl AtatResp for SignalQuality {
+    ///     fn validate(&mut self) -> Result<(), Error> {
+    ///         match self.rsrp {
+    ///             Some(0) => self.rsrp = None,
+    ///             Some(rsrp) if !(-140..=-44).contains(&rsrp) => return Err(Error::Parse),
+    ///             _ => {}
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    fn validate(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
 
 pub trait AtatUrc {
     /// The type of the response. Usually the enum this trait is implemented on.
@@ -68,10 +107,19 @@ pub trait AtatCmd {
     const MAX_LEN: usize;
 
     /// Whether or not this command can be aborted.
+    ///
+    /// A command which timed out is aborted before it is reattempted, rather
+    /// than left running on the device, and its final result code awaited for
+    /// up to [`Config::abort_timeout`]. Commands which are not abortable can
+    /// only be aborted explicitly by force, eg. through
+    /// [`blocking::Client::abort_current`].
+    ///
+    /// [`blocking::Client::abort_current`]: crate::blocking::Client::abort_current
+    /// [`Config::abort_timeout`]: crate::Config::abort_timeout
     const CAN_ABORT: bool = false;
 
     /// The max timeout in milliseconds.
-    const MAX_TIMEOUT_MS: u32 = 1000;
+    const MAX_TIMEOUT_MS: u32 = DEFAULT_TIMEOUT_MS;
 
     /// The max number of times to attempt a command with automatic retries if
     /// using `send_retry`.
@@ -86,6 +134,84 @@ pub trait AtatCmd {
     /// Implemented to enhance expandability of ATAT
     const EXPECTS_RESPONSE_CODE: bool = true;
 
+    /// Human readable description of the serialized command, eg.
+    /// `AT+CFUN=<fun>[,<rst>]\r\n`, with parameters in position order and
+    /// optional parameters in brackets. Emitted by [`atat_derive`], and
+    /// useful for listing commands in eg. a debug console.
+    ///
+    /// [`atat_derive`]: https://crates.io/crates/atat_derive
+    const WIRE_FORMAT: &'static str = "";
+
+    /// Name of the command type, eg. `GetSignalQuality`, emitted by
+    /// [`atat_derive`] for the [`report`] of a command set.
+    ///
+    /// [`atat_derive`]: https://crates.io/crates/atat_derive
+    /// [`report`]: crate::report
+    const NAME: &'static str = "";
+
+    /// Token to send when the device pauses a paginated response, eg.
+    /// `b"\r"`. The pages are joined by `\r\n`, and parsed as a single
+    /// response. Requires the digester to recognize the pause prompt, see
+    /// [`AtDigester::with_custom_continuation`].
+    ///
+    /// [`AtDigester::with_custom_continuation`]: crate::AtDigester::with_custom_continuation
+    const CONTINUATION: Option<&'static [u8]> = None;
+
+    /// Final result codes completing the command successfully besides `OK`,
+    /// eg. `b"SEND OK"`, recognized by the digester while the command is in
+    /// flight. The information response preceding the result code is passed
+    /// to [`AtatCmd::parse`].
+    const SUCCESS_CODES: &'static [&'static [u8]] = &[];
+
+    /// Final result codes failing the command besides `ERROR`, eg.
+    /// `b"SEND FAIL"`, recognized by the digester while the command is in
+    /// flight. The command fails with [`Error::Custom`], or with
+    /// `Error::CustomMessage` holding the result code if the
+    /// `custom-error-messages` feature is enabled.
+    const ERROR_CODES: &'static [&'static [u8]] = &[];
+
+    /// Prefix of the information response, eg. `b"+CSQ:"`. When set, a
+    /// non-empty response not starting with the prefix is taken to belong to
+    /// another command, and fails with [`Error::Desync`].
+    const RESP_PREFIX: Option<&'static [u8]> = None;
+
+    /// The complete serialized command, eg. `b"AT+CSQ\r\n"`, for commands
+    /// without parameters. When set, clients transmit it directly instead
+    /// of writing the command to their buffer. Emitted by [`atat_derive`]
+    /// for commands without fields.
+    ///
+    /// [`atat_derive`]: https://crates.io/crates/atat_derive
+    const WIRE_IMAGE: Option<&'static [u8]> = None;
+
+    /// Override the client's `cmd_cooldown` with a command specific quiet
+    /// time in milliseconds, applied before the next command is sent.
+    const COOLDOWN_MS: Option<u32> = None;
+
+    /// Small id keying the round-trip time statistics of the command type,
+    /// below [`COMMAND_STATS_LEN`]. Commands without an id, or sharing an id,
+    /// are not told apart. Requires the `command-stats` feature, see
+    /// [`ResponseSlot::command_stats`].
+    ///
+    /// [`COMMAND_STATS_LEN`]: crate::COMMAND_STATS_LEN
+    /// [`ResponseSlot::command_stats`]: crate::ResponseSlot::command_stats
+    const STATS_ID: Option<u8> = None;
+
+    /// Extract the sequence id tagging a response, for protocols answering
+    /// pipelined commands out of order. Responses read through
+    /// [`Pipeline::read_routed`] are matched to the command by comparing the
+    /// extracted id with [`AtatCmd::sequence_id`], rather than by order. See
+    /// the [`router`] module.
+    ///
+    /// [`Pipeline::read_routed`]: crate::asynch::Pipeline::read_routed
+    /// [`router`]: crate::router
+    const ID_EXTRACTOR: Option<IdExtractor> = None;
+
+    /// The sequence id tagging the response to this command, see
+    /// [`AtatCmd::ID_EXTRACTOR`].
+    fn sequence_id(&self) -> Option<u32> {
+        None
+    }
+
     /// Write the command and return the number of written bytes.
     fn write(&self, buf: &mut [u8]) -> usize;
 
@@ -93,6 +219,38 @@ pub trait AtatCmd {
     fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error>;
 }
 
+impl<T: AtatCmd + ?Sized> AtatCmd for &T {
+    type Response = T::Response;
+    const MAX_LEN: usize = T::MAX_LEN;
+    const CAN_ABORT: bool = T::CAN_ABORT;
+    const MAX_TIMEOUT_MS: u32 = T::MAX_TIMEOUT_MS;
+    const ATTEMPTS: u8 = T::ATTEMPTS;
+    const REATTEMPT_ON_PARSE_ERR: bool = T::REATTEMPT_ON_PARSE_ERR;
+    const EXPECTS_RESPONSE_CODE: bool = T::EXPECTS_RESPONSE_CODE;
+    const WIRE_FORMAT: &'static str = T::WIRE_FORMAT;
+    const NAME: &'static str = T::NAME;
+    const CONTINUATION: Option<&'static [u8]> = T::CONTINUATION;
+    const SUCCESS_CODES: &'static [&'static [u8]] = T::SUCCESS_CODES;
+    const ERROR_CODES: &'static [&'static [u8]] = T::ERROR_CODES;
+    const RESP_PREFIX: Option<&'static [u8]> = T::RESP_PREFIX;
+    const WIRE_IMAGE: Option<&'static [u8]> = T::WIRE_IMAGE;
+    const COOLDOWN_MS: Option<u32> = T::COOLDOWN_MS;
+    const STATS_ID: Option<u8> = T::STATS_ID;
+    const ID_EXTRACTOR: Option<IdExtractor> = T::ID_EXTRACTOR;
+
+    fn sequence_id(&self) -> Option<u32> {
+        (**self).sequence_id()
+    }
+
+    fn write(&self, buf: &mut [u8]) -> usize {
+        (**self).write(buf)
+    }
+
+    fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error> {
+        (**self).parse(resp)
+    }
+}
+
 impl<T, const L: usize> AtatResp for Vec<T, L> where T: AtatResp {}
 
 impl<const L: usize> AtatResp for String<L> {}
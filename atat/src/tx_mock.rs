@@ -4,6 +4,7 @@ use heapless::String;
 
 pub struct TxMock<'a> {
     buf: String<64>,
+    fail_after: Option<usize>,
     publisher: Publisher<'a, CriticalSectionRawMutex, String<64>, 1, 1, 1>,
 }
 
@@ -20,9 +21,35 @@ impl<'a> TxMock<'a> {
     pub fn new(publisher: Publisher<'a, CriticalSectionRawMutex, String<64>, 1, 1, 1>) -> Self {
         TxMock {
             buf: String::new(),
+            fail_after: None,
             publisher,
         }
     }
+
+    /// Fail a write once, after `n` more bytes were written.
+    pub fn fail_after(mut self, n: usize) -> Self {
+        self.fail_after = Some(n);
+        self
+    }
+
+    fn push(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        let len = match self.fail_after {
+            Some(0) => {
+                self.fail_after = None;
+                return Err(IoError);
+            }
+            Some(n) => {
+                let len = buf.len().min(n);
+                self.fail_after = Some(n - len);
+                len
+            }
+            None => buf.len(),
+        };
+        for c in &buf[..len] {
+            self.buf.push(*c as char).map_err(|_| IoError)?;
+        }
+        Ok(len)
+    }
 }
 
 impl ErrorType for TxMock<'_> {
@@ -31,10 +58,7 @@ impl ErrorType for TxMock<'_> {
 
 impl embedded_io::Write for TxMock<'_> {
     fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        for c in buf {
-            self.buf.push(*c as char).map_err(|_| IoError)?;
-        }
-        Ok(buf.len())
+        self.push(buf)
     }
 
     fn flush(&mut self) -> Result<(), Self::Error> {
@@ -46,10 +70,7 @@ impl embedded_io::Write for TxMock<'_> {
 
 impl embedded_io_async::Write for TxMock<'_> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        for c in buf {
-            self.buf.push(*c as char).map_err(|_| IoError)?;
-        }
-        Ok(buf.len())
+        self.push(buf)
     }
 
     async fn flush(&mut self) -> Result<(), Self::Error> {
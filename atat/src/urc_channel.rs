@@ -1,6 +1,14 @@
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber};
+use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber, WaitResult};
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
 
+use crate::blocking::blocking_timer::BlockingTimer;
 use crate::AtatUrc;
 
 pub type UrcPublisher<'sub, Urc, const CAPACITY: usize, const SUBSCRIBERS: usize> =
@@ -14,14 +22,55 @@ pub enum Error {
     MaximumSubscribersReached,
 }
 
+/// Channel the [`Ingress`] publishes parsed URCs to.
+///
+/// Every URC is delivered to each of up to `SUBSCRIBERS` subscribers, so
+/// independent consumers, eg. a socket stack, a GNSS driver and an SMS
+/// handler, each [`subscribe`](Self::subscribe) or open a
+/// [`stream`](Self::stream) of their own, rather than sharing one queue.
+/// A URC is held until all subscribers received it, so the slowest
+/// subscriber must keep up for the ingress to publish further URCs once
+/// `CAPACITY` URCs are queued.
+///
+/// [`Ingress`]: crate::Ingress
 pub struct UrcChannel<Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize>(
     pub(crate) PubSubChannel<CriticalSectionRawMutex, Urc::Response, CAPACITY, SUBSCRIBERS, 1>,
 );
 
+impl<Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize> core::fmt::Debug
+    for UrcChannel<Urc, CAPACITY, SUBSCRIBERS>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UrcChannel")
+            .field("len", &self.0.len())
+            .field("capacity", &CAPACITY)
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize> defmt::Format
+    for UrcChannel<Urc, CAPACITY, SUBSCRIBERS>
+{
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "UrcChannel {{ len: {=usize}, capacity: {=usize} }}",
+            self.0.len(),
+            CAPACITY
+        )
+    }
+}
+
 impl<Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize>
     UrcChannel<Urc, CAPACITY, SUBSCRIBERS>
 {
+    #[allow(clippy::new_without_default)]
     pub const fn new() -> Self {
+        const {
+            core::assert!(CAPACITY > 0, "the URC channel must queue at least one URC");
+            core::assert!(SUBSCRIBERS > 0, "the URC channel must allow a subscriber");
+        };
         Self(PubSubChannel::new())
     }
 
@@ -31,7 +80,389 @@ impl<Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize>
             .map_err(|_| Error::MaximumSubscribersReached)
     }
 
+    /// Subscribe to the channel as a [`UrcStream`]
+    pub fn stream(&self) -> Result<UrcStream<'_, Urc, CAPACITY, SUBSCRIBERS>, Error> {
+        Ok(UrcStream {
+            subscription: self.subscribe()?,
+            lagged: 0,
+        })
+    }
+
     pub fn free_capacity(&self) -> usize {
         self.0.free_capacity()
     }
+
+    /// Drop all queued URCs, eg. when the ingress publishing to the channel
+    /// was released to hand the serial port to another peripheral.
+    pub fn clear(&self) {
+        self.0.clear();
+    }
+}
+
+/// Publishing of URC lines taken out of the ingress, see
+/// [`asynch::Client::send_and_wait_urc`].
+///
+/// [`asynch::Client::send_and_wait_urc`]: crate::asynch::Client::send_and_wait_urc
+pub trait UrcSink {
+    /// Parse `line` and publish the URC, returning whether it was published.
+    fn publish_line(&self, line: &[u8]) -> bool;
+}
+
+impl<Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize> UrcSink
+    for UrcChannel<Urc, CAPACITY, SUBSCRIBERS>
+{
+    fn publish_line(&self, line: &[u8]) -> bool {
+        let Some(urc) = Urc::parse(line) else {
+            return false;
+        };
+        if self.0.immediate_publisher().try_publish(urc).is_err() {
+            warn!("URC channel full, dropping URC");
+            return false;
+        }
+        true
+    }
+}
+
+/// A cancel-safe stream of URCs, implementing [`futures::Stream`].
+///
+/// A URC is only consumed from the channel once it is returned, so dropping a
+/// pending `next_urc()` future, eg. as the losing branch of a `select`, never
+/// loses a URC.
+pub struct UrcStream<'sub, Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize> {
+    subscription: UrcSubscription<'sub, Urc, CAPACITY, SUBSCRIBERS>,
+    lagged: u64,
+}
+
+impl<'sub, Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize>
+    UrcStream<'sub, Urc, CAPACITY, SUBSCRIBERS>
+{
+    /// Poll for the next URC, registering the waker of `cx` to be woken when
+    /// a new URC is published.
+    pub fn poll_urc(&mut self, cx: &mut Context<'_>) -> Poll<Urc::Response> {
+        loop {
+            match Pin::new(&mut self.subscription.next_message()).poll(cx) {
+                Poll::Ready(WaitResult::Message(urc)) => return Poll::Ready(urc),
+                Poll::Ready(WaitResult::Lagged(n)) => self.on_lagged(n),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Wait for the next URC.
+    pub async fn next_urc(&mut self) -> Urc::Response {
+        core::future::poll_fn(|cx| self.poll_urc(cx)).await
+    }
+
+    /// Get the next URC, if one is available.
+    pub fn try_next_urc(&mut self) -> Option<Urc::Response> {
+        loop {
+            match self.subscription.try_next_message()? {
+                WaitResult::Message(urc) => return Some(urc),
+                WaitResult::Lagged(n) => self.on_lagged(n),
+            }
+        }
+    }
+
+    /// Get the next URC matching `pred`, if one is available.
+    ///
+    /// URCs not matching are skipped by this stream only, and stay queued in
+    /// the channel for its other subscribers, eg. a socket driver takes its
+    /// `+UUSORD` URCs while the `+CREG` URCs are left to the stream of the
+    /// network layer.
+    pub fn try_next_urc_matching(
+        &mut self,
+        mut pred: impl FnMut(&Urc::Response) -> bool,
+    ) -> Option<Urc::Response> {
+        loop {
+            let urc = self.try_next_urc()?;
+            if pred(&urc) {
+                return Some(urc);
+            }
+        }
+    }
+
+    /// Wait for the next URC matching `pred`, see
+    /// [`try_next_urc_matching`](Self::try_next_urc_matching).
+    pub async fn next_urc_matching(
+        &mut self,
+        mut pred: impl FnMut(&Urc::Response) -> bool,
+    ) -> Urc::Response {
+        loop {
+            let urc = self.next_urc().await;
+            if pred(&urc) {
+                return urc;
+            }
+        }
+    }
+
+    /// Block until the next URC is available, or `timeout` passed, eg. from
+    /// the main loop of an application using the [`blocking::Client`].
+    ///
+    /// [`blocking::Client`]: crate::blocking::Client
+    pub fn wait_urc(&mut self, timeout: Duration) -> Option<Urc::Response> {
+        let timer = BlockingTimer::after(timeout);
+        loop {
+            if let Some(urc) = self.try_next_urc() {
+                return Some(urc);
+            }
+            if timer.expires_at() <= Instant::now() {
+                return None;
+            }
+        }
+    }
+
+    /// The total number of URCs lost because this stream was not polled
+    /// before the channel overflowed.
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
+    fn on_lagged(&mut self, n: u64) {
+        warn!("URC stream lagged, {} URCs were lost", n);
+        self.lagged += n;
+    }
+}
+
+impl<'sub, Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize> core::fmt::Debug
+    for UrcStream<'sub, Urc, CAPACITY, SUBSCRIBERS>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UrcStream")
+            .field("available", &self.subscription.available())
+            .field("lagged", &self.lagged)
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'sub, Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize> defmt::Format
+    for UrcStream<'sub, Urc, CAPACITY, SUBSCRIBERS>
+{
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "UrcStream {{ available: {=u64}, lagged: {=u64} }}",
+            self.subscription.available(),
+            self.lagged
+        )
+    }
+}
+
+impl<'sub, Urc: AtatUrc, const CAPACITY: usize, const SUBSCRIBERS: usize> futures::Stream
+    for UrcStream<'sub, Urc, CAPACITY, SUBSCRIBERS>
+{
+    type Item = Urc::Response;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_urc(cx).map(Some)
+    }
+}
+
+/// Adapter publishing the raw bytes of each URC along with the parsed
+/// variant, eg. for audit logging, without parsing the URC twice.
+///
+/// Use `WithRaw<Urc, N>` in place of `Urc` as the URC type of the
+/// [`Ingress`] and the [`UrcChannel`], to receive a [`RawUrc`] holding up
+/// to `N` bytes of the URC line.
+///
+/// ```
+/// use atat::{AtatUrc, UrcChannel, WithRaw};
+///
+/// # #[derive(Clone)]
+/// # struct Urc;
+/// # impl AtatUrc for Urc {
+/// #     type Response = Self;
+/// #     fn parse(resp: &[u8]) -> Option<Self> {
+/// #         resp.starts_with(b"+CREG").then_some(Self)
+/// #     }
+/// # }
+/// static URC_CHANNEL: UrcChannel<WithRaw<Urc, 64>, 4, 1> = UrcChannel::new();
+///
+/// // URC lines are parsed by the ingress as
+/// let urc = WithRaw::<Urc, 64>::parse(b"+CREG: 5").unwrap();
+/// assert_eq!(urc.raw(), b"+CREG: 5");
+/// ```
+///
+/// [`Ingress`]: crate::Ingress
+pub struct WithRaw<Urc: AtatUrc, const N: usize>(PhantomData<Urc>);
+
+/// A URC along with its raw bytes, see [`WithRaw`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RawUrc<T, const N: usize> {
+    urc: T,
+    raw: Vec<u8, N>,
+    truncated: bool,
+}
+
+impl<T, const N: usize> RawUrc<T, N> {
+    /// The parsed URC.
+    pub fn urc(&self) -> &T {
+        &self.urc
+    }
+
+    /// The raw bytes of the URC line, truncated to `N` bytes.
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Whether the URC line did not fit in `N` bytes.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Split into the parsed URC and its raw bytes.
+    pub fn into_parts(self) -> (T, Vec<u8, N>) {
+        (self.urc, self.raw)
+    }
+}
+
+impl<Urc: AtatUrc, const N: usize> AtatUrc for WithRaw<Urc, N> {
+    type Response = RawUrc<Urc::Response, N>;
+
+    fn parse(resp: &[u8]) -> Option<Self::Response> {
+        let urc = Urc::parse(resp)?;
+        let len = resp.len().min(N);
+        if len < resp.len() {
+            warn!("Raw URC truncated to {} of {} bytes", len, resp.len());
+        }
+        Some(RawUrc {
+            urc,
+            raw: Vec::from_slice(&resp[..len]).unwrap(),
+            truncated: len < resp.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Urc {
+        A,
+        B(u8),
+    }
+
+    impl AtatUrc for Urc {
+        type Response = Self;
+
+        fn parse(_resp: &[u8]) -> Option<Self::Response> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_wakes_on_publish() {
+        static CHANNEL: UrcChannel<Urc, 2, 1> = UrcChannel::new();
+        let mut stream = CHANNEL.stream().unwrap();
+        assert_eq!(None, stream.try_next_urc());
+
+        let publisher = tokio::spawn(async {
+            tokio::task::yield_now().await;
+            CHANNEL.0.immediate_publisher().publish_immediate(Urc::A);
+        });
+
+        assert_eq!(Some(Urc::A), stream.next().await);
+        publisher.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stream_is_cancel_safe() {
+        static CHANNEL: UrcChannel<Urc, 2, 1> = UrcChannel::new();
+        let mut stream = CHANNEL.stream().unwrap();
+
+        // Dropping a pending future must not consume anything
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        {
+            let mut fut = core::pin::pin!(stream.next_urc());
+            assert!(fut.as_mut().poll(&mut cx).is_pending());
+        }
+
+        CHANNEL.0.immediate_publisher().publish_immediate(Urc::B(1));
+        assert_eq!(Poll::Ready(Urc::B(1)), stream.poll_urc(&mut cx));
+        assert!(stream.poll_urc(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn stream_counts_lagged() {
+        static CHANNEL: UrcChannel<Urc, 2, 1> = UrcChannel::new();
+        let mut stream = CHANNEL.stream().unwrap();
+
+        let publisher = CHANNEL.0.immediate_publisher();
+        for i in 0..4 {
+            publisher.publish_immediate(Urc::B(i));
+        }
+
+        assert_eq!(Some(Urc::B(2)), stream.try_next_urc());
+        assert_eq!(2, stream.lagged());
+        assert_eq!(Some(Urc::B(3)), stream.try_next_urc());
+        assert_eq!(None, stream.try_next_urc());
+    }
+
+    #[test]
+    fn wait_urc() {
+        static CHANNEL: UrcChannel<Urc, 2, 1> = UrcChannel::new();
+        let mut stream = CHANNEL.stream().unwrap();
+
+        let timeout = Duration::from_millis(20);
+        CHANNEL.0.immediate_publisher().publish_immediate(Urc::A);
+        assert_eq!(Some(Urc::A), stream.wait_urc(timeout));
+
+        let start = Instant::now();
+        assert_eq!(None, stream.wait_urc(timeout));
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[tokio::test]
+    async fn stream_matching() {
+        static CHANNEL: UrcChannel<Urc, 4, 2> = UrcChannel::new();
+        let mut sockets = CHANNEL.stream().unwrap();
+        let mut network = CHANNEL.stream().unwrap();
+
+        let publisher = CHANNEL.0.immediate_publisher();
+        publisher.publish_immediate(Urc::A);
+        publisher.publish_immediate(Urc::B(1));
+        publisher.publish_immediate(Urc::A);
+
+        let is_b = |urc: &Urc| matches!(urc, Urc::B(_));
+        assert_eq!(Some(Urc::B(1)), sockets.try_next_urc_matching(is_b));
+        assert_eq!(None, sockets.try_next_urc_matching(is_b));
+
+        publisher.publish_immediate(Urc::B(2));
+        assert_eq!(Urc::B(2), sockets.next_urc_matching(is_b).await);
+
+        // Skipped URCs are still queued for the other subscriber
+        assert_eq!(
+            Some(Urc::A),
+            network.try_next_urc_matching(|urc| *urc == Urc::A)
+        );
+        assert_eq!(Some(Urc::B(1)), network.try_next_urc());
+    }
+
+    #[test]
+    fn with_raw() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Creg;
+
+        impl AtatUrc for Creg {
+            type Response = Self;
+
+            fn parse(resp: &[u8]) -> Option<Self::Response> {
+                resp.starts_with(b"+CREG").then_some(Self)
+            }
+        }
+
+        assert_eq!(None, WithRaw::<Creg, 8>::parse(b"+CGREG: 5"));
+
+        let urc = WithRaw::<Creg, 8>::parse(b"+CREG: 5").unwrap();
+        assert_eq!((&Creg, &b"+CREG: 5"[..]), (urc.urc(), urc.raw()));
+        assert!(!urc.is_truncated());
+
+        let urc = WithRaw::<Creg, 8>::parse(b"+CREG: 5,\"9E9A\"").unwrap();
+        assert_eq!(b"+CREG: 5", urc.raw());
+        assert!(urc.is_truncated());
+    }
 }
@@ -0,0 +1,284 @@
+//! Rate limiting of URC's, eg. for a misconfigured modem reporting `+CSQ`
+//! every 100 ms, flooding the URC channel.
+//!
+//! Each [`UrcLimit`] is a token bucket for the URC's starting with a given
+//! prefix: up to `burst` URC's pass at once, and one more is allowed every
+//! `period`. URC's over the limit are coalesced into the next one that
+//! passes, ie. dropped, on the assumption that a later report supersedes
+//! earlier ones. The [`UrcLimiter`] counts both, such that the flood can be
+//! diagnosed.
+//!
+//! The limiter is applied by an [`Ingress`] URC handler, which consumes the
+//! lines over the limit before they are parsed and published:
+//!
+//! ```
+//! use atat::urc_limiter::{UrcLimit, UrcLimiter};
+//! use embassy_time::Duration;
+//!
+//! static LIMITER: UrcLimiter<2> = UrcLimiter::new([
+//!     UrcLimit::new(b"+CSQ", 1, Duration::from_secs(10)),
+//!     UrcLimit::new(b"+CEREG", 4, Duration::from_secs(1)),
+//! ]);
+//!
+//! let mut handler = |line: &[u8]| !LIMITER.admit(line);
+//! // let ingress = Ingress::new(..).with_urc_handler(&mut handler);
+//!
+//! let stats = LIMITER.stats(b"+CSQ").unwrap();
+//! assert_eq!(stats.coalesced, 0);
+//! ```
+//!
+//! [`Ingress`]: crate::Ingress
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_time::{Duration, Instant};
+
+use crate::helpers::LossyStr;
+
+/// Rate limit of the URC's starting with a prefix.
+#[derive(Debug, Clone, Copy)]
+pub struct UrcLimit {
+    prefix: &'static [u8],
+    burst: u8,
+    period: Duration,
+}
+
+impl UrcLimit {
+    /// Allow up to `burst` URC's starting with `prefix` at once, and one more
+    /// every `period`.
+    pub const fn new(prefix: &'static [u8], burst: u8, period: Duration) -> Self {
+        Self {
+            prefix,
+            burst,
+            period,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for UrcLimit {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "UrcLimit {{ prefix: {=[u8]:a}, burst: {=u8}, period: {=u64} ms }}",
+            self.prefix,
+            self.burst,
+            self.period.as_millis()
+        )
+    }
+}
+
+/// Counters of a [`UrcLimit`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UrcStats {
+    /// URC's within the limit
+    pub passed: u32,
+    /// URC's over the limit, that were dropped
+    pub coalesced: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: u8,
+    /// Time of the last refill, `None` until the first URC
+    refilled: Option<Instant>,
+    stats: UrcStats,
+}
+
+impl Bucket {
+    const fn new() -> Self {
+        Self {
+            tokens: 0,
+            refilled: None,
+            stats: UrcStats {
+                passed: 0,
+                coalesced: 0,
+            },
+        }
+    }
+
+    fn refill(&mut self, limit: &UrcLimit, now: Instant) {
+        let Some(refilled) = self.refilled else {
+            self.tokens = limit.burst;
+            self.refilled = Some(now);
+            return;
+        };
+
+        let periods = (now - refilled).as_ticks() / limit.period.as_ticks().max(1);
+        if periods == 0 {
+            return;
+        }
+        let tokens = u64::from(self.tokens) + periods;
+        if tokens >= u64::from(limit.burst) {
+            self.tokens = limit.burst;
+            self.refilled = Some(now);
+        } else {
+            self.tokens = tokens as u8;
+            self.refilled =
+                Some(refilled + Duration::from_ticks(periods * limit.period.as_ticks()));
+        }
+    }
+}
+
+/// Token bucket rate limiter of URC's, by prefix.
+///
+/// The limiter is shared by reference, such that the statistics can be read
+/// while the ingress is running.
+pub struct UrcLimiter<const N: usize> {
+    limits: [UrcLimit; N],
+    buckets: Mutex<CriticalSectionRawMutex, RefCell<[Bucket; N]>>,
+}
+
+impl<const N: usize> UrcLimiter<N> {
+    pub const fn new(limits: [UrcLimit; N]) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::new(RefCell::new([Bucket::new(); N])),
+        }
+    }
+
+    /// Returns whether the URC `line` is within its limit, consuming a token
+    /// if so. URC's not matching any limit always pass, and are not counted.
+    pub fn admit(&self, line: &[u8]) -> bool {
+        let Some(i) = self.position(line) else {
+            return true;
+        };
+        let limit = &self.limits[i];
+
+        self.buckets.lock(|buckets| {
+            let bucket = &mut buckets.borrow_mut()[i];
+            bucket.refill(limit, Instant::now());
+            if bucket.tokens > 0 {
+                bucket.tokens -= 1;
+                bucket.stats.passed = bucket.stats.passed.saturating_add(1);
+                true
+            } else {
+                debug!("Coalescing URC over its limit: {:?}", LossyStr(line));
+                bucket.stats.coalesced = bucket.stats.coalesced.saturating_add(1);
+                false
+            }
+        })
+    }
+
+    /// The counters of the limit for `prefix`, if there is one.
+    pub fn stats(&self, prefix: &[u8]) -> Option<UrcStats> {
+        let i = self.limits.iter().position(|l| l.prefix == prefix)?;
+        Some(self.buckets.lock(|buckets| buckets.borrow()[i].stats))
+    }
+
+    /// Reset the counters of all limits.
+    pub fn reset_stats(&self) {
+        self.buckets.lock(|buckets| {
+            for bucket in buckets.borrow_mut().iter_mut() {
+                bucket.stats = UrcStats::default();
+            }
+        });
+    }
+
+    /// The first limit whose prefix `line` starts with
+    fn position(&self, line: &[u8]) -> Option<usize> {
+        self.limits.iter().position(|l| line.starts_with(l.prefix))
+    }
+}
+
+impl<const N: usize> core::fmt::Debug for UrcLimiter<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UrcLimiter")
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for UrcLimiter<N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "UrcLimiter {{ limits: {}, .. }}", self.limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AtDigester, AtatIngress, Ingress, ResponseSlot, UrcChannel};
+    use embassy_time::Timer;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Urc {
+        SignalQuality,
+        Ring,
+    }
+
+    impl crate::AtatUrc for Urc {
+        type Response = Self;
+
+        fn parse(resp: &[u8]) -> Option<Self::Response> {
+            match resp {
+                b"+CSQ: 19,99" => Some(Self::SignalQuality),
+                b"RING" => Some(Self::Ring),
+                _ => None,
+            }
+        }
+    }
+
+    impl crate::Parser for Urc {
+        fn parse(buf: &[u8]) -> Result<(&[u8], usize), crate::digest::ParseError> {
+            let (_, r) = nom::branch::alt((
+                crate::digest::parser::urc_helper("+CSQ"),
+                crate::digest::parser::urc_helper("RING"),
+            ))(buf)?;
+            Ok(r)
+        }
+    }
+
+    #[tokio::test]
+    async fn token_bucket() {
+        let limiter = UrcLimiter::new([UrcLimit::new(b"+CSQ", 2, Duration::from_millis(50))]);
+
+        assert!(limiter.admit(b"+CSQ: 19,99"));
+        assert!(limiter.admit(b"+CSQ: 19,99"));
+        assert!(!limiter.admit(b"+CSQ: 19,99"));
+        // Not limited
+        assert!(limiter.admit(b"RING"));
+        assert_eq!(
+            limiter.stats(b"+CSQ"),
+            Some(UrcStats {
+                passed: 2,
+                coalesced: 1
+            })
+        );
+        assert_eq!(limiter.stats(b"RING"), None);
+
+        // One token is refilled per period
+        Timer::after(Duration::from_millis(60)).await;
+        assert!(limiter.admit(b"+CSQ: 19,99"));
+        assert!(!limiter.admit(b"+CSQ: 19,99"));
+
+        limiter.reset_stats();
+        assert_eq!(limiter.stats(b"+CSQ"), Some(UrcStats::default()));
+    }
+
+    #[test]
+    fn ingress() {
+        static LIMITER: UrcLimiter<1> =
+            UrcLimiter::new([UrcLimit::new(b"+CSQ", 1, Duration::from_secs(60))]);
+
+        let res_slot = ResponseSlot::<64>::new();
+        let urc_channel = UrcChannel::<Urc, 4, 1>::new();
+        let mut sub = urc_channel.subscribe().unwrap();
+        let mut buf = [0; 64];
+        let mut handler = |line: &[u8]| !LIMITER.admit(line);
+        let mut ingress: Ingress<_, Urc, 64, 4, 1> =
+            Ingress::new(AtDigester::<Urc>::new(), &mut buf, &res_slot, &urc_channel)
+                .with_urc_handler(&mut handler);
+
+        ingress
+            .try_write(b"\r\n+CSQ: 19,99\r\n\r\n+CSQ: 19,99\r\n\r\nRING\r\n")
+            .unwrap();
+
+        assert_eq!(sub.try_next_message_pure(), Some(Urc::SignalQuality));
+        assert_eq!(sub.try_next_message_pure(), Some(Urc::Ring));
+        assert_eq!(sub.try_next_message_pure(), None);
+        assert_eq!(LIMITER.stats(b"+CSQ").unwrap().coalesced, 1);
+    }
+}
@@ -0,0 +1,13 @@
+//! Compile-fail coverage of the diagnostics of `atat_derive`.
+//!
+//! The expected compiler output depends on the compiler version, so these
+//! only run with the `ui-tests` feature, eg. `cargo test -p atat --features
+//! ui-tests --test derive_ui`. After changing a diagnostic, regenerate the
+//! `.stderr` files with `TRYBUILD=overwrite`.
+#![cfg(feature = "ui-tests")]
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,13 @@
+use atat::atat_derive::{AtatCmd, AtatResp};
+
+#[derive(Clone, AtatResp)]
+pub struct NoResponse;
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CFUN", NoResponse)]
+pub struct SetModuleFunctionality {
+    #[at_arg(position = "first")]
+    pub fun: u8,
+}
+
+fn main() {}
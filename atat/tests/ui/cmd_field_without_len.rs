@@ -0,0 +1,19 @@
+use atat::atat_derive::{AtatCmd, AtatResp};
+
+#[derive(Clone, AtatResp)]
+pub struct NoResponse;
+
+#[derive(Clone)]
+pub enum Functionality {
+    Minimum,
+    Full,
+}
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CFUN", NoResponse)]
+pub struct SetModuleFunctionality {
+    #[at_arg(position = 0)]
+    pub fun: Functionality,
+}
+
+fn main() {}
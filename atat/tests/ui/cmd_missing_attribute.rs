@@ -0,0 +1,6 @@
+use atat::atat_derive::AtatCmd;
+
+#[derive(Clone, AtatCmd)]
+pub struct GetSignalQuality;
+
+fn main() {}
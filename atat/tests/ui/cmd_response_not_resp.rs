@@ -0,0 +1,12 @@
+use atat::atat_derive::AtatCmd;
+
+#[derive(Clone)]
+pub struct SignalQuality {
+    pub rssi: u8,
+}
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CSQ", SignalQuality)]
+pub struct GetSignalQuality;
+
+fn main() {}
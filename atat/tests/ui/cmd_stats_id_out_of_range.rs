@@ -0,0 +1,10 @@
+use atat::atat_derive::{AtatCmd, AtatResp};
+
+#[derive(Clone, AtatResp)]
+pub struct NoResponse;
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+COPS?", NoResponse, stats_id = 16)]
+pub struct GetOperator;
+
+fn main() {}
@@ -0,0 +1,10 @@
+use atat::atat_derive::{AtatCmd, AtatResp};
+
+#[derive(Clone, AtatResp)]
+pub struct NoResponse;
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CFUN", NoResponse)]
+pub struct SetModuleFunctionality(u8);
+
+fn main() {}
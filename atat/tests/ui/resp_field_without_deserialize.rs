@@ -0,0 +1,12 @@
+use atat::atat_derive::AtatResp;
+
+#[derive(Clone)]
+pub struct Rssi(u8);
+
+#[derive(Clone, AtatResp)]
+pub struct SignalQuality {
+    #[at_arg(position = 0)]
+    pub rssi: Rssi,
+}
+
+fn main() {}
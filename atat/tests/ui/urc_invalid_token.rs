@@ -0,0 +1,9 @@
+use atat::atat_derive::AtatUrc;
+
+#[derive(Clone, AtatUrc)]
+pub enum Urc {
+    #[at_urc(42)]
+    Ring,
+}
+
+fn main() {}
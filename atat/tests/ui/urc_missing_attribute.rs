@@ -0,0 +1,10 @@
+use atat::atat_derive::AtatUrc;
+
+#[derive(Clone, AtatUrc)]
+pub enum Urc {
+    #[at_urc("RING")]
+    Ring,
+    NoCarrier,
+}
+
+fn main() {}
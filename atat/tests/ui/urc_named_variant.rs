@@ -0,0 +1,9 @@
+use atat::atat_derive::AtatUrc;
+
+#[derive(Clone, AtatUrc)]
+pub enum Urc {
+    #[at_urc("+UUSORD")]
+    SocketDataAvailable { socket: u8, length: usize },
+}
+
+fn main() {}
@@ -1,19 +1,34 @@
 use crate::proc_macro::TokenStream;
 
-use quote::{format_ident, quote};
-use syn::parse_macro_input;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, Error, Ident, Type, TypePath};
 
-use crate::parse::{CmdAttributes, ParseInput};
+use crate::{
+    helpers,
+    parse::{CmdAttributes, HexAttributes, ParseInput, Variant},
+};
 
 pub fn atat_cmd(input: TokenStream) -> TokenStream {
     let ParseInput {
         ident,
         at_cmd,
         generics,
-        variants,
+        mut variants,
         ..
     } = parse_macro_input!(input as ParseInput);
 
+    let Some(at_cmd) = at_cmd else {
+        return Error::new(
+            ident.span(),
+            "missing #[at_cmd(\"<command>\", <Response>)] attribute",
+        )
+        .to_compile_error()
+        .into();
+    };
+    if let Err(e) = helpers::require_named_fields(&variants) {
+        return e.to_compile_error().into();
+    }
+
     let CmdAttributes {
         cmd,
         resp,
@@ -26,7 +41,33 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
         cmd_prefix,
         termination,
         quote_escape_strings,
-    } = at_cmd.expect("missing #[at_cmd(...)] attribute");
+        param_sep,
+        cooldown_ms,
+        stats_id,
+        continuation,
+        resp_prefix,
+        success_code,
+        error_code,
+        length_footer,
+        prefix_field,
+    } = at_cmd;
+
+    // The prefix symbol is not a parameter of the command
+    if let Some(field) = &prefix_field {
+        match variants
+            .iter()
+            .position(|v| v.ident.as_ref() == Some(field))
+        {
+            Some(i) => {
+                variants.remove(i);
+            }
+            None => {
+                return Error::new(field.span(), format!("no field named `{field}`"))
+                    .to_compile_error()
+                    .into();
+            }
+        }
+    }
 
     let ident_str = ident.to_string();
 
@@ -43,6 +84,66 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
         None => quote! {},
     };
 
+    let cooldown = match cooldown_ms {
+        Some(cooldown_ms) => {
+            quote! {
+                const COOLDOWN_MS: Option<u32> = Some(#cooldown_ms);
+            }
+        }
+        None => quote! {},
+    };
+
+    let (stats_id, stats_id_check) = match stats_id {
+        Some(stats_id) => (
+            quote! {
+                const STATS_ID: Option<u8> = Some(#stats_id);
+            },
+            quote! {
+                const _: () = ::core::assert!(
+                    (#stats_id as usize) < atat::COMMAND_STATS_LEN,
+                    "'stats_id' must be below atat::COMMAND_STATS_LEN"
+                );
+            },
+        ),
+        None => (quote! {}, quote! {}),
+    };
+
+    let continuation = match continuation {
+        Some(continuation) => {
+            quote! {
+                const CONTINUATION: Option<&'static [u8]> = Some(#continuation.as_bytes());
+            }
+        }
+        None => quote! {},
+    };
+
+    let resp_prefix = match resp_prefix {
+        Some(resp_prefix) => {
+            quote! {
+                const RESP_PREFIX: Option<&'static [u8]> = Some(#resp_prefix.as_bytes());
+            }
+        }
+        None => quote! {},
+    };
+
+    let success_code = match success_code {
+        Some(success_code) => {
+            quote! {
+                const SUCCESS_CODES: &'static [&'static [u8]] = &[#success_code.as_bytes()];
+            }
+        }
+        None => quote! {},
+    };
+
+    let error_code = match error_code {
+        Some(error_code) => {
+            quote! {
+                const ERROR_CODES: &'static [&'static [u8]] = &[#error_code.as_bytes()];
+            }
+        }
+        None => quote! {},
+    };
+
     let abortable = match abortable {
         Some(abortable) => {
             quote! {
@@ -77,27 +178,96 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
     if quote_escape_strings {
         cmd_len += 2;
     }
+    if prefix_field.is_some() {
+        cmd_len += 4;
+    }
+    // The field lengths account for a single byte separator between
+    // parameters, including those of flattened parameter groups
+    let sep_extra = param_sep.len().saturating_sub(1);
+    let separators = separators(&variants);
 
-    let (field_names, field_names_str): (Vec<_>, Vec<_>) = variants
-        .iter()
-        .map(|f| {
-            let ident = f.ident.clone().unwrap();
-            (ident.clone(), ident.to_string())
-        })
-        .unzip();
+    let serialize_fields = serialize_fields(&variants, &quote! { &mut serde_state });
 
-    let struct_len = crate::len::struct_len(variants, n_fields.checked_sub(1).unwrap_or(n_fields));
+    let wire_format = wire_format(
+        &cmd_prefix,
+        prefix_field.as_ref(),
+        &cmd,
+        value_sep,
+        &param_sep,
+        &termination,
+        &variants,
+    );
 
+    // Commands without parameters serialize to a constant
+    let wire_image = if n_fields == 0 && prefix_field.is_none() {
+        let wire_image = format!("{cmd_prefix}{cmd}{termination}");
+        quote! {
+            const WIRE_IMAGE: Option<&'static [u8]> = Some(#wire_image.as_bytes());
+        }
+    } else {
+        quote! {}
+    };
+
+    // Lifetimes are not in scope of the length constant, and commands generic
+    // over types leave the length to their `AtatLen` implementation
     let ident_len = format_ident!("ATAT_{}_LEN", ident.to_string().to_uppercase());
+    let (len_const, len) = if generics.type_params().next().is_none() {
+        let len = crate::len::struct_len(
+            variants
+                .iter()
+                .cloned()
+                .map(helpers::static_lifetimes)
+                .collect(),
+            n_fields.checked_sub(1).unwrap_or(n_fields),
+        );
+        (
+            quote! { const #ident_len: usize = #len; },
+            quote! { #ident_len },
+        )
+    } else {
+        (quote! {}, quote! { <Self as atat::AtatLen>::LEN })
+    };
+
+    let struct_len = crate::len::struct_len(variants, n_fields.checked_sub(1).unwrap_or(n_fields));
+
+    // Spanned at the response type, to report a type without a `Deserialize`
+    // implementation there
+    let parse_response =
+        quote_spanned! {resp.span()=> atat::derive::parse_response::<#resp>(resp) };
+
+    // A command name prefixed at runtime is assembled on the stack
+    let cmd_name = match &prefix_field {
+        Some(field) => {
+            let name_len = cmd.len() + 4;
+            quote! {
+                let mut cmd_buf = [0u8; #name_len];
+                let cmd = atat::derive::prefixed_cmd(self.#field, #cmd, &mut cmd_buf);
+            }
+        }
+        None => quote! {
+            let cmd = #cmd;
+        },
+    };
+
+    // The footer is checked and split off before parsing the payload
+    let strip_footer = match length_footer {
+        Some(footer) => quote! {
+            let resp = atat::derive::strip_length_footer(resp, #footer.as_bytes())?;
+        },
+        None => quote! {},
+    };
 
     let parse = if let Some(parse) = parse {
         quote! {
             #[inline]
             fn parse(&self, res: Result<&[u8], atat::InternalError>) -> core::result::Result<Self::Response, atat::Error> {
                 match res {
-                    Ok(resp) => #parse(resp).map_err(|e| {
-                        atat::Error::Parse
-                    }),
+                    Ok(resp) => {
+                        #strip_footer
+                        #parse(resp).map_err(|e| {
+                            atat::Error::Parse
+                        })
+                    }
                     Err(e) => Err(e.into())
                 }
             }
@@ -107,9 +277,10 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
             #[inline]
            fn parse(&self, res: Result<&[u8], atat::InternalError>) -> core::result::Result<Self::Response, atat::Error> {
                match res {
-                   Ok(resp) => atat::serde_at::from_slice::<#resp>(resp).map_err(|e| {
-                       atat::Error::Parse
-                   }),
+                   Ok(resp) => {
+                       #strip_footer
+                       #parse_response
+                   }
                    Err(e) => Err(e.into())
                }
            }
@@ -122,16 +293,36 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
             const LEN: usize = #struct_len;
         }
 
-        const #ident_len: usize = #struct_len;
+        #len_const
+
+        #stats_id_check
 
         #[automatically_derived]
         impl #impl_generics atat::AtatCmd for #ident #ty_generics #where_clause {
             type Response = #resp;
 
-            const MAX_LEN: usize = { #ident_len + #cmd_len };
+            const MAX_LEN: usize = { #len + #cmd_len + (#separators) * #sep_extra };
+
+            const WIRE_FORMAT: &'static str = #wire_format;
+
+            const NAME: &'static str = stringify!(#ident);
 
             #timeout
 
+            #cooldown
+
+            #stats_id
+
+            #continuation
+
+            #resp_prefix
+
+            #success_code
+
+            #error_code
+
+            #wire_image
+
             #abortable
 
             #attempts
@@ -140,12 +331,13 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
 
             #[inline]
             fn write(&self, buf: &mut [u8]) -> usize {
-                match atat::serde_at::to_slice(self, #cmd, buf, atat::serde_at::SerializeOptions {
-                    value_sep: #value_sep,
-                    cmd_prefix: #cmd_prefix,
-                    termination: #termination,
-                    quote_escape_strings: #quote_escape_strings
-                }) {
+                #cmd_name
+                let mut options = atat::serde_at::SerializeOptions::default().param_sep(#param_sep);
+                options.value_sep = #value_sep;
+                options.cmd_prefix = #cmd_prefix;
+                options.termination = #termination;
+                options.quote_escape_strings = #quote_escape_strings;
+                match atat::serde_at::to_slice(self, cmd, buf, options) {
                     Ok(s) => s,
                     Err(_) => panic!("Failed to serialize command")
                 }
@@ -170,16 +362,128 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
                     #n_fields,
                 )?;
 
-                #(
-                    atat::serde_at::serde::ser::SerializeStruct::serialize_field(
-                        &mut serde_state,
-                        #field_names_str,
-                        &self.#field_names,
-                    )?;
-                )*
+                #(#serialize_fields)*
 
                 atat::serde_at::serde::ser::SerializeStruct::end(serde_state)
             }
         }
     })
 }
+
+/// Serialize each field spanned at its type, so a type without a `Serialize`
+/// implementation is reported at the field. Fields marked with
+/// `#[at_arg(flatten)]` serialize their parameters in place. `state` is the
+/// `&mut impl SerializeStruct` the fields are serialized to.
+pub fn serialize_fields(
+    variants: &[Variant],
+    state: &proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
+    variants
+        .iter()
+        .map(|f| {
+            let ident = f.ident.clone().unwrap();
+            if is_flatten(f) {
+                return quote_spanned! {f.ty.span()=>
+                    atat::derive::serialize_flattened(#state, &self.#ident)?;
+                };
+            }
+            let ident_str = ident.to_string();
+            let value = field_value(&ident, f);
+            quote_spanned! {f.ty.span()=>
+                atat::derive::serialize_field(#state, #ident_str, #value)?;
+            }
+        })
+        .collect()
+}
+
+/// Number of separators between the parameters of `variants`, including
+/// those within flattened parameter groups.
+pub fn separators(variants: &[Variant]) -> proc_macro2::TokenStream {
+    let top_level = variants.len().saturating_sub(1);
+    let nested = variants
+        .iter()
+        .filter(|f| is_flatten(f))
+        .filter_map(|f| f.ty.as_ref());
+    quote! { #top_level #(+ <#nested as atat::derive::AtatParams>::SEPARATORS)* }
+}
+
+fn is_flatten(field: &Variant) -> bool {
+    field.attrs.at_arg.as_ref().is_some_and(|a| a.flatten)
+}
+
+/// Expression serializing a field, applying any `#[at_arg(hex(..))]`
+/// formatting overrides on top of the `HexStr` value.
+fn field_value(ident: &Ident, field: &Variant) -> proc_macro2::TokenStream {
+    let Some(HexAttributes {
+        prefix,
+        caps,
+        group,
+        sep,
+    }) = field.attrs.at_arg.as_ref().and_then(|a| a.hex.clone())
+    else {
+        return quote_spanned! {field.ty.span()=> &self.#ident };
+    };
+
+    let prefix = prefix.map(|v| quote! { add_0x_with_encoding: #v, });
+    let caps = caps.map(|v| quote! { hex_in_caps: #v, });
+    let group = group.map(|v| quote! { delimiter_after_nibble_count: #v, });
+    let sep = sep.map(|v| quote! { delimiter: #v, });
+
+    quote! {
+        &atat::serde_at::HexStr {
+            #prefix
+            #caps
+            #group
+            #sep
+            ..::core::clone::Clone::clone(&self.#ident)
+        }
+    }
+}
+
+/// Describe the serialized command, eg. `AT+CFUN=<fun>[,<rst>]\r\n`, with
+/// fields in position order. Optional fields are wrapped in brackets, and a
+/// prefix symbol chosen at runtime is named like a field, eg. `AT<ns>XCMD`.
+fn wire_format(
+    cmd_prefix: &str,
+    prefix_field: Option<&Ident>,
+    cmd: &str,
+    value_sep: bool,
+    param_sep: &str,
+    termination: &str,
+    variants: &[Variant],
+) -> String {
+    let mut format = match prefix_field {
+        Some(field) => format!("{cmd_prefix}<{field}>{cmd}"),
+        None => format!("{cmd_prefix}{cmd}"),
+    };
+    for (i, variant) in variants.iter().enumerate() {
+        let sep = match i {
+            0 if value_sep => "=",
+            0 => "",
+            _ => param_sep,
+        };
+        let name = variant
+            .ident
+            .as_ref()
+            .map_or_else(|| i.to_string(), ToString::to_string);
+        if is_flatten(variant) {
+            format.push_str(&format!("{sep}<{name}..>"));
+        } else if is_option(variant.ty.as_ref()) {
+            format.push_str(&format!("[{sep}<{name}>]"));
+        } else {
+            format.push_str(&format!("{sep}<{name}>"));
+        }
+    }
+    format.push_str(&termination.escape_debug().to_string());
+    format
+}
+
+fn is_option(ty: Option<&Type>) -> bool {
+    match ty {
+        Some(Type::Path(TypePath { path, .. })) => path
+            .segments
+            .last()
+            .map_or(false, |segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
@@ -1,7 +1,10 @@
 use crate::parse::Variant;
-use proc_macro2::{Literal, Span, TokenStream};
-use quote::{format_ident, quote};
-use syn::{parse_quote, GenericParam, Generics, Ident, Lifetime, LifetimeParam, TypeParamBound};
+use proc_macro2::{Group, Literal, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{
+    parse_quote, spanned::Spanned, GenericParam, Generics, Ident, Lifetime, LifetimeParam,
+    TypeParamBound,
+};
 
 /// Adds a single lifetime symbol eg. <'a>
 #[inline]
@@ -61,6 +64,47 @@ pub fn add_type_parameter_bound(
         .push(where_type.into());
 }
 
+/// Replace the lifetimes in the type of a field with `'static`, to refer to
+/// the type outside of the generics of the struct.
+pub fn static_lifetimes(mut field: Variant) -> Variant {
+    field.ty = field
+        .ty
+        .map(|ty| syn::parse2(static_lifetime_tokens(ty.to_token_stream())).unwrap_or(ty));
+    field
+}
+
+fn static_lifetime_tokens(tokens: TokenStream) -> TokenStream {
+    let mut lifetime = false;
+    tokens
+        .into_iter()
+        .map(|tt| {
+            let is_quote = matches!(&tt, TokenTree::Punct(p) if p.as_char() == '\'');
+            let tt = match tt {
+                TokenTree::Group(g) => {
+                    let mut group = Group::new(g.delimiter(), static_lifetime_tokens(g.stream()));
+                    group.set_span(g.span());
+                    TokenTree::Group(group)
+                }
+                TokenTree::Ident(i) if lifetime => TokenTree::Ident(Ident::new("static", i.span())),
+                tt => tt,
+            };
+            lifetime = is_quote;
+            tt
+        })
+        .collect()
+}
+
+/// Reject tuple structs, whose fields have no name to serialize them by.
+pub fn require_named_fields(variants: &[Variant]) -> syn::Result<()> {
+    match variants.iter().find(|f| f.ident.is_none()) {
+        Some(f) => Err(syn::Error::new(
+            f.ty.span(),
+            "tuple structs are unsupported, use a struct with named fields",
+        )),
+        None => Ok(()),
+    }
+}
+
 pub fn deserialize_struct(ident: &Ident, variants: &[Variant], generics: &Generics) -> TokenStream {
     let ident_str = ident.to_string();
 
@@ -72,6 +116,18 @@ pub fn deserialize_struct(ident: &Ident, variants: &[Variant], generics: &Generi
         })
         .unzip();
     let field_types: Vec<_> = variants.iter().map(|f| f.ty.clone()).collect();
+    // Deserialize each field spanned at its type, so a type without a
+    // `Deserialize` implementation is reported at the field
+    let next_elements = field_types.iter().map(|ty| {
+        quote_spanned! {ty.span()=>
+            atat::derive::next_element::<_, #ty>(&mut seq)?
+        }
+    });
+    let next_values = field_types.iter().map(|ty| {
+        quote_spanned! {ty.span()=>
+            atat::derive::next_value::<_, #ty>(&mut map)?
+        }
+    });
 
     let (anon_field_ind, anon_field): (Vec<usize>, Vec<Ident>) = field_names
         .iter()
@@ -207,7 +263,7 @@ pub fn deserialize_struct(ident: &Ident, variants: &[Variant], generics: &Generi
             {
                 #(
                     let #anon_field =
-                        atat::serde_at::serde::de::SeqAccess::next_element::<#field_types>(&mut seq)?.ok_or_else(||atat::serde_at::serde::de::Error::invalid_length(
+                        #next_elements.ok_or_else(||atat::serde_at::serde::de::Error::invalid_length(
                             #anon_field_ind,
                             &#invalid_len_err,
                         ))?;
@@ -242,9 +298,7 @@ pub fn deserialize_struct(ident: &Ident, variants: &[Variant], generics: &Generi
                                         ),
                                     );
                                 }
-                                #anon_field = Some(
-                                    atat::serde_at::serde::de::MapAccess::next_value::<#field_types>(&mut map)?
-                                );
+                                #anon_field = Some(#next_values);
                             }
                         )*
                         _ => {
@@ -1,6 +1,6 @@
 use crate::proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Ident, Type};
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, Error, Ident, Type};
 
 use crate::parse::{parse_field_attr, ArgAttributes, FieldAttributes, ParseInput, Variant};
 
@@ -20,8 +20,10 @@ pub fn struct_len(variants: Vec<Variant>, init_len: usize) -> proc_macro2::Token
                 quote! { #len }
             }
         } else {
+            // Spanned at the field, to report a type without an `AtatLen`
+            // implementation there
             let ty = field.ty.unwrap();
-            quote! { <#ty as atat::AtatLen>::LEN }
+            quote_spanned! {ty.span()=> <#ty as atat::AtatLen>::LEN }
         };
         struct_len = quote! {
             #len + #struct_len
@@ -66,15 +68,17 @@ pub fn enum_len(
                     ..
                 }) = parse_field_attr(&field.attrs)
                 {
-                    assert!(value.is_none(), "value is not allowed in this position");
-                    assert!(
-                        position.is_none(),
-                        "position is not allowed in this position"
-                    );
+                    if value.is_some() || position.is_some() {
+                        return Error::new(
+                            field.span(),
+                            "value and position are not allowed on variant fields",
+                        )
+                        .to_compile_error();
+                    }
                     quote! { #len }
                 } else {
                     let ty = &field.ty;
-                    quote! { <#ty as atat::AtatLen>::LEN }
+                    quote_spanned! {ty.span()=> <#ty as atat::AtatLen>::LEN }
                 };
                 fields_len = quote! {
                     #fields_len + #field_len + 1
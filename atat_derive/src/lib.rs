@@ -1,14 +1,15 @@
 //! Derive crate for ATAT
 //!
 //! This crate provides derive macros for automatically deriving
-//! [`atat::AtatCmd`], [`atat::AtatResp`], [`atat::AtatUrc`], [`atat::AtatEnum`]
-//! and [`atat::AtatLen`]
+//! [`atat::AtatCmd`], [`atat::AtatResp`], [`atat::AtatUrc`], [`atat::AtatEnum`],
+//! [`atat::AtatLen`] and [`atat::derive::AtatParams`]
 //!
 //! [`atat::AtatCmd`]: ../atat/trait.AtatCmd.html
 //! [`atat::AtatResp`]: ../atat/trait.AtatResp.html
 //! [`atat::AtatUrc`]: ../atat/trait.AtatUrc.html
 //! [`atat::AtatEnum`]: ../atat/trait.AtatEnum.html
 //! [`atat::AtatLen`]: ../atat/derive/trait.AtatLen.html
+//! [`atat::derive::AtatParams`]: ../atat/derive/trait.AtatParams.html
 //!
 //! # Examples
 //!
@@ -43,6 +44,7 @@ mod cmd;
 mod enum_;
 mod helpers;
 mod len;
+mod params;
 mod parse;
 mod resp;
 mod urc;
@@ -51,8 +53,14 @@ use crate::proc_macro::TokenStream;
 
 /// Automatically derive [`atat::AtatResp`] trait
 ///
+/// ### Container attribute (`#[at_resp(..)]`)
+/// - `validate`: **function** Function checking the parsed response, used as
+///    [`atat::AtatResp::validate`]. The passed function needs to have a
+///    signature `fn(&mut Self) -> Result<(), atat::Error>`
+///
 /// [`atat::AtatResp`]: ../atat/trait.AtatResp.html
-#[proc_macro_derive(AtatResp, attributes(at_arg))]
+/// [`atat::AtatResp::validate`]: ../atat/trait.AtatResp.html#method.validate
+#[proc_macro_derive(AtatResp, attributes(at_resp, at_arg))]
 pub fn derive_atat_resp(input: TokenStream) -> TokenStream {
     resp::atat_resp(input)
 }
@@ -68,6 +76,21 @@ pub fn derive_atat_resp(input: TokenStream) -> TokenStream {
 /// The first argument is required, and must be either a string or a byte
 /// literal, specifying the URC token to match for.
 ///
+/// Alternatively, `#[at_urc(nested)]` on a variant with a single field of a
+/// type implementing `AtatUrc` (with `Response = Self`) and `atat::Parser`,
+/// delegates to the URC tokens of that type. This allows composing the URC
+/// enum of a device crate from the URCs of a base crate:
+///
+/// ```ignore
+/// #[derive(Clone, AtatUrc)]
+/// pub enum Urc {
+///     #[at_urc(nested)]
+///     Cellular(cellular::Urc),
+///     #[at_urc("+QIND")]
+///     Qind(Qind),
+/// }
+/// ```
+///
 /// Allowed optionals for `at_urc` are:
 /// - `parse`: **function** Function that should be used to parse for the URC
 ///    instead of using default `atat::digest::parser::urc_helper` function. The
@@ -158,7 +181,33 @@ pub fn derive_atat_enum(input: TokenStream) -> TokenStream {
 /// Allowed options are:
 /// - `timeout_ms`: **integer** The maximum timeout in milliseconds of the
 ///   command
-/// - `abortable`: **bool** Whether or not the command can be aborted
+/// - `cooldown_ms`: **integer** Quiet time in milliseconds after the
+///   command, overriding the client's `cmd_cooldown`. Useful for commands
+///   like `AT+CFUN=1`, after which the modem needs time to settle.
+/// - `stats_id`: **integer** Id keying the round-trip time statistics of the
+///   command, eg. `stats_id = 3`, below `atat::COMMAND_STATS_LEN`. See
+///   `atat::AtatCmd::STATS_ID`.
+/// - `continuation`: **string** Token to send when the device pauses a
+///   paginated response, eg. `continuation = "\r"`. See
+///   `atat::AtDigester::with_custom_continuation`.
+/// - `resp_prefix`: **string** Prefix of the information response, eg.
+///   `resp_prefix = "+CSQ:"`. Responses to the command not starting with it
+///   fail with `atat::Error::Desync`.
+/// - `success_code`: **string** Final result code completing the command
+///   besides `OK`, eg. `success_code = "SEND OK"`. See
+///   `atat::AtatCmd::SUCCESS_CODES`.
+/// - `error_code`: **string** Final result code failing the command besides
+///   `ERROR`, eg. `error_code = "SEND FAIL"`. See
+///   `atat::AtatCmd::ERROR_CODES`.
+/// - `length_footer`: **string** Prefix of a footer line following the
+///   payload of the response and holding its length, eg.
+///   `length_footer = "+XLEN:"` for `+XLEN: <n>` before `OK`. The footer is
+///   checked against the payload and split off before parsing, failing with
+///   `atat::Error::InvalidResponse` on a mismatch. Only length footers are
+///   checked: footers holding a checksum, eg. a CRC of the payload, need a
+///   custom `parse` function.
+/// - `abortable`: **bool** Whether or not the command can be aborted, eg.
+///   when it times out (default false)
 /// - `value_sep`: **bool** Disable the seperator between the command and any
 ///   parameters (default true). Useful to create "fixed" commands, eg.
 ///   `#[at_cmd("+UDCONF=1", NoResponse, value_sep = false)]`.
@@ -168,6 +217,13 @@ pub fn derive_atat_enum(input: TokenStream) -> TokenStream {
 ///   (default '\r\n'). Can also be set to '' (empty).
 /// - `quote_escape_strings`: **bool** Whether to escape strings in commands
 ///   (default true).
+/// - `param_sep`: **string** Overwrite the separator between parameters
+///   (default ','). Useful for vendor command sets expecting eg. `"; "`.
+/// - `prefix_field`: **field** Name of a `char` field holding the prefix
+///   symbol of the command, eg. `prefix_field = namespace` for
+///   `#[at_cmd("XYZCMD", ..)]` writes `AT+XYZCMD` or `AT*XYZCMD` depending on
+///   the field, for firmwares namespacing commands differently. The field is
+///   not a parameter of the command.
 /// - `parse`: **function** Function that should be used to parse the response
 ///    instead of using default `atat::serde_at::from_slice` function. The
 ///    passed functions needs to have a signature `Result<Response, E>` where
@@ -182,6 +238,19 @@ pub fn derive_atat_enum(input: TokenStream) -> TokenStream {
 ///   string. (eg. for command `AT+CMD=a,b`, field `a` would have `position = 1`
 ///   and field `b` would have `position = 2`) (defaults to order of the fields
 ///   in the struct)
+/// - hex: **list** Override the formatting of a `HexStr` field when
+///   serializing, eg. `#[at_arg(hex(prefix = true, caps = false, group = 2,
+///   sep = ':'))]`. Options left out keep the value of the field.
+///   - prefix: **bool** Add `0x` to the encoding
+///   - caps: **bool** Serialize the hex in capital letters
+///   - group: **integer** Number of nibbles between delimiters
+///   - sep: **char** Delimiter between groups of nibbles
+/// - flatten: Serialize the parameters of a field deriving [`AtatParams`] in
+///   place, eg. an APN, username and password shared by several commands.
+///   The group takes the place of the field in the position order, with its
+///   own fields in their position order.
+///
+/// [`AtatParams`]: derive.AtatParams.html
 #[proc_macro_derive(AtatCmd, attributes(at_cmd, at_arg))]
 pub fn derive_atat_cmd(input: TokenStream) -> TokenStream {
     cmd::atat_cmd(input)
@@ -196,3 +265,39 @@ pub fn derive_atat_cmd(input: TokenStream) -> TokenStream {
 pub fn derive_atat_len(input: TokenStream) -> TokenStream {
     len::atat_len(input)
 }
+
+/// Automatically derive [`atat::derive::AtatParams`] and [`atat::AtatLen`]
+/// for a group of parameters, flattened into the parameter list of commands
+/// with `#[at_arg(flatten)]`
+///
+/// The fields take the same `#[at_arg(..)]` attributes as the fields of
+/// [`AtatCmd`].
+///
+/// ```ignore
+/// // Serializing `SetApn`, results in `AT+UPSD=<profile>,<apn>,<username>,<password>\r\n`
+/// #[derive(Clone, AtatParams)]
+/// pub struct Credentials<'a> {
+///     #[at_arg(len = 64)]
+///     pub apn: &'a str,
+///     #[at_arg(len = 32)]
+///     pub username: &'a str,
+///     #[at_arg(len = 32)]
+///     pub password: &'a str,
+/// }
+///
+/// #[derive(Clone, AtatCmd)]
+/// #[at_cmd("+UPSD", NoResponse)]
+/// pub struct SetApn<'a> {
+///     pub profile: u8,
+///     #[at_arg(flatten)]
+///     pub credentials: Credentials<'a>,
+/// }
+/// ```
+///
+/// [`atat::derive::AtatParams`]: ../atat/derive/trait.AtatParams.html
+/// [`atat::AtatLen`]: ../atat/derive/trait.AtatLen.html
+/// [`AtatCmd`]: derive.AtatCmd.html
+#[proc_macro_derive(AtatParams, attributes(at_arg))]
+pub fn derive_atat_params(input: TokenStream) -> TokenStream {
+    params::atat_params(input)
+}
@@ -0,0 +1,57 @@
+use crate::proc_macro::TokenStream;
+
+use quote::quote;
+use syn::parse_macro_input;
+
+use crate::{
+    cmd::{separators, serialize_fields},
+    helpers,
+    len::struct_len,
+    parse::ParseInput,
+};
+
+pub fn atat_params(input: TokenStream) -> TokenStream {
+    let ParseInput {
+        ident,
+        generics,
+        variants,
+        ..
+    } = parse_macro_input!(input as ParseInput);
+
+    if let Err(e) = helpers::require_named_fields(&variants) {
+        return e.to_compile_error().into();
+    }
+
+    let n_fields = variants.len();
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let serialize_fields = serialize_fields(&variants, &quote! { serde_state });
+    let separators = separators(&variants);
+
+    let struct_len = struct_len(variants, n_fields.checked_sub(1).unwrap_or(n_fields));
+
+    TokenStream::from(quote! {
+        #[automatically_derived]
+        impl #impl_generics atat::AtatLen for #ident #ty_generics #where_clause {
+            const LEN: usize = #struct_len;
+        }
+
+        #[automatically_derived]
+        impl #impl_generics atat::derive::AtatParams for #ident #ty_generics #where_clause {
+            const SEPARATORS: usize = #separators;
+
+            #[inline]
+            fn serialize_params<S>(
+                &self,
+                serde_state: &mut S,
+            ) -> core::result::Result<(), S::Error>
+            where
+                S: atat::serde_at::serde::ser::SerializeStruct,
+            {
+                #(#serialize_fields)*
+                Ok(())
+            }
+        }
+    })
+}
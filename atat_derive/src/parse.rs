@@ -1,5 +1,5 @@
-use proc_macro2::Span;
 use syn::parse::{Error, Parse, ParseStream, Result};
+use syn::spanned::Spanned;
 use syn::{
     Attribute, Data, DataEnum, DataStruct, DeriveInput, Expr, ExprLit, ExprPath, Fields, Generics,
     Ident, Lit, LitByteStr, Path, Type,
@@ -10,6 +10,7 @@ pub struct ParseInput {
     pub ident: Ident,
     pub generics: Generics,
     pub at_cmd: Option<CmdAttributes>,
+    pub at_resp: Option<RespAttributes>,
     pub at_enum: Option<EnumAttributes>,
     pub variants: Vec<Variant>,
 }
@@ -28,7 +29,22 @@ pub struct CmdAttributes {
     pub cmd_prefix: String,
     pub termination: String,
     pub quote_escape_strings: bool,
+    pub param_sep: String,
+    pub cooldown_ms: Option<u32>,
+    pub stats_id: Option<u8>,
+    pub continuation: Option<String>,
+    pub resp_prefix: Option<String>,
+    pub success_code: Option<String>,
+    pub error_code: Option<String>,
+    pub length_footer: Option<String>,
+    pub prefix_field: Option<Ident>,
 }
+/// Parsed attributes of `#[at_resp(..)]`
+#[derive(Clone, Default)]
+pub struct RespAttributes {
+    pub validate: Option<Path>,
+}
+
 /// Parsed attributes of `#[at_arg(..)]`
 #[derive(Clone)]
 pub struct ArgAttributes {
@@ -36,13 +52,28 @@ pub struct ArgAttributes {
     pub position: Option<usize>,
     pub len: Option<usize>,
     pub default: bool,
+    pub hex: Option<HexAttributes>,
+    pub flatten: bool,
+}
+
+/// Parsed contents of `#[at_arg(hex(..))]`, overriding the formatting of a
+/// `HexStr` field when serializing
+#[derive(Clone, Default)]
+pub struct HexAttributes {
+    pub prefix: Option<bool>,
+    pub caps: Option<bool>,
+    pub group: Option<usize>,
+    pub sep: Option<char>,
 }
 
 /// Parsed attributes of `#[at_urc(..)]`
 #[derive(Clone)]
 pub struct UrcAttributes {
-    pub code: LitByteStr,
+    /// URC token, absent for `nested` variants
+    pub code: Option<LitByteStr>,
     pub parse: Option<Path>,
+    /// Delegate to the `AtatUrc` implementation of the variant field
+    pub nested: bool,
 }
 
 /// Parsed attributes of `#[at_enum(..)]`
@@ -126,8 +157,11 @@ fn sorted_variants(data: Data) -> Result<Vec<Variant>> {
                 ))
             })
             .collect::<Result<Vec<(usize, Variant)>>>()?,
-        Data::Union(_) => {
-            return Err(Error::new(Span::call_site(), "union types are unsupported"));
+        Data::Union(data) => {
+            return Err(Error::new(
+                data.union_token.span(),
+                "union types are unsupported",
+            ));
         }
     };
 
@@ -163,6 +197,8 @@ impl Parse for ArgAttributes {
             position: None,
             len: None,
             default: false,
+            hex: None,
+            flatten: false,
         };
 
         while {
@@ -171,10 +207,10 @@ impl Parse for ArgAttributes {
                     match name_value.value.clone() {
                         Expr::Lit(ExprLit {
                             lit: Lit::Int(v), ..
-                        }) => attrs.value = Some(v.base10_parse().unwrap()),
+                        }) => attrs.value = Some(v.base10_parse()?),
                         _ => {
                             return Err(Error::new(
-                                Span::call_site(),
+                                name_value.value.span(),
                                 "value argument must be an integer",
                             ))
                         }
@@ -184,10 +220,10 @@ impl Parse for ArgAttributes {
                     match name_value.value.clone() {
                         Expr::Lit(ExprLit {
                             lit: Lit::Int(v), ..
-                        }) => attrs.position = Some(v.base10_parse().unwrap()),
+                        }) => attrs.position = Some(v.base10_parse()?),
                         _ => {
                             return Err(Error::new(
-                                Span::call_site(),
+                                name_value.value.span(),
                                 "position argument must be a positive integer",
                             ))
                         }
@@ -197,10 +233,10 @@ impl Parse for ArgAttributes {
                     match name_value.value.clone() {
                         Expr::Lit(ExprLit {
                             lit: Lit::Int(v), ..
-                        }) => attrs.len = Some(v.base10_parse().unwrap()),
+                        }) => attrs.len = Some(v.base10_parse()?),
                         _ => {
                             return Err(Error::new(
-                                Span::call_site(),
+                                name_value.value.span(),
                                 "len argument must be a positive integer",
                             ))
                         }
@@ -208,14 +244,23 @@ impl Parse for ArgAttributes {
                 }
                 syn::Meta::NameValue(name_value) if name_value.path.is_ident("default") => {
                     return Err(Error::new(
-                        Span::call_site(),
+                        name_value.span(),
                         "default does not have a value. Eg #[at_arg(default)]",
                     ))
                 }
                 syn::Meta::Path(path) if path.is_ident("default") => {
                     attrs.default = true;
                 }
-                _ => return Err(Error::new(Span::call_site(), "unknown argument!")),
+                syn::Meta::Path(path) if path.is_ident("flatten") => {
+                    attrs.flatten = true;
+                }
+                syn::Meta::List(list) if list.path.is_ident("hex") => {
+                    attrs.hex = Some(list.parse_args()?);
+                }
+                meta => return Err(Error::new(
+                    meta.span(),
+                    "unknown argument! Expected value, position, len, default, hex(..) or flatten",
+                )),
             }
 
             input.parse::<syn::token::Comma>().is_ok()
@@ -225,20 +270,69 @@ impl Parse for ArgAttributes {
     }
 }
 
+impl Parse for HexAttributes {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = Self::default();
+
+        while !input.is_empty() {
+            let name_value = input.parse::<syn::MetaNameValue>()?;
+            let lit = match &name_value.value {
+                Expr::Lit(ExprLit { lit, .. }) => lit.clone(),
+                value => return Err(Error::new(value.span(), "hex arguments must be literals")),
+            };
+            match lit {
+                Lit::Bool(v) if name_value.path.is_ident("prefix") => attrs.prefix = Some(v.value),
+                Lit::Bool(v) if name_value.path.is_ident("caps") => attrs.caps = Some(v.value),
+                Lit::Int(v) if name_value.path.is_ident("group") => {
+                    attrs.group = Some(v.base10_parse()?);
+                }
+                Lit::Char(v) if name_value.path.is_ident("sep") => attrs.sep = Some(v.value()),
+                _ => {
+                    return Err(Error::new(
+                        name_value.span(),
+                        "invalid hex argument! Expected prefix = bool, caps = bool, group = integer or sep = char",
+                    ))
+                }
+            }
+
+            if input.parse::<syn::token::Comma>().is_err() {
+                break;
+            }
+        }
+
+        Ok(attrs)
+    }
+}
+
 impl Parse for UrcAttributes {
     fn parse(input: ParseStream) -> Result<Self> {
-        let code = match input.parse::<syn::Lit>() {
-            Ok(Lit::ByteStr(b)) => b,
-            Ok(Lit::Str(s)) => LitByteStr::new(s.value().as_bytes(), input.span()),
-            _ => {
+        if input.peek(Ident) {
+            let ident = input.parse::<Ident>()?;
+            if ident != "nested" || !input.is_empty() {
                 return Err(Error::new(
-                    input.span(),
-                    "expected string value for `at_urc`",
-                ))
+                    ident.span(),
+                    "expected string value or `nested` for `at_urc`",
+                ));
             }
+            return Ok(Self {
+                code: None,
+                parse: None,
+                nested: true,
+            });
+        }
+
+        let span = input.span();
+        let code = match input.parse::<syn::Lit>() {
+            Ok(Lit::ByteStr(b)) => b,
+            Ok(Lit::Str(s)) => LitByteStr::new(s.value().as_bytes(), s.span()),
+            _ => return Err(Error::new(span, "expected string value for `at_urc`")),
         };
 
-        let mut at_urc = Self { code, parse: None };
+        let mut at_urc = Self {
+            code: Some(code),
+            parse: None,
+            nested: false,
+        };
 
         while input.parse::<syn::token::Comma>().is_ok() {
             let optional = input.parse::<syn::MetaNameValue>()?;
@@ -247,7 +341,7 @@ impl Parse for UrcAttributes {
                     Expr::Path(ExprPath { path, .. }) => {
                         at_urc.parse = Some(path);
                     }
-                    _ => return Err(Error::new(input.span(), "expected function for 'parse'")),
+                    value => return Err(Error::new(value.span(), "expected function for 'parse'")),
                 }
             }
         }
@@ -256,6 +350,34 @@ impl Parse for UrcAttributes {
     }
 }
 
+impl Parse for RespAttributes {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut at_resp = Self::default();
+
+        loop {
+            let optional = input.parse::<syn::MetaNameValue>()?;
+            if optional.path.is_ident("validate") {
+                match optional.value {
+                    Expr::Path(ExprPath { path, .. }) => {
+                        at_resp.validate = Some(path);
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected function for 'validate'",
+                        ))
+                    }
+                }
+            }
+            if input.parse::<syn::token::Comma>().is_err() {
+                break;
+            }
+        }
+
+        Ok(at_resp)
+    }
+}
+
 impl Parse for CmdAttributes {
     fn parse(input: ParseStream) -> Result<Self> {
         let cmd = input.parse::<syn::LitStr>()?;
@@ -274,6 +396,15 @@ impl Parse for CmdAttributes {
             cmd_prefix: String::from("AT"),
             termination: String::from("\r\n"),
             quote_escape_strings: true,
+            param_sep: String::from(","),
+            cooldown_ms: None,
+            stats_id: None,
+            continuation: None,
+            resp_prefix: None,
+            success_code: None,
+            error_code: None,
+            length_footer: None,
+            prefix_field: None,
         };
 
         while input.parse::<syn::token::Comma>().is_ok() {
@@ -283,25 +414,123 @@ impl Parse for CmdAttributes {
                     Expr::Lit(ExprLit {
                         lit: Lit::Int(v), ..
                     }) => {
-                        at_cmd.timeout_ms = Some(v.base10_parse().unwrap());
+                        at_cmd.timeout_ms = Some(v.base10_parse()?);
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected integer value for 'timeout_ms'",
                         ))
                     }
                 }
+            } else if optional.path.is_ident("cooldown_ms") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(v), ..
+                    }) => {
+                        at_cmd.cooldown_ms = Some(v.base10_parse()?);
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected integer value for 'cooldown_ms'",
+                        ))
+                    }
+                }
+            } else if optional.path.is_ident("stats_id") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(v), ..
+                    }) => {
+                        at_cmd.stats_id = Some(v.base10_parse()?);
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected integer value for 'stats_id'",
+                        ))
+                    }
+                }
+            } else if optional.path.is_ident("continuation") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(v), ..
+                    }) => {
+                        at_cmd.continuation = Some(v.value());
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected string value for 'continuation'",
+                        ))
+                    }
+                }
+            } else if optional.path.is_ident("resp_prefix") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(v), ..
+                    }) => {
+                        at_cmd.resp_prefix = Some(v.value());
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected string value for 'resp_prefix'",
+                        ))
+                    }
+                }
+            } else if optional.path.is_ident("length_footer") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(v), ..
+                    }) => {
+                        at_cmd.length_footer = Some(v.value());
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected string value for 'length_footer'",
+                        ))
+                    }
+                }
+            } else if optional.path.is_ident("success_code") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(v), ..
+                    }) => {
+                        at_cmd.success_code = Some(v.value());
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected string value for 'success_code'",
+                        ))
+                    }
+                }
+            } else if optional.path.is_ident("error_code") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(v), ..
+                    }) => {
+                        at_cmd.error_code = Some(v.value());
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected string value for 'error_code'",
+                        ))
+                    }
+                }
             } else if optional.path.is_ident("attempts") {
                 match optional.value {
                     Expr::Lit(ExprLit {
                         lit: Lit::Int(v), ..
                     }) => {
-                        at_cmd.attempts = Some(v.base10_parse().unwrap());
+                        at_cmd.attempts = Some(v.base10_parse()?);
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected integer value for 'attempts'",
                         ))
                     }
@@ -313,11 +542,23 @@ impl Parse for CmdAttributes {
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected function for 'parse'",
                         ))
                     }
                 }
+            } else if optional.path.is_ident("prefix_field") {
+                match optional.value {
+                    Expr::Path(ExprPath { path, .. }) if path.get_ident().is_some() => {
+                        at_cmd.prefix_field = path.get_ident().cloned();
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected field name for 'prefix_field'",
+                        ))
+                    }
+                }
             } else if optional.path.is_ident("reattempt_on_parse_err") {
                 match optional.value {
                     Expr::Lit(ExprLit {
@@ -327,7 +568,7 @@ impl Parse for CmdAttributes {
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected bool value for 'reattempt_on_parse_err'",
                         ))
                     }
@@ -341,7 +582,7 @@ impl Parse for CmdAttributes {
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected bool value for 'abortable'",
                         ))
                     }
@@ -355,7 +596,7 @@ impl Parse for CmdAttributes {
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected bool value for 'value_sep'",
                         ))
                     }
@@ -369,7 +610,7 @@ impl Parse for CmdAttributes {
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected string value for 'cmd_prefix'",
                         ))
                     }
@@ -383,7 +624,7 @@ impl Parse for CmdAttributes {
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected string value for 'termination'",
                         ))
                     }
@@ -397,11 +638,33 @@ impl Parse for CmdAttributes {
                     }
                     _ => {
                         return Err(Error::new(
-                            Span::call_site(),
+                            optional.value.span(),
                             "expected bool value for 'quote_escape_strings'",
                         ))
                     }
                 }
+            } else if optional.path.is_ident("param_sep") {
+                match optional.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(v), ..
+                    }) => {
+                        at_cmd.param_sep = v.value();
+                    }
+                    _ => {
+                        return Err(Error::new(
+                            optional.value.span(),
+                            "expected string value for 'param_sep'",
+                        ))
+                    }
+                }
+            } else {
+                return Err(Error::new(
+                    optional.path.span(),
+                    "unknown argument! Expected timeout_ms, attempts, parse, abortable, \
+                     reattempt_on_parse_err, value_sep, cmd_prefix, termination, \
+                     quote_escape_strings, param_sep, cooldown_ms, stats_id, continuation, \
+                     resp_prefix, prefix_field, length_footer, success_code or error_code",
+                ));
             }
         }
 
@@ -414,12 +677,15 @@ impl Parse for ParseInput {
         let derive_input = DeriveInput::parse(input)?;
 
         let mut at_cmd = None;
+        let mut at_resp = None;
         let mut at_enum = None;
 
         // Parse valid container attributes
         for attr in derive_input.attrs {
             if attr.path().is_ident("at_cmd") {
                 at_cmd = Some(attr.parse_args()?);
+            } else if attr.path().is_ident("at_resp") {
+                at_resp = Some(attr.parse_args()?);
             } else if attr.path().is_ident("at_enum") {
                 at_enum = Some(EnumAttributes {
                     repr: attr.parse_args()?,
@@ -431,6 +697,7 @@ impl Parse for ParseInput {
             ident: derive_input.ident,
             generics: derive_input.generics,
             at_cmd,
+            at_resp,
             at_enum,
             variants: sorted_variants(derive_input.data)?,
         })
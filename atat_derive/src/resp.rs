@@ -9,9 +9,14 @@ pub fn atat_resp(input: TokenStream) -> TokenStream {
         ident,
         generics,
         variants,
+        at_resp,
         ..
     } = parse_macro_input!(input as ParseInput);
 
+    if let Err(e) = helpers::require_named_fields(&variants) {
+        return e.to_compile_error().into();
+    }
+
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let mut serde_generics = generics.clone();
     helpers::add_lifetime(&mut serde_generics, "'de");
@@ -19,9 +24,21 @@ pub fn atat_resp(input: TokenStream) -> TokenStream {
 
     let deserialize_struct = helpers::deserialize_struct(&ident, &variants, &generics);
 
+    let validate = match at_resp.and_then(|at_resp| at_resp.validate) {
+        Some(validate) => quote! {
+            #[inline]
+            fn validate(&mut self) -> core::result::Result<(), atat::Error> {
+                #validate(self)
+            }
+        },
+        None => quote! {},
+    };
+
     TokenStream::from(quote! {
         #[automatically_derived]
-        impl #impl_generics atat::AtatResp for #ident #ty_generics #where_clause {}
+        impl #impl_generics atat::AtatResp for #ident #ty_generics #where_clause {
+            #validate
+        }
 
         #[automatically_derived]
         impl #serde_impl_generics atat::serde_at::serde::Deserialize<'de> for #ident #ty_generics #where_clause {
@@ -1,7 +1,7 @@
 use crate::proc_macro::TokenStream;
 
 use quote::quote;
-use syn::{parse_macro_input, Fields};
+use syn::{parse_macro_input, Error, Fields};
 
 use crate::parse::{ParseInput, UrcAttributes};
 
@@ -15,38 +15,80 @@ pub fn atat_urc(input: TokenStream) -> TokenStream {
 
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    assert!(!variants.is_empty(), "there must be at least one variant");
+    if variants.is_empty() {
+        return Error::new(ident.span(), "there must be at least one variant")
+            .to_compile_error()
+            .into();
+    }
 
-    let (match_arms, digest_arms): (Vec<_>, Vec<_>) = variants.iter().map(|variant| {
-        let UrcAttributes {
+    let mut match_arms = Vec::new();
+    let mut nested_arms = Vec::new();
+    let mut digest_arms = Vec::new();
+    for variant in &variants {
+        // Fields of a struct have no variant fields
+        let (Some(variant_ident), Some(_)) = (variant.ident.clone(), &variant.fields) else {
+            return Error::new(ident.span(), "AtatUrc can only be derived for enums")
+                .to_compile_error()
+                .into();
+        };
+        let Some(UrcAttributes {
             code,
-            parse
-        } = variant.attrs.at_urc.clone().unwrap_or_else(|| {
-            panic!(
-                "missing #[at_urc(...)] attribute",
+            parse,
+            nested,
+        }) = variant.attrs.at_urc.clone()
+        else {
+            return Error::new(
+                variant_ident.span(),
+                "missing #[at_urc(\"<token>\")] attribute",
             )
-        });
+            .to_compile_error()
+            .into();
+        };
+
+        if nested {
+            // Delegate to the prefix table of the nested URC type
+            let nested_type = match variant.fields.clone() {
+                Some(Fields::Unnamed(f)) if f.unnamed.len() == 1 => f.unnamed[0].ty.clone(),
+                _ => {
+                    return Error::new(
+                        variant_ident.span(),
+                        "nested variants must have exactly one unnamed field",
+                    )
+                    .to_compile_error()
+                    .into()
+                }
+            };
+            nested_arms.push(quote! {
+                if let Some(urc) = <#nested_type as atat::AtatUrc>::parse(resp) {
+                    return Some(#ident::#variant_ident(urc));
+                }
+            });
+            digest_arms.push(quote! {
+                atat::digest::parser::nested_urc::<#nested_type, _>,
+            });
+            continue;
+        }
 
-        let variant_ident = variant.ident.clone();
+        let code = code.unwrap();
         let parse_arm = match variant.fields.clone() {
-            Some(Fields::Named(_)) => {
-                panic!("cannot handle named enum variants")
-            }
-            Some(Fields::Unnamed(f)) => {
-                let mut field_iter = f.unnamed.iter();
-                let first_field = field_iter.next().expect("variant must have exactly one field");
-                assert!(field_iter.next().is_none(), "cannot handle variants with more than one field");
+            Some(Fields::Unnamed(f)) if f.unnamed.len() == 1 => {
+                let first_field = &f.unnamed[0];
                 quote! {
-                    #code => #ident::#variant_ident(atat::serde_at::from_slice::<#first_field>(&resp).ok()?),
+                    #code => Some(#ident::#variant_ident(atat::serde_at::from_slice::<#first_field>(&resp).ok()?)),
                 }
             }
             Some(Fields::Unit) => {
                 quote! {
-                    #code => #ident::#variant_ident,
+                    #code => Some(#ident::#variant_ident),
                 }
             }
-            None => {
-                panic!()
+            _ => {
+                return Error::new(
+                    variant_ident.span(),
+                    "URC variants must be unit variants or have exactly one unnamed field",
+                )
+                .to_compile_error()
+                .into()
             }
         };
 
@@ -60,8 +102,9 @@ pub fn atat_urc(input: TokenStream) -> TokenStream {
             }
         };
 
-        (parse_arm, digest_arm)
-    }).unzip();
+        match_arms.push(parse_arm);
+        digest_arms.push(digest_arm);
+    }
 
     TokenStream::from(quote! {
         #[automatically_derived]
@@ -72,12 +115,17 @@ pub fn atat_urc(input: TokenStream) -> TokenStream {
             fn parse(resp: &[u8]) -> Option<Self::Response> {
                 // FIXME: this should be more generic than ':' (Split using #code?)
                 let index = resp.iter().position(|&x| x == b':').unwrap_or(resp.len());
-                Some(match &resp[..index] {
+                match &resp[..index] {
                     #(
                         #match_arms
                     )*
-                    _ => return None
-                })
+                    _ => {
+                        #(
+                            #nested_arms
+                        )*
+                        None
+                    }
+                }
             }
         }
 
@@ -0,0 +1,24 @@
+//! Print the size report of the commands of the SARA driver.
+//!
+//! Names of the commands sent, eg. the `CommandState::name` logged during a
+//! test run, may be piped in one per line, to list the commands never sent:
+//!
+//! ```text
+//! cargo run --bin command-report --features std < sent.txt
+//! ```
+use std::io::{stdin, BufRead, IsTerminal};
+
+use atat_examples::sara::COMMANDS;
+
+fn main() {
+    println!("{COMMANDS}");
+
+    if stdin().is_terminal() {
+        return;
+    }
+    let sent: Vec<String> = stdin().lock().lines().map_while(Result::ok).collect();
+    println!();
+    for info in COMMANDS.unused(sent.iter().map(String::as_str)) {
+        println!("Never sent: {}", info.name);
+    }
+}
@@ -15,7 +15,10 @@ use embassy_rp::{
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
+/// Longest frame received, including data carrying URC's
 const INGRESS_BUF_SIZE: usize = 1024;
+/// Longest response to a command
+const RES_SLOT_SIZE: usize = 256;
 const URC_CAPACITY: usize = 128;
 const URC_SUBSCRIBERS: usize = 3;
 
@@ -43,9 +46,9 @@ async fn main(spawner: Spawner) {
     );
     let (reader, writer) = uart.split();
 
-    static RES_SLOT: ResponseSlot<INGRESS_BUF_SIZE> = ResponseSlot::new();
+    static RES_SLOT: ResponseSlot<RES_SLOT_SIZE> = ResponseSlot::new();
     static URC_CHANNEL: UrcChannel<common::Urc, URC_CAPACITY, URC_SUBSCRIBERS> = UrcChannel::new();
-    let ingress = Ingress::new(
+    let ingress = Ingress::new_sized(
         DefaultDigester::<common::Urc>::default(),
         INGRESS_BUF.init([0; INGRESS_BUF_SIZE]),
         &RES_SLOT,
@@ -92,7 +95,7 @@ async fn ingress_task(
         'static,
         DefaultDigester<common::Urc>,
         common::Urc,
-        INGRESS_BUF_SIZE,
+        RES_SLOT_SIZE,
         URC_CAPACITY,
         URC_SUBSCRIBERS,
     >,
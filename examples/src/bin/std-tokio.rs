@@ -9,7 +9,10 @@ use static_cell::StaticCell;
 use std::process::exit;
 use tokio_serial::SerialStream;
 
+/// Longest frame received, including data carrying URC's
 const INGRESS_BUF_SIZE: usize = 1024;
+/// Longest response to a command
+const RES_SLOT_SIZE: usize = 256;
 const URC_CAPACITY: usize = 128;
 const URC_SUBSCRIBERS: usize = 3;
 
@@ -28,9 +31,9 @@ async fn main() -> ! {
 
     let (reader, writer) = SerialStream::pair().expect("Failed to create serial pair");
     static INGRESS_BUF: StaticCell<[u8; INGRESS_BUF_SIZE]> = StaticCell::new();
-    static RES_SLOT: ResponseSlot<INGRESS_BUF_SIZE> = ResponseSlot::new();
+    static RES_SLOT: ResponseSlot<RES_SLOT_SIZE> = ResponseSlot::new();
     static URC_CHANNEL: UrcChannel<common::Urc, URC_CAPACITY, URC_SUBSCRIBERS> = UrcChannel::new();
-    let ingress = Ingress::new(
+    let ingress = Ingress::new_sized(
         DefaultDigester::<common::Urc>::default(),
         INGRESS_BUF.init([0; INGRESS_BUF_SIZE]),
         &RES_SLOT,
@@ -72,7 +75,7 @@ async fn ingress_task<'a>(
         'a,
         DefaultDigester<common::Urc>,
         common::Urc,
-        INGRESS_BUF_SIZE,
+        RES_SLOT_SIZE,
         URC_CAPACITY,
         URC_SUBSCRIBERS,
     >,
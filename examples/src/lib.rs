@@ -1,2 +1,3 @@
 #![no_std]
 pub mod common;
+pub mod sara;
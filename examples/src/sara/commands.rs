@@ -0,0 +1,143 @@
+//! Commands for the SARA reference driver
+use atat::atat_derive::AtatCmd;
+use atat::{AtatCmd, Error, InternalError};
+
+use super::responses::*;
+use crate::common::NoResponse;
+
+/// Echo off E0
+///
+/// Stop the module from echoing back received characters.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("E0", NoResponse)]
+pub struct SetEchoOff;
+
+/// 9.1 Report mobile termination error +CMEE
+///
+/// Configure the formatting of `+CME ERROR` result codes.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CMEE", NoResponse)]
+pub struct SetReportMobileTerminationError {
+    /// `0`: disabled, `1`: numeric, `2`: verbose
+    #[at_arg(position = 0)]
+    pub n: u8,
+}
+
+/// 4.2 Signal quality +CSQ
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CSQ", SignalQuality, resp_prefix = "+CSQ:")]
+pub struct GetSignalQuality;
+
+/// 25.3 Create Socket +USOCR
+///
+/// Creates a socket, returning its identifier.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+USOCR", CreateSocketResponse)]
+pub struct CreateSocket {
+    /// `6`: TCP, `17`: UDP
+    #[at_arg(position = 0)]
+    pub protocol: u8,
+}
+
+/// 25.7 Connect Socket +USOCO
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+USOCO", NoResponse, timeout_ms = 120000)]
+pub struct ConnectSocket<'a> {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+    #[at_arg(position = 1, len = 64)]
+    pub remote_addr: &'a str,
+    #[at_arg(position = 2)]
+    pub remote_port: u16,
+}
+
+/// 25.10 Write socket data +USOWR, binary mode
+///
+/// The module answers with an `@` prompt, after which exactly `length` bytes
+/// of data must be sent using [`WriteSocketData`].
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+USOWR", NoResponse)]
+pub struct PrepareWriteSocketData {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+    #[at_arg(position = 1)]
+    pub length: usize,
+}
+
+/// Raw binary data following a [`PrepareWriteSocketData`] prompt.
+///
+/// Binary data cannot be described with the derive macros, as it is sent
+/// without any prefix or termination, so `AtatCmd` is implemented by hand.
+#[derive(Clone)]
+pub struct WriteSocketData<'a> {
+    pub data: &'a [u8],
+}
+
+impl AtatCmd for WriteSocketData<'_> {
+    type Response = WriteSocketDataResponse;
+
+    const MAX_LEN: usize = 512;
+    const MAX_TIMEOUT_MS: u32 = 10000;
+    const NAME: &'static str = "WriteSocketData";
+
+    fn write(&self, buf: &mut [u8]) -> usize {
+        let len = self.data.len().min(Self::MAX_LEN);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        len
+    }
+
+    fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error> {
+        atat::serde_at::from_slice(resp?).map_err(|_| Error::Parse)
+    }
+}
+
+/// 25.12 Read Socket Data +USORD
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+USORD", ReadSocketDataResponse)]
+pub struct ReadSocketData {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+    #[at_arg(position = 1)]
+    pub length: usize,
+}
+
+/// 25.8 Close Socket +USOCL
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+USOCL", NoResponse, timeout_ms = 120000)]
+pub struct CloseSocket {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+}
+
+/// 7.3 Dial D, entering data mode on the given PDP context
+///
+/// The module answers `CONNECT`, after which the serial line carries raw
+/// data until [`EscapeDataMode`] is sent.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("D*99***1#", NoResponse, timeout_ms = 180000)]
+pub struct EnterDataMode;
+
+/// Escape sequence `+++`, returning from data mode to command mode
+///
+/// The escape sequence must be surrounded by a guard time without any data,
+/// and is sent without the `AT` prefix and termination.
+#[derive(Clone)]
+pub struct EscapeDataMode;
+
+impl AtatCmd for EscapeDataMode {
+    type Response = NoResponse;
+
+    const MAX_LEN: usize = 3;
+    const COOLDOWN_MS: Option<u32> = Some(1000);
+    const NAME: &'static str = "EscapeDataMode";
+
+    fn write(&self, buf: &mut [u8]) -> usize {
+        buf[..3].copy_from_slice(b"+++");
+        3
+    }
+
+    fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, Error> {
+        resp?;
+        Ok(NoResponse)
+    }
+}
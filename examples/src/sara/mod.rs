@@ -0,0 +1,254 @@
+//! Reference driver for the u-blox SARA-R4/R5 family of cellular modules.
+//!
+//! Exercises the full surface of atat using only its public API: plain
+//! commands and responses, URCs, the `@` prompt for binary socket writes, and
+//! entering and escaping data mode. It is meant as a template to copy when
+//! writing a driver for another module.
+//!
+//! The driver only owns the client. Setting up the [`atat::Ingress`] and
+//! subscribing to [`Urc`]s is left to the application, such that it can pick
+//! its own buffer sizes and executor.
+pub mod commands;
+pub mod responses;
+pub mod urc;
+
+use atat::asynch::AtatClient;
+use atat::atat_derive::AtatUrc;
+use atat::{command_report, report::CommandReport, AtatCmd, Error};
+
+use commands::*;
+use responses::*;
+
+/// Maximum number of bytes read per `+USORD`, bounded by the size of
+/// [`ReadSocketDataResponse::data`]
+const READ_CHUNK_SIZE: usize = 128;
+
+/// Commands sent by the driver, see the `command-report` binary
+pub const COMMANDS: CommandReport = command_report![
+    SetEchoOff,
+    SetReportMobileTerminationError,
+    GetSignalQuality,
+    CreateSocket,
+    ConnectSocket<'static>,
+    PrepareWriteSocketData,
+    WriteSocketData<'static>,
+    ReadSocketData,
+    CloseSocket,
+    EnterDataMode,
+    EscapeDataMode,
+];
+
+/// Unsolicited responses emitted by the module
+#[derive(Clone, Debug, PartialEq, AtatUrc)]
+pub enum Urc {
+    #[at_urc("+UUSORD")]
+    SocketDataAvailable(urc::SocketDataAvailable),
+    #[at_urc("+UUSOCL")]
+    SocketClosed(urc::SocketClosed),
+}
+
+/// Identifier of a socket created on the module
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SocketHandle(pub u8);
+
+/// Driver for a SARA module, generic over any atat client.
+pub struct Sara<C: AtatClient> {
+    client: C,
+}
+
+impl<C: AtatClient> Sara<C> {
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+
+    /// Give back the underlying client
+    pub fn release(self) -> C {
+        self.client
+    }
+
+    /// Synchronize with the module and configure it for use by the driver:
+    /// wait for it to respond, disable echo and enable numeric error codes.
+    pub async fn init(&mut self) -> Result<(), Error> {
+        let mut attempts = 10;
+        loop {
+            match self.client.send(&crate::common::AT).await {
+                Ok(_) => break,
+                Err(_) if attempts > 1 => attempts -= 1,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.client.send(&SetEchoOff).await?;
+        self.client
+            .send(&SetReportMobileTerminationError { n: 1 })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn signal_quality(&mut self) -> Result<SignalQuality, Error> {
+        self.client.send(&GetSignalQuality).await
+    }
+
+    /// Create a TCP socket and connect it to `host:port`
+    pub async fn connect(&mut self, host: &str, port: u16) -> Result<SocketHandle, Error> {
+        let CreateSocketResponse { socket } =
+            self.client.send(&CreateSocket { protocol: 6 }).await?;
+        self.client
+            .send(&ConnectSocket {
+                socket,
+                remote_addr: host,
+                remote_port: port,
+            })
+            .await?;
+        Ok(SocketHandle(socket))
+    }
+
+    /// Write binary data to a socket, returning the number of bytes written.
+    ///
+    /// The data is sent after the module prompts for it with `@`, which the
+    /// default digester signals as an empty response to the first command.
+    pub async fn write(&mut self, socket: SocketHandle, data: &[u8]) -> Result<usize, Error> {
+        let mut written = 0;
+        for chunk in data.chunks(WriteSocketData::MAX_LEN) {
+            self.client
+                .send(&PrepareWriteSocketData {
+                    socket: socket.0,
+                    length: chunk.len(),
+                })
+                .await?;
+            let resp = self.client.send(&WriteSocketData { data: chunk }).await?;
+            written += resp.length;
+        }
+        Ok(written)
+    }
+
+    /// Read data announced by [`Urc::SocketDataAvailable`] into `buf`,
+    /// returning the number of bytes read.
+    pub async fn read(&mut self, socket: SocketHandle, buf: &mut [u8]) -> Result<usize, Error> {
+        let resp = self
+            .client
+            .send(&ReadSocketData {
+                socket: socket.0,
+                length: buf.len().min(READ_CHUNK_SIZE),
+            })
+            .await?;
+        let len = resp.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&resp.data.as_bytes()[..len]);
+        Ok(len)
+    }
+
+    pub async fn close(&mut self, socket: SocketHandle) -> Result<(), Error> {
+        self.client
+            .send(&CloseSocket { socket: socket.0 })
+            .await
+            .map(drop)
+    }
+
+    /// Enter data mode. Until [`Sara::escape_data_mode`] is called, the serial
+    /// line carries raw data, which the application has to route around the
+    /// ingress.
+    pub async fn enter_data_mode(&mut self) -> Result<(), Error> {
+        self.client.send(&EnterDataMode).await.map(drop)
+    }
+
+    /// Return from data mode to command mode
+    pub async fn escape_data_mode(&mut self) -> Result<(), Error> {
+        self.client.send(&EscapeDataMode).await.map(drop)
+    }
+}
+
+impl<C: AtatClient> core::fmt::Debug for Sara<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sara").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atat::{heapless::String, AtatUrc};
+
+    fn written<Cmd: AtatCmd>(cmd: &Cmd) -> String<64> {
+        let mut buf = [0; 64];
+        let len = cmd.write(&mut buf);
+        String::try_from(core::str::from_utf8(&buf[..len]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn write_commands() {
+        assert_eq!(written(&SetEchoOff), "ATE0\r\n");
+        assert_eq!(
+            written(&SetReportMobileTerminationError { n: 1 }),
+            "AT+CMEE=1\r\n"
+        );
+        assert_eq!(written(&GetSignalQuality), "AT+CSQ\r\n");
+        assert_eq!(
+            written(&ConnectSocket {
+                socket: 0,
+                remote_addr: "example.com",
+                remote_port: 80,
+            }),
+            "AT+USOCO=0,\"example.com\",80\r\n"
+        );
+        assert_eq!(
+            written(&PrepareWriteSocketData {
+                socket: 0,
+                length: 5,
+            }),
+            "AT+USOWR=0,5\r\n"
+        );
+        assert_eq!(written(&WriteSocketData { data: b"hello" }), "hello");
+        assert_eq!(written(&EscapeDataMode), "+++");
+    }
+
+    #[test]
+    fn parse_responses() {
+        assert_eq!(
+            GetSignalQuality.parse(Ok(b"+CSQ: 19,99")),
+            Ok(SignalQuality { rssi: 19, ber: 99 })
+        );
+        assert_eq!(
+            CreateSocket { protocol: 6 }.parse(Ok(b"+USOCR: 3")),
+            Ok(CreateSocketResponse { socket: 3 })
+        );
+        assert_eq!(
+            WriteSocketData { data: b"hello" }.parse(Ok(b"+USOWR: 3,5")),
+            Ok(WriteSocketDataResponse {
+                socket: 3,
+                length: 5
+            })
+        );
+        assert_eq!(
+            ReadSocketData {
+                socket: 3,
+                length: 5,
+            }
+            .parse(Ok(b"+USORD: 3,5,\"hello\"")),
+            Ok(ReadSocketDataResponse {
+                socket: 3,
+                length: 5,
+                data: String::try_from("hello").unwrap(),
+            })
+        );
+        assert_eq!(
+            EscapeDataMode.parse(Err(atat::InternalError::Error)).err(),
+            Some(Error::Error)
+        );
+    }
+
+    #[test]
+    fn parse_urcs() {
+        assert_eq!(
+            Urc::parse(b"+UUSORD: 3,16"),
+            Some(Urc::SocketDataAvailable(urc::SocketDataAvailable {
+                socket: 3,
+                length: 16,
+            }))
+        );
+        assert_eq!(
+            Urc::parse(b"+UUSOCL: 3"),
+            Some(Urc::SocketClosed(urc::SocketClosed { socket: 3 }))
+        );
+        assert_eq!(Urc::parse(b"+UUSOLI: 3"), None);
+    }
+}
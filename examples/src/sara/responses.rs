@@ -0,0 +1,41 @@
+//! Responses for the SARA reference driver
+use atat::atat_derive::AtatResp;
+use atat::heapless::String;
+
+/// 4.2 Signal quality +CSQ
+#[derive(Clone, Debug, PartialEq, AtatResp)]
+pub struct SignalQuality {
+    /// Received signal strength indication, `99` if not known
+    #[at_arg(position = 0)]
+    pub rssi: u8,
+    /// Channel bit error rate, `99` if not known
+    #[at_arg(position = 1)]
+    pub ber: u8,
+}
+
+/// 25.3 Create Socket +USOCR
+#[derive(Clone, Debug, PartialEq, AtatResp)]
+pub struct CreateSocketResponse {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+}
+
+/// 25.10 Write socket data +USOWR
+#[derive(Clone, Debug, PartialEq, AtatResp)]
+pub struct WriteSocketDataResponse {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+    #[at_arg(position = 1)]
+    pub length: usize,
+}
+
+/// 25.12 Read Socket Data +USORD
+#[derive(Clone, Debug, PartialEq, AtatResp)]
+pub struct ReadSocketDataResponse {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+    #[at_arg(position = 1)]
+    pub length: usize,
+    #[at_arg(position = 2)]
+    pub data: String<128>,
+}
@@ -0,0 +1,18 @@
+//! Unsolicited responses for the SARA reference driver
+use atat::atat_derive::AtatResp;
+
+/// 25.12 Socket data available +UUSORD
+#[derive(Clone, Debug, PartialEq, AtatResp)]
+pub struct SocketDataAvailable {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+    #[at_arg(position = 1)]
+    pub length: usize,
+}
+
+/// 25.9 Socket closed +UUSOCL
+#[derive(Clone, Debug, PartialEq, AtatResp)]
+pub struct SocketClosed {
+    #[at_arg(position = 0)]
+    pub socket: u8,
+}
@@ -0,0 +1,101 @@
+//! Fuzz `serde_at::from_slice` with response shapes commonly seen in
+//! drivers.
+//!
+//! The corpus in `corpus/from_slice` holds real responses captured from
+//! u-blox, Quectel, SIMCom and ESP-AT modules, with the final result code
+//! stripped as the digester would.
+//!
+//! ```sh
+//! cd serde_at/fuzz && cargo +nightly fuzz run from_slice
+//! ```
+#![no_main]
+
+use heapless::{String, Vec};
+use heapless_bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use serde::Deserialize;
+use serde_at::{de::length_delimited::LengthDelimited, DeserializeOptions, HexStr, Timestamp};
+
+#[derive(Deserialize)]
+struct Numbers {
+    _a: u8,
+    _b: i16,
+    _c: Option<u32>,
+    _d: f32,
+    _e: i8,
+}
+
+#[derive(Deserialize)]
+struct Strings {
+    _a: String<32>,
+    _b: Option<String<16>>,
+    _c: bool,
+    _d: char,
+}
+
+#[derive(Deserialize)]
+struct Borrowed<'a> {
+    _a: u8,
+    _b: &'a str,
+    _c: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct Raw {
+    _a: Bytes<16>,
+    _b: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Hex {
+    _a: HexStr<u32>,
+    _b: HexStr<u8>,
+}
+
+#[derive(Deserialize)]
+struct Delimited {
+    _a: u8,
+    _b: i8,
+    _c: LengthDelimited<32>,
+}
+
+#[derive(Deserialize)]
+struct Clock {
+    _a: Timestamp,
+}
+
+#[derive(Deserialize)]
+struct Trailing {
+    _a: String<16>,
+    _b: Option<usize>,
+    _c: String<64>,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    _a: u8,
+    _b: String<16>,
+}
+
+#[derive(Deserialize)]
+struct Handle(#[allow(dead_code)] u128);
+
+fn parse<'a, T: Deserialize<'a>>(data: &'a [u8]) {
+    let _ = serde_at::from_slice::<T>(data);
+    let _ = serde_at::from_slice_with_options::<T>(data, DeserializeOptions { separators: b",;" });
+}
+
+fuzz_target!(|data: &[u8]| {
+    parse::<Numbers>(data);
+    parse::<Strings>(data);
+    parse::<Borrowed<'_>>(data);
+    parse::<Raw>(data);
+    parse::<Hex>(data);
+    parse::<Delimited>(data);
+    parse::<Clock>(data);
+    parse::<Trailing>(data);
+    parse::<Vec<Record, 4>>(data);
+    parse::<Handle>(data);
+    parse::<String<64>>(data);
+    parse::<Bytes<64>>(data);
+});
@@ -0,0 +1,121 @@
+use core::{fmt, marker::PhantomData, str, str::FromStr};
+
+use serde::de::{self, Deserialize, Unexpected, Visitor};
+
+/// Longest number accepted, in bytes
+const MAX_LEN: usize = 64;
+
+/// `DecimalComma`
+/// A quoted number written with a comma as decimal separator, eg. `"23,5"`,
+/// as output by firmwares localizing their responses.
+///
+/// A dot is accepted as decimal separator as well, so the same field parses
+/// regardless of the firmware locale. The number is serialized back quoted,
+/// with a comma.
+///
+/// ```
+/// use serde_at::{from_str, DecimalComma};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Position {
+///     latitude: DecimalComma<f64>,
+///     longitude: DecimalComma<f64>,
+///     altitude: DecimalComma<f32>,
+/// }
+///
+/// let pos: Position = from_str("+QGPSLOC: \"55,6761\",\"12,5683\",\"23,5\"").unwrap();
+/// assert_eq!(pos.latitude, DecimalComma(55.6761));
+/// assert_eq!(pos.altitude.0, 23.5);
+///
+/// let pos: Position = from_str("+QGPSLOC: \"55.6761\",\"12.5683\",\"23.5\"").unwrap();
+/// assert_eq!(pos.longitude, DecimalComma(12.5683));
+/// ```
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Default)]
+pub struct DecimalComma<T>(pub T);
+
+struct DecimalCommaVisitor<T>(PhantomData<T>);
+
+impl<'de, T: FromStr> Visitor<'de> for DecimalCommaVisitor<T> {
+    type Value = DecimalComma<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a quoted number with a comma or dot as decimal separator")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let v = v.trim();
+        if v.len() > MAX_LEN {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        let mut buf = [0; MAX_LEN];
+        for (b, c) in buf.iter_mut().zip(v.bytes()) {
+            *b = if c == b',' { b'.' } else { c };
+        }
+        // Only an ASCII comma has been replaced, keeping the string valid
+        str::from_utf8(&buf[..v.len()])
+            .ok()
+            .and_then(|normalized| normalized.parse().ok())
+            .map(DecimalComma)
+            .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let v = str::from_utf8(v).map_err(|_| E::invalid_value(Unexpected::Bytes(v), &self))?;
+        self.visit_str(v)
+    }
+}
+
+impl<'de, T: FromStr> Deserialize<'de> for DecimalComma<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DecimalCommaVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, to_slice, SerializeOptions};
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Temperature {
+        sensor: u8,
+        celsius: DecimalComma<f32>,
+    }
+
+    #[test]
+    fn deserialize() {
+        let t: Temperature = from_str("+QTEMP: 1,\"23,5\"").unwrap();
+        assert_eq!(t.celsius, DecimalComma(23.5));
+
+        let t: Temperature = from_str("+QTEMP: 1,\"-4.25\"").unwrap();
+        assert_eq!(t.celsius, DecimalComma(-4.25));
+
+        let t: Temperature = from_str("+QTEMP: 1,\"7\"").unwrap();
+        assert_eq!(t.celsius, DecimalComma(7.0));
+
+        assert!(from_str::<Temperature>("+QTEMP: 1,\"23,5,1\"").is_err());
+        assert!(from_str::<Temperature>("+QTEMP: 1,\"warm\"").is_err());
+    }
+
+    #[test]
+    fn serialize() {
+        let mut buf = [0; 32];
+        let t = Temperature {
+            sensor: 1,
+            celsius: DecimalComma(-4.25),
+        };
+        let len = to_slice(&t, "+QTEMP", &mut buf, SerializeOptions::default()).unwrap();
+        assert_eq!(&buf[..len], b"AT+QTEMP=1,\"-4,25\"\r\n");
+    }
+}
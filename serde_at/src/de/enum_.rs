@@ -36,12 +36,11 @@ impl<'de, 'a> de::VariantAccess<'de> for VariantAccess<'a, 'de> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self
+        let c = self
             .de
             .parse_whitespace()
-            .ok_or(Error::EofWhileParsingObject)?
-            == b','
-        {
+            .ok_or(Error::EofWhileParsingObject)?;
+        if self.de.is_separator(c) {
             self.de.eat_char();
             self.de.parse_whitespace();
         }
@@ -6,13 +6,52 @@ use serde::de::Visitor;
 use serde::{de, Deserialize};
 
 struct HexLiteralVisitor<T> {
+    strict: bool,
     _ty: PhantomData<T>,
 }
 
+impl<T> HexLiteralVisitor<T> {
+    const fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            _ty: PhantomData,
+        }
+    }
+
+    /// Strip surrounding quotes, as emitted by some modems, and an optional
+    /// `0x` prefix. In strict mode, the remaining characters must be an even
+    /// number of hex digits.
+    fn digits<'a, E: de::Error>(&self, v: &'a [u8]) -> Result<&'a str, E> {
+        let v = match v {
+            [b'"', inner @ .., b'"'] => inner,
+            v => v,
+        };
+        let v = v
+            .strip_prefix(b"0x")
+            .or_else(|| v.strip_prefix(b"0X"))
+            .unwrap_or(v);
+
+        if self.strict {
+            if !v.iter().all(u8::is_ascii_hexdigit) {
+                return Err(de::Error::custom("Invalid hex digit"));
+            }
+            if v.len() % 2 != 0 {
+                return Err(de::Error::custom("Odd number of hex digits"));
+            }
+        }
+
+        core::str::from_utf8(v).map_err(de::Error::custom)
+    }
+}
+
 /// `HexStr<T>`
 /// A hex string. Has fields used in serializing whether to add a 0x to the encoding
 /// and to make the hex value in capital letters or not.
 /// Can be dereferenced to its value.
+///
+/// When deserializing, an optional `0x` prefix and surrounding quotes are
+/// stripped, and any character that is not a hex digit is ignored, such that
+/// delimiters like `8:d` are accepted. See [`StrictHexStr`] for validation.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct HexStr<T> {
     /// Value of the hex string. Can be dereferenced
@@ -45,6 +84,29 @@ where
     }
 }
 
+/// `StrictHexStr<T>`
+/// A [`HexStr`] that fails to deserialize on any character other than hex
+/// digits, and on an odd number of hex digits, instead of silently ignoring
+/// them. Quotes and a `0x` prefix are accepted as for [`HexStr`].
+/// Serializes like the wrapped [`HexStr`], and can be dereferenced to its
+/// value.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct StrictHexStr<T>(pub HexStr<T>);
+
+impl<T> Deref for StrictHexStr<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0.val
+    }
+}
+
+impl<T> From<HexStr<T>> for StrictHexStr<T> {
+    fn from(hex: HexStr<T>) -> Self {
+        Self(hex)
+    }
+}
+
 macro_rules! impl_hex_literal_visitor {
     ($($int_type:ty)*) => {$(
         impl<'de> Visitor<'de> for HexLiteralVisitor<$int_type> {
@@ -58,26 +120,23 @@ macro_rules! impl_hex_literal_visitor {
             where
                 E: de::Error,
             {
-                let mut s = core::str::from_utf8(v)
-                    .map_err(serde::de::Error::custom)?;
-                if s.starts_with("0x") || s.starts_with("0X") {
-                    s = &s[2..];
-                }
+                let s = self.digits(v)?;
 
                 let mut ret: $int_type = 0;
 
                 for c in s.chars() {
-                    let v = match c {
-                        '0'..='9' => Some((c as $int_type) - ('0' as $int_type)),
-                        'A'..='F' => Some(0xA + ((c as $int_type) - ('A' as $int_type))),
-                        'a'..='f' => Some(0xa + ((c as $int_type) - ('a' as $int_type))),
-                        _ => None
-                    };
+                    let v = c.to_digit(16).map(|d| d as $int_type);
 
                     if let Some(v) = v {
-                        ret = ret
-                            .shl(4i32)
-                            .checked_add(v)
+                        // `HexStr` keeps the low digits of a value too large
+                        // for the type, while `StrictHexStr` rejects it
+                        let shifted = if self.strict {
+                            ret.checked_mul(16)
+                        } else {
+                            Some(ret.shl(4i32))
+                        };
+                        ret = shifted
+                            .and_then(|r| r.checked_add(v))
                             .ok_or(serde::de::Error::custom("Invalid number"))?;
                     }
                 }
@@ -91,11 +150,21 @@ macro_rules! impl_hex_literal_visitor {
                 where
                     D: serde::Deserializer<'de>,
             {
-                let val = deserializer.deserialize_bytes(HexLiteralVisitor::<$int_type> { _ty: PhantomData })?;
+                let val = deserializer.deserialize_bytes(HexLiteralVisitor::<$int_type>::new(false))?;
                 Ok(HexStr { val, ..Default::default() })
             }
         }
 
+        impl<'de> Deserialize<'de> for StrictHexStr<$int_type> {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+            {
+                let val = deserializer.deserialize_bytes(HexLiteralVisitor::<$int_type>::new(true))?;
+                Ok(StrictHexStr(HexStr { val, ..Default::default() }))
+            }
+        }
+
         impl Deref for HexStr<$int_type> {
             type Target = $int_type;
 
@@ -111,9 +180,8 @@ impl_hex_literal_visitor! { u8 u16 u32 u64 u128 }
 #[cfg(feature = "hex_str_arrays")]
 mod unstable {
     use crate::de::hex_str::HexLiteralVisitor;
-    use crate::HexStr;
+    use crate::{HexStr, StrictHexStr};
     use core::fmt;
-    use core::marker::PhantomData;
     use core::ops::Deref;
     use serde::de::Visitor;
     use serde::{de, Deserialize};
@@ -129,10 +197,7 @@ mod unstable {
         where
             E: de::Error,
         {
-            let mut s = core::str::from_utf8(v).map_err(serde::de::Error::custom)?;
-            if s.starts_with("0x") || s.starts_with("0X") {
-                s = &s[2..];
-            }
+            let s = self.digits(v)?;
 
             let mut ret: [u8; N] = [0; N];
             let mut current = 0u8;
@@ -148,6 +213,9 @@ mod unstable {
                 };
 
                 if let Some(v) = v {
+                    if index == N {
+                        return Err(de::Error::custom("Too many hex digits"));
+                    }
                     if nibble_count == 1 {
                         ret[index] = (current << 4) + v;
                         current = 0;
@@ -173,8 +241,8 @@ mod unstable {
         where
             D: serde::Deserializer<'de>,
         {
-            let val: [u8; N] = deserializer
-                .deserialize_bytes(HexLiteralVisitor::<[u8; N]> { _ty: PhantomData })?;
+            let val: [u8; N] =
+                deserializer.deserialize_bytes(HexLiteralVisitor::<[u8; N]>::new(false))?;
             Ok(HexStr {
                 val,
                 add_0x_with_encoding: false,
@@ -186,6 +254,24 @@ mod unstable {
         }
     }
 
+    impl<'de, const N: usize> Deserialize<'de> for StrictHexStr<[u8; N]> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let val: [u8; N] =
+                deserializer.deserialize_bytes(HexLiteralVisitor::<[u8; N]>::new(true))?;
+            Ok(StrictHexStr(HexStr {
+                val,
+                add_0x_with_encoding: false,
+                hex_in_caps: true,
+                delimiter_after_nibble_count: 0,
+                delimiter: ' ',
+                skip_last_0_values: false,
+            }))
+        }
+    }
+
     impl<const N: usize> Deref for HexStr<[u8; N]> {
         type Target = [u8; N];
 
@@ -197,9 +283,10 @@ mod unstable {
 
 #[cfg(test)]
 mod tests {
-    use crate::de::hex_str::HexStr;
+    use crate::de::hex_str::{HexStr, StrictHexStr};
 
     #[test]
+    #[allow(clippy::mixed_case_hex_literals)]
     pub fn test_parsing_a_hex_string() {
         let val: HexStr<u8> = crate::from_str("+CCID: 0x8d").unwrap();
         assert_eq!(*val, 0x8d);
@@ -221,6 +308,73 @@ mod tests {
         assert_eq!(*val, 0x1234567890abcdef1234567890abcdef);
     }
 
+    #[test]
+    pub fn test_quoted_hex_string() {
+        let val: HexStr<u16> = crate::from_str("+CCID: \"1A2B\"").unwrap();
+        assert_eq!(*val, 0x1a2b);
+        let val: HexStr<u16> = crate::from_str("+CCID: \"0x1A2B\"").unwrap();
+        assert_eq!(*val, 0x1a2b);
+        let val: StrictHexStr<u16> = crate::from_str("+CCID: \"1A2B\"").unwrap();
+        assert_eq!(*val, 0x1a2b);
+    }
+
+    #[test]
+    pub fn test_strict_hex_string() {
+        let val: StrictHexStr<u32> = crate::from_str("+CCID: 0xD3AdB3ef").unwrap();
+        assert_eq!(*val, 0xd3adb3ef);
+
+        // Silently ignored by `HexStr`
+        let val: HexStr<u16> = crate::from_str("+CCID: 1G2B").unwrap();
+        assert_eq!(*val, 0x12b);
+        assert!(crate::from_str::<StrictHexStr<u16>>("+CCID: 1G2B").is_err());
+        assert!(crate::from_str::<StrictHexStr<u16>>("+CCID: 8:d").is_err());
+        assert!(crate::from_str::<StrictHexStr<u16>>("+CCID: \"1A2\"").is_err());
+        assert!(crate::from_str::<StrictHexStr<u16>>("+CCID: \"1A2B").is_err());
+    }
+
+    #[test]
+    pub fn test_strict_hex_string_overflow() {
+        let val: StrictHexStr<u16> = crate::from_str("+CCID: FFFF").unwrap();
+        assert_eq!(*val, 0xffff);
+        let val: StrictHexStr<u16> = crate::from_str("+CCID: 0000FFFF").unwrap();
+        assert_eq!(*val, 0xffff);
+        assert!(crate::from_str::<StrictHexStr<u16>>("+CCID: 123456").is_err());
+        assert!(crate::from_str::<StrictHexStr<u8>>("+CCID: 0x100A").is_err());
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    pub fn test_round_trip() {
+        use serde_derive::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct WithHexStr {
+            val: HexStr<u32>,
+            strict: StrictHexStr<u16>,
+        }
+
+        let params = WithHexStr {
+            val: HexStr {
+                val: 0xFF00,
+                add_0x_with_encoding: true,
+                ..Default::default()
+            },
+            strict: StrictHexStr(HexStr {
+                val: 0x0A0B,
+                skip_last_0_values: false,
+                ..Default::default()
+            }),
+        };
+        let s: heapless::String<64> =
+            crate::to_string(&params, "+CMD", crate::SerializeOptions::default()).unwrap();
+        assert_eq!(s, "AT+CMD=\"0xFF00\",\"0A0B\"\r\n");
+
+        let resp = s.trim_start_matches("AT+CMD=").trim_end();
+        let de: WithHexStr = crate::from_str(resp).unwrap();
+        assert_eq!(*de.val, 0xFF00);
+        assert_eq!(*de.strict, 0x0A0B);
+    }
+
     #[cfg(feature = "hex_str_arrays")]
     #[test]
     pub fn test_hex_str_arrays() {
@@ -233,5 +387,11 @@ mod tests {
                 0xcd, 0xef
             ]
         );
+        assert!(crate::from_str::<HexStr<[u8; 2]>>("+CCID: 0x12:34:56").is_err());
+
+        let val: StrictHexStr<[u8; 2]> = crate::from_str("+CCID: \"1234\"").unwrap();
+        assert_eq!(*val, [0x12, 0x34]);
+        // Ambiguous whether the odd nibble is the high or low nibble
+        assert!(crate::from_str::<StrictHexStr<[u8; 2]>>("+CCID: 123").is_err());
     }
 }
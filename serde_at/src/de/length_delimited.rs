@@ -59,10 +59,12 @@ impl<'de, const N: usize> de::Visitor<'de> for LengthDelimitedVisitor<N> {
                     .map_err(|_| de::Error::custom("expected an unsigned int"))?;
                 // +1 to skip the comma after the length.
                 let mut start = pos + 1;
-                let mut end = start + len;
+                let mut end = start
+                    .checked_add(len)
+                    .filter(|&end| end <= v.len())
+                    .ok_or_else(|| de::Error::custom("payload shorter than length"))?;
                 // Check if payload is surrounded by double quotes not included in len.
-                let slice_len = v.len();
-                if slice_len >= (end + 2) && (v[start] == b'"' && v[end + 1] == b'"') {
+                if v.len() >= (end + 2) && (v[start] == b'"' && v[end + 1] == b'"') {
                     start += 1; // Extra +1 to remove first quote (")
                     end += 1; // Move end by 1 to compensate for the quote.
                 }
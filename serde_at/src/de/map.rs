@@ -26,7 +26,7 @@ impl<'a, 'de> de::MapAccess<'de> for MapAccess<'a, 'de> {
             .parse_whitespace()
             .ok_or(Error::EofWhileParsingObject)?
         {
-            b',' if !self.first => {
+            c if !self.first && self.de.is_separator(c) => {
                 self.de.eat_char();
                 self.de.parse_whitespace();
             }
@@ -9,15 +9,27 @@ use self::enum_::VariantAccess;
 use self::map::MapAccess;
 use self::seq::SeqAccess;
 
+mod decimal_comma;
 mod enum_;
 #[cfg(feature = "heapless")]
 pub mod length_delimited;
 mod map;
+mod number_or_string;
+mod records;
+mod scaled;
 mod seq;
 
+pub use self::decimal_comma::DecimalComma;
+pub use self::number_or_string::NumberOrString;
+pub use self::records::RecordIter;
+pub use self::scaled::{Ber, BerScale, Rssi, RssiScale, Scale, Scaled};
+
 /// Hex string helper module
 pub mod hex_str;
 
+/// Timestamp helper module
+pub mod timestamp;
+
 /// Deserialization result
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -46,6 +58,10 @@ pub enum Error {
     /// Invalid number.
     InvalidNumber,
 
+    /// Number too large for the expected type, eg. a 40 digit number for a
+    /// `u32`.
+    NumberOutOfRange,
+
     /// Invalid type
     InvalidType,
 
@@ -66,23 +82,95 @@ pub enum Error {
     CustomErrorWithMessage(heapless::String<128>),
 }
 
+/// Options used by the deserializer, to accept non-standard response formats
+#[derive(Debug, Clone, Copy)]
+pub struct DeserializeOptions<'a> {
+    /// The characters accepted as separator between parameters, eg. `b",;"`
+    /// for responses using `;` between records. Whitespace following a
+    /// separator is always skipped.
+    ///
+    /// **default**: b","
+    pub separators: &'a [u8],
+}
+
+impl<'a> Default for DeserializeOptions<'a> {
+    fn default() -> Self {
+        DeserializeOptions { separators: b"," }
+    }
+}
+
+/// Details on where deserialization failed, returned by
+/// [`from_slice_with_diagnostic`].
+#[cfg(feature = "diagnostics")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Diagnostic {
+    /// Index of the field being parsed, within the innermost struct or
+    /// sequence, or `None` if parsing failed before the first field
+    pub field: Option<usize>,
+    /// Offset into the response where parsing failed, or where the number
+    /// failing with [`Error::NumberOutOfRange`] starts
+    pub position: usize,
+    /// The type the field was expected to hold, eg. `"u8"`
+    pub expected: &'static str,
+}
+
 pub(crate) struct Deserializer<'b> {
     slice: &'b [u8],
     index: usize,
     struct_size_hint: Option<usize>,
     is_trailing_parsing: bool,
+    separators: &'b [u8],
+    #[cfg(feature = "diagnostics")]
+    diagnostic: Diagnostic,
 }
 
 impl<'a> Deserializer<'a> {
-    const fn new(slice: &'a [u8]) -> Deserializer<'_> {
+    const fn new(slice: &'a [u8], options: DeserializeOptions<'a>) -> Deserializer<'a> {
         Deserializer {
             slice,
             index: 0,
             struct_size_hint: None,
             is_trailing_parsing: false,
+            separators: options.separators,
+            #[cfg(feature = "diagnostics")]
+            diagnostic: Diagnostic {
+                field: None,
+                position: 0,
+                expected: "",
+            },
+        }
+    }
+
+    /// Record the type of the value about to be parsed, for diagnostics.
+    #[inline]
+    fn expecting(&mut self, _ty: &'static str) {
+        #[cfg(feature = "diagnostics")]
+        {
+            self.diagnostic.expected = _ty;
+        }
+    }
+
+    /// Record the index of the field about to be parsed, for diagnostics.
+    #[inline]
+    fn at_field(&mut self, _field: usize) {
+        #[cfg(feature = "diagnostics")]
+        {
+            self.diagnostic.field = Some(_field);
         }
     }
 
+    /// Fail a number too large for its type. The deserializer is rewound to
+    /// the start of the number, such that a diagnostic points at it rather
+    /// than at the digit that overflowed.
+    fn number_out_of_range(&mut self, start: usize) -> Error {
+        self.index = start;
+        Error::NumberOutOfRange
+    }
+
+    fn is_separator(&self, c: u8) -> bool {
+        self.separators.contains(&c)
+    }
+
     fn eat_char(&mut self) {
         self.index += 1;
     }
@@ -238,9 +326,11 @@ impl<'a> Deserializer<'a> {
 // Flash, when targeting non 64-bit architectures
 macro_rules! deserialize_unsigned {
     ($self:ident, $visitor:ident, $uxx:ident, $visit_uxx:ident) => {{
+        $self.expecting(stringify!($uxx));
         let peek = $self
             .parse_whitespace()
             .ok_or(Error::EofWhileParsingValue)?;
+        let start = $self.index;
 
         match peek {
             b'-' => Err(Error::InvalidNumber),
@@ -256,11 +346,13 @@ macro_rules! deserialize_unsigned {
                     match $self.peek() {
                         Some(c @ b'0'..=b'9') => {
                             $self.eat_char();
-                            number = number
+                            number = match number
                                 .checked_mul(10)
-                                .ok_or(Error::InvalidNumber)?
-                                .checked_add((c - b'0') as $uxx)
-                                .ok_or(Error::InvalidNumber)?;
+                                .and_then(|n| n.checked_add((c - b'0') as $uxx))
+                            {
+                                Some(number) => number,
+                                None => return Err($self.number_out_of_range(start)),
+                            };
                         }
                         _ => return $visitor.$visit_uxx(number),
                     }
@@ -273,10 +365,12 @@ macro_rules! deserialize_unsigned {
 
 macro_rules! deserialize_signed {
     ($self:ident, $visitor:ident, $ixx:ident, $visit_ixx:ident) => {{
-        let signed = match $self
+        $self.expecting(stringify!($ixx));
+        let peek = $self
             .parse_whitespace()
-            .ok_or(Error::EofWhileParsingValue)?
-        {
+            .ok_or(Error::EofWhileParsingValue)?;
+        let start = $self.index;
+        let signed = match peek {
             b'-' => {
                 $self.eat_char();
                 true
@@ -297,11 +391,12 @@ macro_rules! deserialize_signed {
                     match $self.peek() {
                         Some(c @ b'0'..=b'9') => {
                             $self.eat_char();
-                            number = number
-                                .checked_mul(10)
-                                .ok_or(Error::InvalidNumber)?
-                                .checked_add((c - b'0') as $ixx * if signed { -1 } else { 1 })
-                                .ok_or(Error::InvalidNumber)?;
+                            number = match number.checked_mul(10).and_then(|n| {
+                                n.checked_add((c - b'0') as $ixx * if signed { -1 } else { 1 })
+                            }) {
+                                Some(number) => number,
+                                None => return Err($self.number_out_of_range(start)),
+                            };
                         }
                         _ => return $visitor.$visit_ixx(number),
                     }
@@ -314,6 +409,7 @@ macro_rules! deserialize_signed {
 
 macro_rules! deserialize_fromstr {
     ($self:ident, $visitor:ident, $typ:ident, $visit_fn:ident, $pattern:expr) => {{
+        $self.expecting(stringify!($typ));
         let start = $self.index;
         loop {
             match $self.peek() {
@@ -338,18 +434,27 @@ macro_rules! deserialize_fromstr {
 impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     type Error = Error;
 
-    /// Unsupported. Can’t parse a value without knowing its expected type.
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    /// Deserialize by the shape of the value, as the expected type is not
+    /// known: quoted strings as `str`, integers as `i64` or `u64`, and
+    /// unquoted words as bytes. Anything else fails with
+    /// [`Error::InvalidType`]. This is what lets untagged enums, eg.
+    /// [`NumberOrString`], tell their variants apart.
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'-' => self.deserialize_i64(visitor),
+            b'0'..=b'9' => self.deserialize_u64(visitor),
+            _ => self.deserialize_str(visitor),
+        }
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        self.expecting("bool");
         let peek = self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
         match peek {
             b't' => {
@@ -456,6 +561,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        self.expecting("char");
         let peek = self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
         self.eat_char();
         visitor.visit_char(peek as char)
@@ -465,6 +571,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        self.expecting("string");
         let peek = self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
 
         match peek {
@@ -501,10 +608,11 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        self.expecting("bytes");
         self.parse_at()?;
         let idx = self.slice[self.index..]
             .iter()
-            .position(|b| *b == b',')
+            .position(|b| self.is_separator(*b))
             .unwrap_or(self.slice.len() - self.index);
 
         visitor
@@ -535,7 +643,8 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         match self.parse_whitespace() {
-            Some(b'+' | b',') | None => visitor.visit_none(),
+            Some(b'+') | None => visitor.visit_none(),
+            Some(c) if self.is_separator(c) => visitor.visit_none(),
             Some(_) => visitor.visit_some(self),
         }
     }
@@ -578,6 +687,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        self.expecting("length delimited bytes");
         visitor
             .visit_bytes(self.slice[self.index..].as_ref())
             .map(|v| {
@@ -642,6 +752,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
+        self.expecting("enum");
         self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
         visitor.visit_enum(VariantAccess::new(self))
     }
@@ -659,12 +770,14 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
             b'"' => self.deserialize_str(visitor),
-            b',' | b'}' | b']' => Err(Error::ExpectedSomeValue),
+            b'}' | b']' => Err(Error::ExpectedSomeValue),
+            c if self.is_separator(c) => Err(Error::ExpectedSomeValue),
             _ => loop {
                 match self.peek() {
                     // The visitor is expected to be UnknownAny’s visitor, which
                     // implements visit_unit to return its unit Ok result.
-                    Some(b',' | b'}' | b']') => break visitor.visit_unit(),
+                    Some(b'}' | b']') => break visitor.visit_unit(),
+                    Some(c) if self.is_separator(c) => break visitor.visit_unit(),
                     Some(_) => self.eat_char(),
                     None => break Err(Error::EofWhileParsingString),
                 }
@@ -711,6 +824,7 @@ impl fmt::Display for Error {
                 }
                 Self::ExpectedSomeValue => "Expected this character to start an AT Command string.",
                 Self::InvalidNumber => "Invalid number.",
+                Self::NumberOutOfRange => "Number out of range for the expected type.",
                 Self::InvalidType => "Invalid type",
                 Self::InvalidUnicodeCodePoint => "Invalid unicode code point.",
                 Self::TrailingCharacters => {
@@ -743,12 +857,50 @@ pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T>
 where
     T: de::Deserialize<'a>,
 {
-    let mut de = Deserializer::new(trim_ascii_whitespace(v));
+    from_slice_with_options(v, DeserializeOptions::default())
+}
+
+/// Deserializes an instance of type `T` from bytes of AT Response text, using
+/// custom [`DeserializeOptions`]
+pub fn from_slice_with_options<'a, T>(v: &'a [u8], options: DeserializeOptions<'a>) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut de = Deserializer::new(trim_ascii_whitespace(v), options);
     let value = de::Deserialize::deserialize(&mut de)?;
     de.end()?;
     Ok(value)
 }
 
+/// Deserializes an instance of type `T` from bytes of AT Response text,
+/// returning a [`Diagnostic`] telling which field failed to parse on error.
+///
+/// This is useful for telling whether the response has more or fewer
+/// parameters than expected, or whether the fields are ordered differently.
+#[cfg(feature = "diagnostics")]
+pub fn from_slice_with_diagnostic<'a, T>(
+    v: &'a [u8],
+    options: DeserializeOptions<'a>,
+) -> core::result::Result<T, (Error, Diagnostic)>
+where
+    T: de::Deserialize<'a>,
+{
+    let trimmed = trim_ascii_whitespace(v);
+    let offset = v.iter().take_while(|c| c.is_ascii_whitespace()).count();
+    let mut de = Deserializer::new(trimmed, options);
+    let result = de::Deserialize::deserialize(&mut de).and_then(|value| {
+        de.end()?;
+        Ok(value)
+    });
+    result.map_err(|e| {
+        let diagnostic = Diagnostic {
+            position: offset + de.index.min(trimmed.len()),
+            ..de.diagnostic
+        };
+        (e, diagnostic)
+    })
+}
+
 /// Deserializes an instance of type T from a string of AT Response text
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
@@ -758,6 +910,7 @@ where
 }
 
 #[cfg(all(test, feature = "heapless"))]
+#[allow(clippy::upper_case_acronyms)]
 mod tests {
     use super::length_delimited::LengthDelimited;
     use heapless::String;
@@ -847,6 +1000,100 @@ mod tests {
             })
         );
     }
+    #[test]
+    fn custom_separators() {
+        let options = crate::DeserializeOptions { separators: b";" };
+        assert_eq!(
+            crate::from_slice_with_options(b"+CFG: 2; 56;false", options),
+            Ok(CFG {
+                p1: 2,
+                p2: 56,
+                p3: false
+            })
+        );
+
+        let options = crate::DeserializeOptions { separators: b",;" };
+        assert_eq!(
+            crate::from_slice_with_options(b"+CFG: 2,56;;true", options),
+            Ok(OptionEmpty {
+                p1: 2,
+                p2: 56,
+                p3: None,
+                p4: true
+            })
+        );
+
+        assert!(crate::from_str::<CFG>("+CFG: 2;56;false").is_err());
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn diagnostic() {
+        use crate::{de::Error, from_slice_with_diagnostic, DeserializeOptions, Diagnostic};
+
+        assert_eq!(
+            from_slice_with_diagnostic::<CFG>(
+                b" +CFG: 2,\"56\",false",
+                DeserializeOptions::default()
+            )
+            .unwrap_err(),
+            (
+                Error::InvalidType,
+                Diagnostic {
+                    field: Some(1),
+                    position: 9,
+                    expected: "i16"
+                }
+            )
+        );
+        assert_eq!(
+            from_slice_with_diagnostic::<CFG>(b"+CFG: 2,56,false,3", DeserializeOptions::default())
+                .unwrap_err()
+                .0,
+            Error::TrailingCharacters
+        );
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn diagnostic_number_out_of_range() {
+        use crate::{de::Error, from_slice_with_diagnostic, DeserializeOptions, Diagnostic};
+
+        assert_eq!(
+            from_slice_with_diagnostic::<CFG>(
+                b"+CFG: 2,-1234567890123456789012345678901234567890,false",
+                DeserializeOptions::default()
+            )
+            .unwrap_err(),
+            (
+                Error::NumberOutOfRange,
+                Diagnostic {
+                    field: Some(1),
+                    position: 8,
+                    expected: "i16"
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn number_out_of_range() {
+        use crate::de::Error;
+
+        assert_eq!(
+            crate::from_str::<CFG>("+CFG: 2,32768,false"),
+            Err(Error::NumberOutOfRange)
+        );
+        assert_eq!(
+            crate::from_str::<CFG>("+CFG: 2,-32768,false").unwrap().p2,
+            -32768
+        );
+        assert_eq!(
+            crate::from_str::<u8>("1234567890123456789012345678901234567890"),
+            Err(Error::NumberOutOfRange)
+        );
+    }
+
     #[test]
     fn simple_string() {
         #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -968,6 +1215,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn length_delimited_truncated() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct PayloadResponse {
+            ctx: u8,
+            payload: LengthDelimited<32>,
+        }
+
+        assert!(crate::from_slice::<PayloadResponse>(b"1,9,\"ABCD\"").is_err());
+        assert!(crate::from_slice::<PayloadResponse>(b"1,18446744073709551615,A").is_err());
+    }
+
     #[test]
     fn length_delimited_json() {
         #[derive(Clone, Debug, Deserialize)]
@@ -0,0 +1,160 @@
+use core::{fmt, marker::PhantomData, str};
+
+use serde::de::{
+    self,
+    value::{BorrowedStrDeserializer, StrDeserializer},
+    Deserialize, IntoDeserializer, Visitor,
+};
+
+/// `NumberOrString`
+/// A parameter given either as a number or as a string, depending on eg. a
+/// format selected by a previous command, like `<oper>` of `+COPS`.
+///
+/// The variant is told by the shape of the value: unquoted integers are
+/// deserialized as `N`, and quoted or unquoted text as `S`.
+///
+/// ```
+/// use serde_at::{from_str, NumberOrString};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Operator<'a> {
+///     mode: u8,
+///     format: u8,
+///     #[serde(borrow)]
+///     oper: NumberOrString<u32, &'a str>,
+/// }
+///
+/// let numeric: Operator = from_str("+COPS: 0,2,310260").unwrap();
+/// assert_eq!(numeric.oper, NumberOrString::Number(310260));
+///
+/// let name: Operator = from_str("+COPS: 0,0,\"AT&T\"").unwrap();
+/// assert_eq!(name.oper, NumberOrString::String("AT&T"));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NumberOrString<N, S> {
+    /// The parameter was given as an integer
+    Number(N),
+    /// The parameter was given as text
+    String(S),
+}
+
+struct NumberOrStringVisitor<N, S>(PhantomData<(N, S)>);
+
+impl<'de, N, S> Visitor<'de> for NumberOrStringVisitor<N, S>
+where
+    N: Deserialize<'de>,
+    S: Deserialize<'de>,
+{
+    type Value = NumberOrString<N, S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a number or a string")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        N::deserialize(v.into_deserializer()).map(NumberOrString::Number)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        N::deserialize(v.into_deserializer()).map(NumberOrString::Number)
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        S::deserialize(BorrowedStrDeserializer::new(v)).map(NumberOrString::String)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        S::deserialize(StrDeserializer::new(v)).map(NumberOrString::String)
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let v = str::from_utf8(v).map_err(|_| E::invalid_value(de::Unexpected::Bytes(v), &self))?;
+        self.visit_borrowed_str(v)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let v = str::from_utf8(v).map_err(|_| E::invalid_value(de::Unexpected::Bytes(v), &self))?;
+        self.visit_str(v)
+    }
+}
+
+impl<'de, N, S> Deserialize<'de> for NumberOrString<N, S>
+where
+    N: Deserialize<'de>,
+    S: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NumberOrStringVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, to_slice, SerializeOptions};
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Operator<'a> {
+        mode: u8,
+        #[serde(borrow)]
+        oper: Option<NumberOrString<u16, &'a str>>,
+        act: u8,
+    }
+
+    #[test]
+    fn number_or_string() {
+        let op: Operator<'_> = from_str("+COPS: 1,\"Telia\",7").unwrap();
+        assert_eq!(op.oper, Some(NumberOrString::String("Telia")));
+
+        let op: Operator<'_> = from_str("+COPS: 1,24001,7").unwrap();
+        assert_eq!(op.oper, Some(NumberOrString::Number(24001)));
+
+        let op: Operator<'_> = from_str("+COPS: 1,,7").unwrap();
+        assert_eq!(op.oper, None);
+
+        // Numbers out of range for `N` are not taken for strings
+        assert!(from_str::<Operator<'_>>("+COPS: 1,-1,7").is_err());
+    }
+
+    #[test]
+    fn serialize() {
+        let mut buf = [0; 32];
+        let op = Operator {
+            mode: 1,
+            oper: Some(NumberOrString::String("Telia")),
+            act: 7,
+        };
+        let len = to_slice(&op, "+COPS", &mut buf, SerializeOptions::default()).unwrap();
+        assert_eq!(&buf[..len], b"AT+COPS=1,\"Telia\",7\r\n");
+
+        let op = Operator {
+            oper: Some(NumberOrString::Number(24001)),
+            ..op
+        };
+        let len = to_slice(&op, "+COPS", &mut buf, SerializeOptions::default()).unwrap();
+        assert_eq!(&buf[..len], b"AT+COPS=1,24001,7\r\n");
+    }
+}
@@ -0,0 +1,134 @@
+//! Lazy deserialization of responses with one record per line.
+//!
+use core::{iter::FusedIterator, marker::PhantomData};
+
+use serde::de;
+
+use super::{from_slice_with_options, trim_ascii_whitespace, DeserializeOptions, Result};
+
+/// Iterator deserializing the records of a response one line at a time, eg.
+/// the `+COPS: ...` lines of a long listing.
+///
+/// Unlike deserializing into a `heapless::Vec`, the number of records is not
+/// bounded, and only one record is held in memory at a time. Empty lines are
+/// skipped.
+///
+/// ```
+/// use serde_at::RecordIter;
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Context {
+///     cid: u8,
+///     state: u8,
+/// }
+///
+/// let mut records = RecordIter::<Context>::new(b"+CGACT: 1,1\r\n+CGACT: 2,0");
+/// assert_eq!(records.next(), Some(Ok(Context { cid: 1, state: 1 })));
+/// assert_eq!(records.next(), Some(Ok(Context { cid: 2, state: 0 })));
+/// assert_eq!(records.next(), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordIter<'a, T> {
+    rest: &'a [u8],
+    options: DeserializeOptions<'a>,
+    _ty: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> RecordIter<'a, T> {
+    /// Iterate over the records of the response `v`
+    pub fn new(v: &'a [u8]) -> Self {
+        Self::with_options(v, DeserializeOptions::default())
+    }
+
+    /// Iterate over the records of the response `v`, using custom
+    /// [`DeserializeOptions`]
+    pub fn with_options(v: &'a [u8], options: DeserializeOptions<'a>) -> Self {
+        Self {
+            rest: v,
+            options,
+            _ty: PhantomData,
+        }
+    }
+
+    /// The part of the response not yet deserialized
+    pub fn remainder(&self) -> &'a [u8] {
+        self.rest
+    }
+}
+
+impl<'a, T> Iterator for RecordIter<'a, T>
+where
+    T: de::Deserialize<'a>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+
+            let (line, rest) = match self.rest.windows(2).position(|w| w == b"\r\n") {
+                Some(end) => (&self.rest[..end], &self.rest[end + 2..]),
+                None => (self.rest, &self.rest[self.rest.len()..]),
+            };
+            self.rest = rest;
+
+            let line = trim_ascii_whitespace(line);
+            if !line.is_empty() {
+                return Some(from_slice_with_options(line, self.options));
+            }
+        }
+    }
+}
+
+impl<'a, T> FusedIterator for RecordIter<'a, T> where T: de::Deserialize<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::Error;
+    use serde_derive::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Operator {
+        stat: u8,
+        act: Option<u8>,
+    }
+
+    #[test]
+    fn records() {
+        let response = b"+COPS: 2,7\r\n\r\n+COPS: 1\r\n+COPS: x,1\r\n+COPS: 3,9";
+        let mut records = RecordIter::<Operator>::new(response);
+
+        assert_eq!(
+            records.next(),
+            Some(Ok(Operator {
+                stat: 2,
+                act: Some(7)
+            }))
+        );
+        assert_eq!(records.next(), Some(Ok(Operator { stat: 1, act: None })));
+        // A malformed record does not end the iteration
+        assert_eq!(records.next(), Some(Err(Error::InvalidType)));
+        assert_eq!(records.remainder(), b"+COPS: 3,9");
+        assert_eq!(
+            records.next(),
+            Some(Ok(Operator {
+                stat: 3,
+                act: Some(9)
+            }))
+        );
+        assert_eq!(records.next(), None);
+        assert_eq!(records.next(), None);
+    }
+
+    #[test]
+    fn custom_separators() {
+        let options = DeserializeOptions { separators: b";" };
+        let records =
+            RecordIter::<Operator>::with_options(b"+COPS: 2;7\r\n+COPS: 1;0\r\n", options);
+        assert_eq!(records.map(|r| r.unwrap().stat).sum::<u8>(), 3);
+    }
+}
@@ -0,0 +1,237 @@
+use core::{fmt, marker::PhantomData};
+
+use serde::de::{self, Deserialize, Unexpected, Visitor};
+
+/// Conversion of a raw index, as reported by eg. `+CSQ`, to a physical value.
+pub trait Scale {
+    /// Physical value of each raw index, starting at index 0, in increasing
+    /// order.
+    const TABLE: &'static [i16];
+    /// Raw index reported when the value is not known or not detectable
+    const UNKNOWN: u8 = 99;
+}
+
+/// `Scaled`
+/// A raw index reported by the device, converted to a physical value through
+/// the conversion table of `S`.
+///
+/// The raw index is kept, so it is serialized back exactly as received.
+/// Indices which are neither in the table nor [`Scale::UNKNOWN`] fail to
+/// deserialize.
+///
+/// ```
+/// use serde_at::{from_str, Ber, Rssi};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct SignalQuality {
+///     rssi: Rssi,
+///     ber: Ber,
+/// }
+///
+/// let csq: SignalQuality = from_str("+CSQ: 20,3").unwrap();
+/// assert_eq!(csq.rssi.value(), Some(-73));
+/// assert_eq!(csq.ber.value(), Some(113));
+///
+/// let csq: SignalQuality = from_str("+CSQ: 99,99").unwrap();
+/// assert_eq!((csq.rssi.value(), csq.ber.value()), (None, None));
+/// ```
+pub struct Scaled<S> {
+    raw: u8,
+    _scale: PhantomData<S>,
+}
+
+impl<S: Scale> Scaled<S> {
+    /// Value of the raw index `raw`
+    #[must_use]
+    pub const fn new(raw: u8) -> Self {
+        Self {
+            raw,
+            _scale: PhantomData,
+        }
+    }
+
+    /// Value reported as not known or not detectable
+    #[must_use]
+    pub const fn unknown() -> Self {
+        Self::new(S::UNKNOWN)
+    }
+
+    /// The index of the table closest to the physical value `value`, clamped
+    /// to the range of the table.
+    #[must_use]
+    pub const fn from_value(value: i16) -> Self {
+        let mut raw = 0;
+        while raw + 1 < S::TABLE.len() && S::TABLE[raw + 1] <= value {
+            raw += 1;
+        }
+        // In `i32`, as the distances of extreme values overflow `i16`
+        let value = value as i32;
+        if raw + 1 < S::TABLE.len()
+            && S::TABLE[raw + 1] as i32 - value < value - S::TABLE[raw] as i32
+        {
+            raw += 1;
+        }
+        Self::new(raw as u8)
+    }
+
+    /// The raw index, as reported by the device
+    #[must_use]
+    pub const fn raw(&self) -> u8 {
+        self.raw
+    }
+
+    /// The physical value, or `None` if not known or not detectable
+    #[must_use]
+    pub const fn value(&self) -> Option<i16> {
+        if (self.raw as usize) < S::TABLE.len() {
+            Some(S::TABLE[self.raw as usize])
+        } else {
+            None
+        }
+    }
+}
+
+// Implemented by hand, as deriving bounds the marker type `S`
+impl<S> Clone for Scaled<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for Scaled<S> {}
+
+impl<S> PartialEq for Scaled<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<S> Eq for Scaled<S> {}
+
+impl<S> fmt::Debug for Scaled<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Scaled").field(&self.raw).finish()
+    }
+}
+
+/// Received signal strength in dBm, from `<rssi>` of `+CSQ`. Index 0 is
+/// -113 dBm or less, and 31 is -51 dBm or greater.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RssiScale;
+
+impl Scale for RssiScale {
+    const TABLE: &'static [i16] = &{
+        let mut table = [0; 32];
+        let mut i = 0;
+        while i < table.len() {
+            table[i] = -113 + 2 * i as i16;
+            i += 1;
+        }
+        table
+    };
+}
+
+/// Channel bit error rate in hundredths of a percent, from `<ber>` of
+/// `+CSQ`, as the assumed values of the `RXQUAL` ranges of 3GPP TS 45.008.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BerScale;
+
+impl Scale for BerScale {
+    const TABLE: &'static [i16] = &[14, 28, 57, 113, 226, 453, 905, 1810];
+}
+
+/// `<rssi>` of `+CSQ`, in dBm
+pub type Rssi = Scaled<RssiScale>;
+
+/// `<ber>` of `+CSQ`, in hundredths of a percent
+pub type Ber = Scaled<BerScale>;
+
+struct ScaledVisitor<S>(PhantomData<S>);
+
+impl<'de, S: Scale> Visitor<'de> for ScaledVisitor<S> {
+    type Value = Scaled<S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "an index below {} or {}",
+            S::TABLE.len(),
+            S::UNKNOWN
+        )
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match u8::try_from(v) {
+            Ok(raw) if usize::from(raw) < S::TABLE.len() || raw == S::UNKNOWN => {
+                Ok(Scaled::new(raw))
+            }
+            _ => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+}
+
+impl<'de, S: Scale> Deserialize<'de> for Scaled<S> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_u8(ScaledVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, to_slice, SerializeOptions};
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct SignalQuality {
+        rssi: Rssi,
+        ber: Ber,
+    }
+
+    #[test]
+    fn deserialize() {
+        let csq: SignalQuality = from_str("+CSQ: 0,7").unwrap();
+        assert_eq!(csq.rssi.value(), Some(-113));
+        assert_eq!(csq.ber.value(), Some(1810));
+
+        let csq: SignalQuality = from_str("+CSQ: 31,99").unwrap();
+        assert_eq!(csq.rssi.value(), Some(-51));
+        assert_eq!(csq.ber, Ber::unknown());
+
+        assert!(from_str::<SignalQuality>("+CSQ: 32,0").is_err());
+        assert!(from_str::<SignalQuality>("+CSQ: 10,8").is_err());
+    }
+
+    #[test]
+    fn from_value() {
+        assert_eq!(Rssi::from_value(-113).raw(), 0);
+        assert_eq!(Rssi::from_value(-120).raw(), 0);
+        assert_eq!(Rssi::from_value(-74).raw(), 19);
+        assert_eq!(Rssi::from_value(-73).raw(), 20);
+        assert_eq!(Rssi::from_value(-40).raw(), 31);
+        assert_eq!(Ber::from_value(100).raw(), 3);
+        assert_eq!(Ber::from_value(5000).raw(), 7);
+        assert_eq!(Ber::from_value(i16::MIN).raw(), 0);
+        assert_eq!(Ber::from_value(i16::MAX).raw(), 7);
+        assert_eq!(Rssi::from_value(i16::MIN).raw(), 0);
+        assert_eq!(Rssi::from_value(i16::MAX).raw(), 31);
+    }
+
+    #[test]
+    fn serialize() {
+        let mut buf = [0; 32];
+        let csq = SignalQuality {
+            rssi: Rssi::from_value(-73),
+            ber: Ber::unknown(),
+        };
+        let len = to_slice(&csq, "+CSQ", &mut buf, SerializeOptions::default()).unwrap();
+        assert_eq!(&buf[..len], b"AT+CSQ=20,99\r\n");
+    }
+}
@@ -30,7 +30,7 @@ impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
         T: de::DeserializeSeed<'de>,
     {
         match self.de.parse_whitespace() {
-            Some(b',') => {
+            Some(c) if self.de.is_separator(c) => {
                 self.de.eat_char();
                 self.de
                     .parse_whitespace()
@@ -57,6 +57,7 @@ impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
             }
         };
 
+        self.de.at_field(self.count);
         match seed.deserialize(&mut *self.de) {
             // Misuse EofWhileParsingObject here to indicate finished object in vec cases.
             // See matching TODO in `de::mod`..
@@ -0,0 +1,209 @@
+use core::fmt;
+use serde::de::Visitor;
+use serde::{de, Deserialize};
+
+/// `Timestamp`
+/// A date and time in the `"yy/MM/dd,hh:mm:ss±zz"` format used by eg. `+CCLK`,
+/// where the timezone is given in quarters of an hour.
+///
+/// Deserializing is lenient towards the common vendor variations: the year
+/// may be given with two or four digits, the date may be separated by either
+/// `/` or `-`, and the timezone may be omitted. The flags control how the
+/// value is serialized back.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Timestamp {
+    /// Full year, eg. `2023`. Two digit years are offset by 2000
+    pub year: u16,
+    /// Month of the year, `1..=12`
+    pub month: u8,
+    /// Day of the month, `1..=31`
+    pub day: u8,
+    /// Hour of the day, `0..=23`
+    pub hour: u8,
+    /// Minute of the hour, `0..=59`
+    pub minute: u8,
+    /// Second of the minute, `0..=59`
+    pub second: u8,
+    /// Offset from UTC in quarters of an hour, if given
+    pub timezone: Option<i8>,
+    /// Flag to serialize the year with four digits instead of two
+    pub four_digit_year: bool,
+}
+
+impl Default for Timestamp {
+    fn default() -> Self {
+        Self {
+            year: 2000,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            timezone: Some(0),
+            four_digit_year: false,
+        }
+    }
+}
+
+impl Timestamp {
+    /// Offset from UTC in minutes, if a timezone was given
+    #[must_use]
+    pub fn utc_offset_minutes(&self) -> Option<i16> {
+        self.timezone.map(|tz| i16::from(tz) * 15)
+    }
+
+    /// Parse a timestamp, without surrounding quotes
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.as_bytes();
+        let (date, rest) = s.split_at(s.iter().position(|&c| c == b',' || c == b' ')?);
+        let rest = &rest[1..];
+
+        let mut parts = date.split(|&c| c == b'/' || c == b'-');
+        let year = parts.next()?;
+        let four_digit_year = match year.len() {
+            2 => false,
+            4 => true,
+            _ => return None,
+        };
+        let year = parse_digits(year)?;
+        let year = if four_digit_year { year } else { 2000 + year };
+        let month = parse_field(parts.next()?, 1..=12)?;
+        let day = parse_field(parts.next()?, 1..=31)?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let (time, timezone) = match rest.iter().position(|&c| c == b'+' || c == b'-') {
+            Some(i) => {
+                let (time, tz) = rest.split_at(i);
+                let quarters = i8::try_from(parse_digits(&tz[1..])?).ok()?;
+                if quarters > 96 {
+                    return None;
+                }
+                (time, Some(if tz[0] == b'-' { -quarters } else { quarters }))
+            }
+            None => (rest, None),
+        };
+
+        let mut parts = time.split(|&c| c == b':');
+        let hour = parse_field(parts.next()?, 0..=23)?;
+        let minute = parse_field(parts.next()?, 0..=59)?;
+        let second = parse_field(parts.next()?, 0..=59)?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            timezone,
+            four_digit_year,
+        })
+    }
+}
+
+fn parse_digits(s: &[u8]) -> Option<u16> {
+    if s.is_empty() || s.len() > 4 {
+        return None;
+    }
+    s.iter().try_fold(0u16, |acc, &c| {
+        let d = (c as char).to_digit(10)?;
+        Some(acc * 10 + d as u16)
+    })
+}
+
+fn parse_field(s: &[u8], range: core::ops::RangeInclusive<u8>) -> Option<u8> {
+    if s.len() != 2 {
+        return None;
+    }
+    let v = u8::try_from(parse_digits(s)?).ok()?;
+    range.contains(&v).then_some(v)
+}
+
+struct TimestampVisitor;
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a timestamp in the format \"yy/MM/dd,hh:mm:ss±zz\"")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Timestamp::parse(v).ok_or_else(|| de::Error::custom("Invalid timestamp"))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let s = core::str::from_utf8(v).map_err(de::Error::custom)?;
+        self.visit_str(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(TimestampVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+    use serde_derive::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Clock {
+        time: Timestamp,
+    }
+
+    #[test]
+    fn parse_cclk() {
+        let clock: Clock = crate::from_str("+CCLK: \"23/09/15,12:34:56+08\"").unwrap();
+        assert_eq!(
+            clock.time,
+            Timestamp {
+                year: 2023,
+                month: 9,
+                day: 15,
+                hour: 12,
+                minute: 34,
+                second: 56,
+                timezone: Some(8),
+                four_digit_year: false,
+            }
+        );
+        assert_eq!(clock.time.utc_offset_minutes(), Some(120));
+    }
+
+    #[test]
+    fn parse_variations() {
+        let t = Timestamp::parse("2023-09-15,12:34:56-22").unwrap();
+        assert_eq!(t.year, 2023);
+        assert!(t.four_digit_year);
+        assert_eq!(t.timezone, Some(-22));
+
+        let t = Timestamp::parse("23/09/15 12:34:56").unwrap();
+        assert_eq!((t.year, t.hour, t.timezone), (2023, 12, None));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(Timestamp::parse("23/13/15,12:34:56+08"), None);
+        assert_eq!(Timestamp::parse("23/09/15,24:00:00+08"), None);
+        assert_eq!(Timestamp::parse("23/09/15"), None);
+        assert_eq!(Timestamp::parse("3/09/15,12:34:56+08"), None);
+        assert_eq!(Timestamp::parse("23/09/15,12:34+08"), None);
+    }
+}
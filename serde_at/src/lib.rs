@@ -19,13 +19,22 @@ pub mod ser;
 pub use serde;
 
 #[doc(inline)]
-pub use self::de::{from_slice, from_str, hex_str::HexStr};
+pub use self::de::{
+    from_slice, from_slice_with_options, from_str,
+    hex_str::{HexStr, StrictHexStr},
+    timestamp::Timestamp,
+    Ber, BerScale, DecimalComma, DeserializeOptions, NumberOrString, RecordIter, Rssi, RssiScale,
+    Scale, Scaled,
+};
 #[doc(inline)]
 pub use self::ser::{to_slice, SerializeOptions};
 
 #[cfg(feature = "heapless")]
 pub use self::ser::{to_string, to_vec};
 
+#[cfg(feature = "diagnostics")]
+pub use self::de::{from_slice_with_diagnostic, Diagnostic};
+
 use core::mem::MaybeUninit;
 
 // TODO: Use `MaybeUninit::uninit_array` once it has stabilized?
@@ -42,3 +51,142 @@ unsafe fn slice_assume_init_ref<T>(slice: &[MaybeUninit<T>]) -> &[T] {
     // SAFETY: See `MaybeUninit::slice_assume_init_ref`.
     unsafe { &*(slice as *const [MaybeUninit<T>] as *const [T]) }
 }
+
+#[cfg(test)]
+mod tests {
+    //! Round trips of generated values through the serializer and the
+    //! deserializer, ie. `from_slice(to_slice(x)) == x`.
+
+    use heapless::String;
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_derive::{Deserialize, Serialize};
+
+    const ROUNDS: usize = 1000;
+
+    /// Characters of generated strings. Strings are not escaped, so quotes
+    /// can not round trip.
+    const CHARSET: &[u8] = b"abcXYZ019 ,.:+-_";
+
+    /// Xorshift generator, seeded per test to be reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn bool(&mut self) -> bool {
+            self.next() & 1 == 1
+        }
+
+        fn option<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> Option<T> {
+            self.bool().then(|| f(self))
+        }
+
+        fn string<const N: usize>(&mut self) -> String<N> {
+            let len = self.next() as usize % (N + 1);
+            (0..len)
+                .map(|_| CHARSET[self.next() as usize % CHARSET.len()] as char)
+                .collect()
+        }
+    }
+
+    fn round_trip<T>(value: &T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + core::fmt::Debug,
+    {
+        let mut buf = [0; 256];
+        let options = crate::SerializeOptions {
+            value_sep: false,
+            cmd_prefix: "",
+            termination: "",
+            ..Default::default()
+        };
+        let len = crate::to_slice(value, "", &mut buf, options).unwrap();
+        assert_eq!(
+            crate::from_slice::<T>(&buf[..len]).as_ref(),
+            Ok(value),
+            "serialized as {:?}",
+            core::str::from_utf8(&buf[..len])
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Numbers {
+        a: u8,
+        b: i8,
+        c: u16,
+        d: i16,
+        e: u32,
+        f: i32,
+        g: u64,
+        h: i64,
+    }
+
+    #[test]
+    fn round_trip_numbers() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..ROUNDS {
+            round_trip(&Numbers {
+                a: rng.next() as u8,
+                b: rng.next() as i8,
+                c: rng.next() as u16,
+                d: rng.next() as i16,
+                e: rng.next() as u32,
+                f: rng.next() as i32,
+                g: rng.next(),
+                h: rng.next() as i64,
+            });
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Mixed {
+        id: u8,
+        enabled: bool,
+        name: String<16>,
+        timeout: Option<u32>,
+        apn: Option<String<32>>,
+        offset: i16,
+        roaming: Option<bool>,
+    }
+
+    #[test]
+    fn round_trip_mixed() {
+        let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+        for _ in 0..ROUNDS {
+            round_trip(&Mixed {
+                id: rng.next() as u8,
+                enabled: rng.bool(),
+                name: rng.string(),
+                timeout: rng.option(|r| r.next() as u32),
+                apn: rng.option(Rng::string),
+                offset: rng.next() as i16,
+                roaming: rng.option(Rng::bool),
+            });
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Handle(u32);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Nested {
+        handle: Handle,
+        state: u8,
+    }
+
+    #[test]
+    fn round_trip_newtype() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+        for _ in 0..ROUNDS {
+            round_trip(&Nested {
+                handle: Handle(rng.next() as u32),
+                state: rng.next() as u8,
+            });
+        }
+    }
+}
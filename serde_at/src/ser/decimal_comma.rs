@@ -0,0 +1,42 @@
+use core::fmt;
+
+use serde::ser::Serialize;
+use serde::Serializer;
+
+use crate::DecimalComma;
+
+/// Writes through to a formatter, replacing the decimal point by a comma
+struct CommaWriter<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl fmt::Write for CommaWriter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for part in s.split_inclusive('.') {
+            match part.strip_suffix('.') {
+                Some(part) => {
+                    self.0.write_str(part)?;
+                    self.0.write_str(",")?;
+                }
+                None => self.0.write_str(part)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Comma<'a, T>(&'a T);
+
+impl<T: fmt::Display> fmt::Display for Comma<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+        write!(CommaWriter(f), "{}", self.0)
+    }
+}
+
+impl<T: fmt::Display> Serialize for DecimalComma<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&Comma(&self.0))
+    }
+}
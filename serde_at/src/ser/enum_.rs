@@ -21,7 +21,7 @@ impl<'a, 'b> ser::SerializeTupleVariant for SerializeTupleVariant<'a, 'b> {
         T: ser::Serialize + ?Sized,
     {
         if !self.first {
-            self.ser.push(b',')?;
+            self.ser.push_param_sep()?;
         }
         self.first = false;
 
@@ -54,7 +54,7 @@ impl<'a, 'b> ser::SerializeStructVariant for SerializeStructVariant<'a, 'b> {
         T: ser::Serialize + ?Sized,
     {
         if !self.first {
-            self.ser.push(b',')?;
+            self.ser.push_param_sep()?;
         }
         self.first = false;
 
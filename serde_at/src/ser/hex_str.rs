@@ -1,8 +1,20 @@
-use crate::HexStr;
+use crate::{HexStr, StrictHexStr};
 use core::fmt::Write;
 use serde::ser::Serialize;
 use serde::Serializer;
 
+impl<T> Serialize for StrictHexStr<T>
+where
+    HexStr<T>: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
 macro_rules! impl_hex_str_serialize {
     ($type:ty, $len:expr, $len_delimited:expr, $hex_size:literal) => {
         impl Serialize for HexStr<$type> {
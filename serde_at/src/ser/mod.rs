@@ -4,10 +4,14 @@ use core::fmt;
 
 use serde::ser;
 
+mod decimal_comma;
 mod enum_;
 #[cfg(feature = "heapless")]
 mod hex_str;
+mod number_or_string;
+mod scaled;
 mod struct_;
+mod timestamp;
 
 use self::enum_::{SerializeStructVariant, SerializeTupleVariant};
 use self::struct_::SerializeStruct;
@@ -16,6 +20,7 @@ use self::struct_::SerializeStruct;
 pub type Result<T> = ::core::result::Result<T, Error>;
 
 /// Options used by the serializer, to customize the resulting string
+#[derive(Debug, Clone, Copy)]
 pub struct SerializeOptions<'a> {
     /// Whether or not to include `=` as a seperator between the at command, and
     /// the parameters (serialized struct fields)
@@ -34,6 +39,21 @@ pub struct SerializeOptions<'a> {
     ///
     /// **default**: true
     pub quote_escape_strings: bool,
+    /// The separator inserted between parameters, see
+    /// [`SerializeOptions::param_sep`].
+    pub(crate) param_sep: &'a str,
+}
+
+impl<'a> SerializeOptions<'a> {
+    /// Set the separator inserted between parameters, eg. `", "` or `";"`
+    /// for vendor command sets deviating from the standard.
+    ///
+    /// **default**: ","
+    #[must_use]
+    pub const fn param_sep(mut self, param_sep: &'a str) -> Self {
+        self.param_sep = param_sep;
+        self
+    }
 }
 
 impl<'a> Default for SerializeOptions<'a> {
@@ -43,6 +63,7 @@ impl<'a> Default for SerializeOptions<'a> {
             cmd_prefix: "AT",
             termination: "\r\n",
             quote_escape_strings: true,
+            param_sep: ",",
         }
     }
 }
@@ -66,6 +87,11 @@ pub(crate) struct Serializer<'a> {
     buf: &'a mut [u8],
     written: usize,
     nested_struct: bool,
+    /// Position of the last separator written
+    last_sep: usize,
+    /// Position of the separator preceding a run of `None` values, which are
+    /// omitted along with their separators if nothing follows them
+    trailing_none: Option<usize>,
     cmd: &'a str,
     options: SerializeOptions<'a>,
 }
@@ -76,6 +102,8 @@ impl<'a> Serializer<'a> {
             buf,
             written: 0,
             nested_struct: false,
+            last_sep: 0,
+            trailing_none: None,
             cmd,
             options,
         }
@@ -85,6 +113,7 @@ impl<'a> Serializer<'a> {
         if self.written < self.buf.len() {
             self.buf[self.written] = c;
             self.written += 1;
+            self.trailing_none = None;
             Ok(())
         } else {
             Err(Error::BufferFull)
@@ -95,12 +124,39 @@ impl<'a> Serializer<'a> {
         if self.written + other.len() <= self.buf.len() {
             self.buf[self.written..self.written + other.len()].copy_from_slice(other);
             self.written += other.len();
+            self.trailing_none = None;
             Ok(())
         } else {
             Err(Error::BufferFull)
         }
     }
 
+    /// Push the `=` separating the command from its parameters
+    fn push_value_sep(&mut self) -> Result<()> {
+        self.push_sep(b"=")
+    }
+
+    /// Push the separator between two parameters
+    fn push_param_sep(&mut self) -> Result<()> {
+        self.push_sep(self.options.param_sep.as_bytes())
+    }
+
+    fn push_sep(&mut self, sep: &[u8]) -> Result<()> {
+        // A separator does not end a run of `None` values
+        let trailing_none = self.trailing_none;
+        self.last_sep = self.written;
+        self.extend_from_slice(sep)?;
+        self.trailing_none = trailing_none;
+        Ok(())
+    }
+
+    /// Omit the trailing `None` values, along with their separators.
+    fn trim_trailing_none(&mut self) {
+        if let Some(pos) = self.trailing_none.take() {
+            self.written = pos;
+        }
+    }
+
     fn write_buf(&mut self) -> &mut [u8] {
         &mut self.buf[self.written..]
     }
@@ -108,6 +164,7 @@ impl<'a> Serializer<'a> {
     fn commit(&mut self, amount: usize) -> Result<()> {
         if self.written + amount <= self.buf.len() {
             self.written += amount;
+            self.trailing_none = None;
             Ok(())
         } else {
             Err(Error::BufferFull)
@@ -144,8 +201,8 @@ macro_rules! serialize_unsigned {
 macro_rules! serialize_signed {
     ($self:ident, $N:expr, $v:expr, $ixx:ident, $uxx:ident) => {{
         let v = $v;
-        let (signed, mut v) = if v == $ixx::min_value() {
-            (true, $ixx::max_value() as $uxx + 1)
+        let (signed, mut v) = if v == $ixx::MIN {
+            (true, $ixx::MAX as $uxx + 1)
         } else if v < 0 {
             (true, -v as $uxx)
         } else {
@@ -186,10 +243,7 @@ struct FmtWrapper<'a> {
 
 impl<'a> FmtWrapper<'a> {
     fn new(buf: &'a mut [u8]) -> Self {
-        FmtWrapper {
-            buf: buf,
-            offset: 0,
-        }
+        FmtWrapper { buf, offset: 0 }
     }
 }
 
@@ -322,7 +376,8 @@ impl<'a, 'b> ser::Serializer for &'a mut Serializer<'b> {
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        self.written -= 1;
+        // Leave an empty placeholder, eg. `AT+USOST=0,,1`, unless trailing
+        self.trailing_none.get_or_insert(self.last_sep);
         Ok(())
     }
 
@@ -371,7 +426,7 @@ impl<'a, 'b> ser::Serializer for &'a mut Serializer<'b> {
         T: ser::Serialize + ?Sized,
     {
         self.serialize_u32(variant_index)?;
-        self.push(b',')?;
+        self.push_param_sep()?;
         value.serialize(self)
     }
 
@@ -399,7 +454,7 @@ impl<'a, 'b> ser::Serializer for &'a mut Serializer<'b> {
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
         self.serialize_u32(variant_index)?;
-        self.push(b',')?;
+        self.push_param_sep()?;
         Ok(SerializeTupleVariant::new(self))
     }
 
@@ -428,12 +483,26 @@ impl<'a, 'b> ser::Serializer for &'a mut Serializer<'b> {
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
         self.serialize_u32(variant_index)?;
-        self.push(b',')?;
+        self.push_param_sep()?;
         Ok(SerializeStructVariant::new(self))
     }
 
-    fn collect_str<T: ?Sized>(self, _value: &T) -> Result<Self::Ok> {
-        unreachable!()
+    fn collect_str<T>(self, value: &T) -> Result<Self::Ok>
+    where
+        T: fmt::Display + ?Sized,
+    {
+        use fmt::Write;
+        if self.options.quote_escape_strings {
+            self.push(b'"')?;
+        }
+        let mut wrapper = FmtWrapper::new(self.write_buf());
+        write!(wrapper, "{value}").map_err(|_| Error::BufferFull)?;
+        let written = wrapper.offset;
+        self.commit(written)?;
+        if self.options.quote_escape_strings {
+            self.push(b'"')?;
+        }
+        Ok(())
     }
 }
 
@@ -557,6 +626,7 @@ impl ser::SerializeTuple for Unreachable {
 }
 
 #[cfg(all(test, feature = "heapless"))]
+#[allow(clippy::upper_case_acronyms)]
 mod tests {
     use super::*;
     use crate::HexStr;
@@ -642,6 +712,44 @@ mod tests {
         assert_eq!(s, String::<32>::try_from("AT+CMD\r\n").unwrap());
     }
 
+    #[test]
+    fn struct_with_none_options() {
+        #[derive(Clone, PartialEq, Serialize)]
+        pub struct SendTo<'a> {
+            socket: u8,
+            remote_addr: Option<&'a str>,
+            length: u8,
+            data: Option<&'a str>,
+            timeout: Option<u8>,
+            flags: Option<u8>,
+        }
+
+        let value = SendTo {
+            socket: 0,
+            remote_addr: None,
+            length: 1,
+            data: Some("data"),
+            timeout: None,
+            flags: None,
+        };
+        let s: String<32> = to_string(&value, "+USOST", SerializeOptions::default()).unwrap();
+        assert_eq!(
+            s,
+            String::<32>::try_from("AT+USOST=0,,1,\"data\"\r\n").unwrap()
+        );
+
+        let value = SendTo {
+            socket: 0,
+            remote_addr: None,
+            length: 1,
+            data: None,
+            timeout: None,
+            flags: Some(2),
+        };
+        let s: String<32> = to_string(&value, "+USOST", SerializeOptions::default()).unwrap();
+        assert_eq!(s, String::<32>::try_from("AT+USOST=0,,1,,,2\r\n").unwrap());
+    }
+
     #[test]
     fn struct_with_some_option() {
         #[derive(Clone, PartialEq, Serialize)]
@@ -656,6 +764,37 @@ mod tests {
         assert_eq!(s, String::<32>::try_from("AT+CMD=\"value\"\r\n").unwrap());
     }
 
+    #[test]
+    fn custom_param_sep() {
+        #[derive(Clone, PartialEq, Serialize)]
+        pub struct WithOptions<'a> {
+            a: u8,
+            b: Option<&'a str>,
+            c: PacketSwitchedParam,
+        }
+
+        let options = SerializeOptions::default().param_sep("; ");
+        let value = WithOptions {
+            a: 1,
+            b: Some("value"),
+            c: PacketSwitchedParam::QoSDelay3G(15),
+        };
+        let s: String<32> = to_string(&value, "+CMD", options).unwrap();
+        assert_eq!(
+            s,
+            String::<32>::try_from("AT+CMD=1; \"value\"; 4; 15\r\n").unwrap()
+        );
+
+        let options = SerializeOptions::default().param_sep(", ");
+        let value = WithOptions {
+            a: 1,
+            b: None,
+            c: PacketSwitchedParam::QoSDelay3G(15),
+        };
+        let s: String<32> = to_string(&value, "+CMD", options).unwrap();
+        assert_eq!(s, String::<32>::try_from("AT+CMD=1, , 4, 15\r\n").unwrap());
+    }
+
     #[test]
     fn byte_serialize() {
         #[derive(Clone, PartialEq, Serialize)]
@@ -0,0 +1,15 @@
+use crate::NumberOrString;
+use serde::ser::Serialize;
+use serde::Serializer;
+
+impl<N: Serialize, S: Serialize> Serialize for NumberOrString<N, S> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        match self {
+            Self::Number(n) => n.serialize(serializer),
+            Self::String(s) => s.serialize(serializer),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use crate::{Scale, Scaled};
+use serde::ser::Serialize;
+use serde::Serializer;
+
+impl<S: Scale> Serialize for Scaled<S> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        serializer.serialize_u8(self.raw())
+    }
+}
@@ -28,10 +28,10 @@ impl<'a, 'b> ser::SerializeStruct for SerializeStruct<'a, 'b> {
     {
         if self.first {
             if !self.nested && self.ser.options.value_sep {
-                self.ser.push(b'=')?;
+                self.ser.push_value_sep()?;
             }
         } else {
-            self.ser.push(b',')?;
+            self.ser.push_param_sep()?;
         }
         self.first = false;
 
@@ -41,6 +41,7 @@ impl<'a, 'b> ser::SerializeStruct for SerializeStruct<'a, 'b> {
 
     fn end(self) -> Result<Self::Ok> {
         if !self.nested {
+            self.ser.trim_trailing_none();
             self.ser
                 .extend_from_slice(self.ser.options.termination.as_bytes())?;
         }
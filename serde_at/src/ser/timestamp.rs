@@ -0,0 +1,90 @@
+use crate::Timestamp;
+use serde::ser::Serialize;
+use serde::Serializer;
+
+/// `yyyy/MM/dd,hh:mm:ss+zz`
+const MAX_LEN: usize = 22;
+
+fn push_digits(buf: &mut [u8; MAX_LEN], len: &mut usize, val: u16, digits: u32) {
+    for i in (0..digits).rev() {
+        buf[*len] = b'0' + ((val / 10u16.pow(i)) % 10) as u8;
+        *len += 1;
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut buf = [0u8; MAX_LEN];
+        let mut len = 0;
+
+        if self.four_digit_year {
+            push_digits(&mut buf, &mut len, self.year, 4);
+        } else {
+            push_digits(&mut buf, &mut len, self.year % 100, 2);
+        }
+
+        for (sep, val) in [
+            (b'/', self.month),
+            (b'/', self.day),
+            (b',', self.hour),
+            (b':', self.minute),
+            (b':', self.second),
+        ] {
+            buf[len] = sep;
+            len += 1;
+            push_digits(&mut buf, &mut len, u16::from(val), 2);
+        }
+
+        if let Some(tz) = self.timezone {
+            buf[len] = if tz < 0 { b'-' } else { b'+' };
+            len += 1;
+            push_digits(&mut buf, &mut len, u16::from(tz.unsigned_abs()), 2);
+        }
+
+        // Only ASCII digits and separators have been written
+        serializer.serialize_str(core::str::from_utf8(&buf[..len]).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{to_slice, SerializeOptions, Timestamp};
+    use serde_derive::Serialize;
+
+    #[derive(Serialize)]
+    struct SetClock {
+        time: Timestamp,
+    }
+
+    #[test]
+    fn serialize_cclk() {
+        let mut buf = [0; 64];
+        let cmd = SetClock {
+            time: Timestamp::parse("23/09/15,12:34:56-08").unwrap(),
+        };
+        let len = to_slice(&cmd, "+CCLK", &mut buf, SerializeOptions::default()).unwrap();
+        assert_eq!(&buf[..len], b"AT+CCLK=\"23/09/15,12:34:56-08\"\r\n");
+    }
+
+    #[test]
+    fn serialize_variations() {
+        let mut buf = [0; 64];
+        let cmd = SetClock {
+            time: Timestamp {
+                year: 2023,
+                month: 9,
+                day: 5,
+                hour: 1,
+                minute: 2,
+                second: 3,
+                timezone: None,
+                four_digit_year: true,
+            },
+        };
+        let len = to_slice(&cmd, "+CCLK", &mut buf, SerializeOptions::default()).unwrap();
+        assert_eq!(&buf[..len], b"AT+CCLK=\"2023/09/05,01:02:03\"\r\n");
+    }
+}